@@ -24,6 +24,16 @@ pub struct AlbumConfiguration {
     /// Scanning options.
     #[serde(default)]
     pub scan: AlbumScanConfiguration,
+
+    /// If `true`, the album is left out of transcoding entirely
+    /// (its existing transcoded files, if any, are left untouched).
+    #[serde(default)]
+    pub skip_transcode: bool,
+
+    /// Release year of the album, used for the {year} placeholder
+    /// in `aggregated_library.album_directory_template`.
+    #[serde(default)]
+    pub year: Option<u16>,
 }
 
 impl AlbumConfiguration {
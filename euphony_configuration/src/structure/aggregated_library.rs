@@ -1,19 +1,354 @@
-use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     paths::PathsConfiguration,
     traits::ResolvableWithPathsConfiguration,
+    utilities::expand_environment_variables,
 };
 
-#[derive(Clone)]
+#[derive(Serialize, Clone)]
 pub struct AggregatedLibraryConfiguration {
     pub path: String,
 
     pub transcode_threads: usize,
 
+    /// How many albums to process at the same time (each with its own pool of `transcode_threads`).
+    pub transcode_album_concurrency: usize,
+
+    /// How many artists of a library to scan for changes at the same time before transcoding.
+    pub scan_threads: usize,
+
+    /// If set, deletions in the transcoded library run as a separate phase (before transcoding
+    /// and copying) with this many threads. Otherwise, they share the `transcode_threads` pool.
+    pub delete_threads: Option<usize>,
+
+    /// If set, album state files are buffered while a library is being processed and written
+    /// with this many concurrent writers once all of its albums are done (before the library state).
+    /// Otherwise, they are written right after each album.
+    pub state_write_concurrency: Option<usize>,
+
+    /// Whether to transcode into a staging directory next to `path` and only swap it into place
+    /// once the entire run succeeds.
+    pub staging_mode: bool,
+
+    /// How many times to retry transcoding or copying a file that failed (0 means no retries).
     pub failure_max_retries: u16,
 
+    /// How many seconds to wait before each retry.
     pub failure_delay_seconds: u16,
+
+    /// Whether to record the title, artist and album tags of source audio files
+    /// and re-transcode files whose tags changed since the last transcode.
+    pub retranscode_on_tag_change: bool,
+
+    /// Whether to log a warning when an album's processed files
+    /// end up larger than their sources.
+    pub warn_on_size_increase: bool,
+
+    /// Command (binary followed by its arguments) to run after each successfully-transcoded album.
+    pub post_album_command: Option<Vec<String>>,
+
+    /// Whether a failing `post_album_command` should stop the transcode.
+    pub post_album_command_failure_is_fatal: bool,
+
+    /// Whether to check, after a transcode, that every tracked source audio file
+    /// has a corresponding file in the transcoded library.
+    pub verify_after_run: bool,
+
+    /// How to decide, based on modification times, that an album hasn't changed
+    /// and doesn't need a full scan.
+    pub scan_skip_strategy: ScanSkipStrategy,
+
+    /// What to do with broken symbolic links in source album directories.
+    pub broken_symlink_handling: BrokenSymlinkHandling,
+
+    /// Whether to write a human-readable `TRANSCODE_INFO.txt` into each transcoded album directory.
+    pub write_transcode_info: bool,
+
+    /// Whether to collapse runs of whitespace (and trim leading and trailing whitespace)
+    /// in artist and album directory names when matching and mapping them into the transcoded library.
+    pub normalize_whitespace: bool,
+
+    /// Whether to write an `.m3u8` playlist of the transcoded audio files into each transcoded album directory.
+    pub generate_playlists: bool,
+
+    /// Whether copied data files (e.g. cover art) should keep the modification and access times
+    /// of their source files instead of getting the time of the copy.
+    pub preserve_timestamps: bool,
+
+    /// Size of the buffer (in KiB) used when copying data files into the aggregated library.
+    pub copy_buffer_kib: usize,
+
+    /// Whether to flush each copied data file to disk (fsync) before considering the copy finished.
+    pub fsync_after_copy: bool,
+
+    /// How data files are put into the transcoded library (copied, hard-linked or symlinked).
+    pub data_file_link_mode: DataFileLinkMode,
+
+    /// If set, cover art images (see `cover_art_file_names`) are downscaled with ffmpeg so that neither
+    /// of their dimensions exceeds this many pixels (and re-encoded) instead of simply being copied.
+    pub cover_art_max_dimension: Option<u32>,
+
+    /// File names of data files that are considered cover art (see `is_cover_art_file`).
+    /// Matched case-insensitively against the file name only.
+    pub cover_art_file_names: Vec<String>,
+
+    /// How file names in transcoded album directories are sanitized (see `sanitized_file_path`).
+    pub filename_sanitization: FilenameSanitization,
+
+    /// Glob patterns of source files that are never tracked (and thus never transcoded or copied),
+    /// even if their extension is tracked. Compiled into `ignored_file_glob_set`.
+    pub ignored_file_globs: Vec<String>,
+
+    /// Compiled version of `ignored_file_globs`.
+    #[serde(skip)]
+    pub ignored_file_glob_set: GlobSet,
+
+    /// Whether to read album tags (year, album artist, genre) from the first audio file of each album
+    /// (used for the {year} placeholder of `album_directory_template` and by `stats`).
+    pub read_tags: bool,
+
+    /// If set, the name of each album directory (inside its artist directory) in the transcoded library
+    /// is built from this template instead of simply being the album title.
+    /// The placeholders {artist}, {album} and {year} are available (see `album_directory_name`).
+    pub album_directory_template: Option<String>,
+}
+
+impl AggregatedLibraryConfiguration {
+    /// Returns `true` if the given source file (path relative to its album directory)
+    /// matches any of the `ignored_file_globs`. Patterns are matched against both
+    /// the file name and the relative path.
+    pub fn is_ignored_file<P: AsRef<Path>>(
+        &self,
+        relative_file_path: P,
+    ) -> bool {
+        let relative_file_path = relative_file_path.as_ref();
+
+        if self.ignored_file_glob_set.is_empty() {
+            return false;
+        }
+
+        relative_file_path.file_name().is_some_and(|file_name| {
+            self.ignored_file_glob_set.is_match(file_name)
+        }) || self.ignored_file_glob_set.is_match(relative_file_path)
+    }
+
+    /// Returns `true` if the given data file should be processed as cover art, i.e. if
+    /// `cover_art_max_dimension` is set and the file's name (case-insensitively) is one of `cover_art_file_names`.
+    pub fn is_cover_art_file<P: AsRef<Path>>(&self, file_path: P) -> bool {
+        if self.cover_art_max_dimension.is_none() {
+            return false;
+        }
+
+        let Some(file_name) = file_path.as_ref().file_name() else {
+            return false;
+        };
+        let file_name = file_name.to_string_lossy();
+
+        self.cover_art_file_names.iter().any(|cover_art_file_name| {
+            cover_art_file_name.eq_ignore_ascii_case(&file_name)
+        })
+    }
+
+    /// Returns the given file path (relative to the album directory) with each of its components
+    /// sanitized according to `filename_sanitization` (see `FilenameSanitization::sanitize_file_name`).
+    /// Without sanitization, the path is returned unchanged.
+    pub fn sanitized_file_path<P: AsRef<Path>>(
+        &self,
+        relative_file_path: P,
+    ) -> PathBuf {
+        let relative_file_path = relative_file_path.as_ref();
+
+        if self.filename_sanitization == FilenameSanitization::None {
+            return relative_file_path.to_path_buf();
+        }
+
+        relative_file_path
+            .components()
+            .map(|component| match component {
+                Component::Normal(name) => PathBuf::from(
+                    self.filename_sanitization
+                        .sanitize_file_name(&name.to_string_lossy()),
+                ),
+                other => PathBuf::from(other.as_os_str()),
+            })
+            .collect()
+    }
+
+    /// Returns the name of the album's directory inside its artist directory in the transcoded library.
+    ///
+    /// Without `album_directory_template`, this is simply the (normalized, see `normalized_directory_name`)
+    /// album title. With it, the placeholders in the template are replaced with the artist name,
+    /// album title and `year`. Albums without a year fall back to the plain album title
+    /// if the template uses `{year}`.
+    pub fn album_directory_name(
+        &self,
+        artist_name: &str,
+        album_title: &str,
+        year: Option<u16>,
+    ) -> String {
+        let Some(template) = &self.album_directory_template else {
+            return self.normalized_directory_name(album_title);
+        };
+
+        let directory_name = match year {
+            Some(year) => template.replace("{year}", &year.to_string()),
+            None if template.contains("{year}") => {
+                return self.normalized_directory_name(album_title);
+            }
+            None => template.clone(),
+        };
+
+        self.normalized_directory_name(
+            &directory_name
+                .replace("{artist}", artist_name)
+                .replace("{album}", album_title),
+        )
+    }
+
+    /// Returns the artist or album directory name as it should be matched and used
+    /// in the transcoded library. If `normalize_whitespace` is enabled, each path component
+    /// has its whitespace trimmed and internal runs of whitespace collapsed into a single space.
+    /// Otherwise, the name is returned unchanged.
+    pub fn normalized_directory_name(&self, name: &str) -> String {
+        if !self.normalize_whitespace {
+            return name.to_string();
+        }
+
+        name.split(std::path::is_separator)
+            .map(|component| {
+                component
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>()
+            .join(std::path::MAIN_SEPARATOR_STR)
+    }
+}
+
+/// Heuristic used to skip scanning albums that have not changed since their last transcode.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanSkipStrategy {
+    /// Always fully scan every album.
+    #[default]
+    None,
+
+    /// Skip albums whose directory modification time hasn't changed. This is fast, but some
+    /// filesystems only update it when files are added or removed, missing in-place content edits.
+    DirectoryMtime,
+
+    /// Skip albums whose directory modification time and newest file modification time
+    /// both haven't changed.
+    DirectoryAndNewestFileMtime,
+}
+
+/// How data files (e.g. cover art) are put into the transcoded library.
+/// Linking falls back to copying if the link can't be created (e.g. across filesystems).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DataFileLinkMode {
+    /// Copy the file.
+    #[default]
+    Copy,
+
+    /// Create a hard link to the source file (only possible on the same filesystem).
+    Hardlink,
+
+    /// Create a symbolic link pointing to the (absolute) source file path.
+    Symlink,
+}
+
+/// How file names in transcoded album directories are sanitized for filesystems
+/// that don't allow some characters (see `AggregatedLibraryConfiguration::sanitized_file_path`).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilenameSanitization {
+    /// File names are kept as they are.
+    #[default]
+    None,
+
+    /// Same as `Ntfs`, but characters outside the Basic Multilingual Plane (e.g. emoji) are also replaced,
+    /// as many FAT32 devices store long file names as UCS-2.
+    Fat32,
+
+    /// Characters Windows doesn't allow in file names (`<>:"\|?*` and control characters) as well as
+    /// trailing dots and spaces are replaced with an underscore, and reserved device names
+    /// (e.g. `CON` or `NUL.mp3`) are prefixed with one.
+    Ntfs,
+}
+
+/// File names (without extension, case-insensitive) that Windows reserves for devices.
+const RESERVED_DEVICE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6",
+    "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6",
+    "LPT7", "LPT8", "LPT9",
+];
+
+impl FilenameSanitization {
+    /// Sanitize a single file (or directory) name according to this policy.
+    ///
+    /// The result only depends on the given name, so it is always the same for the same name,
+    /// but different names can end up the same (e.g. `a?.mp3` and `a*.mp3` both become `a_.mp3`).
+    pub fn sanitize_file_name(&self, file_name: &str) -> String {
+        if *self == Self::None {
+            return file_name.to_string();
+        }
+
+        let mut sanitized_file_name: String = file_name
+            .chars()
+            .map(|character| {
+                let is_forbidden = character.is_control()
+                    || matches!(
+                        character,
+                        '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*'
+                    )
+                    || (*self == Self::Fat32 && character as u32 > 0xFFFF);
+
+                if is_forbidden {
+                    '_'
+                } else {
+                    character
+                }
+            })
+            .collect();
+
+        // Trailing dots and spaces are ASCII, so byte and character counts are the same.
+        let trimmed_length =
+            sanitized_file_name.trim_end_matches(['.', ' ']).len();
+        let num_trailing_characters = sanitized_file_name.len() - trimmed_length;
+        if num_trailing_characters > 0 {
+            sanitized_file_name.truncate(trimmed_length);
+            sanitized_file_name.push_str(&"_".repeat(num_trailing_characters));
+        }
+
+        let stem = sanitized_file_name.split('.').next().unwrap_or_default();
+        if RESERVED_DEVICE_NAMES
+            .iter()
+            .any(|reserved_name| reserved_name.eq_ignore_ascii_case(stem))
+        {
+            sanitized_file_name.insert(0, '_');
+        }
+
+        sanitized_file_name
+    }
+}
+
+/// How to handle broken symbolic links (e.g. pointing to an unmounted drive) in source album directories.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BrokenSymlinkHandling {
+    /// Skip the file and log a warning. The file is treated as if it were unchanged,
+    /// so its transcoded counterpart is not removed.
+    #[default]
+    SkipWithWarning,
+
+    /// Stop scanning with an error.
+    Fail,
 }
 
 #[derive(Deserialize, Clone)]
@@ -22,9 +357,126 @@ pub(crate) struct UnresolvedAggregatedLibraryConfiguration {
 
     transcode_threads: usize,
 
+    #[serde(default = "default_transcode_album_concurrency")]
+    transcode_album_concurrency: usize,
+
+    #[serde(default = "default_scan_threads")]
+    scan_threads: usize,
+
+    #[serde(default)]
+    delete_threads: Option<usize>,
+
+    #[serde(default)]
+    state_write_concurrency: Option<usize>,
+
+    #[serde(default)]
+    staging_mode: bool,
+
+    #[serde(default)]
     failure_max_retries: u16,
 
+    #[serde(default)]
     failure_delay_seconds: u16,
+
+    #[serde(default)]
+    retranscode_on_tag_change: bool,
+
+    #[serde(default = "default_warn_on_size_increase")]
+    warn_on_size_increase: bool,
+
+    #[serde(default)]
+    post_album_command: Option<Vec<String>>,
+
+    #[serde(default)]
+    post_album_command_failure_is_fatal: bool,
+
+    #[serde(default)]
+    verify_after_run: bool,
+
+    #[serde(default)]
+    scan_skip_strategy: ScanSkipStrategy,
+
+    #[serde(default)]
+    broken_symlink_handling: BrokenSymlinkHandling,
+
+    #[serde(default)]
+    write_transcode_info: bool,
+
+    #[serde(default)]
+    normalize_whitespace: bool,
+
+    #[serde(default)]
+    generate_playlists: bool,
+
+    #[serde(default)]
+    preserve_timestamps: bool,
+
+    #[serde(default = "default_copy_buffer_kib")]
+    copy_buffer_kib: usize,
+
+    #[serde(default)]
+    fsync_after_copy: bool,
+
+    #[serde(default)]
+    data_file_link_mode: DataFileLinkMode,
+
+    #[serde(default)]
+    cover_art_max_dimension: Option<u32>,
+
+    #[serde(default = "default_cover_art_file_names")]
+    cover_art_file_names: Vec<String>,
+
+    #[serde(default)]
+    filename_sanitization: FilenameSanitization,
+
+    #[serde(default)]
+    ignored_file_globs: Vec<String>,
+
+    #[serde(default)]
+    read_tags: bool,
+
+    #[serde(default)]
+    album_directory_template: Option<String>,
+}
+
+const DEFAULT_TRANSCODE_ALBUM_CONCURRENCY: usize = 1;
+
+fn default_transcode_album_concurrency() -> usize {
+    DEFAULT_TRANSCODE_ALBUM_CONCURRENCY
+}
+
+const DEFAULT_SCAN_THREADS: usize = 1;
+
+fn default_scan_threads() -> usize {
+    DEFAULT_SCAN_THREADS
+}
+
+const DEFAULT_WARN_ON_SIZE_INCREASE: bool = true;
+
+fn default_warn_on_size_increase() -> bool {
+    DEFAULT_WARN_ON_SIZE_INCREASE
+}
+
+const DEFAULT_COPY_BUFFER_KIB: usize = 1024;
+
+fn default_copy_buffer_kib() -> usize {
+    DEFAULT_COPY_BUFFER_KIB
+}
+
+const DEFAULT_COVER_ART_FILE_NAMES: [&str; 6] = [
+    "cover.jpg",
+    "cover.jpeg",
+    "cover.png",
+    "folder.jpg",
+    "folder.jpeg",
+    "folder.png",
+];
+
+fn default_cover_art_file_names() -> Vec<String> {
+    DEFAULT_COVER_ART_FILE_NAMES
+        .iter()
+        .map(|file_name| file_name.to_string())
+        .collect()
 }
 
 impl ResolvableWithPathsConfiguration
@@ -36,20 +488,186 @@ impl ResolvableWithPathsConfiguration
         self,
         paths: &PathsConfiguration,
     ) -> miette::Result<Self::Resolved> {
-        let path = self
-            .path
-            .replace("{LIBRARY_BASE}", &paths.base_library_path);
+        let path =
+            expand_environment_variables(&self.path, "aggregated_library.path")?
+                .replace("{LIBRARY_BASE}", &paths.base_library_path);
 
         if self.transcode_threads == 0 {
             panic!("transcode_threads is set to 0! The minimum value is 1.");
         }
 
+        if self.transcode_album_concurrency == 0 {
+            panic!("transcode_album_concurrency is set to 0! The minimum value is 1.");
+        }
+
+        if self.scan_threads == 0 {
+            panic!("scan_threads is set to 0! The minimum value is 1.");
+        }
+
+        if self.delete_threads == Some(0) {
+            panic!("delete_threads is set to 0! The minimum value is 1.");
+        }
+
+        if self.state_write_concurrency == Some(0) {
+            panic!(
+                "state_write_concurrency is set to 0! The minimum value is 1."
+            );
+        }
+
+        if self.copy_buffer_kib == 0 {
+            panic!("copy_buffer_kib is set to 0! The minimum value is 1.");
+        }
+
+        if self.cover_art_max_dimension == Some(0) {
+            panic!(
+                "cover_art_max_dimension is set to 0! The minimum value is 1."
+            );
+        }
+
+        let mut ignored_file_glob_set_builder = GlobSetBuilder::new();
+        for pattern in &self.ignored_file_globs {
+            let glob = Glob::new(pattern).unwrap_or_else(|error| {
+                panic!("ignored_file_globs pattern \"{pattern}\" is not a valid glob: {error}")
+            });
+
+            ignored_file_glob_set_builder.add(glob);
+        }
+
+        let ignored_file_glob_set = ignored_file_glob_set_builder
+            .build()
+            .unwrap_or_else(|error| {
+                panic!("Could not compile ignored_file_globs: {error}")
+            });
+
+        if let Some(template) = &self.album_directory_template {
+            if !template.contains("{album}") {
+                panic!("album_directory_template \"{template}\" is missing the {{album}} placeholder!");
+            }
+
+            if Path::new(template).is_absolute()
+                || Path::new(template)
+                    .components()
+                    .any(|component| component == Component::ParentDir)
+            {
+                panic!("album_directory_template \"{template}\" must be a relative path without \"..\"!");
+            }
+        }
+
+        let post_album_command = self.post_album_command.map(|command| {
+            if command.is_empty() {
+                panic!("post_album_command is set to an empty list! Either remove it or specify a binary.");
+            }
+
+            command
+                .into_iter()
+                .map(|part| {
+                    part.replace("{TOOLS_BASE}", &paths.base_tools_path)
+                        .replace("{LIBRARY_BASE}", &paths.base_library_path)
+                })
+                .collect()
+        });
+
 
         Ok(AggregatedLibraryConfiguration {
             path,
             transcode_threads: self.transcode_threads,
+            transcode_album_concurrency: self.transcode_album_concurrency,
+            scan_threads: self.scan_threads,
+            delete_threads: self.delete_threads,
+            state_write_concurrency: self.state_write_concurrency,
+            staging_mode: self.staging_mode,
             failure_max_retries: self.failure_max_retries,
             failure_delay_seconds: self.failure_delay_seconds,
+            retranscode_on_tag_change: self.retranscode_on_tag_change,
+            warn_on_size_increase: self.warn_on_size_increase,
+            post_album_command,
+            post_album_command_failure_is_fatal: self
+                .post_album_command_failure_is_fatal,
+            verify_after_run: self.verify_after_run,
+            scan_skip_strategy: self.scan_skip_strategy,
+            broken_symlink_handling: self.broken_symlink_handling,
+            write_transcode_info: self.write_transcode_info,
+            normalize_whitespace: self.normalize_whitespace,
+            generate_playlists: self.generate_playlists,
+            preserve_timestamps: self.preserve_timestamps,
+            copy_buffer_kib: self.copy_buffer_kib,
+            fsync_after_copy: self.fsync_after_copy,
+            data_file_link_mode: self.data_file_link_mode,
+            cover_art_max_dimension: self.cover_art_max_dimension,
+            cover_art_file_names: self.cover_art_file_names,
+            filename_sanitization: self.filename_sanitization,
+            ignored_file_globs: self.ignored_file_globs,
+            ignored_file_glob_set,
+            read_tags: self.read_tags,
+            album_directory_template: self.album_directory_template,
         })
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AggregatedLibraryConfiguration,
+        UnresolvedAggregatedLibraryConfiguration,
+    };
+    use crate::paths::PathsConfiguration;
+    use crate::traits::ResolvableWithPathsConfiguration;
+
+    fn aggregated_library_configuration(
+        normalize_whitespace: bool,
+    ) -> AggregatedLibraryConfiguration {
+        let unresolved_configuration: UnresolvedAggregatedLibraryConfiguration =
+            toml::from_str(&format!(
+                "path = \"/music/transcoded\"\n\
+                transcode_threads = 1\n\
+                normalize_whitespace = {normalize_whitespace}"
+            ))
+            .unwrap();
+
+        unresolved_configuration
+            .resolve(&PathsConfiguration {
+                base_library_path: "/music".to_string(),
+                base_tools_path: "/tools".to_string(),
+                data_directory_path: "/data".into(),
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn normalized_directory_name_collapses_internal_whitespace() {
+        let configuration = aggregated_library_configuration(true);
+
+        assert_eq!(
+            configuration.normalized_directory_name("Some  Album \t Title"),
+            "Some Album Title"
+        );
+    }
+
+    #[test]
+    fn normalized_directory_name_trims_whitespace() {
+        let configuration = aggregated_library_configuration(true);
+
+        assert_eq!(
+            configuration.normalized_directory_name(" Artist  "),
+            "Artist"
+        );
+        assert_eq!(
+            configuration.normalized_directory_name(&format!(
+                "Album {}CD 1 ",
+                std::path::MAIN_SEPARATOR
+            )),
+            format!("Album{}CD 1", std::path::MAIN_SEPARATOR)
+        );
+    }
+
+    #[test]
+    fn normalized_directory_name_is_unchanged_when_disabled() {
+        let configuration = aggregated_library_configuration(false);
+
+        assert_eq!(
+            configuration.normalized_directory_name("Some  Album "),
+            "Some  Album "
+        );
+    }
+}
@@ -1,16 +1,19 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use miette::Result;
-use serde::Deserialize;
+use regex::Regex;
+use serde::{Deserialize, Serialize, Serializer};
 
 use crate::{
     filesystem::get_path_extension_or_empty,
     paths::PathsConfiguration,
+    tools::validate_audio_transcoding_args,
     traits::{ResolvableConfiguration, ResolvableWithPathsConfiguration},
+    utilities::expand_environment_variables,
 };
 
 
-#[derive(Clone)]
+#[derive(Serialize, Clone)]
 pub struct LibraryConfiguration {
     /// Library display name.
     pub name: String,
@@ -19,8 +22,19 @@ pub struct LibraryConfiguration {
     /// which will be dynamically replaced with `essentials.base_library_path` on load).
     pub path: String,
 
+    /// If set, this library's transcoded output goes into this directory instead of
+    /// `aggregated_library.path` (can include {LIBRARY_BASE} as well).
+    pub output_path: Option<String>,
+
     pub ignored_directories_in_base_directory: Option<Vec<String>>,
 
+    /// Whether the library is allowed to be temporarily unavailable (e.g. on an unmounted external drive).
+    /// If `true` and the library path doesn't exist, the library is skipped instead of causing an error.
+    pub optional: bool,
+
+    /// How album (and artist) directories are detected in this library.
+    pub album_detection: AlbumDetection,
+
     /// Validation-related configuration for this library.
     pub validation: LibraryValidationConfiguration,
 
@@ -28,14 +42,40 @@ pub struct LibraryConfiguration {
     pub transcoding: LibraryTranscodingConfiguration,
 }
 
+/// How album directories are found in a library.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlbumDetection {
+    /// Strict `<library>/<artist>/<album>` structure: every directory in the library root
+    /// is an artist and every directory inside an artist directory is an album.
+    #[default]
+    ArtistAlbum,
+
+    /// Any directory (at most `MAX_AUTO_DETECTED_ALBUM_DEPTH` levels deep) that directly contains
+    /// audio files is an album, and its parent directory (relative to the library root) is its artist.
+    AutoDetect,
+}
+
+/// Maximum depth (relative to the library root) at which `AlbumDetection::AutoDetect` looks for albums.
+pub const MAX_AUTO_DETECTED_ALBUM_DEPTH: usize = 8;
+
 #[derive(Deserialize, Clone)]
 pub(crate) struct UnresolvedLibraryConfiguration {
     name: String,
 
     path: String,
 
+    #[serde(default)]
+    output_path: Option<String>,
+
     ignored_directories_in_base_directory: Option<Vec<String>>,
 
+    #[serde(default)]
+    optional: bool,
+
+    #[serde(default)]
+    album_detection: AlbumDetection,
+
     validation: UnresolvedLibraryValidationConfiguration,
 
     transcoding: UnresolvedLibraryTranscodingConfiguration,
@@ -48,34 +88,46 @@ impl ResolvableWithPathsConfiguration for UnresolvedLibraryConfiguration {
         self,
         paths: &PathsConfiguration,
     ) -> miette::Result<Self::Resolved> {
-        let parsed_path = self
-            .path
-            .replace("{LIBRARY_BASE}", &paths.base_library_path);
-
-        let canonicalized_path = dunce::canonicalize(parsed_path)
-            .unwrap_or_else(|_| {
-                panic!(
-                    "Library \"{}\" could not be found at path \"{}\"!",
-                    self.name, self.path,
+        let parsed_path = expand_environment_variables(
+            &self.path,
+            &format!("the path of library \"{}\"", self.name),
+        )?
+        .replace("{LIBRARY_BASE}", &paths.base_library_path);
+
+        // Missing and non-directory paths are reported (all at once) by `Configuration::validate_paths`.
+        // Optional libraries may be temporarily unavailable, in which case they are
+        // skipped at scan time (see `LibraryView::from_library_configuration`).
+        let path = match dunce::canonicalize(&parsed_path) {
+            Ok(canonicalized_path) => {
+                canonicalized_path.to_string_lossy().to_string()
+            }
+            Err(_) => parsed_path,
+        };
+
+
+        let output_path = self
+            .output_path
+            .map(|output_path| {
+                expand_environment_variables(
+                    &output_path,
+                    &format!("the output_path of library \"{}\"", self.name),
                 )
-            });
-
-        if !canonicalized_path.is_dir() {
-            panic!(
-                "Library \"{}\" has path set to \"{}\", but this path is not a directory!",
-                self.name,
-                self.path,
-            );
-        }
-
-        let path = canonicalized_path.to_string_lossy().to_string();
+                .map(|output_path| {
+                    output_path
+                        .replace("{LIBRARY_BASE}", &paths.base_library_path)
+                })
+            })
+            .transpose()?;
 
 
         Ok(LibraryConfiguration {
             name: self.name,
             path,
+            output_path,
             ignored_directories_in_base_directory: self
                 .ignored_directories_in_base_directory,
+            optional: self.optional,
+            album_detection: self.album_detection,
             validation: self.validation.resolve()?,
             transcoding: self.transcoding.resolve()?,
         })
@@ -84,7 +136,7 @@ impl ResolvableWithPathsConfiguration for UnresolvedLibraryConfiguration {
 
 
 
-#[derive(Clone)]
+#[derive(Serialize, Clone)]
 pub struct LibraryValidationConfiguration {
     /// A list of allowed audio extensions. Any not specified here are forbidden
     /// (flagged when running validation), see configuration template for more information.
@@ -93,6 +145,27 @@ pub struct LibraryValidationConfiguration {
     pub allowed_other_file_extensions: Vec<String>,
 
     pub allowed_other_files_by_name: Vec<String>,
+
+    /// Categories of unexpected files that are reported as warnings instead of errors
+    /// (warnings don't cause `validate` to fail).
+    pub warning_categories: Vec<UnexpectedFileCategory>,
+}
+
+/// Where an unexpected file was found during validation (and what kind of file it was).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnexpectedFileCategory {
+    /// A disallowed file directly in the library directory.
+    LibraryRoot,
+
+    /// A disallowed file in an artist directory.
+    ArtistDirectory,
+
+    /// An audio file with an extension not in `allowed_audio_file_extensions` in an album directory.
+    AlbumDirectoryAudio,
+
+    /// A disallowed non-audio file in an album directory.
+    AlbumDirectoryOther,
 }
 
 #[derive(Deserialize, Clone)]
@@ -102,6 +175,9 @@ pub(crate) struct UnresolvedLibraryValidationConfiguration {
     allowed_other_file_extensions: Vec<String>,
 
     allowed_other_files_by_name: Vec<String>,
+
+    #[serde(default)]
+    warning_categories: Vec<UnexpectedFileCategory>,
 }
 
 impl ResolvableConfiguration for UnresolvedLibraryValidationConfiguration {
@@ -125,13 +201,14 @@ impl ResolvableConfiguration for UnresolvedLibraryValidationConfiguration {
             allowed_audio_file_extensions,
             allowed_other_file_extensions,
             allowed_other_files_by_name: self.allowed_other_files_by_name,
+            warning_categories: self.warning_categories,
         })
     }
 }
 
 
 
-#[derive(Clone)]
+#[derive(Serialize, Clone)]
 pub struct LibraryTranscodingConfiguration {
     /// A list of audio file extensions (e.g. "mp3", "flac" - don't include ".").
     /// Files with these extensions are considered audio files and are transcoded using ffmpeg
@@ -144,6 +221,78 @@ pub struct LibraryTranscodingConfiguration {
 
     /// Dynamically contains extensions from both `audio_file_extensions` and `other_file_extensions`.
     pub all_tracked_extensions: Vec<String>,
+
+    /// If set, transcoded audio files are renamed to `<track number> <title>.<extension>`.
+    /// The track number and title are parsed from the source file name (without extension)
+    /// using this pattern's `track` and (optional) `title` capture groups.
+    #[serde(serialize_with = "serialize_optional_regex")]
+    pub rename_audio_files_pattern: Option<Regex>,
+
+    /// How to decide whether a source file has changed since the last transcode.
+    pub change_detection: ChangeDetection,
+
+    /// Which file attributes are compared when detecting changes (never empty).
+    /// With `ChangeDetection::Hash`, times are ignored regardless.
+    pub change_detection_fields: Vec<ChangeDetectionField>,
+
+    /// If set, albums of this library are processed with this many threads
+    /// instead of `aggregated_library.transcode_threads`.
+    pub transcode_threads: Option<usize>,
+
+    /// If set, audio files of this library are transcoded with these ffmpeg arguments
+    /// instead of `tools.ffmpeg.audio_transcoding_args` (always set along with
+    /// `audio_transcoding_output_extension`, see `Configuration::ffmpeg_configuration_for_library`).
+    pub audio_transcoding_args: Option<Vec<String>>,
+
+    /// If set, the extension of this library's audio files after transcoding
+    /// (instead of `tools.ffmpeg.audio_transcoding_output_extension`).
+    pub audio_transcoding_output_extension: Option<String>,
+}
+
+/// Strategy used to detect changes in source files between transcodes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeDetection {
+    /// Compare file sizes and creation/modification times.
+    #[default]
+    Mtime,
+
+    /// Compare file sizes and content hashes, ignoring times. Slower (every tracked file
+    /// is read when scanning), but survives restoring from backups or syncing across machines.
+    Hash,
+}
+
+/// A file attribute that can be compared when detecting changes
+/// (see `LibraryTranscodingConfiguration::change_detection_fields`).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeDetectionField {
+    /// File size in bytes.
+    Size,
+
+    /// File creation time (unreliable on some filesystems and after copying files).
+    Created,
+
+    /// File modification time.
+    Modified,
+}
+
+pub const DEFAULT_CHANGE_DETECTION_FIELDS: [ChangeDetectionField; 3] = [
+    ChangeDetectionField::Size,
+    ChangeDetectionField::Created,
+    ChangeDetectionField::Modified,
+];
+
+fn default_change_detection_fields() -> Vec<ChangeDetectionField> {
+    DEFAULT_CHANGE_DETECTION_FIELDS.to_vec()
+}
+
+/// Serializes `rename_audio_files_pattern` as its source pattern string.
+fn serialize_optional_regex<S: Serializer>(
+    regex: &Option<Regex>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    regex.as_ref().map(Regex::as_str).serialize(serializer)
 }
 
 impl LibraryTranscodingConfiguration {
@@ -168,18 +317,111 @@ impl LibraryTranscodingConfiguration {
 
         Ok(self.other_file_extensions.contains(&extension))
     }
+
+    /// Returns the normalized file name (without extension) for the given audio file name
+    /// (also without extension), if renaming is enabled (see `rename_audio_files_pattern`)
+    /// and the file name could be parsed. The normalized file name is `<track number> <title>`
+    /// (or just `<track number>` if no title was parsed), with the track number zero-padded to two digits.
+    pub fn renamed_audio_file_stem(&self, file_stem: &str) -> Option<String> {
+        let captures = self
+            .rename_audio_files_pattern
+            .as_ref()?
+            .captures(file_stem)?;
+
+        let track_number: u32 = captures.name("track")?.as_str().parse().ok()?;
+        let title = captures
+            .name("title")
+            .map(|title| title.as_str().trim())
+            .unwrap_or_default();
+
+        if title.is_empty() {
+            Some(format!("{track_number:02}"))
+        } else {
+            Some(format!("{track_number:02} {title}"))
+        }
+    }
+
+    /// Given an audio file path (relative to the source album directory), return its
+    /// path relative to the transcoded album directory: the extension is swapped
+    /// for `output_extension` and, if enabled, the file is renamed (see `renamed_audio_file_stem`).
+    ///
+    /// This does not account for collisions between renamed files
+    /// (see `AlbumSourceFileList::map_source_file_paths_to_transcoded_file_paths_relative`).
+    pub fn transcoded_audio_file_path<P: AsRef<Path>>(
+        &self,
+        source_audio_file_path: P,
+        output_extension: &str,
+    ) -> PathBuf {
+        let source_audio_file_path = source_audio_file_path.as_ref();
+
+        let renamed_file_stem = source_audio_file_path
+            .file_stem()
+            .and_then(|file_stem| file_stem.to_str())
+            .and_then(|file_stem| self.renamed_audio_file_stem(file_stem));
+
+        match renamed_file_stem {
+            // (not using `with_extension` here, since the title might contain a dot)
+            Some(renamed_file_stem) => source_audio_file_path.with_file_name(
+                format!("{renamed_file_stem}.{output_extension}"),
+            ),
+            None => source_audio_file_path.with_extension(output_extension),
+        }
+    }
 }
 
 #[derive(Deserialize, Clone)]
 pub(crate) struct UnresolvedLibraryTranscodingConfiguration {
     audio_file_extensions: Vec<String>,
     other_file_extensions: Vec<String>,
+
+    #[serde(default)]
+    rename_audio_files_pattern: Option<String>,
+
+    #[serde(default)]
+    change_detection: ChangeDetection,
+
+    #[serde(default = "default_change_detection_fields")]
+    change_detection_fields: Vec<ChangeDetectionField>,
+
+    #[serde(default)]
+    transcode_threads: Option<usize>,
+
+    #[serde(default)]
+    audio_transcoding_args: Option<Vec<String>>,
+
+    #[serde(default)]
+    audio_transcoding_output_extension: Option<String>,
 }
 
 impl ResolvableConfiguration for UnresolvedLibraryTranscodingConfiguration {
     type Resolved = LibraryTranscodingConfiguration;
 
     fn resolve(self) -> miette::Result<Self::Resolved> {
+        if self.transcode_threads == Some(0) {
+            panic!("transcode_threads is set to 0! The minimum value is 1.");
+        }
+
+        if self.change_detection_fields.is_empty() {
+            panic!("change_detection_fields is empty! Set at least one of \"size\", \"created\" or \"modified\".");
+        }
+
+        if self.audio_transcoding_args.is_some()
+            != self.audio_transcoding_output_extension.is_some()
+        {
+            panic!("Only one of audio_transcoding_args and audio_transcoding_output_extension is set! Either set both or neither.");
+        }
+
+        if let Some(audio_transcoding_args) = &self.audio_transcoding_args {
+            validate_audio_transcoding_args(
+                audio_transcoding_args,
+                "transcoding.audio_transcoding_args",
+            )?;
+        }
+
+        let audio_transcoding_output_extension = self
+            .audio_transcoding_output_extension
+            .map(|extension| extension.to_ascii_lowercase());
+
         let audio_file_extensions: Vec<String> = self
             .audio_file_extensions
             .into_iter()
@@ -192,17 +434,137 @@ impl ResolvableConfiguration for UnresolvedLibraryTranscodingConfiguration {
             .map(|extention| extention.to_ascii_lowercase())
             .collect();
 
+        // Whether a file is transcoded or copied must be unambiguous (within a library - different libraries
+        // may treat the same extension differently, e.g. transcode `wav` in one and copy it in another).
+        if let Some(ambiguous_extension) = audio_file_extensions
+            .iter()
+            .find(|extension| other_file_extensions.contains(extension))
+        {
+            panic!(
+                "\"{ambiguous_extension}\" is listed in both audio_file_extensions and other_file_extensions! \
+                Each extension can either be transcoded or copied, not both."
+            );
+        }
+
         let mut all_tracked_extensions = Vec::with_capacity(
             audio_file_extensions.len() + other_file_extensions.len(),
         );
         all_tracked_extensions.extend(audio_file_extensions.iter().cloned());
         all_tracked_extensions.extend(other_file_extensions.iter().cloned());
 
+        let rename_audio_files_pattern = self.rename_audio_files_pattern.map(|pattern| {
+            let compiled_pattern = Regex::new(&pattern).unwrap_or_else(|error| {
+                panic!("rename_audio_files_pattern \"{pattern}\" is not a valid regular expression: {error}")
+            });
+
+            if !compiled_pattern
+                .capture_names()
+                .any(|name| name == Some("track"))
+            {
+                panic!("rename_audio_files_pattern \"{pattern}\" is missing the \"track\" capture group!");
+            }
+
+            compiled_pattern
+        });
+
 
         Ok(LibraryTranscodingConfiguration {
             audio_file_extensions,
             other_file_extensions,
             all_tracked_extensions,
+            rename_audio_files_pattern,
+            change_detection: self.change_detection,
+            change_detection_fields: self.change_detection_fields,
+            transcode_threads: self.transcode_threads,
+            audio_transcoding_args: self.audio_transcoding_args,
+            audio_transcoding_output_extension,
         })
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::{LibraryConfiguration, UnresolvedLibraryConfiguration};
+    use crate::paths::PathsConfiguration;
+    use crate::traits::ResolvableWithPathsConfiguration;
+
+    fn library_configuration(
+        name: &str,
+        audio_file_extensions: &str,
+        other_file_extensions: &str,
+    ) -> LibraryConfiguration {
+        let unresolved_configuration: UnresolvedLibraryConfiguration =
+            toml::from_str(&format!(
+                "name = \"{name}\"\n\
+                path = \"{{LIBRARY_BASE}}/{name}\"\n\
+                [validation]\n\
+                allowed_audio_file_extensions = {audio_file_extensions}\n\
+                allowed_other_file_extensions = {other_file_extensions}\n\
+                allowed_other_files_by_name = []\n\
+                [transcoding]\n\
+                audio_file_extensions = {audio_file_extensions}\n\
+                other_file_extensions = {other_file_extensions}"
+            ))
+            .unwrap();
+
+        unresolved_configuration
+            .resolve(&PathsConfiguration {
+                base_library_path: "/music".to_string(),
+                base_tools_path: "/tools".to_string(),
+                data_directory_path: "/data".into(),
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn libraries_can_treat_the_same_extension_differently() {
+        let transcoding_library = library_configuration(
+            "Lossless",
+            "[\"flac\", \"wav\"]",
+            "[\"jpg\"]",
+        );
+        let archival_library =
+            library_configuration("Archive", "[\"flac\"]", "[\"jpg\", \"wav\"]");
+
+        let transcoding = &transcoding_library.transcoding;
+        assert!(transcoding
+            .is_path_audio_file_by_extension("Album/01 Track.wav")
+            .unwrap());
+        assert!(!transcoding
+            .is_path_data_file_by_extension("Album/01 Track.wav")
+            .unwrap());
+
+        let archival = &archival_library.transcoding;
+        assert!(!archival
+            .is_path_audio_file_by_extension("Album/01 Track.wav")
+            .unwrap());
+        assert!(archival
+            .is_path_data_file_by_extension("Album/01 Track.wav")
+            .unwrap());
+
+        // Extensions are matched case-insensitively in both libraries.
+        assert!(transcoding
+            .is_path_audio_file_by_extension("Album/01 Track.WAV")
+            .unwrap());
+        assert!(archival
+            .is_path_data_file_by_extension("Album/01 Track.WAV")
+            .unwrap());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\"wav\" is listed in both audio_file_extensions and other_file_extensions"
+    )]
+    fn extension_listed_as_both_audio_and_data_is_rejected() {
+        library_configuration("Lossless", "[\"flac\", \"wav\"]", "[\"wav\"]");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\"wav\" is listed in both audio_file_extensions and other_file_extensions"
+    )]
+    fn extension_listed_as_both_audio_and_data_in_different_case_is_rejected() {
+        library_configuration("Lossless", "[\"WAV\"]", "[\"wav\"]");
+    }
+}
@@ -1,24 +1,45 @@
 use std::path::PathBuf;
 
 use chrono::Local;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     paths::PathsConfiguration,
     traits::ResolvableWithPathsConfiguration,
-    utilities::get_running_executable_directory,
+    utilities::{
+        expand_environment_variables,
+        get_running_executable_directory,
+    },
 };
 
 
-#[derive(Clone)]
+#[derive(Serialize, Clone)]
 pub struct LoggingConfiguration {
     pub default_log_output_path: Option<PathBuf>,
+
+    /// Format of the log file (both `default_log_output_path` and `--log-to-file`).
+    pub file_format: LogFileFormat,
+}
+
+/// Format of the lines written into the log file.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFileFormat {
+    /// The same lines as printed to the terminal (without colours).
+    #[default]
+    Text,
+
+    /// One JSON object per line, with a `timestamp`, `level` (`info`, `warn` or `error`) and `message`.
+    Jsonl,
 }
 
 
 #[derive(Deserialize, Clone)]
 pub(crate) struct UnresolvedLoggingConfiguration {
     default_log_output_path: Option<PathBuf>,
+
+    #[serde(default)]
+    file_format: LogFileFormat,
 }
 
 
@@ -36,20 +57,25 @@ impl ResolvableWithPathsConfiguration for UnresolvedLoggingConfiguration {
         let time_now = Local::now();
         let formatted_time_now = time_now.format("%Y-%m-%d_%H-%M-%S");
 
-        let default_log_output_path =
-            self.default_log_output_path.as_ref().map(|output_path| {
-                let path_as_string = output_path
-                    .to_string_lossy()
-                    .to_string()
-                    .replace("{LIBRARY_BASE}", &paths.base_library_path)
-                    .replace("{SELF}", &executable_directory)
-                    .replace("{DATETIME}", &formatted_time_now.to_string());
+        let default_log_output_path = self
+            .default_log_output_path
+            .as_ref()
+            .map(|output_path| -> miette::Result<PathBuf> {
+                let path_as_string = expand_environment_variables(
+                    &output_path.to_string_lossy(),
+                    "logging.default_log_output_path",
+                )?
+                .replace("{LIBRARY_BASE}", &paths.base_library_path)
+                .replace("{SELF}", &executable_directory)
+                .replace("{DATETIME}", &formatted_time_now.to_string());
 
-                PathBuf::from(path_as_string)
-            });
+                Ok(PathBuf::from(path_as_string))
+            })
+            .transpose()?;
 
         Ok(LoggingConfiguration {
             default_log_output_path,
+            file_format: self.file_format,
         })
     }
 }
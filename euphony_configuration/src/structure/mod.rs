@@ -9,12 +9,13 @@ pub mod tools;
 pub mod ui;
 pub mod validation;
 
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use miette::{miette, Context, Result};
-use serde::Deserialize;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
 
 use crate::aggregated_library::{
     AggregatedLibraryConfiguration,
@@ -23,14 +24,22 @@ use crate::aggregated_library::{
 use crate::library::{LibraryConfiguration, UnresolvedLibraryConfiguration};
 use crate::logging::{LoggingConfiguration, UnresolvedLoggingConfiguration};
 use crate::paths::{PathsConfiguration, UnresolvedPathsConfiguration};
-use crate::tools::{ToolsConfiguration, UnresolvedToolsConfiguration};
+use crate::tools::{
+    FfmpegToolsConfiguration,
+    ToolsConfiguration,
+    UnresolvedToolsConfiguration,
+};
 use crate::traits::{
     ResolvableConfiguration,
     ResolvableWithContextConfiguration,
     ResolvableWithPathsConfiguration,
 };
 use crate::ui::{UiConfiguration, UnresolvedUiConfiguration};
-use crate::utilities::get_default_configuration_file_path;
+use crate::utilities::{
+    get_configuration_file_search_locations,
+    get_default_configuration_file_location,
+    get_default_configuration_file_path,
+};
 use crate::validation::{
     UnresolvedValidationConfiguration,
     ValidationConfiguration,
@@ -38,7 +47,7 @@ use crate::validation::{
 
 /// This struct contains the entire `euphony` configuration,
 /// from tool paths to libraries and so forth.
-#[derive(Clone)]
+#[derive(Serialize, Clone)]
 pub struct Configuration {
     pub paths: PathsConfiguration,
 
@@ -84,12 +93,24 @@ impl Configuration {
 
         // Read the configuration file into memory.
         let configuration_string = fs::read_to_string(&configuration_filepath)
-            .expect("Could not read configuration file!");
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not read configuration file {:?}.",
+                    configuration_filepath
+                )
+            })?;
 
         // Parse the string into the `Config` structure.
         let unresolved_configuration: UnresolvedConfiguration =
             toml::from_str(&configuration_string)
-                .expect("Could not load configuration file!");
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!(
+                        "Could not parse configuration file {:?}.",
+                        configuration_filepath
+                    )
+                })?;
 
         let configuration_file_path = dunce::canonicalize(configuration_filepath)
             .expect("Could not canonicalize configuration file path even though it has loaded!");
@@ -102,6 +123,209 @@ impl Configuration {
         Ok(resolved_configuration)
     }
 
+    /// Returns every configuration problem that is suspicious, but not necessarily wrong
+    /// (e.g. an ffmpeg output format that doesn't match the output extension).
+    ///
+    /// These don't prevent the configuration from loading, the caller should show them to the user.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = Vec::new();
+
+        if let Some(warning) = self.tools.ffmpeg.output_format_mismatch_warning()
+        {
+            warnings.push(warning);
+        }
+
+        warnings
+    }
+
+    /// Check that the ffmpeg binary exists, that every library path exists and is a directory
+    /// (missing optional libraries are allowed) and that each transcoded library path (`aggregated_library.path` and any library `output_path`)
+    /// is either a directory or can be created (i.e. its parent directory exists).
+    ///
+    /// Returns a single error listing every problem, so that e.g. an unmounted drive is reported
+    /// upfront instead of failing in the middle of a run.
+    pub fn validate_paths(&self) -> Result<()> {
+        let mut path_problems: Vec<String> = Vec::new();
+
+        if !Path::new(&self.tools.ffmpeg.binary).is_file() {
+            path_problems.push(format!(
+                "tools.ffmpeg.binary: no file exists at path \"{}\"",
+                self.tools.ffmpeg.binary
+            ));
+        }
+
+        for library in self.libraries.values() {
+            let library_path = Path::new(&library.path);
+
+            if !library_path.exists() {
+                if !library.optional {
+                    path_problems.push(format!(
+                        "library \"{}\": path \"{}\" does not exist",
+                        library.name, library.path
+                    ));
+                }
+            } else if !library_path.is_dir() {
+                path_problems.push(format!(
+                    "library \"{}\": path \"{}\" is not a directory",
+                    library.name, library.path
+                ));
+            }
+        }
+
+        for (description, transcoded_library_path) in
+            self.transcoded_library_paths()
+        {
+            let path = Path::new(transcoded_library_path);
+
+            if path.exists() {
+                if !path.is_dir() {
+                    path_problems.push(format!(
+                        "{}: path \"{}\" is not a directory",
+                        description, transcoded_library_path
+                    ));
+                }
+            } else if !path.parent().is_some_and(|parent| parent.is_dir()) {
+                // The transcoded library itself is created on the first transcode,
+                // but a missing parent most likely means an unmounted drive.
+                path_problems.push(format!(
+                    "{}: neither path \"{}\" nor its parent directory exist",
+                    description, transcoded_library_path
+                ));
+            }
+        }
+
+        if path_problems.is_empty() {
+            return Ok(());
+        }
+
+        Err(miette!(
+            "Some paths in the configuration are not available (is a drive unmounted?):\n{}",
+            path_problems
+                .iter()
+                .map(|problem| format!("  - {problem}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))
+    }
+
+    /// Get the paths of all transcoded libraries (`aggregated_library.path` and any library `output_path`),
+    /// each along with a description of where it is configured (for error messages).
+    pub fn transcoded_library_paths(&self) -> Vec<(String, &str)> {
+        let mut transcoded_library_paths: Vec<(String, &str)> = vec![(
+            "aggregated_library".to_string(),
+            &self.aggregated_library.path,
+        )];
+
+        for library in self.libraries.values() {
+            if let Some(output_path) = &library.output_path {
+                transcoded_library_paths.push((
+                    format!("library \"{}\" output_path", library.name),
+                    output_path,
+                ));
+            }
+        }
+
+        transcoded_library_paths
+    }
+
+    /// Returns `true` if the given directory is the root of one of the transcoded libraries
+    /// (see `transcoded_library_paths`).
+    pub fn is_transcoded_library_root<P: AsRef<Path>>(
+        &self,
+        directory_path: P,
+    ) -> bool {
+        let directory_path = normalized_directory_path(directory_path.as_ref());
+
+        self.transcoded_library_paths().into_iter().any(
+            |(_, transcoded_library_path)| {
+                normalized_directory_path(Path::new(transcoded_library_path))
+                    == directory_path
+            },
+        )
+    }
+
+    /// Check that no library path is the same as, inside of, or contains any of the transcoded library paths
+    /// (see `transcoded_library_paths`). Otherwise euphony would end up scanning (and transcoding)
+    /// its own output on the next run, or writing transcodes into a source library.
+    ///
+    /// Returns a single error listing every overlapping pair.
+    fn validate_library_and_transcoded_library_paths_are_separate(
+        &self,
+    ) -> Result<()> {
+        let transcoded_library_paths = self.transcoded_library_paths();
+
+        let mut path_problems: Vec<String> = Vec::new();
+
+        for library in self.libraries.values() {
+            let library_path =
+                normalized_directory_path(Path::new(&library.path));
+
+            for (description, transcoded_library_path) in
+                &transcoded_library_paths
+            {
+                let transcoded_library_path = normalized_directory_path(
+                    Path::new(transcoded_library_path),
+                );
+
+                if library_path.starts_with(&transcoded_library_path)
+                    || transcoded_library_path.starts_with(&library_path)
+                {
+                    path_problems.push(format!(
+                        "library \"{}\" ({:?}) overlaps with the transcoded library of {} ({:?})",
+                        library.name, library_path, description, transcoded_library_path
+                    ));
+                }
+            }
+        }
+
+        if path_problems.is_empty() {
+            return Ok(());
+        }
+
+        Err(miette!(
+            "Library paths must not be inside of (or contain) a transcoded library, \
+            otherwise euphony would transcode its own output:\n{}",
+            path_problems
+                .iter()
+                .map(|problem| format!("  - {problem}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))
+    }
+
+    /// Returns the location `load_default_path` loads the configuration file from
+    /// (`./data/configuration.toml` next to the binary), whether the file exists or not.
+    pub fn default_configuration_file_location() -> Result<PathBuf> {
+        get_default_configuration_file_location()
+    }
+
+    /// Find the configuration file to use when no path was given explicitly, returning the first
+    /// existing file from the search locations (see `get_configuration_file_search_locations`
+    /// for the priority order). The binary-relative `./data/configuration.toml` is searched last.
+    pub fn discover() -> Result<PathBuf> {
+        let search_locations = get_configuration_file_search_locations()
+            .wrap_err_with(|| {
+                miette!("Could not get configuration file search locations.")
+            })?;
+
+        if let Some(configuration_file_path) = search_locations
+            .iter()
+            .find(|search_location| search_location.is_file())
+        {
+            return Ok(configuration_file_path.clone());
+        }
+
+        Err(miette!(
+            "Could not find a configuration file (pass one with --config or create one with `euphony init-config`). \
+            Searched in:\n{}",
+            search_locations
+                .iter()
+                .map(|search_location| format!("  - {}", search_location.display()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))
+    }
+
     pub fn load_default_path() -> Result<Configuration> {
         Configuration::load_from_path(
             get_default_configuration_file_path().wrap_err_with(|| {
@@ -135,6 +359,47 @@ impl Configuration {
         None
     }
 
+    /// Get the path of the transcoded library the given library is transcoded into:
+    /// its own `output_path` if set, otherwise `aggregated_library.path`.
+    pub fn transcoded_library_path<'a>(
+        &'a self,
+        library: &'a LibraryConfiguration,
+    ) -> &'a str {
+        library
+            .output_path
+            .as_deref()
+            .unwrap_or(&self.aggregated_library.path)
+    }
+
+    /// Get the ffmpeg configuration the given library's audio files are transcoded with:
+    /// `tools.ffmpeg`, unless the library sets its own `transcoding.audio_transcoding_args`
+    /// and `transcoding.audio_transcoding_output_extension`. In that case, those replace
+    /// the global ones and `tools.ffmpeg.audio_transcoding_output_format` is not used.
+    pub fn ffmpeg_configuration_for_library<'a>(
+        &'a self,
+        library: &LibraryConfiguration,
+    ) -> Cow<'a, FfmpegToolsConfiguration> {
+        let (
+            Some(audio_transcoding_args),
+            Some(audio_transcoding_output_extension),
+        ) = (
+            &library.transcoding.audio_transcoding_args,
+            &library.transcoding.audio_transcoding_output_extension,
+        )
+        else {
+            return Cow::Borrowed(&self.tools.ffmpeg);
+        };
+
+        Cow::Owned(FfmpegToolsConfiguration {
+            binary: self.tools.ffmpeg.binary.clone(),
+            audio_transcoding_args: audio_transcoding_args.clone(),
+            audio_transcoding_output_extension:
+                audio_transcoding_output_extension.clone(),
+            audio_transcoding_output_format: None,
+            per_file_timeout_seconds: self.tools.ffmpeg.per_file_timeout_seconds,
+        })
+    }
+
     pub fn get_library_by_full_name<S: AsRef<str>>(
         &self,
         library_name: S,
@@ -143,6 +408,94 @@ impl Configuration {
             .values()
             .find(|library| library.name.eq(library_name.as_ref()))
     }
+
+    /// Find the library matching the given name selector, e.g. as passed on the command line.
+    ///
+    /// A library whose name is exactly `library_name_selector` always wins. Otherwise, the selector
+    /// is matched as a case-insensitive substring of the library names. If no library or more than
+    /// one library matches, an error listing the candidates is returned.
+    pub fn get_library_by_name_selector<S: AsRef<str>>(
+        &self,
+        library_name_selector: S,
+    ) -> Result<&LibraryConfiguration> {
+        let library_names: Vec<&str> = self
+            .libraries
+            .values()
+            .map(|library| library.name.as_str())
+            .collect();
+
+        let selected_library_name =
+            select_library_name(&library_names, library_name_selector.as_ref())?;
+
+        self.get_library_by_full_name(selected_library_name)
+            .ok_or_else(|| miette!("BUG: Selected library does not exist."))
+    }
+}
+
+/// Select the library name matching the given name selector (see `Configuration::get_library_by_name_selector`).
+fn select_library_name<'name>(
+    library_names: &[&'name str],
+    library_name_selector: &str,
+) -> Result<&'name str> {
+    if let Some(library_name) = library_names
+        .iter()
+        .find(|library_name| **library_name == library_name_selector)
+    {
+        return Ok(library_name);
+    }
+
+    let lowercase_selector = library_name_selector.to_lowercase();
+    let matching_library_names: Vec<&'name str> = library_names
+        .iter()
+        .copied()
+        .filter(|library_name| {
+            library_name.to_lowercase().contains(&lowercase_selector)
+        })
+        .collect();
+
+    match matching_library_names.as_slice() {
+        [library_name] => Ok(library_name),
+        [] => Err(miette!(
+            "No library matches \"{}\" (available libraries: {}).",
+            library_name_selector,
+            library_names
+                .iter()
+                .map(|library_name| format!("\"{}\"", library_name))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )),
+        _ => Err(miette!(
+            "\"{}\" is ambiguous, it matches multiple libraries: {}.",
+            library_name_selector,
+            matching_library_names
+                .iter()
+                .map(|library_name| format!("\"{}\"", library_name))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Canonicalize the given directory path for comparison with other paths. Transcoded libraries might not exist yet,
+/// in which case only their parent directory is canonicalized (or, if that doesn't exist either, nothing is).
+fn normalized_directory_path(directory_path: &Path) -> PathBuf {
+    if let Ok(canonicalized_path) = dunce::canonicalize(directory_path) {
+        return canonicalized_path;
+    }
+
+    match (
+        directory_path.parent(),
+        directory_path.file_name(),
+    ) {
+        (Some(parent_directory_path), Some(directory_name)) => {
+            dunce::canonicalize(parent_directory_path)
+                .map(|parent_directory_path| {
+                    parent_directory_path.join(directory_name)
+                })
+                .unwrap_or_else(|_| directory_path.to_path_buf())
+        }
+        _ => directory_path.to_path_buf(),
+    }
 }
 
 impl ResolvableWithContextConfiguration for UnresolvedConfiguration {
@@ -169,7 +522,7 @@ impl ResolvableWithContextConfiguration for UnresolvedConfiguration {
 
         let aggregated_library = self.aggregated_library.resolve(&paths)?;
 
-        Ok(Configuration {
+        let configuration = Configuration {
             paths,
             logging,
             ui,
@@ -178,6 +531,54 @@ impl ResolvableWithContextConfiguration for UnresolvedConfiguration {
             libraries,
             aggregated_library,
             configuration_file_path,
-        })
+        };
+
+        configuration
+            .validate_library_and_transcoded_library_paths_are_separate()?;
+
+        Ok(configuration)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::select_library_name;
+
+    const LIBRARY_NAMES: [&str; 3] = ["Lossless", "Lossless Archive", "Lossy"];
+
+    #[test]
+    fn library_selector_matches_unambiguous_prefix() {
+        assert_eq!(
+            select_library_name(&LIBRARY_NAMES, "lossy").unwrap(),
+            "Lossy"
+        );
+        assert_eq!(
+            select_library_name(&LIBRARY_NAMES, "Lossless Arch").unwrap(),
+            "Lossless Archive"
+        );
+    }
+
+    #[test]
+    fn library_selector_prefers_exact_name() {
+        // "Lossless" is also a substring of "Lossless Archive".
+        assert_eq!(
+            select_library_name(&LIBRARY_NAMES, "Lossless").unwrap(),
+            "Lossless"
+        );
+    }
+
+    #[test]
+    fn library_selector_rejects_ambiguous_selector() {
+        let error = select_library_name(&LIBRARY_NAMES, "loss").unwrap_err();
+
+        assert!(error.to_string().contains("ambiguous"));
+    }
+
+    #[test]
+    fn library_selector_rejects_unknown_selector() {
+        let error = select_library_name(&LIBRARY_NAMES, "vinyl").unwrap_err();
+
+        assert!(error.to_string().contains("No library matches"));
     }
 }
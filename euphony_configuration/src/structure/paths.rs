@@ -1,21 +1,46 @@
-use serde::Deserialize;
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use miette::miette;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     traits::ResolvableConfiguration,
-    utilities::get_running_executable_directory,
+    utilities::{
+        expand_environment_variables,
+        get_running_executable_directory,
+    },
 };
 
+/// Name of the subdirectory of the data directory that contains the per-album error records.
+const ALBUM_ERROR_RECORDS_DIRECTORY_NAME: &str = "errored-albums";
+
 /// Base paths - reusable values such as the base library path and base tools path.
-#[derive(Clone)]
+#[derive(Serialize, Clone)]
 pub struct PathsConfiguration {
     pub base_library_path: String,
     pub base_tools_path: String,
+
+    /// Directory that contains all of euphony's runtime files that don't belong
+    /// into a library (e.g. error records). This directory might not exist yet.
+    pub data_directory_path: PathBuf,
 }
 
 #[derive(Deserialize, Clone)]
 pub(crate) struct UnresolvedPathsConfiguration {
     base_library_path: String,
     base_tools_path: String,
+
+    #[serde(default)]
+    data_directory_path: Option<String>,
+}
+
+impl PathsConfiguration {
+    /// Directory (inside the data directory) that contains the error records of all albums.
+    pub fn album_error_records_directory_path(&self) -> PathBuf {
+        self.data_directory_path
+            .join(ALBUM_ERROR_RECORDS_DIRECTORY_NAME)
+    }
 }
 
 
@@ -28,12 +53,16 @@ impl ResolvableConfiguration for UnresolvedPathsConfiguration {
             .to_string_lossy()
             .to_string();
 
-        let base_library_path = self
-            .base_library_path
-            .replace("{SELF}", &executable_directory);
-        let base_tools_path = self
-            .base_tools_path
-            .replace("{SELF}", &executable_directory);
+        let base_library_path = expand_environment_variables(
+            &self.base_library_path,
+            "paths.base_library_path",
+        )?
+        .replace("{SELF}", &executable_directory);
+        let base_tools_path = expand_environment_variables(
+            &self.base_tools_path,
+            "paths.base_tools_path",
+        )?
+        .replace("{SELF}", &executable_directory);
 
         let base_library_path = dunce::canonicalize(base_library_path)
             .unwrap_or_else(|_| panic!(
@@ -51,10 +80,32 @@ impl ResolvableConfiguration for UnresolvedPathsConfiguration {
             .to_string_lossy()
             .to_string();
 
+        // Unlike the other two paths, the data directory is created on demand,
+        // which is why it isn't canonicalized here.
+        let data_directory_path = match self.data_directory_path {
+            Some(data_directory_path) => PathBuf::from(
+                expand_environment_variables(
+                    &data_directory_path,
+                    "paths.data_directory_path",
+                )?
+                .replace("{SELF}", &executable_directory),
+            ),
+            None => ProjectDirs::from("", "", "euphony")
+                .ok_or_else(|| {
+                    miette!(
+                        "Could not determine the default data directory, \
+                        please set paths.data_directory_path."
+                    )
+                })?
+                .data_dir()
+                .to_path_buf(),
+        };
+
 
         Ok(PathsConfiguration {
             base_library_path,
             base_tools_path,
+            data_directory_path,
         })
     }
 }
@@ -1,17 +1,18 @@
 use std::path::Path;
 
-use miette::Result;
-use serde::Deserialize;
+use miette::{miette, Result};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     filesystem::get_path_extension_or_empty,
     paths::PathsConfiguration,
     traits::ResolvableWithPathsConfiguration,
+    utilities::expand_environment_variables,
 };
 
 
 
-#[derive(Clone)]
+#[derive(Serialize, Clone)]
 pub struct ToolsConfiguration {
     pub ffmpeg: FfmpegToolsConfiguration,
 }
@@ -36,7 +37,7 @@ impl ResolvableWithPathsConfiguration for UnresolvedToolsConfiguration {
 
 
 
-#[derive(Clone)]
+#[derive(Serialize, Clone)]
 pub struct FfmpegToolsConfiguration {
     /// Configures the ffmpeg binary location.
     /// The {TOOLS_BASE} placeholder is available (see `base_tools_path` in the `essentials` table)
@@ -44,14 +45,47 @@ pub struct FfmpegToolsConfiguration {
 
     /// These are the arguments passed to ffmpeg when converting an audio file into MP3 V0.
     /// The placeholders {INPUT_FILE} and {OUTPUT_FILE} will be replaced with the absolute path to those files.
+    /// Both placeholders are guaranteed to be present (see `validate_audio_transcoding_args`).
+    /// If unset in the configuration file, this is `DEFAULT_AUDIO_TRANSCODING_ARGS`.
     pub audio_transcoding_args: Vec<String>,
 
     /// This setting should be the extension of the audio files after transcoding.
     /// The default conversion is to MP3, but the user may set any ffmpeg conversion above, which is why this exists.
     pub audio_transcoding_output_extension: String,
+
+    /// If set, this is passed to ffmpeg as `-f <format>` (just before the output file),
+    /// instead of letting ffmpeg guess the container format from the output extension.
+    pub audio_transcoding_output_format: Option<String>,
+
+    /// If set, an ffmpeg process transcoding a single file is killed after running for this many seconds
+    /// and the file is marked as errored (guards against ffmpeg hanging on pathological input).
+    pub per_file_timeout_seconds: Option<u64>,
 }
 
 impl FfmpegToolsConfiguration {
+    /// Returns a warning if `audio_transcoding_output_format` is a known format that usually
+    /// doesn't produce files with `audio_transcoding_output_extension` (a likely misconfiguration,
+    /// but not necessarily a wrong one, so it doesn't prevent the configuration from loading).
+    pub fn output_format_mismatch_warning(&self) -> Option<String> {
+        let output_format = self.audio_transcoding_output_format.as_ref()?;
+
+        let (_, expected_extensions) = KNOWN_OUTPUT_FORMAT_EXTENSIONS
+            .iter()
+            .find(|(format, _)| format.eq(output_format))?;
+
+        if expected_extensions
+            .contains(&self.audio_transcoding_output_extension.as_str())
+        {
+            return None;
+        }
+
+        Some(format!(
+            "tools.ffmpeg.audio_transcoding_output_format \"{}\" usually doesn't produce \
+            \".{}\" files (expected one of {:?}), check your ffmpeg configuration.",
+            output_format, self.audio_transcoding_output_extension, expected_extensions,
+        ))
+    }
+
     /// Returns `Ok(true)` if the given path's extension matches
     /// the ffmpeg transcoding output path.
     ///
@@ -64,15 +98,115 @@ impl FfmpegToolsConfiguration {
 
         Ok(self.audio_transcoding_output_extension.eq(&extension))
     }
+
+    /// Returns the ffmpeg arguments for transcoding `input_file_path` into `output_file_path`,
+    /// i.e. `audio_transcoding_args` with the placeholders replaced and, if set,
+    /// `-f <audio_transcoding_output_format>` inserted just before the output file argument.
+    pub fn audio_transcoding_arguments(
+        &self,
+        input_file_path: &str,
+        output_file_path: &str,
+    ) -> Vec<String> {
+        let mut arguments =
+            Vec::with_capacity(self.audio_transcoding_args.len() + 2);
+
+        for argument in &self.audio_transcoding_args {
+            if argument.contains("{OUTPUT_FILE}") {
+                if let Some(output_format) =
+                    &self.audio_transcoding_output_format
+                {
+                    arguments.push("-f".to_string());
+                    arguments.push(output_format.clone());
+                }
+            }
+
+            arguments.push(
+                argument
+                    .replace("{INPUT_FILE}", input_file_path)
+                    .replace("{OUTPUT_FILE}", output_file_path),
+            );
+        }
+
+        arguments
+    }
 }
 
+/// ffmpeg arguments used when `tools.ffmpeg.audio_transcoding_args` is not set (MP3 V0).
+pub const DEFAULT_AUDIO_TRANSCODING_ARGS: &[&str] = &[
+    "-i",
+    "{INPUT_FILE}",
+    "-vn",
+    "-codec:a",
+    "libmp3lame",
+    "-q:a",
+    "0",
+    "-y",
+    "{OUTPUT_FILE}",
+];
+
+/// Output file extension used when `tools.ffmpeg.audio_transcoding_args` is not set.
+pub const DEFAULT_AUDIO_TRANSCODING_OUTPUT_EXTENSION: &str = "mp3";
+
+/// Ensure an ffmpeg argument template contains both the `{INPUT_FILE}` and `{OUTPUT_FILE}` placeholders,
+/// otherwise ffmpeg would either have nothing to read or write its output somewhere euphony doesn't expect.
+///
+/// `configuration_key` is only used in the error message.
+pub(crate) fn validate_audio_transcoding_args(
+    audio_transcoding_args: &[String],
+    configuration_key: &str,
+) -> Result<()> {
+    let missing_placeholders = ["{INPUT_FILE}", "{OUTPUT_FILE}"]
+        .into_iter()
+        .filter(|placeholder| {
+            !audio_transcoding_args
+                .iter()
+                .any(|argument| argument.contains(placeholder))
+        })
+        .collect::<Vec<_>>();
+
+    if !missing_placeholders.is_empty() {
+        return Err(miette!(
+            "{} is missing the {} placeholder(s): {:?}",
+            configuration_key,
+            missing_placeholders.join(" and "),
+            audio_transcoding_args
+        ));
+    }
+
+    Ok(())
+}
+
+/// Output file extensions that make sense for some of the more common ffmpeg output formats (muxers).
+/// Used to warn about obviously incoherent `audio_transcoding_output_format` and
+/// `audio_transcoding_output_extension` combinations. Formats missing from this list aren't checked.
+const KNOWN_OUTPUT_FORMAT_EXTENSIONS: &[(&str, &[&str])] = &[
+    ("mp3", &["mp3"]),
+    ("ogg", &["ogg", "oga", "opus"]),
+    ("opus", &["opus"]),
+    ("flac", &["flac"]),
+    ("wav", &["wav"]),
+    ("ipod", &["m4a"]),
+    ("mp4", &["m4a", "mp4"]),
+    ("adts", &["aac"]),
+    ("aiff", &["aif", "aiff"]),
+    ("matroska", &["mka"]),
+];
+
 #[derive(Deserialize, Clone)]
 pub(crate) struct UnresolvedFfmpegToolsConfiguration {
     binary: String,
 
-    audio_transcoding_args: Vec<String>,
+    #[serde(default)]
+    audio_transcoding_args: Option<Vec<String>>,
 
-    audio_transcoding_output_extension: String,
+    #[serde(default)]
+    audio_transcoding_output_extension: Option<String>,
+
+    #[serde(default)]
+    audio_transcoding_output_format: Option<String>,
+
+    #[serde(default)]
+    per_file_timeout_seconds: Option<u64>,
 }
 
 impl ResolvableWithPathsConfiguration for UnresolvedFfmpegToolsConfiguration {
@@ -82,26 +216,66 @@ impl ResolvableWithPathsConfiguration for UnresolvedFfmpegToolsConfiguration {
         self,
         paths: &PathsConfiguration,
     ) -> miette::Result<Self::Resolved> {
-        let ffmpeg = self.binary.replace("{TOOLS_BASE}", &paths.base_tools_path);
+        let ffmpeg =
+            expand_environment_variables(&self.binary, "tools.ffmpeg.binary")?
+                .replace("{TOOLS_BASE}", &paths.base_tools_path);
 
-        let canonicalized_ffmpeg = dunce::canonicalize(ffmpeg.clone())
-            .unwrap_or_else(|_| panic!(
-                "Could not canonicalize ffmpeg binary path: \"{ffmpeg}\", make sure the path is valid.",
-            ));
+        if self.per_file_timeout_seconds == Some(0) {
+            panic!(
+                "per_file_timeout_seconds is set to 0! The minimum value is 1."
+            );
+        }
 
-        let binary = canonicalized_ffmpeg.to_string_lossy().to_string();
+        // A missing binary is reported by `Configuration::validate_paths` (or the `doctor` command),
+        // so that the rest of the configuration can still be loaded.
+        let binary = match dunce::canonicalize(&ffmpeg) {
+            Ok(canonicalized_ffmpeg) => {
+                canonicalized_ffmpeg.to_string_lossy().to_string()
+            }
+            Err(_) => ffmpeg,
+        };
 
-        if !canonicalized_ffmpeg.is_file() {
-            panic!("No file exists at this path: {}", self.binary);
-        }
+        let (audio_transcoding_args, audio_transcoding_output_extension) = match (
+            self.audio_transcoding_args,
+            self.audio_transcoding_output_extension,
+        ) {
+            (Some(arguments), Some(extension)) => (arguments, extension),
+            (None, None) => (
+                DEFAULT_AUDIO_TRANSCODING_ARGS
+                    .iter()
+                    .map(|argument| argument.to_string())
+                    .collect(),
+                DEFAULT_AUDIO_TRANSCODING_OUTPUT_EXTENSION.to_string(),
+            ),
+            (Some(_), None) => {
+                panic!("audio_transcoding_args is set, but audio_transcoding_output_extension isn't! Either set both or neither.");
+            }
+            (None, Some(_)) => {
+                panic!("audio_transcoding_output_extension is set, but audio_transcoding_args isn't! Either set both or neither.");
+            }
+        };
+
+        validate_audio_transcoding_args(
+            &audio_transcoding_args,
+            "tools.ffmpeg.audio_transcoding_args",
+        )?;
 
         let audio_transcoding_output_extension =
-            self.audio_transcoding_output_extension.to_ascii_lowercase();
+            audio_transcoding_output_extension.to_ascii_lowercase();
+
+        if let Some(output_format) = &self.audio_transcoding_output_format {
+            if output_format.is_empty() {
+                panic!("audio_transcoding_output_format is set to an empty string! Either remove it or specify a format.");
+            }
+        }
 
         Ok(FfmpegToolsConfiguration {
             binary,
-            audio_transcoding_args: self.audio_transcoding_args,
+            audio_transcoding_args,
             audio_transcoding_output_extension,
+            audio_transcoding_output_format: self
+                .audio_transcoding_output_format,
+            per_file_timeout_seconds: self.per_file_timeout_seconds,
         })
     }
 }
@@ -1,30 +1,71 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::traits::ResolvableConfiguration;
 
-#[derive(Clone)]
+/// Default value for `ui.refresh_interval_ms` (roughly 60 frames per second).
+const DEFAULT_REFRESH_INTERVAL_MS: u64 = 16;
+
+fn default_refresh_interval_ms() -> u64 {
+    DEFAULT_REFRESH_INTERVAL_MS
+}
+
+/// Default value for `ui.bare.progress_summary_interval_seconds`.
+const DEFAULT_PROGRESS_SUMMARY_INTERVAL_SECONDS: u64 = 2;
+
+fn default_progress_summary_interval_seconds() -> u64 {
+    DEFAULT_PROGRESS_SUMMARY_INTERVAL_SECONDS
+}
+
+
+#[derive(Serialize, Clone)]
 pub struct UiConfiguration {
+    /// Minimum time between two redraws of the terminal UI, in milliseconds.
+    /// Any queue or progress updates that happen in between are coalesced into the next frame.
+    pub refresh_interval_ms: u64,
+
+    /// Subcommand (optionally with its arguments, e.g. `"transcode --bare-terminal"`)
+    /// to run when euphony is started without one.
+    pub default_command: Option<String>,
+
     pub transcoding: TranscodingUiConfiguration,
+
+    pub bare: BareUiConfiguration,
 }
 
 #[derive(Deserialize, Clone)]
 pub(crate) struct UnresolvedUiConfiguration {
+    #[serde(default = "default_refresh_interval_ms")]
+    refresh_interval_ms: u64,
+
+    #[serde(default)]
+    default_command: Option<String>,
+
     transcoding: UnresolvedTranscodingUiConfiguration,
+
+    #[serde(default)]
+    bare: UnresolvedBareUiConfiguration,
 }
 
 impl ResolvableConfiguration for UnresolvedUiConfiguration {
     type Resolved = UiConfiguration;
 
     fn resolve(self) -> miette::Result<Self::Resolved> {
+        if self.refresh_interval_ms == 0 {
+            panic!("refresh_interval_ms is set to 0! The minimum value is 1.");
+        }
+
         Ok(UiConfiguration {
+            refresh_interval_ms: self.refresh_interval_ms,
+            default_command: self.default_command,
             transcoding: self.transcoding.resolve()?,
+            bare: self.bare.resolve()?,
         })
     }
 }
 
 
 
-#[derive(Clone)]
+#[derive(Serialize, Clone)]
 pub struct TranscodingUiConfiguration {
     pub show_logs_tab_on_exit: bool,
 }
@@ -44,3 +85,45 @@ impl ResolvableConfiguration for UnresolvedTranscodingUiConfiguration {
         })
     }
 }
+
+
+
+#[derive(Serialize, Clone)]
+pub struct BareUiConfiguration {
+    /// Minimum time between two progress summary lines of the bare terminal backend
+    /// (`--bare-terminal`), in seconds. Progress updates in between are coalesced.
+    pub progress_summary_interval_seconds: u64,
+}
+
+
+#[derive(Deserialize, Clone)]
+pub(crate) struct UnresolvedBareUiConfiguration {
+    #[serde(default = "default_progress_summary_interval_seconds")]
+    progress_summary_interval_seconds: u64,
+}
+
+impl Default for UnresolvedBareUiConfiguration {
+    fn default() -> Self {
+        Self {
+            progress_summary_interval_seconds:
+                default_progress_summary_interval_seconds(),
+        }
+    }
+}
+
+impl ResolvableConfiguration for UnresolvedBareUiConfiguration {
+    type Resolved = BareUiConfiguration;
+
+    fn resolve(self) -> miette::Result<Self::Resolved> {
+        if self.progress_summary_interval_seconds == 0 {
+            panic!(
+                "progress_summary_interval_seconds is set to 0! The minimum value is 1."
+            );
+        }
+
+        Ok(BareUiConfiguration {
+            progress_summary_interval_seconds: self
+                .progress_summary_interval_seconds,
+        })
+    }
+}
@@ -1,15 +1,22 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::traits::ResolvableConfiguration;
 
-#[derive(Clone)]
+#[derive(Serialize, Clone)]
 pub struct ValidationConfiguration {
     pub extensions_considered_audio_files: Vec<String>,
+
+    /// Whether validation should recurse into album subdirectories,
+    /// regardless of the per-album scanning depth used for transcoding.
+    pub recurse_album_subdirs: bool,
 }
 
 #[derive(Deserialize, Clone)]
 pub(crate) struct UnresolvedValidationConfiguration {
     extensions_considered_audio_files: Vec<String>,
+
+    #[serde(default)]
+    recurse_album_subdirs: bool,
 }
 
 impl ResolvableConfiguration for UnresolvedValidationConfiguration {
@@ -27,6 +34,7 @@ impl ResolvableConfiguration for UnresolvedValidationConfiguration {
 
         Ok(ValidationConfiguration {
             extensions_considered_audio_files,
+            recurse_album_subdirs: self.recurse_album_subdirs,
         })
     }
 }
@@ -1,3 +1,4 @@
+use std::env;
 use std::env::args;
 use std::path::{Path, PathBuf};
 
@@ -79,12 +80,62 @@ pub fn get_running_executable_directory() -> Result<PathBuf> {
     Ok(executable_directory)
 }
 
+/// Returns the location the default configuration file is expected at (whether it exists or not).
+/// This is `./data/configuration.toml` relative to the executable directory
+/// (see `get_running_executable_directory`).
+pub fn get_default_configuration_file_location() -> Result<PathBuf> {
+    let mut configuration_filepath = get_running_executable_directory()
+        .wrap_err_with(|| miette!("Could not get the executable directory."))?;
+    configuration_filepath.push("data");
+    configuration_filepath.push("configuration.toml");
+
+    Ok(configuration_filepath)
+}
+
+/// Returns the locations searched for the configuration file when no path is given explicitly,
+/// in order of priority:
+/// 1. `./configuration.toml` in the current working directory,
+/// 2. `$XDG_CONFIG_HOME/euphony/configuration.toml` (if `XDG_CONFIG_HOME` is set),
+/// 3. `$HOME/.config/euphony/configuration.toml` (if `HOME` is set),
+/// 4. `./data/configuration.toml` relative to the executable directory
+///    (see `get_default_configuration_file_location`).
+pub fn get_configuration_file_search_locations() -> Result<Vec<PathBuf>> {
+    let mut search_locations = Vec::with_capacity(4);
+
+    let current_directory = env::current_dir()
+        .into_diagnostic()
+        .wrap_err_with(|| miette!("Could not get the current directory."))?;
+    search_locations.push(current_directory.join("configuration.toml"));
+
+    let user_configuration_directories = [
+        env::var_os("XDG_CONFIG_HOME").map(PathBuf::from),
+        env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")),
+    ];
+
+    for user_configuration_directory in
+        user_configuration_directories.into_iter().flatten()
+    {
+        // An empty or relative `XDG_CONFIG_HOME` is invalid and should be ignored.
+        if !user_configuration_directory.is_absolute() {
+            continue;
+        }
+
+        search_locations.push(
+            user_configuration_directory
+                .join("euphony")
+                .join("configuration.toml"),
+        );
+    }
+
+    search_locations.push(get_default_configuration_file_location()?);
+
+    Ok(search_locations)
+}
+
 /// Returns the default configuration filepath. This is `./data/configuration.toml`, with (potentially)
 /// an additional `../../` escape if we're running inside the `./target/debug` directory of a cargo project.
 pub fn get_default_configuration_file_path() -> Result<String> {
-    let mut configuration_filepath = get_running_executable_directory()
-        .wrap_err_with(|| miette!("Could not get the executable directory."))?;
-    configuration_filepath.push("./data/configuration.toml");
+    let configuration_filepath = get_default_configuration_file_location()?;
 
     if !configuration_filepath.exists() {
         panic!("Could not find configuration.toml in data directory.");
@@ -98,3 +149,45 @@ pub fn get_default_configuration_file_path() -> Result<String> {
 
     Ok(configuration_filepath.to_string_lossy().to_string())
 }
+
+/// Replace every `${NAME}` in a configuration value with the value of the `NAME` environment variable.
+/// A `$` that isn't followed by `{` is left as is.
+///
+/// `configuration_key` describes where the value comes from (e.g. `aggregated_library.path`)
+/// and is only used in error messages (unset variables and unterminated `${`).
+pub fn expand_environment_variables(
+    value: &str,
+    configuration_key: &str,
+) -> Result<String> {
+    let mut expanded_value = String::with_capacity(value.len());
+    let mut remaining_value = value;
+
+    while let Some(variable_start) = remaining_value.find("${") {
+        expanded_value.push_str(&remaining_value[..variable_start]);
+
+        let variable_and_rest = &remaining_value[variable_start + 2..];
+        let variable_end = variable_and_rest.find('}').ok_or_else(|| {
+            miette!(
+                "Unterminated environment variable (\"${{\" without \"}}\") in {}: \"{}\".",
+                configuration_key,
+                value
+            )
+        })?;
+
+        let variable_name = &variable_and_rest[..variable_end];
+        let variable_value = env::var(variable_name).map_err(|_| {
+            miette!(
+                "Environment variable \"{}\" (used in {}) is not set.",
+                variable_name,
+                configuration_key
+            )
+        })?;
+
+        expanded_value.push_str(&variable_value);
+        remaining_value = &variable_and_rest[variable_end + 1..];
+    }
+
+    expanded_value.push_str(remaining_value);
+
+    Ok(expanded_value)
+}
@@ -1,3 +1,4 @@
+pub mod scan;
 pub mod state;
 pub mod utilities;
 pub mod view;
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use euphony_configuration::library::LibraryConfiguration;
+use euphony_configuration::{AlbumConfiguration, Configuration};
+use miette::{miette, Context, Result};
+
+use crate::view::{AlbumSourceFileList, LibraryView, SharedAlbumView};
+
+
+/// An artist found by `scan_library`, along with all of their albums.
+#[derive(Clone, Debug)]
+pub struct ScannedArtist {
+    /// Artist name (the artist's directory name, see `LibraryView::artists`).
+    pub name: String,
+
+    /// Artist directory in the source library.
+    pub source_directory: PathBuf,
+
+    /// Artist directory in the transcoded library.
+    pub transcoded_directory: PathBuf,
+
+    /// All albums by this artist, sorted by title.
+    pub albums: Vec<ScannedAlbum>,
+}
+
+/// An album found by `scan_library`, along with its tracked files.
+#[derive(Clone, Debug)]
+pub struct ScannedAlbum {
+    /// Album title (the album's directory name, see `ArtistView::albums`).
+    pub title: String,
+
+    /// Per-album configuration (loaded from `.album.override.euphony`, if present).
+    pub configuration: AlbumConfiguration,
+
+    /// Album directory in the source library.
+    pub source_directory: PathBuf,
+
+    /// Album directory in the transcoded library.
+    pub transcoded_directory: PathBuf,
+
+    /// Tracked audio files, relative to `source_directory`.
+    pub audio_files: Vec<PathBuf>,
+
+    /// Tracked data files, relative to `source_directory`.
+    pub data_files: Vec<PathBuf>,
+
+    /// Audio or data files that are broken symbolic links and would be skipped
+    /// (see `aggregated_library.broken_symlink_handling`), relative to `source_directory`.
+    pub broken_symlinks: Vec<PathBuf>,
+
+    /// Maps from each tracked file (relative to `source_directory`) to its path
+    /// in the transcoded album directory (relative to `transcoded_directory`).
+    pub transcoded_file_paths: BTreeMap<PathBuf, PathBuf>,
+}
+
+impl ScannedAlbum {
+    fn from_album_view(album: SharedAlbumView) -> Result<Self> {
+        let source_file_list =
+            AlbumSourceFileList::from_album_view(album.clone())?;
+
+        let transcoded_file_paths = source_file_list
+            .map_source_file_paths_to_transcoded_file_paths_relative()
+            .into_flattened_map()
+            .into_iter()
+            .collect();
+
+        let album_locked = album.read();
+
+        Ok(Self {
+            title: album_locked.title.clone(),
+            configuration: album_locked.configuration.clone(),
+            source_directory: album_locked.album_directory_in_source_library(),
+            transcoded_directory: album_locked
+                .album_directory_in_transcoded_library(),
+            audio_files: source_file_list.audio_files,
+            data_files: source_file_list.data_files,
+            broken_symlinks: source_file_list.broken_symlinks,
+            transcoded_file_paths,
+        })
+    }
+}
+
+/// Scan the given library and return all of its artists and their albums (including
+/// each album's tracked files) as plain owned data, sorted by artist name and album title.
+///
+/// This is a convenience wrapper over the `LibraryView` -> `ArtistView` -> `AlbumView` traversal
+/// (and `AlbumSourceFileList`) for tools that only need a snapshot of the library
+/// and don't want to deal with the shared view types. Nothing is written to disk
+/// and no state files are read, so the result says nothing about what has already been transcoded.
+pub fn scan_library(
+    config: &Configuration,
+    library_config: &LibraryConfiguration,
+) -> Result<Vec<ScannedArtist>> {
+    let library =
+        LibraryView::from_library_configuration(config, library_config)?;
+
+    let mut artists = library.read().artists()?.into_iter().collect::<Vec<_>>();
+    artists.sort_unstable_by(|(first_name, _), (second_name, _)| {
+        first_name.cmp(second_name)
+    });
+
+    artists
+        .into_iter()
+        .map(|(artist_name, artist)| {
+            let mut albums =
+                artist.read().albums()?.into_iter().collect::<Vec<_>>();
+            albums.sort_unstable_by(|(first_title, _), (second_title, _)| {
+                first_title.cmp(second_title)
+            });
+
+            let scanned_albums = albums
+                .into_iter()
+                .map(|(album_title, album)| {
+                    ScannedAlbum::from_album_view(album).wrap_err_with(|| {
+                        miette!(
+                            "Could not scan album \"{}\" by \"{}\".",
+                            album_title,
+                            artist_name
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let artist_locked = artist.read();
+
+            Ok(ScannedArtist {
+                name: artist_name,
+                source_directory: artist_locked
+                    .artist_directory_in_source_library(),
+                transcoded_directory: artist_locked
+                    .artist_directory_in_transcoded_library(),
+                albums: scanned_albums,
+            })
+        })
+        .collect()
+}
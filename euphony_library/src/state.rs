@@ -5,11 +5,23 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use miette::{miette, Result};
+use euphony_configuration::library::{
+    ChangeDetectionField,
+    LibraryConfiguration,
+};
+use fs_more::directory::DirectoryScan;
+use miette::{miette, Context, IntoDiagnostic, Result};
 use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
 
 use self::{
-    common::FileTrackedMetadata,
+    common::{
+        AlbumFileState,
+        ContentHashing,
+        FileAccessError,
+        FileTrackedMetadata,
+        STATE_FILE_EXTENSION,
+    },
+    progress::AlbumTranscodeProgress,
     source::SourceAlbumState,
     transcoded::{TranscodedAlbumState, TranscodedAlbumStateLoadError},
 };
@@ -19,6 +31,9 @@ use crate::{
 };
 
 pub mod common;
+pub mod errored;
+pub mod migration;
+pub mod progress;
 pub mod source;
 pub mod transcoded;
 
@@ -69,10 +84,34 @@ pub struct AlbumFileChangesV2<'view> {
     ///
     /// Paths are absolute and point to the *transcoded album directory*.
     pub excess_in_transcoded: ExtendedSortedFileList<PathBuf>,
+
+    /// Tracked files as saved in the source album state during the last transcode
+    /// (empty if the album hasn't been transcoded before).
+    saved_source_files: AlbumFileState,
+
+    /// Whether changed and removed files are being ignored (see `retain_only_new_files`).
+    ignores_changed_and_removed_files: bool,
+
+    /// Whether some files were already completed in an interrupted previous run
+    /// (see `retain_files_not_completed_in_previous_run`). Such albums still need their
+    /// state files saved, even if no other changes remain.
+    has_files_completed_in_previous_run: bool,
+
+    /// Saved state files of this album that were ignored because their schema version
+    /// couldn't be migrated to the current one, along with that schema version.
+    /// The album is then processed as if it was never transcoded.
+    pub unmigratable_saved_states: Vec<(PathBuf, u32)>,
+
+    /// If the album is unchanged, but its saved source state has outdated album directory times
+    /// (see `aggregated_library.scan_skip_strategy`), this is the saved source state with refreshed times.
+    /// Saving it allows the next scan to skip the album.
+    pub source_state_with_refreshed_directory_times: Option<SourceAlbumState>,
 }
 
 impl<'view> AlbumFileChangesV2<'view> {
-    fn default_with_album_view(album: SharedAlbumView<'view>) -> Self {
+    pub(crate) fn default_with_album_view(
+        album: SharedAlbumView<'view>,
+    ) -> Self {
         Self {
             album_view: album,
             tracked_source_files: None,
@@ -81,6 +120,11 @@ impl<'view> AlbumFileChangesV2<'view> {
             removed_from_source_since_last_transcode: SortedFileList::default(),
             missing_in_transcoded: SortedFileList::default(),
             excess_in_transcoded: ExtendedSortedFileList::default(),
+            saved_source_files: AlbumFileState::default(),
+            ignores_changed_and_removed_files: false,
+            has_files_completed_in_previous_run: false,
+            unmigratable_saved_states: Vec::new(),
+            source_state_with_refreshed_directory_times: None,
         }
     }
 
@@ -105,6 +149,7 @@ impl<'view> AlbumFileChangesV2<'view> {
             library_configuration,
             source_album_directory,
             transcoded_album_directory,
+            album_scan_depth,
         ) = {
             let album_locked = album.read();
 
@@ -113,6 +158,7 @@ impl<'view> AlbumFileChangesV2<'view> {
                 album_locked.library_configuration().clone(),
                 album_locked.album_directory_in_source_library(),
                 album_locked.album_directory_in_transcoded_library(),
+                album_locked.configuration.scan.depth as usize,
             )
         };
 
@@ -127,7 +173,7 @@ impl<'view> AlbumFileChangesV2<'view> {
         // **The groups are disjoint.**
 
 
-        let saved_source_album_file_state = &saved_source_state
+        let saved_source_album_file_state = saved_source_state
             .map(|state| state.tracked_files)
             .unwrap_or_default();
 
@@ -235,6 +281,7 @@ impl<'view> AlbumFileChangesV2<'view> {
                     .intersection(&saved_source_file_list_audio),
                 &saved_source_album_file_state.audio_files,
                 &fresh_source_album_file_state.audio_files,
+                &library_configuration.transcoding.change_detection_fields,
             );
 
             let data_files_changed = Self::filter_to_changed_files(
@@ -242,6 +289,7 @@ impl<'view> AlbumFileChangesV2<'view> {
                     .intersection(&saved_source_file_list_data),
                 &saved_source_album_file_state.data_files,
                 &fresh_source_album_file_state.data_files,
+                &library_configuration.transcoding.change_detection_fields,
             );
 
             SortedFileList::new(
@@ -262,59 +310,107 @@ impl<'view> AlbumFileChangesV2<'view> {
          *          transcoded/copied versions are still present in the transcoded album directory.
          */
         let removed_from_source_since_last_transcode = {
-            let audio_files_removed = saved_source_file_list_audio
-                .sub(&fresh_source_file_list_audio)
-                .into_iter()
-                .filter_map(|audio_file| {
-                    // We don't need to bother with the file if it doesn't exist
-                    // in the transcoded directory.
-                    let transcoded_file_path =
-                        match SourceAlbumState::get_transcoded_file_path(
-                            &configuration,
-                            &library_configuration,
-                            &audio_file,
-                        ) {
-                            Ok(transcoded_path) => transcoded_path,
-                            Err(error) => {
-                                return Some(Err(error));
-                            }
-                        };
-
-                    match transcoded_file_path.exists()
-                        && transcoded_file_path.is_file()
-                    {
-                        true => None,
-                        false => Some(Ok(audio_file)),
-                    }
-                })
-                .collect::<Result<Vec<String>>>()?;
-
-            let data_files_removed = saved_source_file_list_data
-                .sub(&fresh_source_file_list_data)
-                .into_iter()
-                .filter_map(|data_file| {
-                    // We don't need to bother with the file if it doesn't exist
-                    // in the transcoded directory.
-                    let transcoded_file_path =
-                        match SourceAlbumState::get_transcoded_file_path(
-                            &configuration,
-                            &library_configuration,
-                            &data_file,
-                        ) {
-                            Ok(transcoded_path) => transcoded_path,
-                            Err(error) => {
-                                return Some(Err(error));
+            // Transcoded files might have been renamed when transcoding, so we prefer
+            // to look up their paths in the saved transcoded state.
+            let saved_original_to_transcoded_paths =
+                saved_transcoded_state.as_ref().map(|state| {
+                    state.transcoded_to_original_file_paths.to_inverted_map()
+                });
+
+            // Broken symbolic links are treated as unchanged (see `BrokenSymlinkHandling`),
+            // so their transcoded counterparts aren't removed.
+            let broken_symlinks = album_file_list
+                .broken_symlinks
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect::<HashSet<String>>();
+
+            let find_removed_transcoded_files =
+                |saved_source_files: &HashSet<String>,
+                 fresh_source_files: &HashSet<String>|
+                 -> Result<Vec<String>> {
+                    saved_source_files
+                        .sub(fresh_source_files)
+                        .into_iter()
+                        .filter(|source_file| !broken_symlinks.contains(source_file))
+                        .filter_map(|source_file| {
+                            let saved_transcoded_file_path =
+                                saved_original_to_transcoded_paths
+                                    .as_ref()
+                                    .and_then(|map| map.get(&source_file))
+                                    .map(PathBuf::from);
+
+                            let transcoded_file_path =
+                                match saved_transcoded_file_path {
+                                    Some(transcoded_path) => transcoded_path,
+                                    None => match SourceAlbumState::get_transcoded_file_path(
+                                        &configuration,
+                                        &library_configuration,
+                                        &source_file,
+                                    ) {
+                                        Ok(transcoded_path) => transcoded_path,
+                                        Err(error) => {
+                                            return Some(Err(error));
+                                        }
+                                    },
+                                };
+
+                            // We don't need to bother with the file if it doesn't exist
+                            // in the transcoded directory.
+                            if transcoded_album_directory
+                                .join(&transcoded_file_path)
+                                .is_file()
+                            {
+                                Some(Ok(transcoded_file_path
+                                    .to_string_lossy()
+                                    .to_string()))
+                            } else {
+                                None
                             }
-                        };
-
-                    match transcoded_file_path.exists()
-                        && transcoded_file_path.is_file()
-                    {
-                        true => None,
-                        false => Some(Ok(data_file)),
+                        })
+                        .collect()
+                };
+
+            let mut audio_files_removed = find_removed_transcoded_files(
+                &saved_source_file_list_audio,
+                &fresh_source_file_list_audio,
+            )?;
+
+            // Audio files that are still tracked, but whose transcoded path changed since the last transcode
+            // (e.g. after changing the library's `audio_transcoding_output_extension`). Their new transcoded
+            // path is missing (see group 4), while the old one is removed here.
+            if let Some(saved_transcoded_state) = &saved_transcoded_state {
+                let expected_transcoded_audio_files =
+                    source_to_transcode_relative_path_map
+                        .audio
+                        .values()
+                        .collect::<HashSet<&PathBuf>>();
+
+                for (saved_transcoded_file, source_file) in
+                    &saved_transcoded_state
+                        .transcoded_to_original_file_paths
+                        .audio
+                {
+                    let saved_transcoded_file_path =
+                        PathBuf::from(saved_transcoded_file);
+
+                    let is_stale = fresh_source_file_list_audio
+                        .contains(source_file)
+                        && !expected_transcoded_audio_files
+                            .contains(&saved_transcoded_file_path)
+                        && transcoded_album_directory
+                            .join(&saved_transcoded_file_path)
+                            .is_file();
+
+                    if is_stale {
+                        audio_files_removed.push(saved_transcoded_file.clone());
                     }
-                })
-                .collect::<Result<Vec<String>>>()?;
+                }
+            }
+            let data_files_removed = find_removed_transcoded_files(
+                &saved_source_file_list_data,
+                &fresh_source_file_list_data,
+            )?;
 
             SortedFileList::new(
                 Self::convert_relative_paths_to_absolute(
@@ -346,6 +442,7 @@ impl<'view> AlbumFileChangesV2<'view> {
                     .intersection(&saved_source_file_list_audio),
                 &saved_source_album_file_state.audio_files,
                 &fresh_source_album_file_state.audio_files,
+                &library_configuration.transcoding.change_detection_fields,
             )
             .into_iter()
             .map(PathBuf::from)
@@ -369,6 +466,7 @@ impl<'view> AlbumFileChangesV2<'view> {
                     .intersection(&saved_source_file_list_data),
                 &saved_source_album_file_state.data_files,
                 &fresh_source_album_file_state.data_files,
+                &library_configuration.transcoding.change_detection_fields,
             )
             .into_iter()
             .map(PathBuf::from)
@@ -409,69 +507,45 @@ impl<'view> AlbumFileChangesV2<'view> {
 
         /*
          * Group 5: unexpected excess files in the transcoded directory
-         *          (not matching previous transcode)
+         *          (neither matching the previous transcode nor expected from the current source files)
          */
-        let excess_in_transcoded = {
-            let fresh_state_in_transcoded_directory =
-                fresh_transcoded_file_list_audio
-                    .union(&fresh_transcoded_file_list_data)
-                    .map(PathBuf::from)
-                    .collect::<HashSet<PathBuf>>();
-
-            let previous_transcode_expected_files =
-                saved_transcoded_file_list_audio
-                    .union(&saved_transcoded_file_list_data)
-                    .map(PathBuf::from)
-                    .collect::<HashSet<PathBuf>>();
-
-            let expected_transcoded_directory_files =
-                source_to_transcode_relative_path_map
-                    .into_flattened_map()
-                    .values()
-                    .cloned()
-                    .collect::<HashSet<PathBuf>>();
-
-            let excess_files = fresh_state_in_transcoded_directory
-                .sub(&previous_transcode_expected_files)
-                .sub(&expected_transcoded_directory_files);
-
-            // We now sort the files based on the configuration.
-            let mut excess_audio_files: Vec<PathBuf> = Vec::new();
-            let mut excess_data_files: Vec<PathBuf> = Vec::new();
-            let mut excess_unknown_files: Vec<PathBuf> = Vec::new();
-
-            for excess_file in excess_files {
-                if library_configuration
-                    .transcoding
-                    .is_path_audio_file_by_extension(&excess_file)?
-                {
-                    excess_audio_files.push(excess_file);
-                } else if library_configuration
-                    .transcoding
-                    .is_path_data_file_by_extension(&excess_file)?
-                {
-                    excess_data_files.push(excess_file);
-                } else {
-                    // This can happen if the user copies some completely other file into the
-                    // transcoded album directory.
-                    excess_unknown_files.push(excess_file);
+        let excess_in_transcoded = match &saved_transcoded_state {
+            Some(saved_transcoded_state) => {
+                let mut known_transcoded_files =
+                    saved_transcoded_file_list_audio
+                        .union(&saved_transcoded_file_list_data)
+                        .chain(saved_transcoded_state.transcode_info_file.iter())
+                        .chain(saved_transcoded_state.playlist_file.iter())
+                        .map(PathBuf::from)
+                        .collect::<HashSet<PathBuf>>();
+
+                known_transcoded_files.extend(
+                    source_to_transcode_relative_path_map
+                        .into_flattened_map()
+                        .into_values(),
+                );
+
+                // Transcoded counterparts of broken symbolic links are kept around (see `BrokenSymlinkHandling`).
+                for broken_symlink in &album_file_list.broken_symlinks {
+                    known_transcoded_files.insert(
+                        SourceAlbumState::get_transcoded_file_path(
+                            &configuration,
+                            &library_configuration,
+                            broken_symlink,
+                        )?,
+                    );
                 }
-            }
 
-            ExtendedSortedFileList::new(
-                Self::convert_relative_paths_to_absolute(
-                    &transcoded_album_directory,
-                    excess_audio_files,
-                ),
-                Self::convert_relative_paths_to_absolute(
+                Self::scan_for_excess_transcoded_files(
                     &transcoded_album_directory,
-                    excess_data_files,
-                ),
-                Self::convert_relative_paths_to_absolute(
-                    &transcoded_album_directory,
-                    excess_unknown_files,
-                ),
-            )
+                    album_scan_depth,
+                    &known_transcoded_files,
+                    &library_configuration,
+                )?
+            }
+            // Without a saved transcoded state we can't tell which files in the transcoded
+            // album directory were created by euphony, so none of them are treated as excess.
+            None => ExtendedSortedFileList::default(),
         };
 
         Ok(Self {
@@ -482,6 +556,11 @@ impl<'view> AlbumFileChangesV2<'view> {
             removed_from_source_since_last_transcode,
             missing_in_transcoded,
             excess_in_transcoded,
+            saved_source_files: saved_source_album_file_state,
+            ignores_changed_and_removed_files: false,
+            has_files_completed_in_previous_run: false,
+            unmigratable_saved_states: Vec::new(),
+            source_state_with_refreshed_directory_times: None,
         })
     }
 
@@ -491,34 +570,15 @@ impl<'view> AlbumFileChangesV2<'view> {
     /// This is useful in cases where the source album is completely removed between transcodes
     /// and otherwise isn't picked up by the diffing code (and needs to be manually added).
     ///
-    /// `album` is a reference to the `AlbumView` the album states are associated with and
-    /// `album_file_list` is the associated source file list.
-    pub fn generate_entire_transcoded_album_deletion<P: AsRef<Path>>(
+    /// `album` is a reference to the `AlbumView` the album states are associated with.
+    /// Its transcoded directory (see `AlbumView::album_directory_in_transcoded_library`) is the one
+    /// that gets cleared, so for albums that no longer exist in the source library, the view's
+    /// configuration must match the one the album was transcoded with (e.g. its `year`).
+    pub fn generate_entire_transcoded_album_deletion(
         album: SharedAlbumView<'view>,
-        album_path_relative_to_library: P,
     ) -> Result<Self> {
-        let transcoded_album_directory = {
-            let album_path_relative_to_library =
-                album_path_relative_to_library.as_ref();
-            if !album_path_relative_to_library.is_relative() {
-                return Err(miette!(
-                    "Invalid album_path_relative_to_library: not relative."
-                ));
-            }
-
-            let album_view = album.read();
-            let artist_view = album_view.read_lock_artist();
-            let library_view = artist_view.read_lock_library();
-
-            let configuration = library_view.euphony_configuration.clone();
-
-            let transcoded_library_directory =
-                Path::new(&configuration.aggregated_library.path)
-                    .join(album_path_relative_to_library);
-
-
-            transcoded_library_directory
-        };
+        let transcoded_album_directory =
+            album.read().album_directory_in_transcoded_library();
 
         if !transcoded_album_directory.exists() {
             // No files to remove.
@@ -564,6 +624,8 @@ impl<'view> AlbumFileChangesV2<'view> {
             .transcoded_files
             .data_files
             .keys()
+            .chain(saved_transcoded_state.transcode_info_file.iter())
+            .chain(saved_transcoded_state.playlist_file.iter())
             .filter(|path| {
                 let absolute_transcoded_file_path =
                     transcoded_album_directory.join(path);
@@ -595,14 +657,119 @@ impl<'view> AlbumFileChangesV2<'view> {
             removed_from_source_since_last_transcode,
             missing_in_transcoded: SortedFileList::default(),
             excess_in_transcoded: ExtendedSortedFileList::default(),
+            saved_source_files: AlbumFileState::default(),
+            ignores_changed_and_removed_files: false,
+            has_files_completed_in_previous_run: false,
+            unmigratable_saved_states: Vec::new(),
+            source_state_with_refreshed_directory_times: None,
         })
     }
 
+    /// Discard all changes except new files (`added_in_source_since_last_transcode`)
+    /// and files missing from the transcoded album directory (`missing_in_transcoded`).
+    /// This means no transcoded files will be removed or overwritten.
+    ///
+    /// The source album state generated afterwards (see `generate_source_album_state`) keeps
+    /// the saved metadata of the ignored changed and removed files, so a later full transcode
+    /// still picks them up.
+    pub fn retain_only_new_files(&mut self) {
+        self.changed_in_source_since_last_transcode = SortedFileList::default();
+        self.removed_from_source_since_last_transcode =
+            SortedFileList::default();
+        self.excess_in_transcoded = ExtendedSortedFileList::default();
+        self.ignores_changed_and_removed_files = true;
+    }
+
+    /// Discard new, changed and missing files that were already completed in an interrupted
+    /// previous run (see `AlbumTranscodeProgress`), as long as their source files haven't changed
+    /// since and their transcoded counterparts still exist. Returns the number of discarded files.
+    ///
+    /// The source and transcoded album states generated afterwards still include the discarded files.
+    pub fn retain_files_not_completed_in_previous_run(
+        &mut self,
+        progress: &AlbumTranscodeProgress,
+    ) -> Result<usize> {
+        let Some(tracked_source_files) = &self.tracked_source_files else {
+            return Ok(0);
+        };
+
+        let (source_album_directory, change_detection_fields) = {
+            let album = self.read_lock_album();
+
+            (
+                album.album_directory_in_source_library(),
+                album
+                    .library_configuration()
+                    .transcoding
+                    .change_detection_fields
+                    .clone(),
+            )
+        };
+
+        let source_to_transcoded_map = tracked_source_files
+            .map_source_file_paths_to_transcoded_file_paths_absolute()
+            .into_flattened_map();
+
+        let is_completed = |source_file_path: &PathBuf| -> Result<bool> {
+            let transcoded_file_exists = source_to_transcoded_map
+                .get(source_file_path)
+                .is_some_and(|transcoded_path| transcoded_path.is_file());
+
+            if !transcoded_file_exists || !source_file_path.is_file() {
+                return Ok(false);
+            }
+
+            let Ok(relative_source_file_path) =
+                source_file_path.strip_prefix(&source_album_directory)
+            else {
+                return Ok(false);
+            };
+
+            Ok(progress.is_completed(
+                &relative_source_file_path.to_string_lossy(),
+                &FileTrackedMetadata::from_file_path(source_file_path)?,
+                &change_detection_fields,
+            ))
+        };
+
+        let mut num_discarded_files: usize = 0;
+
+        for file_list in [
+            &mut self.added_in_source_since_last_transcode,
+            &mut self.changed_in_source_since_last_transcode,
+            &mut self.missing_in_transcoded,
+        ] {
+            for files in [&mut file_list.audio, &mut file_list.data] {
+                let mut remaining_files = Vec::with_capacity(files.len());
+
+                for file in files.drain(..) {
+                    if is_completed(&file)? {
+                        num_discarded_files += 1;
+                    } else {
+                        remaining_files.push(file);
+                    }
+                }
+
+                *files = remaining_files;
+            }
+        }
+
+        if num_discarded_files > 0 {
+            self.has_files_completed_in_previous_run = true;
+        }
+
+        Ok(num_discarded_files)
+    }
+
     /// Returns `true` if any changes were detected since last transcode
     /// (essentially always `true` if no previous transcoding has been done
     /// and the directory has some audio/data files).
+    ///
+    /// Albums whose files were all completed in an interrupted previous run also count as changed,
+    /// as their state files still need to be saved.
     pub fn has_changes(&self) -> bool {
-        !self.added_in_source_since_last_transcode.is_empty()
+        self.has_files_completed_in_previous_run
+            || !self.added_in_source_since_last_transcode.is_empty()
             || !self.changed_in_source_since_last_transcode.is_empty()
             || !self.removed_from_source_since_last_transcode.is_empty()
             || !self.missing_in_transcoded.is_empty()
@@ -638,12 +805,57 @@ impl<'view> AlbumFileChangesV2<'view> {
     ///
     /// This method does no further disk lookups, all information is already in the memory.
     pub fn generate_source_album_state(&self) -> Result<SourceAlbumState> {
-        SourceAlbumState::generate_from_tracked_files(
-            self.tracked_source_files.as_ref().ok_or_else(|| {
-                miette!("Can't generate source album state, no tracked files.")
-            })?,
-            self.read_lock_album().album_directory_in_source_library(),
-        )
+        let mut source_album_state =
+            SourceAlbumState::generate_from_tracked_files(
+                self.tracked_source_files.as_ref().ok_or_else(|| {
+                    miette!(
+                        "Can't generate source album state, no tracked files."
+                    )
+                })?,
+                self.read_lock_album().album_directory_in_source_library(),
+                Some(&self.saved_source_files),
+                ContentHashing::Complete,
+            )?;
+
+        if self.ignores_changed_and_removed_files {
+            let change_detection_fields = self
+                .read_lock_album()
+                .library_configuration()
+                .transcoding
+                .change_detection_fields
+                .clone();
+
+            // Changed and removed files weren't processed, so we keep their saved metadata
+            // in order for the next (full) transcode to still detect them.
+            for (saved_file_map, fresh_file_map) in [
+                (
+                    &self.saved_source_files.audio_files,
+                    &mut source_album_state.tracked_files.audio_files,
+                ),
+                (
+                    &self.saved_source_files.data_files,
+                    &mut source_album_state.tracked_files.data_files,
+                ),
+            ] {
+                for (file_path, saved_metadata) in saved_file_map {
+                    let is_unchanged = fresh_file_map
+                        .get(file_path)
+                        .is_some_and(|fresh_metadata| {
+                            saved_metadata.matches(
+                                fresh_metadata,
+                                &change_detection_fields,
+                            )
+                        });
+
+                    if !is_unchanged {
+                        fresh_file_map
+                            .insert(file_path.clone(), saved_metadata.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(source_album_state)
     }
 
     /// Generate a `TranscodedAlbumState`
@@ -670,6 +882,7 @@ impl<'view> AlbumFileChangesV2<'view> {
         map_key_iterator: I,
         first_metadata_map: &HashMap<String, FileTrackedMetadata>,
         second_metadata_map: &HashMap<String, FileTrackedMetadata>,
+        change_detection_fields: &[ChangeDetectionField],
     ) -> Vec<String> {
         map_key_iterator
             .filter_map(|file_name| {
@@ -681,7 +894,7 @@ impl<'view> AlbumFileChangesV2<'view> {
                     .get(file_name.as_str())
                     .expect("BUG: Could not find intersecting key in second metadata map.");
 
-                match first_metadata.matches(second_metadata) {
+                match first_metadata.matches(second_metadata, change_detection_fields) {
                     true => {
                         None
                     }
@@ -695,6 +908,7 @@ impl<'view> AlbumFileChangesV2<'view> {
         map_key_iterator: I,
         first_metadata_map: &HashMap<String, FileTrackedMetadata>,
         second_metadata_map: &HashMap<String, FileTrackedMetadata>,
+        change_detection_fields: &[ChangeDetectionField],
     ) -> Vec<String> {
         map_key_iterator
             .filter_map(|file_name| {
@@ -706,7 +920,7 @@ impl<'view> AlbumFileChangesV2<'view> {
                     .get(file_name.as_str())
                     .expect("BUG: Could not find intersecting key in second metadata map.");
 
-                match first_metadata.matches(second_metadata) {
+                match first_metadata.matches(second_metadata, change_detection_fields) {
                     true => {
                         Some(file_name.to_string())
                     }
@@ -718,6 +932,73 @@ impl<'view> AlbumFileChangesV2<'view> {
 
     /// Given an iterator over relative paths (can be `String`, `str`),
     /// construct a vector that contains absolute paths.
+    /// Scan the transcoded album directory (`scan_depth` directories deep) for files
+    /// that aren't in `known_transcoded_files` (relative paths), ignoring euphony's own state files.
+    /// Returned paths are absolute.
+    fn scan_for_excess_transcoded_files(
+        transcoded_album_directory: &Path,
+        scan_depth: usize,
+        known_transcoded_files: &HashSet<PathBuf>,
+        library_configuration: &LibraryConfiguration,
+    ) -> Result<ExtendedSortedFileList<PathBuf>> {
+        let mut excess_files = ExtendedSortedFileList::default();
+
+        if !transcoded_album_directory.is_dir() {
+            return Ok(excess_files);
+        }
+
+        let transcoded_album_scan = DirectoryScan::scan_with_options(
+            transcoded_album_directory,
+            Some(scan_depth),
+            false,
+        )
+        .map_err(FileAccessError::new)
+        .wrap_err_with(|| {
+            miette!(
+                "Errored while scanning transcoded album directory: {:?}",
+                transcoded_album_directory
+            )
+        })?;
+
+        for file_path in transcoded_album_scan.files {
+            let relative_file_path = file_path
+                .strip_prefix(transcoded_album_directory)
+                .into_diagnostic()?;
+
+            let is_state_file = relative_file_path
+                .extension()
+                .is_some_and(|extension| extension == STATE_FILE_EXTENSION);
+
+            if is_state_file
+                || known_transcoded_files.contains(relative_file_path)
+            {
+                continue;
+            }
+
+            if library_configuration
+                .transcoding
+                .is_path_audio_file_by_extension(&file_path)?
+            {
+                excess_files.audio.push(file_path);
+            } else if library_configuration
+                .transcoding
+                .is_path_data_file_by_extension(&file_path)?
+            {
+                excess_files.data.push(file_path);
+            } else {
+                // This can happen if the user copies some completely other file into the
+                // transcoded album directory.
+                excess_files.unknown.push(file_path);
+            }
+        }
+
+        excess_files.audio.sort_unstable();
+        excess_files.data.sort_unstable();
+        excess_files.unknown.sort_unstable();
+
+        Ok(excess_files)
+    }
+
     fn convert_relative_paths_to_absolute<
         D: AsRef<Path>,
         E: AsRef<Path>,
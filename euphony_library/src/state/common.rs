@@ -1,9 +1,16 @@
 use std::collections::HashMap;
+use std::fs::File;
+use std::io;
 use std::path::{Path, PathBuf};
 use std::time::UNIX_EPOCH;
 
-use miette::{miette, Context, IntoDiagnostic, Result};
+use euphony_configuration::aggregated_library::ScanSkipStrategy;
+use euphony_configuration::library::{ChangeDetection, ChangeDetectionField};
+use fs_more::directory::DirectoryScan;
+use lofty::{Accessor, TaggedFileExt};
+use miette::{miette, Context, Diagnostic, IntoDiagnostic, Report, Result};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::utilities::f64_approximate_eq;
 use crate::view::AlbumSourceFileList;
@@ -23,29 +30,89 @@ pub struct AlbumFileState {
     pub data_files: HashMap<String, FileTrackedMetadata>,
 }
 
+
+/// A file or directory of an album could not be accessed while scanning it (e.g. an I/O
+/// or a permission error while reading its metadata or contents). Unlike other scan errors
+/// (e.g. a saved state that can't be parsed), such errors only affect the album being scanned.
+#[derive(Error, Debug, Diagnostic)]
+#[error(transparent)]
+pub struct FileAccessError(Box<dyn std::error::Error + Send + Sync>);
+
+impl FileAccessError {
+    pub fn new<E: std::error::Error + Send + Sync + 'static>(error: E) -> Self {
+        Self(Box::new(error))
+    }
+
+    /// Returns `true` if `error` (or any error in its chain) is a `FileAccessError`.
+    pub fn is_cause_of(error: &Report) -> bool {
+        error.chain().any(|cause| cause.is::<Self>())
+    }
+}
+
 impl AlbumFileState {
     /// Generate an `AlbumFileState` instance from the `AlbumSourceFileList`
     /// you got from `AlbumView`. A bit complicated, I know.
     ///
     /// The data in the instance refers to the state in the **source (untranscoded) album directory**.
+    ///
+    /// If the library's `transcoding.change_detection` is set to `hash`, content hashes are computed
+    /// as described by `content_hashing`, reusing the hashes from `saved_file_state` where possible.
     pub fn generate_source_state_from_source_file_list<P: AsRef<Path>>(
         tracked_source_files: &AlbumSourceFileList,
         base_source_album_directory: P,
+        saved_file_state: Option<&AlbumFileState>,
+        content_hashing: ContentHashing,
     ) -> Result<Self> {
         let base_source_album_directory = base_source_album_directory.as_ref();
 
-        let audio_file_map = Self::build_file_map_from_paths(
+        let (track_audio_tags, hash_contents, change_detection_fields) = {
+            let album = tracked_source_files.album.read();
+            let transcoding_configuration =
+                &album.library_configuration().transcoding;
+
+            (
+                album
+                    .euphony_configuration()
+                    .aggregated_library
+                    .retranscode_on_tag_change,
+                transcoding_configuration.change_detection
+                    == ChangeDetection::Hash,
+                transcoding_configuration.change_detection_fields.clone(),
+            )
+        };
+
+        let mut audio_file_map = Self::build_file_map_from_paths(
             base_source_album_directory,
             &tracked_source_files.audio_files,
             true,
+            track_audio_tags,
         )?;
 
-        let data_file_map = Self::build_file_map_from_paths(
+        let mut data_file_map = Self::build_file_map_from_paths(
             base_source_album_directory,
             &tracked_source_files.data_files,
             true,
+            false,
         )?;
 
+        if hash_contents {
+            let empty_file_state = AlbumFileState::default();
+            let saved_file_state = saved_file_state.unwrap_or(&empty_file_state);
+
+            for (file_map, saved_file_map) in [
+                (&mut audio_file_map, &saved_file_state.audio_files),
+                (&mut data_file_map, &saved_file_state.data_files),
+            ] {
+                Self::hash_file_contents_in_map(
+                    base_source_album_directory,
+                    file_map,
+                    saved_file_map,
+                    content_hashing,
+                    &change_detection_fields,
+                )?;
+            }
+        }
+
         Ok(Self {
             audio_files: audio_file_map,
             data_files: data_file_map,
@@ -75,12 +142,14 @@ impl AlbumFileState {
             base_transcoded_album_directory,
             &transcoded_audio_file_list,
             false,
+            false,
         )?;
 
         let data_file_map = Self::build_file_map_from_paths(
             base_transcoded_album_directory,
             &transcoded_data_file_list,
             false,
+            false,
         )?;
 
         Ok(Self {
@@ -94,10 +163,14 @@ impl AlbumFileState {
     /// to `FileTrackedMetadata` instances containing per-file metadata.
     ///
     /// We usually need this to perform diffing between transcodes.
+    ///
+    /// If `read_audio_tags` is `true`, the files are additionally opened to read
+    /// their tags (see `FileTrackedTags`).
     fn build_file_map_from_paths<P: AsRef<Path>>(
         album_base_directory_path: P,
         relative_file_paths: &Vec<PathBuf>,
         require_all_files_to_exist: bool,
+        read_audio_tags: bool,
     ) -> Result<HashMap<String, FileTrackedMetadata>> {
         let album_directory_path = album_base_directory_path.as_ref();
 
@@ -118,10 +191,16 @@ impl AlbumFileState {
                 }
             }
 
-            let tracked_file_metadata = FileTrackedMetadata::from_file_path(
-                album_directory_path.join(file_relative_path),
-            )
-            .wrap_err_with(|| miette!("Could not generate file metadata."))?;
+            let mut tracked_file_metadata =
+                FileTrackedMetadata::from_file_path(&file_absolute_path)
+                    .wrap_err_with(|| {
+                        miette!("Could not generate file metadata.")
+                    })?;
+
+            if read_audio_tags {
+                tracked_file_metadata.tags =
+                    FileTrackedTags::from_file_path(&file_absolute_path);
+            }
 
             let file_relative_path_string =
                 file_relative_path.to_string_lossy().to_string();
@@ -131,6 +210,68 @@ impl AlbumFileState {
 
         Ok(file_map)
     }
+
+    /// Fill in the content hashes of the files in `file_map` (see `FileTrackedMetadata::hash`),
+    /// with paths relative to `album_base_directory_path`.
+    ///
+    /// A saved hash is reused if the file's size and times (only the given `fields`) are unchanged.
+    /// Otherwise, the file is read in full to hash it, except if `content_hashing` is
+    /// `ContentHashing::ChangeDetection` and the file is new or its size has changed
+    /// (such files have changed regardless of their contents).
+    fn hash_file_contents_in_map<P: AsRef<Path>>(
+        album_base_directory_path: P,
+        file_map: &mut HashMap<String, FileTrackedMetadata>,
+        saved_file_map: &HashMap<String, FileTrackedMetadata>,
+        content_hashing: ContentHashing,
+        fields: &[ChangeDetectionField],
+    ) -> Result<()> {
+        let album_directory_path = album_base_directory_path.as_ref();
+
+        for (file_relative_path, tracked_file_metadata) in file_map.iter_mut() {
+            let saved_metadata = saved_file_map.get(file_relative_path);
+
+            if let Some(saved_hash) = saved_metadata
+                .filter(|saved_metadata| {
+                    saved_metadata.size_bytes == tracked_file_metadata.size_bytes
+                        && saved_metadata
+                            .times_match(tracked_file_metadata, fields)
+                })
+                .and_then(|saved_metadata| saved_metadata.hash.as_ref())
+            {
+                tracked_file_metadata.hash = Some(saved_hash.clone());
+                continue;
+            }
+
+            let size_matches_saved =
+                saved_metadata.is_some_and(|saved_metadata| {
+                    saved_metadata.size_bytes == tracked_file_metadata.size_bytes
+                });
+
+            if content_hashing == ContentHashing::ChangeDetection
+                && !size_matches_saved
+            {
+                continue;
+            }
+
+            tracked_file_metadata.hash = Some(hash_file_contents(
+                &album_directory_path.join(file_relative_path),
+            )?);
+        }
+
+        Ok(())
+    }
+}
+
+/// Which files to hash when generating a source album file state with `transcoding.change_detection`
+/// set to `hash` (see `AlbumFileState::generate_source_state_from_source_file_list`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContentHashing {
+    /// Only hash the files needed to detect changes: files with the same size as in the saved state,
+    /// but different times (or no saved hash).
+    ChangeDetection,
+
+    /// Hash all files (reusing unchanged saved hashes), e.g. when the state is about to be saved.
+    Complete,
 }
 
 /// A single tracked file. Contains the logic for comparing multiple tracked files between runs.
@@ -139,6 +280,16 @@ pub struct FileTrackedMetadata {
     pub size_bytes: u64,
     pub time_modified: f64,
     pub time_created: f64,
+
+    /// Selected audio tags of the file. This is only present for audio files
+    /// and only when `aggregated_library.retranscode_on_tag_change` is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<FileTrackedTags>,
+
+    /// Hex-encoded BLAKE3 hash of the file's contents. This is only present
+    /// when the library's `transcoding.change_detection` is set to `hash`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
 }
 
 impl FileTrackedMetadata {
@@ -149,6 +300,8 @@ impl FileTrackedMetadata {
             size_bytes,
             time_modified,
             time_created,
+            tags: None,
+            hash: None,
         }
     }
 
@@ -156,24 +309,27 @@ impl FileTrackedMetadata {
     /// the filesystem for the given `file_path`.
     pub fn from_file_path<P: AsRef<Path>>(file_path: P) -> Result<Self> {
         let file_path = file_path.as_ref();
-        if !file_path.is_file() {
-            return Err(miette!("File path is not a file!"));
-        }
 
-        let file_metadata =
-            file_path.metadata().into_diagnostic().wrap_err_with(|| {
-                miette!(
-                    "Could not retrieve metadata for file: {:?}",
-                    file_path
-                )
-            })?;
+        // Not using `Path::is_file`, since that would hide an unreadable file behind a generic error.
+        let file_metadata = file_path
+            .metadata()
+            .map_err(FileAccessError::new)
+            .wrap_err_with(|| {
+            miette!(
+                "Could not retrieve metadata for file: {:?}",
+                file_path
+            )
+        })?;
 
+        if !file_metadata.is_file() {
+            return Err(miette!("File path is not a file!"));
+        }
 
         let file_size_bytes = file_metadata.len();
 
         let file_creation_time = file_metadata
             .created()
-            .into_diagnostic()
+            .map_err(FileAccessError::new)
             .wrap_err_with(|| {
                 miette!(
                     "Could not retrieve creation time for file: {:?}",
@@ -185,7 +341,7 @@ impl FileTrackedMetadata {
 
         let file_modification_time = file_metadata
             .modified()
-            .into_diagnostic()
+            .map_err(FileAccessError::new)
             .wrap_err_with(|| {
                 miette!(
                     "Could not retrieve modification time for file: {:?}",
@@ -203,33 +359,578 @@ impl FileTrackedMetadata {
         ))
     }
 
-    /// Check whether the `FileTrackedMetadata` pair matches.
+    /// Check whether the `FileTrackedMetadata` pair matches, comparing only the given `fields`
+    /// (see `LibraryTranscodingConfiguration::change_detection_fields`).
+    /// `self` must be the previously saved metadata and `fresh` the metadata that was just read from disk.
     ///
-    /// - any change in file size will cause it to return `false`,
-    /// - any change in file creation/modification time (larger than 0.1) will cause it to return `false`.
-    pub fn matches(&self, other: &Self) -> bool {
-        if self.size_bytes != other.size_bytes {
+    /// - any change in file size will cause it to return `false` (if comparing `Size`,
+    ///   or if the saved metadata has a content hash),
+    /// - if both sides have content hashes recorded, any change in the hash will cause it to return `false`
+    ///   (times are ignored in that case); if only the fresh side has a hash (the saved metadata predates
+    ///   `change_detection = "hash"`), it returns `false` as well,
+    /// - any change in file creation/modification time (larger than 0.1) will cause it to return `false`
+    ///   (if comparing `Created`/`Modified`, respectively),
+    /// - if fresh tags are recorded (tag tracking is enabled), any change in those tags
+    ///   or missing saved tags will cause it to return `false`.
+    pub fn matches(
+        &self,
+        fresh: &Self,
+        fields: &[ChangeDetectionField],
+    ) -> bool {
+        if fields.contains(&ChangeDetectionField::Size)
+            && self.size_bytes != fresh.size_bytes
+        {
             return false;
         }
 
+        match (&self.hash, &fresh.hash) {
+            (Some(saved_hash), Some(fresh_hash)) => {
+                return saved_hash == fresh_hash && self.tags_match(fresh);
+            }
+            // There is no saved hash to compare the fresh one to, so the file needs to be processed
+            // again (this also records its hash).
+            (None, Some(_)) => return false,
+            // Files whose size changed aren't hashed when detecting changes (see `ContentHashing`).
+            (Some(_), None) if self.size_bytes != fresh.size_bytes => {
+                return false;
+            }
+            _ => {}
+        }
+
+        self.times_match(fresh, fields) && self.tags_match(fresh)
+    }
+
+    /// Returns `false` if the file creation/modification time changed by more than 0.1
+    /// (if comparing `Created`/`Modified`, respectively).
+    fn times_match(
+        &self,
+        fresh: &Self,
+        fields: &[ChangeDetectionField],
+    ) -> bool {
         static DEFAULT_MAX_TIME_DISTANCE: f64 = 0.1;
 
-        if !f64_approximate_eq(
-            self.time_created,
-            other.time_created,
-            DEFAULT_MAX_TIME_DISTANCE,
-        ) {
+        if fields.contains(&ChangeDetectionField::Created)
+            && !f64_approximate_eq(
+                self.time_created,
+                fresh.time_created,
+                DEFAULT_MAX_TIME_DISTANCE,
+            )
+        {
+            return false;
+        }
+
+        if fields.contains(&ChangeDetectionField::Modified)
+            && !f64_approximate_eq(
+                self.time_modified,
+                fresh.time_modified,
+                DEFAULT_MAX_TIME_DISTANCE,
+            )
+        {
             return false;
         }
 
+        true
+    }
+
+    /// Returns `false` if fresh tags are recorded and they either differ from the saved ones
+    /// or the saved metadata has no tags (e.g. it was saved before tag tracking was enabled).
+    fn tags_match(&self, fresh: &Self) -> bool {
+        match (&self.tags, &fresh.tags) {
+            (Some(saved_tags), Some(fresh_tags)) => saved_tags == fresh_tags,
+            (None, Some(_)) => false,
+            (_, None) => true,
+        }
+    }
+}
+
+/// Hash the entire contents of the given file with BLAKE3 and return the hex-encoded hash.
+fn hash_file_contents(file_path: &Path) -> Result<String> {
+    let mut file = File::open(file_path)
+        .map_err(FileAccessError::new)
+        .wrap_err_with(|| {
+            miette!("Could not open file for hashing: {:?}", file_path)
+        })?;
+
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut file, &mut hasher)
+        .map_err(FileAccessError::new)
+        .wrap_err_with(|| miette!("Could not hash file: {:?}", file_path))?;
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+
+/// A small subset of audio file tags that are tracked between transcodes
+/// when `aggregated_library.retranscode_on_tag_change` is enabled.
+///
+/// This allows us to detect tag-only edits, where the file size and modification time
+/// might not change in a meaningful way.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct FileTrackedTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+impl FileTrackedTags {
+    /// Read the tags of the audio file at `file_path`.
+    ///
+    /// Returns `None` if the file could not be read or parsed as an audio file.
+    /// A file without any tags will return `Some` with all fields set to `None`.
+    pub fn from_file_path<P: AsRef<Path>>(file_path: P) -> Option<Self> {
+        let tagged_file = lofty::read_from_path(file_path.as_ref()).ok()?;
+
+        let Some(tag) = tagged_file
+            .primary_tag()
+            .or_else(|| tagged_file.first_tag())
+        else {
+            return Some(Self::default());
+        };
+
+        Some(Self {
+            title: tag.title().map(|title| title.to_string()),
+            artist: tag.artist().map(|artist| artist.to_string()),
+            album: tag.album().map(|album| album.to_string()),
+        })
+    }
+}
+
+
+/// Modification times of an album directory, recorded in the source album state
+/// (see `aggregated_library.scan_skip_strategy`) and in the library scan cache
+/// so that unchanged albums can be skipped without a full scan.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AlbumDirectoryTimes {
+    /// Modification time of the album directory itself.
+    pub directory_time_modified: f64,
+
+    /// Newest modification time of any file or subdirectory inside the album directory
+    /// (excluding euphony's own state files). This is only present
+    /// for the `directory_and_newest_file_mtime` strategy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub newest_file_time_modified: Option<f64>,
+
+    /// Number of files and subdirectories inside the album directory
+    /// (excluding euphony's own state files). Like `newest_file_time_modified`,
+    /// this is only present for the `directory_and_newest_file_mtime` strategy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_entries: Option<usize>,
+}
+
+impl AlbumDirectoryTimes {
+    /// Read the modification times of the given album directory as required by `strategy`.
+    /// Returns `None` if the strategy is `ScanSkipStrategy::None`.
+    ///
+    /// `scan_depth` is the album scan depth (see `scan.depth` in the album configuration).
+    pub fn from_directory_path<P: AsRef<Path>>(
+        album_directory_path: P,
+        strategy: ScanSkipStrategy,
+        scan_depth: usize,
+    ) -> Result<Option<Self>> {
+        let album_directory_path = album_directory_path.as_ref();
+
+        if strategy == ScanSkipStrategy::None {
+            return Ok(None);
+        }
+
+        let directory_time_modified = path_time_modified(album_directory_path)?;
+
+        let (newest_file_time_modified, num_entries) =
+            if strategy == ScanSkipStrategy::DirectoryAndNewestFileMtime {
+                let (newest_time_modified, num_entries) =
+                    scan_album_directory_entries(
+                        album_directory_path,
+                        scan_depth,
+                        directory_time_modified,
+                    )?;
+
+                (Some(newest_time_modified), Some(num_entries))
+            } else {
+                (None, None)
+            };
+
+        Ok(Some(Self {
+            directory_time_modified,
+            newest_file_time_modified,
+            num_entries,
+        }))
+    }
+
+    /// Re-read the modification time of the album directory itself, e.g. after euphony created
+    /// one of its own state files in it (which changes the directory's modification time,
+    /// but not the contents that are tracked).
+    pub fn refresh_directory_time_modified<P: AsRef<Path>>(
+        &mut self,
+        album_directory_path: P,
+    ) -> Result<()> {
+        self.directory_time_modified =
+            path_time_modified(album_directory_path.as_ref())?;
+
+        // The newest time always includes the directory's own modification time
+        // (see `scan_album_directory_entries`).
+        if let Some(newest_file_time_modified) =
+            &mut self.newest_file_time_modified
+        {
+            *newest_file_time_modified =
+                newest_file_time_modified.max(self.directory_time_modified);
+        }
+
+        Ok(())
+    }
+
+    /// Check whether the recorded times (within 0.1 seconds) and entry counts match.
+    /// Times recorded with different strategies never match.
+    pub fn matches(&self, other: &Self) -> bool {
+        static DEFAULT_MAX_TIME_DISTANCE: f64 = 0.1;
+
         if !f64_approximate_eq(
-            self.time_modified,
-            other.time_modified,
+            self.directory_time_modified,
+            other.directory_time_modified,
             DEFAULT_MAX_TIME_DISTANCE,
         ) {
             return false;
         }
 
-        true
+        let newest_times_match = match (
+            self.newest_file_time_modified,
+            other.newest_file_time_modified,
+        ) {
+            (Some(self_time), Some(other_time)) => f64_approximate_eq(
+                self_time,
+                other_time,
+                DEFAULT_MAX_TIME_DISTANCE,
+            ),
+            (None, None) => true,
+            _ => false,
+        };
+
+        newest_times_match && self.num_entries == other.num_entries
+    }
+}
+
+/// Scan the album directory `scan_depth` directories deep and return the newest modification time
+/// of any file or subdirectory in it (at least `directory_time_modified`) and the number of
+/// such files and subdirectories. Euphony's own state files are ignored.
+fn scan_album_directory_entries(
+    album_directory_path: &Path,
+    scan_depth: usize,
+    directory_time_modified: f64,
+) -> Result<(f64, usize)> {
+    let album_scan = DirectoryScan::scan_with_options(
+        album_directory_path,
+        Some(scan_depth),
+        false,
+    )
+    .map_err(FileAccessError::new)
+    .wrap_err_with(|| {
+        miette!(
+            "Errored while scanning album directory: {:?}",
+            album_directory_path
+        )
+    })?;
+
+    let mut newest_time_modified = directory_time_modified;
+    let mut num_entries: usize = 0;
+
+    for path in album_scan.files.iter().chain(album_scan.directories.iter()) {
+        let is_state_file = path
+            .extension()
+            .is_some_and(|extension| extension == STATE_FILE_EXTENSION);
+        if is_state_file {
+            continue;
+        }
+
+        newest_time_modified =
+            newest_time_modified.max(path_time_modified(path)?);
+        num_entries += 1;
+    }
+
+    Ok((newest_time_modified, num_entries))
+}
+
+/// Extension of euphony's own state files, which are ignored when looking for the newest file
+/// (saving the state would otherwise always make the album look changed).
+pub(crate) const STATE_FILE_EXTENSION: &str = "euphony";
+
+/// Get the modification time of a file or directory as seconds since the UNIX epoch.
+fn path_time_modified(path: &Path) -> Result<f64> {
+    Ok(path
+        .metadata()
+        .map_err(FileAccessError::new)
+        .wrap_err_with(|| {
+            miette!("Could not retrieve metadata for path: {:?}", path)
+        })?
+        .modified()
+        .map_err(FileAccessError::new)
+        .wrap_err_with(|| {
+            miette!(
+                "Could not retrieve modification time for path: {:?}",
+                path
+            )
+        })?
+        .duration_since(UNIX_EPOCH)
+        .into_diagnostic()?
+        .as_secs_f64())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use euphony_configuration::library::ChangeDetectionField;
+    use miette::{miette, Context, IntoDiagnostic};
+
+    use super::{
+        path_time_modified,
+        AlbumFileState,
+        ContentHashing,
+        FileAccessError,
+        FileTrackedMetadata,
+        FileTrackedTags,
+    };
+
+    const ALL_FIELDS: [ChangeDetectionField; 3] = [
+        ChangeDetectionField::Size,
+        ChangeDetectionField::Modified,
+        ChangeDetectionField::Created,
+    ];
+
+    fn metadata_with_tags(tags: Option<FileTrackedTags>) -> FileTrackedMetadata {
+        let mut metadata = FileTrackedMetadata::new(1024, 1000.0, 1000.0);
+        metadata.tags = tags;
+        metadata
+    }
+
+    fn tags_with_title(title: &str) -> FileTrackedTags {
+        FileTrackedTags {
+            title: Some(title.to_string()),
+            ..FileTrackedTags::default()
+        }
+    }
+
+    #[test]
+    fn missing_saved_tags_are_a_mismatch_when_tracking_tags() {
+        let saved = metadata_with_tags(None);
+        let fresh = metadata_with_tags(Some(tags_with_title("Title")));
+
+        assert!(!saved.matches(&fresh, &ALL_FIELDS));
+    }
+
+    #[test]
+    fn missing_fresh_tags_match_when_not_tracking_tags() {
+        let saved = metadata_with_tags(Some(tags_with_title("Title")));
+        let fresh = metadata_with_tags(None);
+
+        assert!(saved.matches(&fresh, &ALL_FIELDS));
+        assert!(metadata_with_tags(None).matches(&fresh, &ALL_FIELDS));
+    }
+
+    #[test]
+    fn changed_tags_are_a_mismatch() {
+        let saved = metadata_with_tags(Some(tags_with_title("Title")));
+        let fresh = metadata_with_tags(Some(tags_with_title("New Title")));
+
+        assert!(!saved.matches(&fresh, &ALL_FIELDS));
+        assert!(saved.matches(&saved.clone(), &ALL_FIELDS));
+    }
+
+    #[test]
+    fn copied_file_matches_when_not_tracking_creation_time() {
+        // Copying a library (e.g. to a new disk) gives every file a new creation time,
+        // but keeps the size and (usually) the modification time.
+        let saved = FileTrackedMetadata::new(1024, 1000.0, 1000.0);
+        let fresh = FileTrackedMetadata::new(1024, 1000.0, 5000.0);
+
+        assert!(saved.matches(
+            &fresh,
+            &[ChangeDetectionField::Size, ChangeDetectionField::Modified,]
+        ));
+        assert!(!saved.matches(&fresh, &ALL_FIELDS));
+    }
+
+    #[test]
+    fn excluded_fields_are_ignored() {
+        let saved = FileTrackedMetadata::new(1024, 1000.0, 1000.0);
+
+        let resized = FileTrackedMetadata::new(2048, 1000.0, 1000.0);
+        assert!(!saved.matches(&resized, &ALL_FIELDS));
+        assert!(saved.matches(
+            &resized,
+            &[
+                ChangeDetectionField::Modified,
+                ChangeDetectionField::Created,
+            ]
+        ));
+
+        let modified = FileTrackedMetadata::new(1024, 5000.0, 1000.0);
+        assert!(!saved.matches(&modified, &ALL_FIELDS));
+        assert!(saved.matches(
+            &modified,
+            &[ChangeDetectionField::Size, ChangeDetectionField::Created,]
+        ));
+    }
+
+    fn metadata_with_hash(
+        size_bytes: u64,
+        hash: Option<&str>,
+    ) -> FileTrackedMetadata {
+        let mut metadata = FileTrackedMetadata::new(size_bytes, 1000.0, 1000.0);
+        metadata.hash = hash.map(str::to_string);
+        metadata
+    }
+
+    #[test]
+    fn missing_saved_hash_is_a_mismatch() {
+        let saved = metadata_with_hash(1024, None);
+        let fresh = metadata_with_hash(1024, Some("fresh"));
+
+        assert!(!saved.matches(&fresh, &ALL_FIELDS));
+    }
+
+    #[test]
+    fn equal_hashes_match_despite_changed_times() {
+        let saved = metadata_with_hash(1024, Some("hash"));
+        let mut fresh = metadata_with_hash(1024, Some("hash"));
+        fresh.time_modified += 100.0;
+
+        assert!(saved.matches(&fresh, &ALL_FIELDS));
+    }
+
+    #[test]
+    fn resized_file_without_fresh_hash_is_a_mismatch() {
+        let saved = metadata_with_hash(1024, Some("hash"));
+        let fresh = metadata_with_hash(2048, None);
+
+        // Even if the size isn't a change detection field.
+        assert!(!saved.matches(&fresh, &[ChangeDetectionField::Modified]));
+    }
+
+    /// Write a 3-byte file and hash it against the given saved metadata. If `same_times` is `true`,
+    /// the saved times are set to the ones of the written file.
+    fn hash_against_saved_metadata(
+        mut saved_metadata: FileTrackedMetadata,
+        same_times: bool,
+        content_hashing: ContentHashing,
+    ) -> FileTrackedMetadata {
+        let test_directory = std::env::temp_dir().join(format!(
+            "euphony-hash-test-{}-{:?}-{}",
+            std::process::id(),
+            content_hashing,
+            saved_metadata.size_bytes,
+        ));
+        fs::create_dir_all(&test_directory).unwrap();
+
+        let file_path = test_directory.join("01.flac");
+        fs::write(&file_path, "abc").unwrap();
+
+        let mut file_map = AlbumFileState::build_file_map_from_paths(
+            &test_directory,
+            &vec![PathBuf::from("01.flac")],
+            true,
+            false,
+        )
+        .unwrap();
+
+        if same_times {
+            saved_metadata.time_modified = file_map["01.flac"].time_modified;
+            saved_metadata.time_created = file_map["01.flac"].time_created;
+        }
+
+        let saved_file_map =
+            HashMap::from([("01.flac".to_string(), saved_metadata)]);
+
+        AlbumFileState::hash_file_contents_in_map(
+            &test_directory,
+            &mut file_map,
+            &saved_file_map,
+            content_hashing,
+            &ALL_FIELDS,
+        )
+        .unwrap();
+
+        fs::remove_dir_all(&test_directory).unwrap();
+
+        file_map.remove("01.flac").unwrap()
+    }
+
+    #[test]
+    fn unchanged_file_reuses_saved_hash() {
+        let saved = metadata_with_hash(3, Some("saved"));
+
+        let fresh = hash_against_saved_metadata(
+            saved,
+            true,
+            ContentHashing::ChangeDetection,
+        );
+
+        assert_eq!(fresh.hash.as_deref(), Some("saved"));
+    }
+
+    #[test]
+    fn file_with_changed_times_is_hashed() {
+        let saved = metadata_with_hash(3, Some("saved"));
+
+        let fresh = hash_against_saved_metadata(
+            saved,
+            false,
+            ContentHashing::ChangeDetection,
+        );
+
+        assert_eq!(
+            fresh.hash,
+            Some(blake3::hash(b"abc").to_hex().to_string())
+        );
+    }
+
+    #[test]
+    fn resized_file_is_only_hashed_when_complete() {
+        let saved = metadata_with_hash(1024, Some("saved"));
+
+        let fresh = hash_against_saved_metadata(
+            saved.clone(),
+            true,
+            ContentHashing::ChangeDetection,
+        );
+        assert_eq!(fresh.hash, None);
+
+        let fresh =
+            hash_against_saved_metadata(saved, true, ContentHashing::Complete);
+        assert_eq!(
+            fresh.hash,
+            Some(blake3::hash(b"abc").to_hex().to_string())
+        );
+    }
+
+    #[test]
+    fn file_access_errors_are_detected_through_context() {
+        let missing_file_path = std::env::temp_dir().join(format!(
+            "euphony-common-test-{}-missing-file",
+            std::process::id()
+        ));
+
+        let error = FileTrackedMetadata::from_file_path(&missing_file_path)
+            .wrap_err_with(|| miette!("Failed to scan album."))
+            .unwrap_err();
+        assert!(FileAccessError::is_cause_of(&error));
+
+        let error = path_time_modified(&missing_file_path).unwrap_err();
+        assert!(FileAccessError::is_cause_of(&error));
+    }
+
+    #[test]
+    fn other_errors_are_not_file_access_errors() {
+        let error = Err::<(), _>(miette!("BUG: Something went wrong."))
+            .wrap_err_with(|| miette!("Failed to scan album."))
+            .unwrap_err();
+        assert!(!FileAccessError::is_cause_of(&error));
+
+        let error = serde_json::from_str::<AlbumFileState>("{ invalid")
+            .into_diagnostic()
+            .wrap_err_with(|| miette!("Could not parse state file."))
+            .unwrap_err();
+        assert!(!FileAccessError::is_cause_of(&error));
     }
 }
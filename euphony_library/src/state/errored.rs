@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use miette::{miette, Context, Diagnostic, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+
+pub const ALBUM_ERROR_RECORD_FILE_NAME: &str = ".album.last-errors.euphony";
+const ALBUM_ERROR_RECORD_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Error, Debug, Diagnostic)]
+pub enum AlbumErrorRecordLoadError {
+    #[error("no error record found on disk")]
+    NotFound,
+
+    #[error(
+        "schema version mismatch: {0} (current is {})",
+        ALBUM_ERROR_RECORD_SCHEMA_VERSION
+    )]
+    SchemaVersionMismatch(u32),
+
+    #[error("io::Error encountered while loading error record")]
+    IoError(#[from] io::Error),
+
+    #[error("serde_json::Error encountered while loading error record")]
+    JSONError(#[from] serde_json::Error),
+}
+
+/// A single file that failed to process.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ErroredFileRecord {
+    /// Path of the file that failed to process (as reported by the failed job).
+    pub file_path: String,
+
+    /// Error message of the failed job.
+    pub error: String,
+}
+
+/// A record of files that errored the last time an album was processed.
+///
+/// This is saved as `.album.last-errors.euphony` in the album's directory inside euphony's
+/// data directory (see `AlbumView::album_directory_in_error_records`) when processing an album
+/// produced errors and removed again once it processes cleanly.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AlbumErrorRecord {
+    /// Indicates the current error record schema version.
+    pub schema_version: u32,
+
+    /// Files that errored during the last run.
+    pub errored_files: Vec<ErroredFileRecord>,
+}
+
+impl AlbumErrorRecord {
+    pub fn new(errored_files: Vec<ErroredFileRecord>) -> Self {
+        Self {
+            schema_version: ALBUM_ERROR_RECORD_SCHEMA_VERSION,
+            errored_files,
+        }
+    }
+
+    /// Load the album error record for the given album error record directory path.
+    ///
+    /// This method will use the `.album.last-errors.euphony` file
+    /// (see `ALBUM_ERROR_RECORD_FILE_NAME`) directly inside the given directory
+    /// (see `AlbumView::album_directory_in_error_records`).
+    pub fn load_from_directory<P: AsRef<Path>>(
+        directory_path: P,
+    ) -> Result<Self, AlbumErrorRecordLoadError> {
+        let record_file_path =
+            Self::get_record_file_path_for_directory(directory_path);
+
+        if !record_file_path.is_file() {
+            return Err(AlbumErrorRecordLoadError::NotFound);
+        }
+
+        let file_contents = fs::read_to_string(record_file_path)?;
+        let error_record: Self = serde_json::from_str(&file_contents)?;
+
+        if error_record.schema_version != ALBUM_ERROR_RECORD_SCHEMA_VERSION {
+            return Err(AlbumErrorRecordLoadError::SchemaVersionMismatch(
+                error_record.schema_version,
+            ));
+        }
+
+        Ok(error_record)
+    }
+
+    /// Get the path of the error record file inside the given directory.
+    /// This is set by `ALBUM_ERROR_RECORD_FILE_NAME`, which is currently `.album.last-errors.euphony`.
+    pub fn get_record_file_path_for_directory<P: AsRef<Path>>(
+        directory_path: P,
+    ) -> PathBuf {
+        directory_path.as_ref().join(ALBUM_ERROR_RECORD_FILE_NAME)
+    }
+
+    /// Save the album error record into the given directory as JSON,
+    /// overwriting any previous record. The directory is created if it doesn't exist yet.
+    pub fn save_to_directory<P: AsRef<Path>>(
+        &self,
+        output_directory_path: P,
+    ) -> Result<()> {
+        fs::create_dir_all(output_directory_path.as_ref())
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!("Could not create album error record directory.")
+            })?;
+
+        let output_file_path =
+            Self::get_record_file_path_for_directory(output_directory_path);
+
+        let serialized_record = serde_json::to_string(self)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!("Could not serialize album error record into string.")
+            })?;
+
+        let mut output_file =
+            File::create(output_file_path)
+                .into_diagnostic()
+                .wrap_err_with(|| miette!("Could not open file for writing."))?;
+
+        output_file
+            .write_all(serialized_record.as_bytes())
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!("Could not write album error record to file.")
+            })?;
+
+        Ok(())
+    }
+}
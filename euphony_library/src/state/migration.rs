@@ -0,0 +1,157 @@
+use serde_json::Value;
+
+
+/// A single schema migration step of a state file: the schema version it upgrades from
+/// and the function that upgrades the (not yet deserialized) JSON by exactly one version.
+///
+/// The function can return `None` if the given JSON can't be upgraded. There is no need
+/// to update `schema_version` inside the step, `migrate_state_json` takes care of that.
+pub type StateMigrationStep = (u32, fn(Value) -> Option<Value>);
+
+/// Read the `schema_version` field of a (not yet deserialized) state file.
+pub fn read_schema_version(state: &Value) -> Option<u32> {
+    state
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .and_then(|schema_version| u32::try_from(schema_version).ok())
+}
+
+/// Upgrade the JSON of a state file from `from_version` to `current_version` by applying
+/// each matching step in `migration_steps` in order.
+///
+/// Returns `None` if any of the required steps is missing or fails, which also includes
+/// state files with a newer schema version than `current_version`.
+pub fn migrate_state_json(
+    mut state: Value,
+    from_version: u32,
+    current_version: u32,
+    migration_steps: &[StateMigrationStep],
+) -> Option<Value> {
+    if from_version > current_version {
+        return None;
+    }
+
+    for version in from_version..current_version {
+        let (_, migration_step) = migration_steps
+            .iter()
+            .find(|(step_from_version, _)| *step_from_version == version)?;
+
+        state = migration_step(state)?;
+
+        *state.as_object_mut()?.get_mut("schema_version")? =
+            Value::from(version + 1);
+    }
+
+    Some(state)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{migrate_state_json, read_schema_version, StateMigrationStep};
+
+    /// Version 1 of the sample state called its file map `files`.
+    fn rename_files_to_tracked_files(mut state: Value) -> Option<Value> {
+        let files = state.as_object_mut()?.remove("files")?;
+        state
+            .as_object_mut()?
+            .insert("tracked_files".to_string(), files);
+
+        Some(state)
+    }
+
+    /// Version 2 of the sample state had no `album_directory_times`.
+    fn add_album_directory_times(mut state: Value) -> Option<Value> {
+        state
+            .as_object_mut()?
+            .insert("album_directory_times".to_string(), Value::Null);
+
+        Some(state)
+    }
+
+    const SAMPLE_MIGRATION_STEPS: &[StateMigrationStep] = &[
+        (1, rename_files_to_tracked_files),
+        (2, add_album_directory_times),
+    ];
+
+    fn sample_version_1_state() -> Value {
+        json!({
+            "schema_version": 1,
+            "files": {
+                "audio_files": {},
+                "data_files": {}
+            }
+        })
+    }
+
+    #[test]
+    fn older_state_is_migrated_step_by_step() {
+        let migrated_state = migrate_state_json(
+            sample_version_1_state(),
+            1,
+            3,
+            SAMPLE_MIGRATION_STEPS,
+        )
+        .unwrap();
+
+        assert_eq!(
+            migrated_state,
+            json!({
+                "schema_version": 3,
+                "tracked_files": {
+                    "audio_files": {},
+                    "data_files": {}
+                },
+                "album_directory_times": null
+            })
+        );
+        assert_eq!(read_schema_version(&migrated_state), Some(3));
+    }
+
+    #[test]
+    fn current_state_is_unchanged() {
+        let state = json!({ "schema_version": 3, "tracked_files": {} });
+
+        assert_eq!(
+            migrate_state_json(state.clone(), 3, 3, SAMPLE_MIGRATION_STEPS),
+            Some(state)
+        );
+    }
+
+    #[test]
+    fn missing_or_failing_step_is_not_migrated() {
+        // There is no step from version 0.
+        assert_eq!(
+            migrate_state_json(
+                sample_version_1_state(),
+                0,
+                3,
+                SAMPLE_MIGRATION_STEPS
+            ),
+            None
+        );
+
+        // The version 1 step requires the `files` field.
+        assert_eq!(
+            migrate_state_json(
+                json!({ "schema_version": 1 }),
+                1,
+                3,
+                SAMPLE_MIGRATION_STEPS
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn newer_state_is_not_migrated() {
+        let state = json!({ "schema_version": 4 });
+
+        assert_eq!(
+            migrate_state_json(state, 4, 3, SAMPLE_MIGRATION_STEPS),
+            None
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use euphony_configuration::library::ChangeDetectionField;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+
+use super::common::FileTrackedMetadata;
+
+
+pub const ALBUM_TRANSCODE_PROGRESS_FILE_NAME: &str =
+    ".album.transcode-progress.euphony";
+
+
+/// A single line of the album transcode progress file: a source file whose transcoded
+/// (or copied) counterpart was fully written.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct CompletedFileRecord {
+    /// Path of the source file, relative to the source album directory.
+    source_file_path: String,
+
+    /// Metadata of the source file at the time it finished processing.
+    source_file_metadata: FileTrackedMetadata,
+}
+
+/// Files of a partially-processed album that were completed before the previous run was interrupted.
+///
+/// While an album is being processed, every completed file is appended as a JSON line to
+/// `.album.transcode-progress.euphony` (see `ALBUM_TRANSCODE_PROGRESS_FILE_NAME`) in the transcoded
+/// album directory. Once the album's state files have been saved, the progress file is removed.
+/// If it is still there on the next run, the album was interrupted and its completed files
+/// don't need to be processed again (see `AlbumFileChangesV2::retain_files_not_completed_in_previous_run`).
+///
+/// The progress file is never used in place of the album state files, it can only cause
+/// files to be skipped if their source is still exactly the same as when they were completed.
+#[derive(Clone, Debug, Default)]
+pub struct AlbumTranscodeProgress {
+    /// Maps from source file paths (relative to the source album directory)
+    /// to their metadata at the time they were completed.
+    completed_files: HashMap<String, FileTrackedMetadata>,
+}
+
+impl AlbumTranscodeProgress {
+    /// Get the path of the progress file inside the given transcoded album directory.
+    /// This is set by `ALBUM_TRANSCODE_PROGRESS_FILE_NAME`, which is currently `.album.transcode-progress.euphony`.
+    pub fn get_progress_file_path_for_directory<P: AsRef<Path>>(
+        directory_path: P,
+    ) -> PathBuf {
+        directory_path
+            .as_ref()
+            .join(ALBUM_TRANSCODE_PROGRESS_FILE_NAME)
+    }
+
+    /// Load the progress file from the given transcoded album directory.
+    /// Returns `None` if there is no progress file (i.e. the album wasn't interrupted).
+    ///
+    /// Lines that can't be parsed (e.g. a line cut short by a crash) are ignored,
+    /// their files are simply processed again.
+    pub fn load_from_directory<P: AsRef<Path>>(
+        directory_path: P,
+    ) -> Result<Option<Self>> {
+        let progress_file_path =
+            Self::get_progress_file_path_for_directory(directory_path);
+
+        if !progress_file_path.is_file() {
+            return Ok(None);
+        }
+
+        let file_contents = fs::read_to_string(&progress_file_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not read album transcode progress at {:?}.",
+                    progress_file_path
+                )
+            })?;
+
+        let completed_files = file_contents
+            .lines()
+            .filter_map(|line| {
+                serde_json::from_str::<CompletedFileRecord>(line).ok()
+            })
+            .map(|record| {
+                (
+                    record.source_file_path,
+                    record.source_file_metadata,
+                )
+            })
+            .collect();
+
+        Ok(Some(Self { completed_files }))
+    }
+
+    /// Returns `true` if the given source file (relative to the source album directory)
+    /// was completed in the interrupted run and hasn't changed since (comparing only the given `fields`).
+    pub fn is_completed(
+        &self,
+        source_file_path: &str,
+        fresh_source_file_metadata: &FileTrackedMetadata,
+        fields: &[ChangeDetectionField],
+    ) -> bool {
+        self.completed_files.get(source_file_path).is_some_and(
+            |completed_metadata| {
+                completed_metadata.matches(fresh_source_file_metadata, fields)
+            },
+        )
+    }
+
+    /// Remove the progress file from the given transcoded album directory, if it exists.
+    pub fn remove_from_directory<P: AsRef<Path>>(
+        directory_path: P,
+    ) -> Result<()> {
+        let progress_file_path =
+            Self::get_progress_file_path_for_directory(directory_path);
+
+        if !progress_file_path.is_file() {
+            return Ok(());
+        }
+
+        fs::remove_file(&progress_file_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not remove album transcode progress at {:?}.",
+                    progress_file_path
+                )
+            })
+    }
+}
+
+/// Appends completed files to an album's progress file (see `AlbumTranscodeProgress`).
+pub struct AlbumTranscodeProgressWriter {
+    progress_file_path: PathBuf,
+
+    progress_file: File,
+}
+
+impl AlbumTranscodeProgressWriter {
+    /// Open (or create) the progress file in the given transcoded album directory for appending.
+    /// Any records left behind by an interrupted run are kept.
+    pub fn open_in_directory<P: AsRef<Path>>(directory_path: P) -> Result<Self> {
+        let directory_path = directory_path.as_ref();
+
+        fs::create_dir_all(directory_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not create transcoded album directory at {:?}.",
+                    directory_path
+                )
+            })?;
+
+        let progress_file_path =
+            AlbumTranscodeProgress::get_progress_file_path_for_directory(
+                directory_path,
+            );
+
+        let progress_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&progress_file_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not open album transcode progress at {:?}.",
+                    progress_file_path
+                )
+            })?;
+
+        // A crash may have cut the last record short, in which case the next record
+        // must start on a new line so that only the cut one is lost.
+        let ends_with_partial_record = fs::read(&progress_file_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not read album transcode progress at {:?}.",
+                    progress_file_path
+                )
+            })?
+            .last()
+            .is_some_and(|last_byte| *last_byte != b'\n');
+
+        let mut writer = Self {
+            progress_file_path,
+            progress_file,
+        };
+
+        if ends_with_partial_record {
+            writer.write_and_sync("\n")?;
+        }
+
+        Ok(writer)
+    }
+
+    fn write_and_sync(&mut self, contents: &str) -> Result<()> {
+        self.progress_file
+            .write_all(contents.as_bytes())
+            .and_then(|_| self.progress_file.sync_data())
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not write to album transcode progress at {:?}.",
+                    self.progress_file_path
+                )
+            })
+    }
+
+    /// Record that the given source file (relative to the source album directory) has been completed.
+    ///
+    /// Each record is a single line written at once and flushed to disk before returning,
+    /// so a crash can at most cut short the line being written.
+    pub fn record_completed(
+        &mut self,
+        source_file_path: String,
+        source_file_metadata: FileTrackedMetadata,
+    ) -> Result<()> {
+        let mut serialized_record =
+            serde_json::to_string(&CompletedFileRecord {
+                source_file_path,
+                source_file_metadata,
+            })
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!("Could not serialize completed file record into string.")
+            })?;
+        serialized_record.push('\n');
+
+        self.write_and_sync(&serialized_record)
+    }
+}
@@ -9,13 +9,22 @@ use miette::{miette, Context, Diagnostic, IntoDiagnostic, Result};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use super::common::AlbumFileState;
+use super::common::{AlbumDirectoryTimes, AlbumFileState, ContentHashing};
+use super::migration::{
+    migrate_state_json,
+    read_schema_version,
+    StateMigrationStep,
+};
 use crate::view::AlbumSourceFileList;
 
 
 pub const SOURCE_ALBUM_STATE_FILE_NAME: &str = ".album.source-state.euphony";
 const SOURCE_ALBUM_STATE_SCHEMA_VERSION: u32 = 2;
 
+/// Migration steps for older source album states (see `SourceAlbumState::migrate`).
+/// Whenever `SOURCE_ALBUM_STATE_SCHEMA_VERSION` is bumped, add a step from the previous version here.
+const SOURCE_ALBUM_STATE_MIGRATION_STEPS: &[StateMigrationStep] = &[];
+
 
 #[derive(Error, Debug, Diagnostic)]
 pub enum SourceAlbumStateLoadError {
@@ -23,7 +32,7 @@ pub enum SourceAlbumStateLoadError {
     NotFound,
 
     #[error(
-        "schema version mismatch: {0} (current is {})",
+        "schema version mismatch: {0} (current is {}, no migration is available)",
         SOURCE_ALBUM_STATE_SCHEMA_VERSION
     )]
     SchemaVersionMismatch(u32),
@@ -56,6 +65,11 @@ pub struct SourceAlbumState {
     /// Keys are file paths relative to the directory for which the state
     /// is being generated for / is saved inside of.
     pub tracked_files: AlbumFileState,
+
+    /// Modification times of the source album directory at the time the state was generated
+    /// (see `aggregated_library.scan_skip_strategy`). Only present when the strategy is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_directory_times: Option<AlbumDirectoryTimes>,
 }
 
 impl SourceAlbumState {
@@ -73,15 +87,33 @@ impl SourceAlbumState {
         }
 
         let file_contents = fs::read_to_string(file_path)?;
-        let state: Self = serde_json::from_str(&file_contents)?;
+        let mut raw_state: serde_json::Value =
+            serde_json::from_str(&file_contents)?;
 
-        if state.schema_version != SOURCE_ALBUM_STATE_SCHEMA_VERSION {
-            return Err(SourceAlbumStateLoadError::SchemaVersionMismatch(
-                state.schema_version,
-            ));
+        if let Some(schema_version) = read_schema_version(&raw_state)
+            .filter(|version| *version != SOURCE_ALBUM_STATE_SCHEMA_VERSION)
+        {
+            raw_state = Self::migrate(schema_version, raw_state).ok_or(
+                SourceAlbumStateLoadError::SchemaVersionMismatch(schema_version),
+            )?;
         }
 
-        Ok(state)
+        Ok(serde_json::from_value(raw_state)?)
+    }
+
+    /// Upgrade the (not yet deserialized) JSON of a source album state with an older schema version
+    /// to the current one (see `SOURCE_ALBUM_STATE_SCHEMA_VERSION`), so that the diffing baseline
+    /// isn't lost on upgrades. Returns `None` if there is no migration path from `from_version`.
+    pub fn migrate(
+        from_version: u32,
+        state: serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        migrate_state_json(
+            state,
+            from_version,
+            SOURCE_ALBUM_STATE_SCHEMA_VERSION,
+            SOURCE_ALBUM_STATE_MIGRATION_STEPS,
+        )
     }
 
     /// Load the source album state for the given album directory path. If the directory does not have
@@ -165,6 +197,10 @@ impl SourceAlbumState {
     /// Save the source album state into the given directory as JSON.
     /// If the file exists without `allow_overwrite` being `true`, this method will return an `Err`.
     ///
+    /// Creating the state file changes the modification time of the album directory,
+    /// so in that case the recorded album directory times (if any) are refreshed afterwards
+    /// (see `aggregated_library.scan_skip_strategy`).
+    ///
     /// *This method is preferred over `SourceAlbumState::save_to_file` since it automatically uses
     /// the correct file name (see `SOURCE_ALBUM_STATE_FILE_NAME`).*
     pub fn save_to_directory<P: AsRef<Path>>(
@@ -172,11 +208,31 @@ impl SourceAlbumState {
         output_directory_path: P,
         allow_overwrite: bool,
     ) -> Result<()> {
-        let output_file_path = output_directory_path
-            .as_ref()
-            .join(SOURCE_ALBUM_STATE_FILE_NAME);
+        let output_directory_path = output_directory_path.as_ref();
+        let output_file_path =
+            output_directory_path.join(SOURCE_ALBUM_STATE_FILE_NAME);
+
+        let is_new_state_file = !output_file_path.exists();
 
-        self.save_to_file(output_file_path, allow_overwrite)
+        self.save_to_file(&output_file_path, allow_overwrite)?;
+
+        if let (true, Some(album_directory_times)) =
+            (is_new_state_file, &self.album_directory_times)
+        {
+            let mut refreshed_album_directory_times =
+                album_directory_times.clone();
+            refreshed_album_directory_times
+                .refresh_directory_time_modified(output_directory_path)?;
+
+            // Overwriting the now-existing state file doesn't change the directory's modification time.
+            let refreshed_state = Self {
+                album_directory_times: Some(refreshed_album_directory_times),
+                ..self.clone()
+            };
+            refreshed_state.save_to_file(&output_file_path, true)?;
+        }
+
+        Ok(())
     }
 
     /// Generate a new `SourceAlbumState` instance by looking at the file list provided by
@@ -184,26 +240,39 @@ impl SourceAlbumState {
     ///
     /// A path to the base of the source directory is also required for consistency with the
     /// `TranscodedAlbumState` version of this method.
+    ///
+    /// Content hashes (if enabled) are computed as described by `content_hashing`, reusing the hashes
+    /// from `saved_file_state` where possible (see `AlbumFileState::generate_source_state_from_source_file_list`).
     pub fn generate_from_tracked_files<P: AsRef<Path>>(
         tracked_album_files: &AlbumSourceFileList,
         base_source_album_directory: P,
+        saved_file_state: Option<&AlbumFileState>,
+        content_hashing: ContentHashing,
     ) -> Result<Self> {
         let tracked_files =
             AlbumFileState::generate_source_state_from_source_file_list(
                 tracked_album_files,
                 base_source_album_directory,
+                saved_file_state,
+                content_hashing,
             )?;
 
+        let album_directory_times =
+            tracked_album_files.album.read().album_directory_times()?;
+
         Ok(Self {
             schema_version: SOURCE_ALBUM_STATE_SCHEMA_VERSION,
             tracked_files,
+            album_directory_times,
         })
     }
 
     /// Provided a source file path (relative to the source album directory),
     /// get the associated relative file path in the transcoded album directory.
     ///
-    /// This method will do the necessary file extension swapping (e.g. FLAC -> MP3).
+    /// This method will do the necessary file extension swapping (e.g. FLAC -> MP3),
+    /// renaming (see `rename_audio_files_pattern` in the library configuration)
+    /// and sanitization (see `aggregated_library.filename_sanitization`).
     pub fn get_transcoded_file_path<P: AsRef<Path>>(
         configuration: &Configuration,
         library_configuration: &LibraryConfiguration,
@@ -223,12 +292,20 @@ impl SourceAlbumState {
                 )
             })?
         {
-            Ok(source_file_path.with_extension(
-                &configuration
-                    .tools
-                    .ffmpeg
-                    .audio_transcoding_output_extension,
-            ))
+            Ok(
+                configuration.aggregated_library.sanitized_file_path(
+                    library_configuration
+                        .transcoding
+                        .transcoded_audio_file_path(
+                            source_file_path,
+                            &configuration
+                                .ffmpeg_configuration_for_library(
+                                    library_configuration,
+                                )
+                                .audio_transcoding_output_extension,
+                        ),
+                ),
+            )
         } else if library_configuration
             .transcoding
             .is_path_data_file_by_extension(source_file_path)
@@ -236,7 +313,9 @@ impl SourceAlbumState {
                 miette!("Failed to check whether the file has a data extension.")
             })?
         {
-            Ok(source_file_path.to_path_buf())
+            Ok(configuration
+                .aggregated_library
+                .sanitized_file_path(source_file_path))
         } else {
             Err(miette!(
                 "Invalid file: not an audio nor data file: {:?}",
@@ -9,6 +9,11 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use super::common::AlbumFileState;
+use super::migration::{
+    migrate_state_json,
+    read_schema_version,
+    StateMigrationStep,
+};
 use crate::view::common::SortedFileMap;
 use crate::view::AlbumSourceFileList;
 
@@ -16,13 +21,17 @@ use crate::view::AlbumSourceFileList;
 const TRANSCODED_ALBUM_STATE_FILE_NAME: &str = ".album.transcode-state.euphony";
 const TRANSCODED_ALBUM_STATE_SCHEMA_VERSION: u32 = 2;
 
+/// Migration steps for older transcoded album states (see `TranscodedAlbumState::migrate`).
+/// Whenever `TRANSCODED_ALBUM_STATE_SCHEMA_VERSION` is bumped, add a step from the previous version here.
+const TRANSCODED_ALBUM_STATE_MIGRATION_STEPS: &[StateMigrationStep] = &[];
+
 #[derive(Error, Debug, Diagnostic)]
 pub enum TranscodedAlbumStateLoadError {
     #[error("no state found on disk")]
     NotFound,
 
     #[error(
-        "schema version mismatch: {0} (current is {})",
+        "schema version mismatch: {0} (current is {}, no migration is available)",
         TRANSCODED_ALBUM_STATE_SCHEMA_VERSION
     )]
     SchemaVersionMismatch(u32),
@@ -49,6 +58,18 @@ pub struct TranscodedAlbumState {
     /// A map of transcoded files (for both audio and data files).
     /// Keys are file paths relative to the transcoded album directory.
     pub transcoded_files: AlbumFileState,
+
+    /// Name of the transcode info file written into the transcoded album directory
+    /// (see `aggregated_library.write_transcode_info`), if any. Tracked so that it is removed
+    /// along with the rest of the transcoded album.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcode_info_file: Option<String>,
+
+    /// Name of the `.m3u8` playlist written into the transcoded album directory
+    /// (see `aggregated_library.generate_playlists`), if any. Tracked for the same reason
+    /// as `transcode_info_file`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playlist_file: Option<String>,
 }
 
 impl TranscodedAlbumState {
@@ -66,19 +87,35 @@ impl TranscodedAlbumState {
         }
 
         let file_contents = fs::read_to_string(file_path)?;
-        let transcoded_state: Self = serde_json::from_str(&file_contents)?;
+        let mut raw_state: serde_json::Value =
+            serde_json::from_str(&file_contents)?;
 
-        if transcoded_state.schema_version
-            != TRANSCODED_ALBUM_STATE_SCHEMA_VERSION
+        if let Some(schema_version) = read_schema_version(&raw_state)
+            .filter(|version| *version != TRANSCODED_ALBUM_STATE_SCHEMA_VERSION)
         {
-            return Err(
+            raw_state = Self::migrate(schema_version, raw_state).ok_or(
                 TranscodedAlbumStateLoadError::SchemaVersionMismatch(
-                    transcoded_state.schema_version,
+                    schema_version,
                 ),
-            );
+            )?;
         }
 
-        Ok(transcoded_state)
+        Ok(serde_json::from_value(raw_state)?)
+    }
+
+    /// Upgrade the (not yet deserialized) JSON of a transcoded album state with an older schema version
+    /// to the current one (see `TRANSCODED_ALBUM_STATE_SCHEMA_VERSION`), so that the diffing baseline
+    /// isn't lost on upgrades. Returns `None` if there is no migration path from `from_version`.
+    pub fn migrate(
+        from_version: u32,
+        state: serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        migrate_state_json(
+            state,
+            from_version,
+            TRANSCODED_ALBUM_STATE_SCHEMA_VERSION,
+            TRANSCODED_ALBUM_STATE_MIGRATION_STEPS,
+        )
     }
 
     /// Load the transcoded album state for the given transcoded album directory path.
@@ -232,6 +269,8 @@ impl TranscodedAlbumState {
                 transcoded_to_source_data_map_string,
             ),
             transcoded_files: transcoded_file_state,
+            transcode_info_file: None,
+            playlist_file: None,
         })
     }
 
@@ -1,4 +1,5 @@
 use std::fmt::Debug;
+use std::path::Path;
 
 /// Represents a double `Vec`: one for audio files, the other for data files.
 /// If you want to deal with unknown files as well, see `ExtendedSortedFileList`.
@@ -62,3 +63,13 @@ impl<T> ExtendedSortedFileList<T> {
 pub fn f64_approximate_eq(first: f64, second: f64, max_distance: f64) -> bool {
     (first - second).abs() < max_distance
 }
+
+/// Returns `true` if the given path is a symbolic link whose target doesn't exist
+/// (e.g. because it points to an unmounted drive).
+pub fn is_broken_symlink<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+
+    path.symlink_metadata()
+        .is_ok_and(|metadata| metadata.file_type().is_symlink())
+        && path.metadata().is_err()
+}
@@ -5,6 +5,7 @@ pub mod library;
 
 pub use album::{
     AlbumSourceFileList,
+    AlbumTags,
     AlbumView,
     SharedAlbumView,
     WeakAlbumView,
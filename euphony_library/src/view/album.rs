@@ -1,26 +1,78 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 
+use euphony_configuration::aggregated_library::{
+    BrokenSymlinkHandling,
+    ScanSkipStrategy,
+};
 use euphony_configuration::library::LibraryConfiguration;
 use euphony_configuration::{AlbumConfiguration, Configuration};
 use fs_more::directory::DirectoryScan;
+use lofty::{Accessor, ItemKey, TaggedFileExt};
 use miette::{miette, Context, Result};
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use super::common::{ArcRwLock, SortedFileMap, WeakRwLock};
 use super::{ArtistView, SharedArtistView};
+use crate::state::common::{
+    AlbumDirectoryTimes,
+    ContentHashing,
+    FileAccessError,
+};
 use crate::state::source::{SourceAlbumState, SourceAlbumStateLoadError};
 use crate::state::transcoded::{
     TranscodedAlbumState,
     TranscodedAlbumStateLoadError,
 };
 use crate::state::AlbumFileChangesV2;
+use crate::utilities::is_broken_symlink;
 
 pub type SharedAlbumView<'a> = ArcRwLock<AlbumView<'a>>;
 #[allow(dead_code)]
 pub type WeakAlbumView<'a> = WeakRwLock<AlbumView<'a>>;
 
+/// Maximum album directory depth scanned when `validation.recurse_album_subdirs` is enabled
+/// (protects against pathologically deep directory trees).
+pub const MAX_VALIDATION_SCAN_DEPTH: usize = 16;
+
+/// A few album-wide tags, read from the first tracked audio file of an album
+/// when `aggregated_library.read_tags` is enabled (see `AlbumView::read_album_tags`).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AlbumTags {
+    /// Release year (from the date or year tag).
+    pub year: Option<u16>,
+
+    pub album_artist: Option<String>,
+
+    pub genre: Option<String>,
+}
+
+impl AlbumTags {
+    /// Read the album tags of the audio file at `file_path`.
+    ///
+    /// Returns `None` if the file could not be read or parsed as an audio file.
+    /// A file without any tags will return `Some` with all fields set to `None`.
+    fn from_file_path<P: AsRef<Path>>(file_path: P) -> Option<Self> {
+        let tagged_file = lofty::read_from_path(file_path.as_ref()).ok()?;
+
+        let Some(tag) = tagged_file
+            .primary_tag()
+            .or_else(|| tagged_file.first_tag())
+        else {
+            return Some(Self::default());
+        };
+
+        Some(Self {
+            year: tag.year().and_then(|year| u16::try_from(year).ok()),
+            album_artist: tag
+                .get_string(&ItemKey::AlbumArtist)
+                .map(|album_artist| album_artist.to_string()),
+            genre: tag.genre().map(|genre| genre.to_string()),
+        })
+    }
+}
+
 pub struct AlbumView<'config> {
     weak_self: WeakRwLock<Self>,
 
@@ -32,6 +84,9 @@ pub struct AlbumView<'config> {
 
     /// Album name.
     pub title: String,
+
+    /// Album tags, read at most once per view (see `read_album_tags`).
+    album_tags: OnceLock<Option<AlbumTags>>,
 }
 
 impl<'config> AlbumView<'config> {
@@ -63,6 +118,7 @@ impl<'config> AlbumView<'config> {
                 artist,
                 configuration: album_configuration,
                 title: album_title,
+                album_tags: OnceLock::new(),
             })
         }))
     }
@@ -108,17 +164,117 @@ impl<'config> AlbumView<'config> {
     }
 
     /// Get the mapped album directory - an album path inside the transcoded library.
+    /// Its name follows `aggregated_library.album_directory_template`, if set.
     pub fn album_directory_in_transcoded_library(&self) -> PathBuf {
-        self.read_lock_artist()
-            .artist_directory_in_transcoded_library()
-            .join(self.title.clone())
+        let aggregated_library =
+            &self.euphony_configuration().aggregated_library;
+        let album_year = self.album_year();
+        let artist = self.read_lock_artist();
+
+        artist.artist_directory_in_transcoded_library().join(
+            aggregated_library.album_directory_name(
+                &artist.name,
+                &self.title,
+                album_year,
+            ),
+        )
+    }
+
+    /// Return the album's release year: the `year` from its override file or,
+    /// if that is not set, the year from its tags (see `read_album_tags`).
+    pub fn album_year(&self) -> Option<u16> {
+        self.configuration.year.or_else(|| {
+            self.read_album_tags()
+                .and_then(|album_tags| album_tags.year)
+        })
+    }
+
+    /// Return the album's tags, read from its first tracked audio file (sorted by path).
+    ///
+    /// Returns `None` if `aggregated_library.read_tags` is disabled or if the album has no audio files
+    /// that could be read. The tags are only read once per `AlbumView` and then cached.
+    pub fn read_album_tags(&self) -> Option<&AlbumTags> {
+        if !self.euphony_configuration().aggregated_library.read_tags {
+            return None;
+        }
+
+        self.album_tags
+            .get_or_init(|| self.read_album_tags_from_first_audio_file())
+            .as_ref()
+    }
+
+    fn read_album_tags_from_first_audio_file(&self) -> Option<AlbumTags> {
+        // A missing album directory (e.g. for a removed album) simply has no tags.
+        let album_scan = self.scan_album_directory().ok()?;
+        let transcoding_configuration =
+            &self.library_configuration().transcoding;
+
+        let first_audio_file_path = album_scan
+            .files
+            .into_iter()
+            .filter(|file_path| {
+                transcoding_configuration
+                    .is_path_audio_file_by_extension(file_path)
+                    .unwrap_or(false)
+            })
+            .min()?;
+
+        AlbumTags::from_file_path(first_audio_file_path)
+    }
+
+    /// Get the directory the album's error record is saved in (inside euphony's data directory,
+    /// see `paths.data_directory_path`). Records are grouped by library, so albums with the same
+    /// artist and title in different libraries don't share an error record.
+    pub fn album_directory_in_error_records(&self) -> PathBuf {
+        self.euphony_configuration()
+            .paths
+            .album_error_records_directory_path()
+            .join(&self.library_configuration().name)
+            .join(self.directory_path_relative_to_library_root())
+    }
+
+    /// Returns `true` if the album's transcoded directory is the same as, is inside of,
+    /// or contains its source directory (e.g. when a library is nested inside the aggregated library).
+    ///
+    /// Processing such an album would overwrite (or later re-scan) its own source files.
+    pub fn has_overlapping_source_and_transcoded_directories(&self) -> bool {
+        let source_album_directory = self.album_directory_in_source_library();
+        let transcoded_album_directory =
+            self.album_directory_in_transcoded_library();
+
+        source_album_directory.starts_with(&transcoded_album_directory)
+            || transcoded_album_directory.starts_with(&source_album_directory)
     }
 
     /// Scan the album directory and return a list of files
     /// that should be validated against the configured validation rules.
     #[allow(dead_code)]
+    ///
+    /// If `validation.recurse_album_subdirs` is enabled, this recurses into album subdirectories
+    /// (up to `MAX_VALIDATION_SCAN_DEPTH` levels deep), regardless of the album's scan depth.
     pub fn album_validation_files(&self) -> Result<Vec<PathBuf>> {
-        let album_scan = self.scan_album_directory()?;
+        let album_scan = if self
+            .euphony_configuration()
+            .validation
+            .recurse_album_subdirs
+        {
+            let validation_scan_depth = MAX_VALIDATION_SCAN_DEPTH
+                .max(self.configuration.scan.depth as usize);
+
+            DirectoryScan::scan_with_options(
+                self.album_directory_in_source_library(),
+                Some(validation_scan_depth),
+                false,
+            )
+            .wrap_err_with(|| {
+                miette!(
+                    "Errored while recursively scanning album directory: {:?}",
+                    self.album_directory_in_source_library()
+                )
+            })?
+        } else {
+            self.scan_album_directory()?
+        };
 
         Ok(album_scan.files.into_iter().collect())
     }
@@ -139,6 +295,35 @@ impl<'config> AlbumView<'config> {
         })
     }
 
+    /// Read the current modification times of the source album directory,
+    /// as required by the configured `aggregated_library.scan_skip_strategy`.
+    ///
+    /// Returns `None` if skipping unchanged albums is disabled.
+    pub fn album_directory_times(&self) -> Result<Option<AlbumDirectoryTimes>> {
+        AlbumDirectoryTimes::from_directory_path(
+            self.album_directory_in_source_library(),
+            self.euphony_configuration()
+                .aggregated_library
+                .scan_skip_strategy,
+            self.configuration.scan.depth as usize,
+        )
+    }
+
+    /// Read the newest modification time (as seconds since the UNIX epoch) of the source album
+    /// directory and any file or subdirectory inside it (up to the album's scan depth),
+    /// excluding euphony's own state files.
+    pub fn newest_source_time_modified(&self) -> Result<f64> {
+        AlbumDirectoryTimes::from_directory_path(
+            self.album_directory_in_source_library(),
+            ScanSkipStrategy::DirectoryAndNewestFileMtime,
+            self.configuration.scan.depth as usize,
+        )?
+        .and_then(|directory_times| directory_times.newest_file_time_modified)
+        .ok_or_else(|| {
+            miette!("BUG: Newest file modification time was not read.")
+        })
+    }
+
     /// This method returns an `AlbumSourceFileList`,
     /// which is a collection of tracked audio and data files.
     ///
@@ -156,53 +341,126 @@ impl<'config> AlbumView<'config> {
     /// to generate a set of changes since the last transcoding.
     ///
     /// If no transcoding has been done previously, this will mean all files will be marked as new
-    /// (see `added_in_source_since_last_transcode`).
+    /// (see `added_in_source_since_last_transcode`). The same happens if `ignore_saved_states`
+    /// is `true` - the state files on disk are then not loaded at all.
+    ///
+    /// If `aggregated_library.scan_skip_strategy` is enabled and the album directory's modification
+    /// times match the ones in the saved source state, the full scan is skipped
+    /// and no changes are returned.
     ///
     /// **This is a relatively expensive IO operation as it requires quite a bit of disk access.
     /// Reuse the results as much as possible to maintain good performance.**
-    pub fn scan_for_changes(&self) -> Result<AlbumFileChangesV2<'config>> {
+    pub fn scan_for_changes(
+        &self,
+        ignore_saved_states: bool,
+    ) -> Result<AlbumFileChangesV2<'config>> {
+        self.scan_for_changes_with_options(ignore_saved_states, true)
+    }
+
+    /// Same as `scan_for_changes`, but if `allow_scan_skip` is `false`, the album is always fully scanned,
+    /// even if `aggregated_library.scan_skip_strategy` would skip it. This is needed to find
+    /// excess files in the transcoded album directory, which don't change the source album directory.
+    pub fn scan_for_changes_with_options(
+        &self,
+        ignore_saved_states: bool,
+        allow_scan_skip: bool,
+    ) -> Result<AlbumFileChangesV2<'config>> {
         // TODO Implement caching via internal mutability for this costly scan operation.
         let source_album_directory_path =
             self.album_directory_in_source_library();
         let transcoded_album_directory_path =
             self.album_directory_in_transcoded_library();
 
-        let tracked_source_files: AlbumSourceFileList<'config> =
-            self.tracked_source_files()?;
+        let mut unmigratable_saved_states: Vec<(PathBuf, u32)> = Vec::new();
 
         // Load states from disk (if they exist) and generate fresh filesystem states as well.
-        let saved_source_album_state =
+        let saved_source_album_state = if ignore_saved_states {
+            None
+        } else {
             match SourceAlbumState::load_from_directory(
                 &source_album_directory_path,
             ) {
                 Ok(state) => Some(state),
                 Err(error) => match error {
-                    SourceAlbumStateLoadError::NotFound
-                    | SourceAlbumStateLoadError::SchemaVersionMismatch(_) => {
+                    SourceAlbumStateLoadError::NotFound => None,
+                    SourceAlbumStateLoadError::SchemaVersionMismatch(
+                        schema_version,
+                    ) => {
+                        unmigratable_saved_states.push((
+                            SourceAlbumState::get_state_file_path_for_directory(
+                                &source_album_directory_path,
+                            ),
+                            schema_version,
+                        ));
                         None
                     }
                     _ => return Err(error.into()),
                 },
-            };
+            }
+        };
+
+        if let Some(saved_directory_times) = saved_source_album_state
+            .as_ref()
+            .and_then(|state| state.album_directory_times.as_ref())
+            .filter(|_| allow_scan_skip)
+        {
+            let album_is_unchanged = self.album_directory_times()?.is_some_and(
+                |fresh_directory_times| {
+                    fresh_directory_times.matches(saved_directory_times)
+                },
+            );
+
+            if album_is_unchanged
+                && TranscodedAlbumState::get_state_file_path_for_directory(
+                    &transcoded_album_directory_path,
+                )
+                .is_file()
+            {
+                return Ok(AlbumFileChangesV2::default_with_album_view(
+                    self.weak_self.upgrade().ok_or_else(|| {
+                        miette!("Could not upgrade AlbumView's weak_self!")
+                    })?,
+                ));
+            }
+        }
+
+        let tracked_source_files: AlbumSourceFileList<'config> =
+            self.tracked_source_files()?;
+
         let fresh_source_album_state =
             SourceAlbumState::generate_from_tracked_files(
                 &tracked_source_files,
                 &source_album_directory_path,
+                saved_source_album_state
+                    .as_ref()
+                    .map(|state| &state.tracked_files),
+                ContentHashing::ChangeDetection,
             )?;
 
-        let saved_transcoded_album_state =
+        let saved_transcoded_album_state = if ignore_saved_states {
+            None
+        } else {
             match TranscodedAlbumState::load_from_directory(
                 &transcoded_album_directory_path,
             ) {
                 Ok(state) => Some(state),
                 Err(error) => match error {
-                    TranscodedAlbumStateLoadError::NotFound
-                    | TranscodedAlbumStateLoadError::SchemaVersionMismatch(_) => {
+                    TranscodedAlbumStateLoadError::NotFound => None,
+                    TranscodedAlbumStateLoadError::SchemaVersionMismatch(
+                        schema_version,
+                    ) => {
+                        unmigratable_saved_states.push((
+                            TranscodedAlbumState::get_state_file_path_for_directory(
+                                &transcoded_album_directory_path,
+                            ),
+                            schema_version,
+                        ));
                         None
                     }
                     _ => return Err(error.into()),
                 },
-            };
+            }
+        };
 
         // FIXME This is returning a list of files that should exist after transcoding instead of the current filesystem state.
         //       Document this and add an obvious way to generate both, then use the current filesystem state here
@@ -213,9 +471,31 @@ impl<'config> AlbumView<'config> {
                 transcoded_album_directory_path,
             )?;
 
+        // If the saved directory times no longer match, but the full scan below finds no changes,
+        // the saved state only needs its times refreshed for the album to be skipped next time.
+        let source_state_with_refreshed_directory_times = match (
+            &saved_source_album_state,
+            &fresh_source_album_state.album_directory_times,
+        ) {
+            (Some(saved_state), Some(fresh_directory_times))
+                if !saved_state.album_directory_times.as_ref().is_some_and(
+                    |saved_directory_times| {
+                        saved_directory_times.matches(fresh_directory_times)
+                    },
+                ) =>
+            {
+                let mut refreshed_state = saved_state.clone();
+                refreshed_state.album_directory_times =
+                    Some(fresh_directory_times.clone());
+
+                Some(refreshed_state)
+            }
+            _ => None,
+        };
+
         // Let `AlbumFileChangesV2` compare all the snapshots and generate a unified way
         // of detecting and listing changes (i.e. required work for transcoding).
-        let full_changes: AlbumFileChangesV2<'config> =
+        let mut full_changes: AlbumFileChangesV2<'config> =
             AlbumFileChangesV2::generate_from_source_and_transcoded_state(
                 saved_source_album_state,
                 fresh_source_album_state,
@@ -227,6 +507,13 @@ impl<'config> AlbumView<'config> {
                 tracked_source_files,
             )?;
 
+        full_changes.unmigratable_saved_states = unmigratable_saved_states;
+
+        if !full_changes.has_changes() {
+            full_changes.source_state_with_refreshed_directory_times =
+                source_state_with_refreshed_directory_times;
+        }
+
         Ok(full_changes)
     }
 }
@@ -251,6 +538,11 @@ pub struct AlbumSourceFileList<'config> {
     /// Data file paths associated with the album.
     /// Paths are relative to the album source directory.
     pub data_files: Vec<PathBuf>,
+
+    /// Audio or data files that are broken symbolic links and were skipped
+    /// (see `aggregated_library.broken_symlink_handling`).
+    /// Paths are relative to the album source directory.
+    pub broken_symlinks: Vec<PathBuf>,
 }
 
 impl<'config> AlbumSourceFileList<'config> {
@@ -269,25 +561,50 @@ impl<'config> AlbumSourceFileList<'config> {
             &album_directory,
             Some(locked_album_view.configuration.scan.depth as usize),
             true,
-        )?;
+        )
+        .map_err(FileAccessError::new)?;
+
+        let aggregated_library_configuration =
+            &locked_album_view.euphony_configuration().aggregated_library;
+        let broken_symlink_handling =
+            aggregated_library_configuration.broken_symlink_handling;
 
         let mut audio_files: Vec<PathBuf> = Vec::new();
         let mut data_files: Vec<PathBuf> = Vec::new();
+        let mut broken_symlinks: Vec<PathBuf> = Vec::new();
 
         for file_path in album_scan.files {
             // (relative to album source directory)
             let file_relative_path =
-                pathdiff::diff_paths(file_path, &album_directory).ok_or_else(
+                pathdiff::diff_paths(&file_path, &album_directory).ok_or_else(
                     || miette!("Could not generate relative path."),
                 )?;
 
-            if transcoding_configuration
-                .is_path_audio_file_by_extension(&file_relative_path)?
+            if aggregated_library_configuration
+                .is_ignored_file(&file_relative_path)
             {
+                continue;
+            }
+
+            let is_audio_file = transcoding_configuration
+                .is_path_audio_file_by_extension(&file_relative_path)?;
+            let is_data_file = !is_audio_file
+                && transcoding_configuration
+                    .is_path_data_file_by_extension(&file_relative_path)?;
+
+            if (is_audio_file || is_data_file) && is_broken_symlink(&file_path) {
+                if broken_symlink_handling == BrokenSymlinkHandling::Fail {
+                    return Err(miette!(
+                        "Broken symbolic link in album directory: {:?} \
+                        (see aggregated_library.broken_symlink_handling).",
+                        file_path
+                    ));
+                }
+
+                broken_symlinks.push(file_relative_path);
+            } else if is_audio_file {
                 audio_files.push(file_relative_path);
-            } else if transcoding_configuration
-                .is_path_data_file_by_extension(&file_relative_path)?
-            {
+            } else if is_data_file {
                 data_files.push(file_relative_path);
             }
         }
@@ -298,6 +615,7 @@ impl<'config> AlbumSourceFileList<'config> {
             album: album_view,
             audio_files,
             data_files,
+            broken_symlinks,
         })
     }
 
@@ -307,47 +625,210 @@ impl<'config> AlbumSourceFileList<'config> {
     /// On the surface it might make sense that the relative paths would stay the same,
     /// *but that isn't always true* (e.g. extension changes when transcoding, etc.).
     ///
+    /// If renaming is enabled for the library (see `rename_audio_files_pattern`), audio files
+    /// are renamed. Whenever a renamed file would collide with another transcoded file, the
+    /// colliding file keeps its original name instead (see `find_renaming_collisions`).
+    ///
     /// *Paths are still relative.*
     pub fn map_source_file_paths_to_transcoded_file_paths_relative(
         &self,
     ) -> SortedFileMap<PathBuf, PathBuf> {
-        let album = self.album_read();
-        let transcoded_audio_file_extension = &album
-            .euphony_configuration()
-            .tools
-            .ffmpeg
-            .audio_transcoding_output_extension;
+        self.generate_source_to_transcoded_map_and_collisions().0
+    }
 
-        // Transform audio file extensions and create a map from original to transcoded paths.
-        // Paths are *still* relative to the album directory.
-        let mut map_original_to_transcoded_audio: HashMap<PathBuf, PathBuf> =
-            HashMap::with_capacity(self.audio_files.len());
+    /// Returns source audio file paths (relative to the album directory) whose renamed
+    /// transcoded path would collide with another transcoded file. Such files keep their
+    /// original name in the transcoded album directory.
+    pub fn find_renaming_collisions(&self) -> Vec<PathBuf> {
+        self.generate_source_to_transcoded_map_and_collisions().1
+    }
 
-        for source_audio_file_path in &self.audio_files {
-            let relative_transcoded_audio_file_path = source_audio_file_path
-                .with_extension(transcoded_audio_file_extension);
+    /// Returns pairs of source files (relative to the album directory) whose transcoded paths
+    /// only differ in letter case (or not at all), e.g. `Cover.jpg` and `cover.jpg`.
+    /// On case-insensitive filesystems (the default on Windows and macOS), such files would
+    /// overwrite each other in the transcoded album directory.
+    ///
+    /// Each colliding file is paired with the (alphabetically) first file it collides with.
+    pub fn find_case_insensitive_collisions(&self) -> Vec<(PathBuf, PathBuf)> {
+        let mut source_files_by_lowercase_transcoded_path: BTreeMap<
+            String,
+            Vec<PathBuf>,
+        > = BTreeMap::new();
+
+        for (source_file_path, transcoded_file_path) in self
+            .map_source_file_paths_to_transcoded_file_paths_relative()
+            .into_flattened_map()
+        {
+            source_files_by_lowercase_transcoded_path
+                .entry(transcoded_file_path.to_string_lossy().to_lowercase())
+                .or_default()
+                .push(source_file_path);
+        }
 
-            map_original_to_transcoded_audio.insert(
-                source_audio_file_path.clone(),
-                relative_transcoded_audio_file_path,
-            );
+        let mut collisions: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+        for mut source_file_paths in
+            source_files_by_lowercase_transcoded_path.into_values()
+        {
+            source_file_paths.sort_unstable();
+
+            let (first_source_file_path, other_source_file_paths) =
+                source_file_paths.split_first().expect(
+                    "BUG: Transcoded path without any source file paths.",
+                );
+
+            for other_source_file_path in other_source_file_paths {
+                collisions.push((
+                    first_source_file_path.clone(),
+                    other_source_file_path.clone(),
+                ));
+            }
         }
 
+        collisions
+    }
+
+    /// Returns pairs of source files (relative to the album directory) whose transcoded paths
+    /// are only the same because of file name sanitization (see `aggregated_library.filename_sanitization`),
+    /// e.g. `What?.flac` and `What*.flac` with the `ntfs` policy. Such files would overwrite each other
+    /// in the transcoded album directory.
+    ///
+    /// Each colliding file is paired with the (alphabetically) first file it collides with.
+    pub fn find_sanitization_collisions(&self) -> Vec<(PathBuf, PathBuf)> {
+        let aggregated_library_configuration =
+            &self.album_read().euphony_configuration().aggregated_library;
+
+        let mut source_files_by_transcoded_path: BTreeMap<
+            PathBuf,
+            Vec<PathBuf>,
+        > = BTreeMap::new();
+
+        for (source_file_path, transcoded_file_path) in self
+            .map_source_file_paths_to_transcoded_file_paths_relative()
+            .into_flattened_map()
+        {
+            source_files_by_transcoded_path
+                .entry(transcoded_file_path)
+                .or_default()
+                .push(source_file_path);
+        }
+
+        let mut collisions: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+        for mut source_file_paths in
+            source_files_by_transcoded_path.into_values()
+        {
+            // Collisions of files that weren't changed by sanitization have other causes.
+            let is_caused_by_sanitization =
+                source_file_paths.iter().any(|source_file_path| {
+                    aggregated_library_configuration
+                        .sanitized_file_path(source_file_path)
+                        != *source_file_path
+                });
+
+            if source_file_paths.len() < 2 || !is_caused_by_sanitization {
+                continue;
+            }
+
+            source_file_paths.sort_unstable();
+
+            let (first_source_file_path, other_source_file_paths) =
+                source_file_paths.split_first().expect(
+                    "BUG: Transcoded path without any source file paths.",
+                );
+
+            for other_source_file_path in other_source_file_paths {
+                collisions.push((
+                    first_source_file_path.clone(),
+                    other_source_file_path.clone(),
+                ));
+            }
+        }
+
+        collisions
+    }
+
+    fn generate_source_to_transcoded_map_and_collisions(
+        &self,
+    ) -> (SortedFileMap<PathBuf, PathBuf>, Vec<PathBuf>) {
+        let album = self.album_read();
+        let ffmpeg_configuration = album
+            .euphony_configuration()
+            .ffmpeg_configuration_for_library(album.library_configuration());
+        let transcoded_audio_file_extension =
+            &ffmpeg_configuration.audio_transcoding_output_extension;
+        let transcoding_configuration =
+            &album.library_configuration().transcoding;
+        let aggregated_library_configuration =
+            &album.euphony_configuration().aggregated_library;
+
 
         let mut map_original_to_transcoded_data: HashMap<PathBuf, PathBuf> =
             HashMap::with_capacity(self.data_files.len());
 
         for source_data_file_path in &self.data_files {
-            // Neither relative path nor the extension changes, so we just insert two copies.
+            // Neither relative path nor the extension changes (apart from sanitization).
             map_original_to_transcoded_data.insert(
                 source_data_file_path.clone(),
-                source_data_file_path.clone(),
+                aggregated_library_configuration
+                    .sanitized_file_path(source_data_file_path),
             );
         }
 
-        SortedFileMap::new(
-            map_original_to_transcoded_audio,
-            map_original_to_transcoded_data,
+        // Keeps track of transcoded paths that are already taken, so we can detect renaming collisions.
+        let mut used_transcoded_paths: HashSet<PathBuf> =
+            map_original_to_transcoded_data.values().cloned().collect();
+        let mut renaming_collisions: Vec<PathBuf> = Vec::new();
+
+        // Transform audio file extensions (and possibly names) and create a map from original
+        // to transcoded paths. Paths are *still* relative to the album directory.
+        let mut map_original_to_transcoded_audio: HashMap<PathBuf, PathBuf> =
+            HashMap::with_capacity(self.audio_files.len());
+
+        // Sorted, so collisions are resolved the same way on each run.
+        let mut sorted_audio_files: Vec<&PathBuf> =
+            self.audio_files.iter().collect();
+        sorted_audio_files.sort_unstable();
+
+        for source_audio_file_path in sorted_audio_files {
+            let unrenamed_transcoded_audio_file_path =
+                aggregated_library_configuration.sanitized_file_path(
+                    source_audio_file_path
+                        .with_extension(transcoded_audio_file_extension),
+                );
+
+            let mut relative_transcoded_audio_file_path =
+                aggregated_library_configuration.sanitized_file_path(
+                    transcoding_configuration.transcoded_audio_file_path(
+                        source_audio_file_path,
+                        transcoded_audio_file_extension,
+                    ),
+                );
+
+            if relative_transcoded_audio_file_path
+                != unrenamed_transcoded_audio_file_path
+                && used_transcoded_paths
+                    .contains(&relative_transcoded_audio_file_path)
+            {
+                renaming_collisions.push(source_audio_file_path.clone());
+                relative_transcoded_audio_file_path =
+                    unrenamed_transcoded_audio_file_path;
+            }
+
+            used_transcoded_paths
+                .insert(relative_transcoded_audio_file_path.clone());
+            map_original_to_transcoded_audio.insert(
+                source_audio_file_path.clone(),
+                relative_transcoded_audio_file_path,
+            );
+        }
+
+        (
+            SortedFileMap::new(
+                map_original_to_transcoded_audio,
+                map_original_to_transcoded_data,
+            ),
+            renaming_collisions,
         )
     }
 
@@ -2,12 +2,18 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use euphony_configuration::library::AlbumDetection;
 use fs_more::directory::DirectoryScan;
 use miette::{miette, Context, Result};
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use super::{
-    common::{ArcRwLock, ChangedAlbumsMap, WeakRwLock},
+    common::{
+        directory_contains_audio_files,
+        ArcRwLock,
+        ChangedAlbumsMap,
+        WeakRwLock,
+    },
     AlbumView,
     LibraryView,
     SharedAlbumView,
@@ -74,9 +80,14 @@ impl<'config> ArtistView<'config> {
 
     /// Get the mapped artist directory - an artist directory path inside the transcoded library.
     pub fn artist_directory_in_transcoded_library(&self) -> PathBuf {
-        self.read_lock_library()
-            .root_directory_in_transcoded_library()
-            .join(self.name.clone())
+        let library = self.read_lock_library();
+
+        library.root_directory_in_transcoded_library().join(
+            library
+                .euphony_configuration
+                .aggregated_library
+                .normalized_directory_name(&self.name),
+        )
     }
 
     /// Get a specific album by its title. Returns `None` if the album isn't present.
@@ -118,10 +129,26 @@ impl<'config> ArtistView<'config> {
 
         let artist_directory_scan = self.scan_artist_directory()?;
 
+        let library_configuration =
+            self.read_lock_library().library_configuration;
+        let auto_detect_albums =
+            library_configuration.album_detection == AlbumDetection::AutoDetect;
+
         let mut album_map: HashMap<String, SharedAlbumView<'config>> =
             HashMap::with_capacity(artist_directory_scan.directories.len());
 
         for directory in artist_directory_scan.directories {
+            // With auto-detection, an artist directory can also contain other (nested) artist directories,
+            // which are not albums themselves (see `LibraryView::auto_detect_artist_directories`).
+            if auto_detect_albums
+                && !directory_contains_audio_files(
+                    library_configuration,
+                    &directory,
+                )?
+            {
+                continue;
+            }
+
             let album_directory_name = directory
                 .file_name()
                 .ok_or_else(|| miette!("Could not parse directory file name."))?
@@ -141,9 +168,13 @@ impl<'config> ArtistView<'config> {
     /// Returns a HashMap that maps from the album title to a tuple
     /// containing the album view and the detected changes.
     ///
-    /// For more information, see the `albums` method.
+    /// Albums with `skip_transcode` set in their override file are never included.
+    ///
+    /// For more information, see the `albums` method. For `ignore_saved_states`,
+    /// see `AlbumView::scan_for_changes`.
     pub fn scan_for_albums_with_changes(
         &self,
+        ignore_saved_states: bool,
     ) -> Result<ChangedAlbumsMap<'config>> {
         let all_albums: HashMap<String, SharedAlbumView<'config>> =
             self.albums()?;
@@ -154,7 +185,11 @@ impl<'config> ArtistView<'config> {
                 let changes = {
                     let album_locked = album.read();
 
-                    album_locked.scan_for_changes()
+                    if album_locked.configuration.skip_transcode {
+                        return None;
+                    }
+
+                    album_locked.scan_for_changes(ignore_saved_states)
                 };
 
                 let changes = match changes {
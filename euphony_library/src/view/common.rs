@@ -6,8 +6,12 @@
 
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::path::Path;
 use std::sync::{Arc, Weak};
 
+use euphony_configuration::library::LibraryConfiguration;
+use fs_more::directory::DirectoryScan;
+use miette::{miette, Context, Result};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 
@@ -20,6 +24,37 @@ pub type WeakRwLock<T> = Weak<RwLock<T>>;
 pub type ChangedAlbumsMap<'a> =
     HashMap<String, (SharedAlbumView<'a>, AlbumFileChangesV2<'a>)>;
 
+/// Returns `true` if the given directory directly (not in any subdirectory) contains
+/// at least one audio file of the given library (see `transcoding.audio_file_extensions`).
+///
+/// This is how `AlbumDetection::AutoDetect` tells album directories apart.
+pub(crate) fn directory_contains_audio_files<P: AsRef<Path>>(
+    library_configuration: &LibraryConfiguration,
+    directory_path: P,
+) -> Result<bool> {
+    let directory_path = directory_path.as_ref();
+
+    let directory_scan =
+        DirectoryScan::scan_with_options(directory_path, Some(0), true)
+            .wrap_err_with(|| {
+                miette!(
+                    "Errored while scanning directory: {:?}",
+                    directory_path
+                )
+            })?;
+
+    for file_path in directory_scan.files {
+        if library_configuration
+            .transcoding
+            .is_path_audio_file_by_extension(&file_path)?
+        {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
 /// Represents a double `HashMap`: one for audio files, the other for data files.
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct SortedFileMap<K: Eq + Hash, V> {
@@ -1,16 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use euphony_configuration::library::LibraryConfiguration;
+use euphony_configuration::library::{
+    AlbumDetection,
+    LibraryConfiguration,
+    MAX_AUTO_DETECTED_ALBUM_DEPTH,
+};
 use euphony_configuration::Configuration;
 use fs_more::directory::DirectoryScan;
-use miette::{miette, Context, Diagnostic, Result};
+use miette::{miette, Context, Diagnostic, IntoDiagnostic, Result};
 use parking_lot::RwLock;
 use thiserror::Error;
 
-use super::common::{ArcRwLock, WeakRwLock};
+use super::common::{directory_contains_audio_files, ArcRwLock, WeakRwLock};
 use super::{ArtistView, SharedArtistView};
 
 pub type SharedLibraryView<'config> = ArcRwLock<LibraryView<'config>>;
@@ -67,9 +71,13 @@ impl<'config> LibraryView<'config> {
     }
 
     /// Get the mapped target path for the library (inside the transcoded library).
-    /// This is pretty much just the root of the transcoded library.
+    /// This is pretty much just the root of the transcoded library
+    /// (or the library's own `output_path`, if it is set).
     pub fn root_directory_in_transcoded_library(&self) -> PathBuf {
-        PathBuf::from(self.euphony_configuration.aggregated_library.path.clone())
+        PathBuf::from(
+            self.euphony_configuration
+                .transcoded_library_path(self.library_configuration),
+        )
     }
 
     /// Get a specific artist by their name. Returns `None` if the artist name isn't present in the library.
@@ -105,12 +113,29 @@ impl<'config> LibraryView<'config> {
     /// NOTE: In euphony, *"artist name" is understood as the artist's directory name*. This is because
     /// euphony does not scan the artist's albums and extract the common album artist tags from the file tags,
     /// but instead relies on the directory tree to tell artist names and album titles apart.
+    /// With `AlbumDetection::AutoDetect`, the artist name is the artist directory's path relative
+    /// to the library root (see `auto_detect_artist_directories`).
     pub fn artists(&self) -> Result<HashMap<String, SharedArtistView<'config>>> {
         let self_arc: SharedLibraryView = self
             .weak_self
             .upgrade()
             .ok_or_else(|| miette!("Could not upgrade weak reference."))?;
 
+        if self.library_configuration.album_detection
+            == AlbumDetection::AutoDetect
+        {
+            return self
+                .auto_detect_artist_directories()?
+                .into_iter()
+                .map(|artist_name| {
+                    Ok((
+                        artist_name.clone(),
+                        ArtistView::new(self_arc.clone(), artist_name, false)?,
+                    ))
+                })
+                .collect();
+        }
+
         let library_directory_scan = self.scan_root_directory()?;
 
         let mut artist_map: HashMap<String, SharedArtistView> =
@@ -152,6 +177,79 @@ impl<'config> LibraryView<'config> {
         Ok(library_directory_scan.files.into_iter().collect())
     }
 
+    /// Find all artist directories in the library for `AlbumDetection::AutoDetect`, returning
+    /// their paths relative to the library root.
+    ///
+    /// Every directory that directly contains audio files is an album and its parent directory
+    /// is an artist. The following rules resolve ambiguous layouts:
+    /// - album directories are not descended into, so their subdirectories (e.g. `CD1`, `CD2`)
+    ///   always belong to the album, even if they contain audio files themselves,
+    /// - directories with audio files directly in the library root have no artist and are skipped,
+    /// - directories listed in `ignored_directories_in_base_directory` are skipped,
+    /// - directories deeper than `MAX_AUTO_DETECTED_ALBUM_DEPTH` are not searched.
+    fn auto_detect_artist_directories(&self) -> Result<BTreeSet<String>> {
+        let library_root = self.root_directory_in_source_library();
+
+        let mut artist_directories: BTreeSet<String> = BTreeSet::new();
+        let mut pending_directories: Vec<(PathBuf, usize)> =
+            vec![(library_root.clone(), 0)];
+
+        while let Some((directory, depth)) = pending_directories.pop() {
+            let directory_scan =
+                DirectoryScan::scan_with_options(&directory, Some(0), true)
+                    .wrap_err_with(|| {
+                        miette!(
+                            "Errored while scanning library directory: {:?}",
+                            directory
+                        )
+                    })?;
+
+            for subdirectory in directory_scan.directories {
+                if depth == 0 {
+                    let subdirectory_name = subdirectory
+                        .file_name()
+                        .ok_or_else(|| {
+                            miette!("Could not parse directory file name.")
+                        })?
+                        .to_string_lossy()
+                        .to_string();
+
+                    if let Some(ignored_directory_list) = &self
+                        .library_configuration
+                        .ignored_directories_in_base_directory
+                    {
+                        if ignored_directory_list.contains(&subdirectory_name) {
+                            continue;
+                        }
+                    }
+                }
+
+                if directory_contains_audio_files(
+                    self.library_configuration,
+                    &subdirectory,
+                )? {
+                    if depth > 0 {
+                        let artist_directory = directory
+                            .strip_prefix(&library_root)
+                            .into_diagnostic()?
+                            .to_string_lossy()
+                            .to_string();
+
+                        artist_directories.insert(artist_directory);
+                    }
+
+                    continue;
+                }
+
+                if depth + 1 < MAX_AUTO_DETECTED_ALBUM_DEPTH {
+                    pending_directories.push((subdirectory, depth + 1));
+                }
+            }
+        }
+
+        Ok(artist_directories)
+    }
+
     /// Perform a zero-depth directory scan of the root library directory.
     fn scan_root_directory(&self) -> Result<DirectoryScan> {
         DirectoryScan::scan_with_options(
@@ -0,0 +1,214 @@
+use std::fs;
+use std::path::Path;
+
+use crossterm::style::Stylize;
+use euphony_configuration::Configuration;
+use euphony_library::view::library::LibraryViewError;
+use euphony_library::view::LibraryView;
+use miette::{miette, Context, IntoDiagnostic, Result};
+
+use crate::commands::log_optional_library_unavailable;
+use crate::console::frontends::SimpleTerminal;
+use crate::console::LogBackend;
+
+
+/// Remove the directory if it is empty. Returns `true` if it was removed.
+fn remove_directory_if_empty(directory_path: &Path) -> Result<bool> {
+    if !directory_path.is_dir() {
+        return Ok(false);
+    }
+
+    // `std::fs::remove_dir` already guarantees that it will only remove empty directories.
+    let is_empty = directory_path
+        .read_dir()
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!(
+                "Failed to read directory contents of {:?}",
+                directory_path
+            )
+        })?
+        .next()
+        .is_none();
+
+    if !is_empty {
+        return Ok(false);
+    }
+
+    fs::remove_dir(directory_path)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!(
+                "Failed to remove empty directory at {:?}",
+                directory_path
+            )
+        })?;
+
+    Ok(true)
+}
+
+/// Associated with the `clean` command.
+///
+/// Scans every album for orphaned files in its transcoded album directory
+/// (see `AlbumFileChangesV2::excess_in_transcoded`) and prints them. If `delete` is `true`, the files are also removed, along with any album
+/// and artist directories left empty, without transcoding anything.
+pub fn cmd_clean(
+    configuration: &Configuration,
+    delete: bool,
+    terminal: &mut SimpleTerminal,
+) -> Result<()> {
+    if delete {
+        terminal.log_println(
+            "Command: remove orphaned files from the transcoded library."
+                .cyan()
+                .bold(),
+        );
+    } else {
+        terminal.log_println(
+            "Command: list orphaned files in the transcoded library (pass --delete to remove them)."
+                .cyan()
+                .bold(),
+        );
+    }
+
+    let mut total_orphaned_files: usize = 0;
+    let mut total_removed_directories: usize = 0;
+
+    for library_configuration in configuration.libraries.values() {
+        let library = match LibraryView::from_library_configuration(
+            configuration,
+            library_configuration,
+        ) {
+            Ok(library) => library,
+            Err(LibraryViewError::NoSuchDirectory(path))
+                if library_configuration.optional =>
+            {
+                log_optional_library_unavailable(
+                    terminal,
+                    &library_configuration.name,
+                    &path,
+                );
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let library_locked = library.read();
+
+        let mut artists =
+            library_locked.artists()?.into_iter().collect::<Vec<_>>();
+        artists.sort_unstable_by(|(first_name, _), (second_name, _)| {
+            first_name.cmp(second_name)
+        });
+
+        for (artist_name, artist) in artists {
+            let mut albums =
+                artist.read().albums()?.into_iter().collect::<Vec<_>>();
+            albums.sort_unstable_by(|(first_title, _), (second_title, _)| {
+                first_title.cmp(second_title)
+            });
+
+            for (album_title, album) in albums {
+                let transcoded_album_directory =
+                    album.read().album_directory_in_transcoded_library();
+
+                // Orphaned files don't change the source album directory,
+                // so `scan_skip_strategy` must not skip the album here.
+                let album_changes = album
+                    .read()
+                    .scan_for_changes_with_options(false, false)
+                    .wrap_err_with(|| {
+                        miette!(
+                            "Failed to look for orphaned files of album \"{} - {}\".",
+                            artist_name,
+                            album_title
+                        )
+                    })?;
+                let orphaned_files = album_changes.excess_in_transcoded;
+
+                if orphaned_files.is_empty() {
+                    continue;
+                }
+
+                terminal.log_println(format!(
+                    "{} - {} (library: {})",
+                    artist_name.clone().bold(),
+                    album_title.clone().bold(),
+                    library_locked.name()
+                ));
+
+                for (file_kind, file_paths) in [
+                    ("audio", &orphaned_files.audio),
+                    ("data", &orphaned_files.data),
+                    ("unknown", &orphaned_files.unknown),
+                ] {
+                    for file_path in file_paths {
+                        terminal.log_println(format!(
+                            "  {} {:?}",
+                            format!("[orphaned {file_kind}]").yellow(),
+                            file_path
+                        ));
+
+                        if delete {
+                            fs::remove_file(file_path)
+                                .into_diagnostic()
+                                .wrap_err_with(|| {
+                                    miette!(
+                                        "Failed to remove orphaned file at {:?}.",
+                                        file_path
+                                    )
+                                })?;
+                        }
+
+                        total_orphaned_files += 1;
+                    }
+                }
+
+                if delete
+                    && remove_directory_if_empty(&transcoded_album_directory)?
+                {
+                    terminal.log_println(format!(
+                        "  Removed empty album directory at {:?}.",
+                        transcoded_album_directory
+                    ));
+                    total_removed_directories += 1;
+                }
+            }
+
+            let artist_transcoded_directory =
+                artist.read().artist_directory_in_transcoded_library();
+
+            if delete && remove_directory_if_empty(&artist_transcoded_directory)?
+            {
+                terminal.log_println(format!(
+                    "Removed empty artist directory at {:?}.",
+                    artist_transcoded_directory
+                ));
+                total_removed_directories += 1;
+            }
+        }
+    }
+
+    if total_orphaned_files == 0 {
+        terminal.log_println(
+            "No orphaned files found in the transcoded library."
+                .green()
+                .bold(),
+        );
+    } else if delete {
+        terminal.log_println(
+            format!(
+                "Removed {} orphaned files and {} empty directories.",
+                total_orphaned_files, total_removed_directories
+            )
+            .green()
+            .bold(),
+        );
+    } else {
+        terminal.log_println(format!(
+            "Found {} orphaned files, run with --delete to remove them.",
+            total_orphaned_files
+        ));
+    }
+
+    Ok(())
+}
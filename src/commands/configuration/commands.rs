@@ -2,6 +2,8 @@ use std::path::Path;
 
 use crossterm::style::Stylize;
 use euphony_configuration::Configuration;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Serialize;
 
 use crate::console::frontends::SimpleTerminal;
 use crate::console::LogBackend;
@@ -25,10 +27,43 @@ fn terminal_print_group_header<S: AsRef<str>>(
     ));
 }
 
+/// Machine-readable formats of `show-config --format`.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum SerializedConfigurationFormat {
+    Json,
+    Toml,
+}
+
 /// Associated with the `show-config` command.
 ///
-/// Prints the entire configuration.
-pub fn cmd_show_config(config: &Configuration, terminal: &mut SimpleTerminal) {
+/// Prints the entire configuration. If `serialized_format` is set, only the fully-resolved
+/// configuration (with all placeholders and environment variables expanded) is printed
+/// in that format instead, so the output can be piped elsewhere.
+pub fn cmd_show_config(
+    config: &Configuration,
+    serialized_format: Option<SerializedConfigurationFormat>,
+    terminal: &mut SimpleTerminal,
+) -> Result<()> {
+    if let Some(serialized_format) = serialized_format {
+        let serialized_configuration = match serialized_format {
+            SerializedConfigurationFormat::Json => {
+                serde_json::to_string_pretty(config)
+                    .into_diagnostic()
+                    .wrap_err_with(|| {
+                        miette!("Could not serialize configuration into JSON.")
+                    })?
+            }
+            SerializedConfigurationFormat::Toml => {
+                toml::to_string(config).into_diagnostic().wrap_err_with(
+                    || miette!("Could not serialize configuration into TOML."),
+                )?
+            }
+        };
+
+        terminal.log_println(serialized_configuration);
+        return Ok(());
+    }
+
     terminal.log_println(format!(
         "Configuration file: {}",
         config.configuration_file_path.to_string_lossy(),
@@ -46,6 +81,10 @@ pub fn cmd_show_config(config: &Configuration, terminal: &mut SimpleTerminal) {
         "    base_tools_path = {}",
         config.paths.base_tools_path,
     ));
+    terminal.log_println(format!(
+        "    data_directory_path = {}",
+        config.paths.data_directory_path.to_string_lossy(),
+    ));
     terminal.log_newline();
 
 
@@ -55,6 +94,32 @@ pub fn cmd_show_config(config: &Configuration, terminal: &mut SimpleTerminal) {
         "    default_log_output_path = {:?}",
         config.logging.default_log_output_path
     ));
+    terminal.log_println(format!(
+        "    file_format = {:?}",
+        config.logging.file_format
+    ));
+
+
+    // UI
+    terminal_print_group_header(terminal, "ui");
+    terminal.log_println(format!(
+        "    refresh_interval_ms = {}",
+        config.ui.refresh_interval_ms,
+    ));
+    terminal.log_println(format!(
+        "    default_command = {:?}",
+        config.ui.default_command,
+    ));
+    terminal.log_println(format!(" => {}", "transcoding".bold()));
+    terminal.log_println(format!(
+        "    show_logs_tab_on_exit = {}",
+        config.ui.transcoding.show_logs_tab_on_exit,
+    ));
+    terminal.log_println(format!(" => {}", "bare".bold()));
+    terminal.log_println(format!(
+        "    progress_summary_interval_seconds = {}",
+        config.ui.bare.progress_summary_interval_seconds,
+    ));
 
 
     // Validation (basics)
@@ -63,6 +128,10 @@ pub fn cmd_show_config(config: &Configuration, terminal: &mut SimpleTerminal) {
         "    extensions_considered_audio_files = {:?}",
         config.validation.extensions_considered_audio_files,
     ));
+    terminal.log_println(format!(
+        "    recurse_album_subdirs = {}",
+        config.validation.recurse_album_subdirs,
+    ));
 
 
     // Tools
@@ -80,6 +149,14 @@ pub fn cmd_show_config(config: &Configuration, terminal: &mut SimpleTerminal) {
         "    audio_transcoding_output_extension = {:?}",
         config.tools.ffmpeg.audio_transcoding_output_extension,
     ));
+    terminal.log_println(format!(
+        "    audio_transcoding_output_format = {:?}",
+        config.tools.ffmpeg.audio_transcoding_output_format,
+    ));
+    terminal.log_println(format!(
+        "    per_file_timeout_seconds = {:?}",
+        config.tools.ffmpeg.per_file_timeout_seconds,
+    ));
     terminal.log_newline();
 
 
@@ -108,6 +185,10 @@ pub fn cmd_show_config(config: &Configuration, terminal: &mut SimpleTerminal) {
                 }
             }
         ));
+        terminal.log_println(format!(
+            "    output_path = {:?}",
+            library.output_path
+        ));
         terminal.log_println(format!(
             "    ignored_directories_in_base_directory = {:?}",
             library
@@ -115,6 +196,11 @@ pub fn cmd_show_config(config: &Configuration, terminal: &mut SimpleTerminal) {
                 .as_ref()
                 .unwrap_or(&Vec::new())
         ));
+        terminal.log_println(format!("    optional = {}", library.optional));
+        terminal.log_println(format!(
+            "    album_detection = {:?}",
+            library.album_detection
+        ));
 
         // `validation` sub-table
         terminal.log_println(format!("     => {}", "validation".italic()));
@@ -130,6 +216,10 @@ pub fn cmd_show_config(config: &Configuration, terminal: &mut SimpleTerminal) {
             "        allowed_other_files_by_name = {:?}",
             library.validation.allowed_other_files_by_name,
         ));
+        terminal.log_println(format!(
+            "        warning_categories = {:?}",
+            library.validation.warning_categories,
+        ));
 
         // `transcoding` sub-table
         terminal.log_println(format!("     => {}", "transcoding".italic()));
@@ -141,6 +231,34 @@ pub fn cmd_show_config(config: &Configuration, terminal: &mut SimpleTerminal) {
             "        other_file_extensions = {:?}",
             library.transcoding.other_file_extensions,
         ));
+        terminal.log_println(format!(
+            "        rename_audio_files_pattern = {:?}",
+            library
+                .transcoding
+                .rename_audio_files_pattern
+                .as_ref()
+                .map(|pattern| pattern.as_str()),
+        ));
+        terminal.log_println(format!(
+            "        change_detection = {:?}",
+            library.transcoding.change_detection,
+        ));
+        terminal.log_println(format!(
+            "        change_detection_fields = {:?}",
+            library.transcoding.change_detection_fields,
+        ));
+        terminal.log_println(format!(
+            "        transcode_threads = {:?}",
+            library.transcoding.transcode_threads,
+        ));
+        terminal.log_println(format!(
+            "        audio_transcoding_args = {:?}",
+            library.transcoding.audio_transcoding_args,
+        ));
+        terminal.log_println(format!(
+            "        audio_transcoding_output_extension = {:?}",
+            library.transcoding.audio_transcoding_output_extension,
+        ));
 
         terminal.log_newline();
     }
@@ -156,6 +274,26 @@ pub fn cmd_show_config(config: &Configuration, terminal: &mut SimpleTerminal) {
         "  transcode_threads = {}",
         config.aggregated_library.transcode_threads,
     ));
+    terminal.log_println(format!(
+        "  transcode_album_concurrency = {}",
+        config.aggregated_library.transcode_album_concurrency,
+    ));
+    terminal.log_println(format!(
+        "  scan_threads = {}",
+        config.aggregated_library.scan_threads,
+    ));
+    terminal.log_println(format!(
+        "  delete_threads = {:?}",
+        config.aggregated_library.delete_threads,
+    ));
+    terminal.log_println(format!(
+        "  state_write_concurrency = {:?}",
+        config.aggregated_library.state_write_concurrency,
+    ));
+    terminal.log_println(format!(
+        "  staging_mode = {}",
+        config.aggregated_library.staging_mode,
+    ));
     terminal.log_println(format!(
         "  failure_max_retries = {}",
         config.aggregated_library.failure_max_retries,
@@ -164,15 +302,142 @@ pub fn cmd_show_config(config: &Configuration, terminal: &mut SimpleTerminal) {
         "  failure_delay_seconds = {}",
         config.aggregated_library.failure_delay_seconds,
     ));
+    terminal.log_println(format!(
+        "  retranscode_on_tag_change = {}",
+        config.aggregated_library.retranscode_on_tag_change,
+    ));
+    terminal.log_println(format!(
+        "  warn_on_size_increase = {}",
+        config.aggregated_library.warn_on_size_increase,
+    ));
+    terminal.log_println(format!(
+        "  post_album_command = {:?}",
+        config.aggregated_library.post_album_command,
+    ));
+    terminal.log_println(format!(
+        "  post_album_command_failure_is_fatal = {}",
+        config
+            .aggregated_library
+            .post_album_command_failure_is_fatal,
+    ));
+    terminal.log_println(format!(
+        "  verify_after_run = {}",
+        config.aggregated_library.verify_after_run,
+    ));
+    terminal.log_println(format!(
+        "  scan_skip_strategy = {:?}",
+        config.aggregated_library.scan_skip_strategy,
+    ));
+    terminal.log_println(format!(
+        "  broken_symlink_handling = {:?}",
+        config.aggregated_library.broken_symlink_handling,
+    ));
+    terminal.log_println(format!(
+        "  write_transcode_info = {}",
+        config.aggregated_library.write_transcode_info,
+    ));
+    terminal.log_println(format!(
+        "  normalize_whitespace = {}",
+        config.aggregated_library.normalize_whitespace,
+    ));
+    terminal.log_println(format!(
+        "  generate_playlists = {}",
+        config.aggregated_library.generate_playlists,
+    ));
+    terminal.log_println(format!(
+        "  preserve_timestamps = {}",
+        config.aggregated_library.preserve_timestamps,
+    ));
+    terminal.log_println(format!(
+        "  copy_buffer_kib = {}",
+        config.aggregated_library.copy_buffer_kib,
+    ));
+    terminal.log_println(format!(
+        "  fsync_after_copy = {}",
+        config.aggregated_library.fsync_after_copy,
+    ));
+    terminal.log_println(format!(
+        "  data_file_link_mode = {:?}",
+        config.aggregated_library.data_file_link_mode,
+    ));
+    terminal.log_println(format!(
+        "  cover_art_max_dimension = {:?}",
+        config.aggregated_library.cover_art_max_dimension,
+    ));
+    terminal.log_println(format!(
+        "  cover_art_file_names = {:?}",
+        config.aggregated_library.cover_art_file_names,
+    ));
+    terminal.log_println(format!(
+        "  filename_sanitization = {:?}",
+        config.aggregated_library.filename_sanitization,
+    ));
+    terminal.log_println(format!(
+        "  ignored_file_globs = {:?}",
+        config.aggregated_library.ignored_file_globs,
+    ));
+    terminal.log_println(format!(
+        "  album_directory_template = {:?}",
+        config.aggregated_library.album_directory_template,
+    ));
+    terminal.log_println(format!(
+        "  read_tags = {}",
+        config.aggregated_library.read_tags,
+    ));
+
+    Ok(())
+}
+
+/// A single library, as printed by `list-libraries --format json`.
+#[derive(Serialize)]
+struct LibrarySummary<'config> {
+    name: &'config str,
+
+    path: &'config str,
+
+    allowed_audio_file_extensions: &'config [String],
+
+    ignored_directories_in_base_directory: &'config [String],
 }
 
 /// Associated with the `list-libraries` command.
 ///
 /// Prints the registered music libraries from the current configuration.
+/// If `output_json` is `true`, only a JSON array of the libraries (see `LibrarySummary`)
+/// is printed instead, so the output can be piped elsewhere.
 pub fn cmd_list_libraries(
     config: &Configuration,
+    output_json: bool,
     terminal: &mut SimpleTerminal,
-) {
+) -> Result<()> {
+    if output_json {
+        let library_summaries = config
+            .libraries
+            .values()
+            .map(|library| LibrarySummary {
+                name: &library.name,
+                path: &library.path,
+                allowed_audio_file_extensions: &library
+                    .validation
+                    .allowed_audio_file_extensions,
+                ignored_directories_in_base_directory: library
+                    .ignored_directories_in_base_directory
+                    .as_deref()
+                    .unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+
+        let serialized_libraries =
+            serde_json::to_string_pretty(&library_summaries)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!("Could not serialize libraries into JSON.")
+                })?;
+
+        terminal.log_println(serialized_libraries);
+        return Ok(());
+    }
+
     terminal.log_println(format!(
         "Configuration file: {}",
         config.configuration_file_path.to_string_lossy(),
@@ -192,6 +457,10 @@ pub fn cmd_list_libraries(
         ));
 
         terminal.log_println(format!("    path = \"{}\"", library.path,));
+        terminal.log_println(format!(
+            "    output_path = {:?}",
+            library.output_path
+        ));
         terminal.log_println(format!(
             "    ignored_directories_in_base_directory = {:?}",
             library
@@ -214,6 +483,10 @@ pub fn cmd_list_libraries(
             "        allowed_other_files_by_name = {:?}",
             library.validation.allowed_other_files_by_name,
         ));
+        terminal.log_println(format!(
+            "        warning_categories = {:?}",
+            library.validation.warning_categories,
+        ));
 
         // `transcoding` sub-table
         terminal.log_println(format!("     => {}", "transcoding".italic()));
@@ -225,7 +498,37 @@ pub fn cmd_list_libraries(
             "        other_file_extensions = {:?}",
             library.transcoding.other_file_extensions,
         ));
+        terminal.log_println(format!(
+            "        rename_audio_files_pattern = {:?}",
+            library
+                .transcoding
+                .rename_audio_files_pattern
+                .as_ref()
+                .map(|pattern| pattern.as_str()),
+        ));
+        terminal.log_println(format!(
+            "        change_detection = {:?}",
+            library.transcoding.change_detection,
+        ));
+        terminal.log_println(format!(
+            "        change_detection_fields = {:?}",
+            library.transcoding.change_detection_fields,
+        ));
+        terminal.log_println(format!(
+            "        transcode_threads = {:?}",
+            library.transcoding.transcode_threads,
+        ));
+        terminal.log_println(format!(
+            "        audio_transcoding_args = {:?}",
+            library.transcoding.audio_transcoding_args,
+        ));
+        terminal.log_println(format!(
+            "        audio_transcoding_output_extension = {:?}",
+            library.transcoding.audio_transcoding_output_extension,
+        ));
 
         terminal.log_newline();
     }
+
+    Ok(())
 }
@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+
+use euphony_configuration::Configuration;
+use euphony_library::utilities::{ExtendedSortedFileList, SortedFileList};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Serialize;
+
+use super::inspect_state::find_album;
+use crate::console::frontends::SimpleTerminal;
+use crate::console::LogBackend;
+
+
+#[derive(Serialize)]
+struct FileGroup {
+    audio: Vec<PathBuf>,
+    data: Vec<PathBuf>,
+}
+
+impl From<&SortedFileList<PathBuf>> for FileGroup {
+    fn from(file_list: &SortedFileList<PathBuf>) -> Self {
+        Self {
+            audio: file_list.audio.clone(),
+            data: file_list.data.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExtendedFileGroup {
+    audio: Vec<PathBuf>,
+    data: Vec<PathBuf>,
+    unknown: Vec<PathBuf>,
+}
+
+impl From<&ExtendedSortedFileList<PathBuf>> for ExtendedFileGroup {
+    fn from(file_list: &ExtendedSortedFileList<PathBuf>) -> Self {
+        Self {
+            audio: file_list.audio.clone(),
+            data: file_list.data.clone(),
+            unknown: file_list.unknown.clone(),
+        }
+    }
+}
+
+/// The five (disjoint) change groups of `AlbumFileChangesV2`, with absolute paths.
+#[derive(Serialize)]
+struct AlbumDiff {
+    library: String,
+
+    artist: String,
+
+    album: String,
+
+    source_album_directory: PathBuf,
+
+    transcoded_album_directory: PathBuf,
+
+    /// Paths point to the source album directory.
+    added_in_source_since_last_transcode: FileGroup,
+
+    /// Paths point to the source album directory.
+    changed_in_source_since_last_transcode: FileGroup,
+
+    /// Paths point to the transcoded album directory.
+    removed_from_source_since_last_transcode: FileGroup,
+
+    /// Paths point to the source album directory.
+    missing_in_transcoded: FileGroup,
+
+    /// Paths point to the transcoded album directory.
+    excess_in_transcoded: ExtendedFileGroup,
+}
+
+/// Associated with the `diff` command.
+///
+/// Scans a single album exactly like `transcode` does (see `AlbumView::scan_for_changes`)
+/// and prints the resulting changes as JSON. This is strictly read-only.
+pub fn cmd_diff(
+    configuration: &Configuration,
+    library_name: &str,
+    artist_name: &str,
+    album_title: &str,
+    terminal: &mut SimpleTerminal,
+) -> Result<()> {
+    let (library_configuration, album) = find_album(
+        configuration,
+        library_name,
+        artist_name,
+        album_title,
+    )?;
+
+    let album_locked = album.read();
+    let changes = album_locked.scan_for_changes(false)?;
+
+    let album_diff = AlbumDiff {
+        library: library_configuration.name.clone(),
+        artist: artist_name.to_string(),
+        album: album_title.to_string(),
+        source_album_directory: album_locked.album_directory_in_source_library(),
+        transcoded_album_directory: album_locked
+            .album_directory_in_transcoded_library(),
+        added_in_source_since_last_transcode: (&changes
+            .added_in_source_since_last_transcode)
+            .into(),
+        changed_in_source_since_last_transcode: (&changes
+            .changed_in_source_since_last_transcode)
+            .into(),
+        removed_from_source_since_last_transcode: (&changes
+            .removed_from_source_since_last_transcode)
+            .into(),
+        missing_in_transcoded: (&changes.missing_in_transcoded).into(),
+        excess_in_transcoded: (&changes.excess_in_transcoded).into(),
+    };
+
+    let serialized_diff = serde_json::to_string_pretty(&album_diff)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!("Could not serialize album changes into JSON.")
+        })?;
+
+    terminal.log_println(serialized_diff);
+
+    Ok(())
+}
@@ -0,0 +1,440 @@
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::panic;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::style::Stylize;
+use euphony_configuration::Configuration;
+use miette::{miette, Result};
+
+use crate::console::frontends::SimpleTerminal;
+use crate::console::LogBackend;
+
+
+/// Name of the temporary file `doctor` creates (and removes right away) to check that a directory is writable.
+const WRITE_TEST_FILE_NAME: &str = ".euphony-doctor-write-test";
+
+/// How long to wait for `ffmpeg -version` before giving up on it.
+const FFMPEG_VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The outcome of a single `doctor` check, along with its details.
+enum CheckOutcome {
+    /// Everything is in order.
+    Pass(String),
+
+    /// Not a problem right now, but worth knowing about (doesn't cause `doctor` to fail).
+    Warning(String),
+
+    /// Something that would make `transcode` (or other commands) fail.
+    Fail(String),
+}
+
+/// A single line of the `doctor` checklist.
+struct Check {
+    /// What was checked (e.g. `ffmpeg binary`).
+    description: String,
+
+    outcome: CheckOutcome,
+}
+
+impl Check {
+    fn new<S: Into<String>>(description: S, outcome: CheckOutcome) -> Self {
+        Self {
+            description: description.into(),
+            outcome,
+        }
+    }
+
+    fn is_failure(&self) -> bool {
+        matches!(self.outcome, CheckOutcome::Fail(_))
+    }
+
+    fn format(&self) -> String {
+        let (status, details) = match &self.outcome {
+            CheckOutcome::Pass(details) => ("[ OK ]".green(), details),
+            CheckOutcome::Warning(details) => ("[WARN]".yellow(), details),
+            CheckOutcome::Fail(details) => ("[FAIL]".red(), details),
+        };
+
+        format!(
+            "{} {}: {}",
+            status,
+            self.description.clone().bold(),
+            details
+        )
+    }
+}
+
+/// Check that we can create (and remove) a file in the given directory.
+fn check_directory_writable(directory_path: &Path) -> std::io::Result<()> {
+    let write_test_file_path = directory_path.join(WRITE_TEST_FILE_NAME);
+
+    File::create(&write_test_file_path)?;
+    fs::remove_file(&write_test_file_path)
+}
+
+/// Check that the given directory exists and is both readable and writable.
+fn check_existing_directory(directory_path: &Path) -> CheckOutcome {
+    if !directory_path.exists() {
+        return CheckOutcome::Fail(format!(
+            "{:?} does not exist.",
+            directory_path
+        ));
+    }
+
+    if !directory_path.is_dir() {
+        return CheckOutcome::Fail(format!(
+            "{:?} is not a directory.",
+            directory_path
+        ));
+    }
+
+    if let Err(error) = fs::read_dir(directory_path) {
+        return CheckOutcome::Fail(format!(
+            "{:?} is not readable: {}",
+            directory_path, error
+        ));
+    }
+
+    if let Err(error) = check_directory_writable(directory_path) {
+        return CheckOutcome::Fail(format!(
+            "{:?} is not writable: {}",
+            directory_path, error
+        ));
+    }
+
+    CheckOutcome::Pass(format!(
+        "{:?} is readable and writable.",
+        directory_path
+    ))
+}
+
+/// Check a directory that euphony creates on demand (e.g. the aggregated library):
+/// either it already exists and is writable, or its parent directory is writable, so it can be created.
+fn check_directory_or_creatable(directory_path: &Path) -> CheckOutcome {
+    if directory_path.exists() {
+        return check_existing_directory(directory_path);
+    }
+
+    let Some(parent_directory_path) =
+        directory_path.parent().filter(|parent| parent.is_dir())
+    else {
+        return CheckOutcome::Fail(format!(
+            "neither {:?} nor its parent directory exist.",
+            directory_path
+        ));
+    };
+
+    match check_directory_writable(parent_directory_path) {
+        Ok(()) => CheckOutcome::Pass(format!(
+            "{:?} doesn't exist yet, but can be created.",
+            directory_path
+        )),
+        Err(error) => CheckOutcome::Fail(format!(
+            "{:?} doesn't exist and can't be created ({:?} is not writable: {}).",
+            directory_path, parent_directory_path, error
+        )),
+    }
+}
+
+/// Check that the ffmpeg binary exists and is executable.
+fn check_ffmpeg_binary(ffmpeg_binary_path: &Path) -> CheckOutcome {
+    let metadata = match fs::metadata(ffmpeg_binary_path) {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            return CheckOutcome::Fail(format!(
+                "{:?} can't be accessed: {}",
+                ffmpeg_binary_path, error
+            ));
+        }
+    };
+
+    if !metadata.is_file() {
+        return CheckOutcome::Fail(format!(
+            "{:?} is not a file.",
+            ffmpeg_binary_path
+        ));
+    }
+
+    #[cfg(unix)]
+    if metadata.permissions().mode() & 0o111 == 0 {
+        return CheckOutcome::Fail(format!(
+            "{:?} is not executable.",
+            ffmpeg_binary_path
+        ));
+    }
+
+    CheckOutcome::Pass(format!("{:?} exists.", ffmpeg_binary_path))
+}
+
+/// Run `ffmpeg -version` and report the first line of its output (e.g. `ffmpeg version 6.1.1 ...`).
+/// Gives up (and kills ffmpeg) after `FFMPEG_VERSION_TIMEOUT`.
+fn check_ffmpeg_version(ffmpeg_binary_path: &Path) -> CheckOutcome {
+    let mut ffmpeg_child_process = match Command::new(ffmpeg_binary_path)
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child_process) => child_process,
+        Err(error) => {
+            return CheckOutcome::Fail(format!(
+                "could not run `ffmpeg -version`: {}",
+                error
+            ));
+        }
+    };
+
+    let started_at = Instant::now();
+    let exit_status = loop {
+        match ffmpeg_child_process.try_wait() {
+            Ok(Some(exit_status)) => break exit_status,
+            Ok(None) if started_at.elapsed() >= FFMPEG_VERSION_TIMEOUT => {
+                let _ = ffmpeg_child_process.kill();
+                let _ = ffmpeg_child_process.wait();
+
+                return CheckOutcome::Fail(format!(
+                    "`ffmpeg -version` didn't finish in {} seconds.",
+                    FFMPEG_VERSION_TIMEOUT.as_secs()
+                ));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(error) => {
+                return CheckOutcome::Fail(format!(
+                    "could not wait for `ffmpeg -version`: {}",
+                    error
+                ));
+            }
+        }
+    };
+
+    if !exit_status.success() {
+        return CheckOutcome::Fail(format!(
+            "`ffmpeg -version` exited with {}.",
+            exit_status
+        ));
+    }
+
+    // The version output is short, so it fits into the pipe buffer and can be read after ffmpeg exits.
+    let mut ffmpeg_stdout = String::new();
+    if let Some(mut stdout) = ffmpeg_child_process.stdout.take() {
+        let _ = stdout.read_to_string(&mut ffmpeg_stdout);
+    }
+
+    match ffmpeg_stdout.lines().next() {
+        Some(version_line) if !version_line.trim().is_empty() => {
+            CheckOutcome::Pass(version_line.trim().to_string())
+        }
+        _ => CheckOutcome::Warning(
+            "`ffmpeg -version` succeeded, but didn't print a version."
+                .to_string(),
+        ),
+    }
+}
+
+/// Load the configuration from the given path (or the discovered one, see `Configuration::discover`).
+///
+/// Invalid configuration values make the loader panic, so such panics are caught
+/// and returned as errors as well (without printing the usual panic message).
+fn load_configuration(
+    configuration_file_path: Option<&Path>,
+) -> Result<Configuration> {
+    let configuration_file_path = match configuration_file_path {
+        Some(configuration_file_path) => configuration_file_path.to_path_buf(),
+        None => Configuration::discover()?,
+    };
+
+    let previous_panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let load_result = panic::catch_unwind(|| {
+        Configuration::load_from_path(configuration_file_path.clone())
+    });
+
+    panic::set_hook(previous_panic_hook);
+
+    match load_result {
+        Ok(result) => result,
+        Err(panic_payload) => {
+            let panic_message = panic_payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic_payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".to_string());
+
+            Err(miette!(
+                "{:?} is invalid: {}",
+                configuration_file_path,
+                panic_message
+            ))
+        }
+    }
+}
+
+/// Associated with the `doctor` command.
+///
+/// Runs a series of checks that would otherwise only fail deep into a transcode: whether the configuration
+/// loads, whether the configured ffmpeg binary is executable (and its version), and whether each library,
+/// the aggregated library (and any library `output_path`) and the data directory are accessible and writable.
+/// Nothing is modified, apart from briefly creating a temporary file in each checked directory.
+///
+/// Unlike other commands, `doctor` loads the configuration itself (from `configuration_file_path`,
+/// or the discovered one if `None`), so that a configuration that doesn't load is reported as a failed check.
+///
+/// Returns the number of failed checks.
+pub fn cmd_doctor(
+    configuration_file_path: Option<&Path>,
+    terminal: &mut SimpleTerminal,
+) -> Result<usize> {
+    terminal.log_println(
+        "Command: check the configuration, tools and library paths."
+            .cyan()
+            .bold(),
+    );
+    terminal.log_newline();
+
+    let mut checks: Vec<Check> = Vec::new();
+
+    match load_configuration(configuration_file_path) {
+        Ok(configuration) => {
+            checks.push(Check::new(
+                "configuration",
+                CheckOutcome::Pass(format!(
+                    "{:?} loaded.",
+                    configuration.configuration_file_path
+                )),
+            ));
+
+            check_tools_and_paths(&configuration, &mut checks);
+        }
+        Err(error) => {
+            // Nothing else can be checked without a configuration.
+            checks.push(Check::new(
+                "configuration",
+                CheckOutcome::Fail(
+                    error
+                        .chain()
+                        .map(|error| error.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+            ));
+        }
+    }
+
+    for check in &checks {
+        terminal.log_println(check.format());
+    }
+
+    let num_failed_checks =
+        checks.iter().filter(|check| check.is_failure()).count();
+
+    terminal.log_newline();
+    if num_failed_checks == 0 {
+        terminal.log_println("All checks passed.".green().bold());
+    } else {
+        terminal.log_println(
+            format!(
+                "{} of {} checks failed.",
+                num_failed_checks,
+                checks.len()
+            )
+            .red()
+            .bold(),
+        );
+    }
+
+    Ok(num_failed_checks)
+}
+
+/// Check the configured ffmpeg binary and every configured library (and other) directory.
+fn check_tools_and_paths(
+    configuration: &Configuration,
+    checks: &mut Vec<Check>,
+) {
+    let ffmpeg_binary_path = Path::new(&configuration.tools.ffmpeg.binary);
+    let ffmpeg_binary_check = check_ffmpeg_binary(ffmpeg_binary_path);
+    let is_ffmpeg_binary_usable =
+        matches!(ffmpeg_binary_check, CheckOutcome::Pass(_));
+
+    checks.push(Check::new("ffmpeg binary", ffmpeg_binary_check));
+
+    if is_ffmpeg_binary_usable {
+        checks.push(Check::new(
+            "ffmpeg version",
+            check_ffmpeg_version(ffmpeg_binary_path),
+        ));
+    }
+
+    let output_format_check =
+        match configuration.tools.ffmpeg.output_format_mismatch_warning() {
+            Some(warning) => CheckOutcome::Warning(warning),
+            None => CheckOutcome::Pass(format!(
+                "Transcoding into \".{}\" files.",
+                configuration
+                    .tools
+                    .ffmpeg
+                    .audio_transcoding_output_extension
+            )),
+        };
+    checks.push(Check::new(
+        "ffmpeg output format",
+        output_format_check,
+    ));
+
+    let mut library_configurations =
+        configuration.libraries.values().collect::<Vec<_>>();
+    library_configurations
+        .sort_unstable_by(|first, second| first.name.cmp(&second.name));
+
+    for library_configuration in &library_configurations {
+        let library_path = Path::new(&library_configuration.path);
+
+        // Optional libraries may be temporarily unavailable (see `LibraryConfiguration::optional`).
+        let outcome = if !library_path.exists() && library_configuration.optional
+        {
+            CheckOutcome::Warning(format!(
+                "{:?} doesn't exist, the library will be skipped (it is optional).",
+                library_path
+            ))
+        } else {
+            check_existing_directory(library_path)
+        };
+
+        checks.push(Check::new(
+            format!("library \"{}\"", library_configuration.name),
+            outcome,
+        ));
+    }
+
+    checks.push(Check::new(
+        "aggregated library",
+        check_directory_or_creatable(Path::new(
+            &configuration.aggregated_library.path,
+        )),
+    ));
+
+    for library_configuration in &library_configurations {
+        if let Some(output_path) = &library_configuration.output_path {
+            checks.push(Check::new(
+                format!(
+                    "library \"{}\" output_path",
+                    library_configuration.name
+                ),
+                check_directory_or_creatable(Path::new(output_path)),
+            ));
+        }
+    }
+
+    checks.push(Check::new(
+        "data directory",
+        check_directory_or_creatable(&configuration.paths.data_directory_path),
+    ));
+}
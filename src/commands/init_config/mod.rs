@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::Path;
+
+use crossterm::style::Stylize;
+use miette::{miette, Context, IntoDiagnostic, Result};
+
+use crate::console::frontends::SimpleTerminal;
+use crate::console::LogBackend;
+
+
+/// The fully-commented configuration template (the same file as `data/configuration.TEMPLATE.toml`).
+const CONFIGURATION_TEMPLATE: &str =
+    include_str!("../../../data/configuration.TEMPLATE.toml");
+
+
+/// Associated with the `init-config` command.
+///
+/// Writes the commented configuration template to `output_file_path`, creating any missing
+/// parent directories. An existing file is only overwritten if `force` is `true`.
+pub fn cmd_init_config(
+    output_file_path: &Path,
+    force: bool,
+    terminal: &mut SimpleTerminal,
+) -> Result<()> {
+    if output_file_path.exists() && !force {
+        return Err(miette!(
+            "{:?} already exists, refusing to overwrite it (pass --force to overwrite).",
+            output_file_path
+        ));
+    }
+
+    if output_file_path.is_dir() {
+        return Err(miette!(
+            "{:?} is a directory, not a file.",
+            output_file_path
+        ));
+    }
+
+    if let Some(parent_directory) = output_file_path.parent() {
+        if !parent_directory.as_os_str().is_empty() {
+            fs::create_dir_all(parent_directory)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!(
+                        "Could not create directory {:?}.",
+                        parent_directory
+                    )
+                })?;
+        }
+    }
+
+    fs::write(output_file_path, CONFIGURATION_TEMPLATE)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!(
+                "Could not write configuration template to {:?}.",
+                output_file_path
+            )
+        })?;
+
+    terminal.log_println(format!(
+        "{} {:?}",
+        "Wrote configuration template to".green(),
+        output_file_path
+    ));
+    terminal.log_println(
+        "Edit the paths, tools and libraries in it, then run `euphony show-config` \
+        (with --config if you wrote it elsewhere than the default location) to check it.",
+    );
+
+    Ok(())
+}
@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::PathBuf;
+
+use crossterm::style::Stylize;
+use euphony_configuration::library::LibraryConfiguration;
+use euphony_configuration::Configuration;
+use euphony_library::state::common::{AlbumFileState, FileTrackedMetadata};
+use euphony_library::state::source::{
+    SourceAlbumState,
+    SourceAlbumStateLoadError,
+};
+use euphony_library::state::transcoded::{
+    TranscodedAlbumState,
+    TranscodedAlbumStateLoadError,
+};
+use euphony_library::view::{LibraryView, SharedAlbumView};
+use miette::{miette, Result};
+
+use crate::console::frontends::SimpleTerminal;
+use crate::console::LogBackend;
+
+
+/// Format the tracked metadata of a single file into a single line.
+fn format_tracked_metadata(metadata: &FileTrackedMetadata) -> String {
+    let mut formatted_metadata = format!(
+        "{} bytes, modified {:.3}, created {:.3}",
+        metadata.size_bytes, metadata.time_modified, metadata.time_created
+    );
+
+    if let Some(tags) = &metadata.tags {
+        formatted_metadata.push_str(&format!(
+            ", tags: title={:?} artist={:?} album={:?}",
+            tags.title, tags.artist, tags.album
+        ));
+    }
+
+    formatted_metadata
+}
+
+/// Print the given files and their metadata, sorted by path.
+fn print_tracked_files(
+    terminal: &SimpleTerminal,
+    header: &str,
+    files: &HashMap<String, FileTrackedMetadata>,
+) {
+    terminal.log_println(format!("    {} ({} files):", header, files.len()));
+
+    let mut sorted_files = files.iter().collect::<Vec<_>>();
+    sorted_files.sort_unstable_by_key(|(file_path, _)| *file_path);
+
+    for (file_path, metadata) in sorted_files {
+        terminal.log_println(format!(
+            "      {} ({})",
+            file_path,
+            format_tracked_metadata(metadata)
+        ));
+    }
+}
+
+fn print_album_file_state(
+    terminal: &SimpleTerminal,
+    album_file_state: &AlbumFileState,
+) {
+    print_tracked_files(
+        terminal,
+        "audio files",
+        &album_file_state.audio_files,
+    );
+    print_tracked_files(
+        terminal,
+        "data files",
+        &album_file_state.data_files,
+    );
+}
+
+/// Print a single group of changed files (skipped if empty).
+fn print_change_group<P: Debug>(
+    terminal: &SimpleTerminal,
+    header: &str,
+    files: &[P],
+) {
+    if files.is_empty() {
+        return;
+    }
+
+    terminal.log_println(format!("    {} ({} files):", header, files.len()));
+
+    for file in files {
+        terminal.log_println(format!("      {:?}", file));
+    }
+}
+
+/// Look up a single album by its library (full name or configuration key),
+/// artist and album directory name.
+pub(crate) fn find_album<'config>(
+    configuration: &'config Configuration,
+    library_name: &str,
+    artist_name: &str,
+    album_title: &str,
+) -> Result<(
+    &'config LibraryConfiguration,
+    SharedAlbumView<'config>,
+)> {
+    let library_configuration = configuration
+        .get_library_by_full_name(library_name)
+        .or_else(|| configuration.libraries.get(library_name))
+        .ok_or_else(|| miette!("No such library: \"{}\".", library_name))?;
+
+    let library = LibraryView::from_library_configuration(
+        configuration,
+        library_configuration,
+    )?;
+
+    let artist = library
+        .read()
+        .artist(artist_name.to_string())?
+        .ok_or_else(|| miette!("No such artist: \"{}\".", artist_name))?;
+
+    let album = artist
+        .read()
+        .album(album_title.to_string())?
+        .ok_or_else(|| miette!("No such album: \"{}\".", album_title))?;
+
+    Ok((library_configuration, album))
+}
+
+/// Associated with the `inspect-state` command.
+///
+/// Loads and prints the saved source and transcoded state of a single album
+/// (given as `<library name>/<artist>/<album>`), as well as the changes euphony currently sees in it.
+/// This is strictly read-only.
+pub fn cmd_inspect_state(
+    configuration: &Configuration,
+    album_path: &str,
+    terminal: &mut SimpleTerminal,
+) -> Result<()> {
+    let [library_name, artist_name, album_title]: [&str; 3] = album_path
+        .splitn(3, '/')
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| {
+            miette!(
+                "Invalid album path \"{}\", expected <library>/<artist>/<album>.",
+                album_path
+            )
+        })?;
+
+    let (library_configuration, album) = find_album(
+        configuration,
+        library_name,
+        artist_name,
+        album_title,
+    )?;
+
+    let (source_album_directory, transcoded_album_directory) = {
+        let album_locked = album.read();
+
+        (
+            album_locked.album_directory_in_source_library(),
+            album_locked.album_directory_in_transcoded_library(),
+        )
+    };
+
+    terminal.log_println(format!(
+        "Album: {} - {} (library: {})",
+        artist_name.bold(),
+        album_title.bold(),
+        library_configuration.name
+    ));
+    terminal.log_println(format!(
+        "  Source directory: {:?}",
+        source_album_directory
+    ));
+    terminal.log_println(format!(
+        "  Transcoded directory: {:?}",
+        transcoded_album_directory
+    ));
+    terminal.log_newline();
+
+
+    // Saved source state (`.album.source-state.euphony`)
+    terminal.log_println("Saved source album state:".cyan().bold());
+    match SourceAlbumState::load_from_directory(&source_album_directory) {
+        Ok(source_state) => {
+            terminal.log_println(format!(
+                "    schema version: {}",
+                source_state.schema_version
+            ));
+            print_album_file_state(terminal, &source_state.tracked_files);
+        }
+        Err(SourceAlbumStateLoadError::NotFound) => {
+            terminal.log_println("    (none)");
+        }
+        Err(error) => {
+            terminal.log_eprintln(format!("    {} {}", "ERROR:".red(), error));
+        }
+    }
+    terminal.log_newline();
+
+
+    // Saved transcoded state (`.album.transcode-state.euphony`)
+    terminal.log_println("Saved transcoded album state:".cyan().bold());
+    match TranscodedAlbumState::load_from_directory(&transcoded_album_directory)
+    {
+        Ok(transcoded_state) => {
+            terminal.log_println(format!(
+                "    schema version: {}",
+                transcoded_state.schema_version
+            ));
+
+            let mut sorted_file_map = transcoded_state
+                .transcoded_to_original_file_paths
+                .clone()
+                .into_flattened_map()
+                .into_iter()
+                .collect::<Vec<_>>();
+            sorted_file_map.sort_unstable();
+
+            terminal.log_println(format!(
+                "    transcoded -> original files ({} files):",
+                sorted_file_map.len()
+            ));
+            for (transcoded_file_path, original_file_path) in sorted_file_map {
+                terminal.log_println(format!(
+                    "      {} -> {}",
+                    transcoded_file_path, original_file_path
+                ));
+            }
+
+            print_album_file_state(terminal, &transcoded_state.transcoded_files);
+        }
+        Err(TranscodedAlbumStateLoadError::NotFound) => {
+            terminal.log_println("    (none)");
+        }
+        Err(error) => {
+            terminal.log_eprintln(format!("    {} {}", "ERROR:".red(), error));
+        }
+    }
+    terminal.log_newline();
+
+
+    // Current changes (what `transcode` would do with this album).
+    terminal.log_println("Current changes:".cyan().bold());
+
+    let changes = album.read().scan_for_changes(false)?;
+
+    if !changes.has_changes() {
+        terminal.log_println("    (none, the album is up to date)");
+        return Ok(());
+    }
+
+    let groups: [(&str, &[PathBuf]); 10] = [
+        (
+            "added in source (audio)",
+            &changes.added_in_source_since_last_transcode.audio,
+        ),
+        (
+            "added in source (data)",
+            &changes.added_in_source_since_last_transcode.data,
+        ),
+        (
+            "changed in source (audio)",
+            &changes.changed_in_source_since_last_transcode.audio,
+        ),
+        (
+            "changed in source (data)",
+            &changes.changed_in_source_since_last_transcode.data,
+        ),
+        (
+            "removed from source (audio)",
+            &changes.removed_from_source_since_last_transcode.audio,
+        ),
+        (
+            "removed from source (data)",
+            &changes.removed_from_source_since_last_transcode.data,
+        ),
+        (
+            "missing in transcoded (audio)",
+            &changes.missing_in_transcoded.audio,
+        ),
+        (
+            "missing in transcoded (data)",
+            &changes.missing_in_transcoded.data,
+        ),
+        (
+            "excess in transcoded (audio)",
+            &changes.excess_in_transcoded.audio,
+        ),
+        (
+            "excess in transcoded (data)",
+            &changes.excess_in_transcoded.data,
+        ),
+    ];
+
+    for (header, files) in groups {
+        print_change_group(terminal, header, files);
+    }
+
+    print_change_group(
+        terminal,
+        "excess in transcoded (unknown)",
+        &changes.excess_in_transcoded.unknown,
+    );
+
+    Ok(())
+}
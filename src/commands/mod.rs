@@ -1,8 +1,60 @@
+pub use clean::cmd_clean;
 pub use configuration::cmd_list_libraries;
-pub use configuration::cmd_show_config;
-pub use transcode::cmd_transcode_all;
+pub use configuration::{cmd_show_config, SerializedConfigurationFormat};
+use crossterm::style::Stylize;
+pub use diff::cmd_diff;
+pub use doctor::cmd_doctor;
+use euphony_configuration::Configuration;
+pub use init_config::cmd_init_config;
+pub use inspect_state::cmd_inspect_state;
+pub use reindex::cmd_reindex;
+pub use relink::cmd_relink;
+pub use stats::cmd_stats;
+pub use transcode::check::cmd_transcode_check;
+pub use transcode::list_errored::cmd_list_errored;
+pub use transcode::{cmd_transcode_all, TranscodeOptions};
 pub use validation::cmd_validate;
+pub use verify::cmd_verify;
+pub use what_if_remove::cmd_what_if_remove;
 
+use crate::console::LogBackend;
+
+pub mod clean;
 pub mod configuration;
+pub mod diff;
+pub mod doctor;
+pub mod init_config;
+pub mod inspect_state;
+pub mod reindex;
+pub mod relink;
+pub mod stats;
 pub mod transcode;
 pub mod validation;
+pub mod verify;
+pub mod what_if_remove;
+
+
+/// Log every configuration warning (see `Configuration::warnings`).
+pub(crate) fn log_configuration_warnings<L: LogBackend>(
+    terminal: &L,
+    configuration: &Configuration,
+) {
+    for warning in configuration.warnings() {
+        terminal.log_eprintln(format!("{} {}", "WARNING:".yellow(), warning));
+    }
+}
+
+/// Log a warning that the given optional library is unavailable (there is no directory at its path)
+/// and will be skipped.
+pub(crate) fn log_optional_library_unavailable<L: LogBackend>(
+    terminal: &L,
+    library_name: &str,
+    library_path: &str,
+) {
+    terminal.log_eprintln(format!(
+        "{} optional library \"{}\" is unavailable (no directory at {}), skipping.",
+        "WARNING:".yellow(),
+        library_name,
+        library_path
+    ));
+}
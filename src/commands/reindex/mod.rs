@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use crossterm::style::Stylize;
+use euphony_configuration::Configuration;
+use euphony_library::state::common::ContentHashing;
+use euphony_library::state::source::SourceAlbumState;
+use euphony_library::state::transcoded::TranscodedAlbumState;
+use euphony_library::view::library::LibraryViewError;
+use euphony_library::view::{AlbumSourceFileList, LibraryView, SharedAlbumView};
+use miette::{miette, Context, Result};
+
+use crate::commands::log_optional_library_unavailable;
+use crate::commands::transcode::library_state::{
+    LibraryState,
+    TrackedAlbum,
+    TrackedArtistAlbums,
+    LIBRARY_STATE_FILE_NAME,
+};
+use crate::console::frontends::SimpleTerminal;
+use crate::console::LogBackend;
+use crate::globals::is_verbose_enabled;
+
+
+/// Generates fresh `.album.source-state.euphony` and `.album.transcode-state.euphony` files
+/// for the given album from its current filesystem contents and saves them.
+///
+/// Returns `false` if the album does not have a transcoded counterpart yet
+/// (in which case no state is saved for it).
+fn reindex_album(
+    album: SharedAlbumView,
+    terminal: &SimpleTerminal,
+) -> Result<bool> {
+    let (source_album_directory, transcoded_album_directory) = {
+        let album_locked = album.read();
+
+        (
+            album_locked.album_directory_in_source_library(),
+            album_locked.album_directory_in_transcoded_library(),
+        )
+    };
+
+    if !transcoded_album_directory.is_dir() {
+        // Marking such an album as processed would only make euphony think the transcoded
+        // files were removed manually - it's better to leave it for the next transcode.
+        terminal.log_eprintln(format!(
+            "  {} no transcoded album directory at {:?}, skipping.",
+            "WARNING:".yellow(),
+            transcoded_album_directory
+        ));
+
+        return Ok(false);
+    }
+
+    let tracked_source_files = AlbumSourceFileList::from_album_view(album)?;
+
+    let source_album_state = SourceAlbumState::generate_from_tracked_files(
+        &tracked_source_files,
+        &source_album_directory,
+        None,
+        ContentHashing::Complete,
+    )?;
+    let transcoded_album_state =
+        TranscodedAlbumState::generate_from_tracked_files(
+            &tracked_source_files,
+            &transcoded_album_directory,
+        )?;
+
+    source_album_state.save_to_directory(&source_album_directory, true)?;
+    transcoded_album_state
+        .save_to_directory(&transcoded_album_directory, true)?;
+
+    if is_verbose_enabled() {
+        terminal.log_println(format!(
+            "  Saved album states for {:?}.",
+            source_album_directory
+        ));
+    }
+
+    Ok(true)
+}
+
+/// Associated with the `reindex` command.
+///
+/// Regenerates all source, transcoded and library state files from the current filesystem
+/// contents, without transcoding, copying or deleting anything. This assumes the source and
+/// transcoded libraries are already in sync (e.g. after manually reconciling them).
+pub fn cmd_reindex(
+    configuration: &Configuration,
+    terminal: &mut SimpleTerminal,
+) -> Result<()> {
+    terminal.log_println(
+        "Command: regenerate all state files (no transcoding)."
+            .cyan()
+            .bold(),
+    );
+
+    let mut total_reindexed_albums: usize = 0;
+    let mut total_skipped_albums: usize = 0;
+
+    for library_configuration in configuration.libraries.values() {
+        let library = match LibraryView::from_library_configuration(
+            configuration,
+            library_configuration,
+        ) {
+            Ok(library) => library,
+            Err(LibraryViewError::NoSuchDirectory(path))
+                if library_configuration.optional =>
+            {
+                log_optional_library_unavailable(
+                    terminal,
+                    &library_configuration.name,
+                    &path,
+                );
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let library_locked = library.read();
+
+        terminal.log_println(format!(
+            "Reindexing library: {}",
+            library_locked.name().bold()
+        ));
+
+        let mut artists =
+            library_locked.artists()?.into_iter().collect::<Vec<_>>();
+        artists.sort_unstable_by(|(first_name, _), (second_name, _)| {
+            first_name.cmp(second_name)
+        });
+
+        let mut tracked_artists: HashMap<String, TrackedArtistAlbums> =
+            HashMap::with_capacity(artists.len());
+
+        for (artist_name, artist) in artists {
+            let mut albums =
+                artist.read().albums()?.into_iter().collect::<Vec<_>>();
+            albums.sort_unstable_by(|(first_title, _), (second_title, _)| {
+                first_title.cmp(second_title)
+            });
+
+            let mut tracked_albums: Vec<TrackedAlbum> =
+                Vec::with_capacity(albums.len());
+
+            for (album_title, album) in albums {
+                let album_path =
+                    album.read().directory_path_relative_to_library_root();
+                let album_year = album.read().album_year();
+
+                let album_was_reindexed = reindex_album(album, terminal)
+                    .wrap_err_with(|| {
+                        miette!(
+                            "Failed to reindex album \"{} - {}\".",
+                            artist_name,
+                            album_title
+                        )
+                    })?;
+
+                if album_was_reindexed {
+                    total_reindexed_albums += 1;
+                } else {
+                    total_skipped_albums += 1;
+                }
+
+                tracked_albums.push(TrackedAlbum {
+                    album_title,
+                    album_source_relative_path: dunce::simplified(&album_path)
+                        .to_string_lossy()
+                        .to_string(),
+                    album_year,
+                });
+            }
+
+            tracked_artists.insert(
+                artist_name,
+                TrackedArtistAlbums { tracked_albums },
+            );
+        }
+
+        LibraryState::new(tracked_artists).save_to_directory(
+            library_locked.root_directory_in_source_library(),
+            true,
+        )?;
+
+        if is_verbose_enabled() {
+            terminal.log_println(format!(
+                "Saved library state into {} for library {}.",
+                LIBRARY_STATE_FILE_NAME,
+                library_locked.name()
+            ));
+        }
+    }
+
+    terminal.log_println(
+        format!(
+            "Reindexed {} albums ({} skipped).",
+            total_reindexed_albums, total_skipped_albums
+        )
+        .green()
+        .bold(),
+    );
+
+    Ok(())
+}
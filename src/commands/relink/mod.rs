@@ -0,0 +1,655 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::fs;
+use std::hash::Hash;
+use std::path::{Component, Path, PathBuf};
+
+use crossterm::style::Stylize;
+use euphony_configuration::Configuration;
+use euphony_library::state::source::SourceAlbumState;
+use euphony_library::view::{ArtistView, LibraryView, SharedLibraryView};
+use miette::{miette, Context, IntoDiagnostic, Result};
+
+use crate::commands::transcode::library_state::{
+    LibraryState,
+    TrackedAlbum,
+    TrackedArtistAlbums,
+};
+use crate::console::frontends::SimpleTerminal;
+use crate::console::LogBackend;
+use crate::globals::is_verbose_enabled;
+
+
+/// A single album move from the mapping file: both paths are relative to the library root
+/// and are in the `<artist>/<album>` form.
+struct AlbumRelink {
+    old_artist: String,
+    old_album: String,
+    new_artist: String,
+    new_album: String,
+}
+
+impl AlbumRelink {
+    fn old_relative_path(&self) -> String {
+        relative_album_path(&self.old_artist, &self.old_album)
+    }
+
+    fn new_relative_path(&self) -> String {
+        relative_album_path(&self.new_artist, &self.new_album)
+    }
+}
+
+/// The same format `transcode` stores in `TrackedAlbum::album_source_relative_path`.
+fn relative_album_path(artist_name: &str, album_title: &str) -> String {
+    dunce::simplified(&PathBuf::from(artist_name).join(album_title))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Split a relative album path (e.g. `Artist/Album`) into the artist name and the album title.
+fn parse_relative_album_path(relative_path: &str) -> Result<(String, String)> {
+    let components = Path::new(relative_path.trim())
+        .components()
+        .map(|component| match component {
+            Component::Normal(component) => {
+                Ok(component.to_string_lossy().to_string())
+            }
+            _ => Err(miette!(
+                "Invalid album path \"{}\": expected a relative <artist>/<album> path.",
+                relative_path
+            )),
+        })
+        .collect::<Result<Vec<String>>>()?;
+
+    let [artist_name, album_title]: [String; 2] =
+        components.try_into().map_err(|_| {
+            miette!(
+                "Invalid album path \"{}\": expected a relative <artist>/<album> path.",
+                relative_path
+            )
+        })?;
+
+    Ok((artist_name, album_title))
+}
+
+/// Parse a single CSV line into its fields. Fields may be quoted with `"`
+/// (with `""` standing for a literal quote), which is needed for paths containing commas.
+fn parse_csv_line(line: &str) -> Result<Vec<String>> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut current_field = String::new();
+    let mut is_inside_quotes = false;
+
+    let mut characters = line.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' if is_inside_quotes => {
+                if characters.peek() == Some(&'"') {
+                    characters.next();
+                    current_field.push('"');
+                } else {
+                    is_inside_quotes = false;
+                }
+            }
+            '"' if current_field.trim().is_empty() => {
+                current_field.clear();
+                is_inside_quotes = true;
+            }
+            ',' if !is_inside_quotes => {
+                fields.push(std::mem::take(&mut current_field));
+            }
+            _ => current_field.push(character),
+        }
+    }
+
+    if is_inside_quotes {
+        return Err(miette!(
+            "Unterminated quoted field in line: {}",
+            line
+        ));
+    }
+
+    fields.push(current_field);
+    Ok(fields)
+}
+
+/// Load the old -> new album path mapping. A `.json` file must contain an object mapping
+/// old paths to new ones, any other file is read as CSV with two columns (old path, new path).
+/// A CSV header line of `old,new` is skipped.
+fn load_relink_mapping(mapping_file_path: &Path) -> Result<Vec<AlbumRelink>> {
+    let mapping_file_contents = fs::read_to_string(mapping_file_path)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!(
+                "Could not read mapping file {:?}.",
+                mapping_file_path
+            )
+        })?;
+
+    let is_json = mapping_file_path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+
+    let path_pairs: Vec<(String, String)> = if is_json {
+        let mapping: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&mapping_file_contents)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!(
+                        "Mapping file {:?} is not a JSON object of old to new paths.",
+                        mapping_file_path
+                    )
+                })?;
+
+        mapping
+            .into_iter()
+            .map(|(old_path, new_path)| match new_path {
+                serde_json::Value::String(new_path) => Ok((old_path, new_path)),
+                _ => Err(miette!(
+                    "Mapping for \"{}\" is not a string.",
+                    old_path
+                )),
+            })
+            .collect::<Result<_>>()?
+    } else {
+        let mut path_pairs: Vec<(String, String)> = Vec::new();
+
+        for (line_index, line) in mapping_file_contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields = parse_csv_line(line).wrap_err_with(|| {
+                miette!("Line {} is invalid.", line_index + 1)
+            })?;
+
+            let [old_path, new_path]: [String; 2] =
+                fields.try_into().map_err(|_| {
+                    miette!(
+                        "Line {} of the mapping file must have exactly two columns (old path, new path).",
+                        line_index + 1
+                    )
+                })?;
+
+            if line_index == 0
+                && old_path.trim().eq_ignore_ascii_case("old")
+                && new_path.trim().eq_ignore_ascii_case("new")
+            {
+                continue;
+            }
+
+            path_pairs.push((old_path, new_path));
+        }
+
+        path_pairs
+    };
+
+    path_pairs
+        .into_iter()
+        .map(|(old_path, new_path)| {
+            let (old_artist, old_album) = parse_relative_album_path(&old_path)?;
+            let (new_artist, new_album) = parse_relative_album_path(&new_path)?;
+
+            Ok(AlbumRelink {
+                old_artist,
+                old_album,
+                new_artist,
+                new_album,
+            })
+        })
+        .collect()
+}
+
+/// Reject sets of `(old, new)` locations whose moves depend on each other: the same location
+/// being moved twice, two locations being moved to the same place, or a location being moved
+/// to where another one is moved away from (e.g. `A -> B` and `B -> C`). Depending on the order
+/// the moves are applied in, these would clash with or overwrite each other.
+fn check_moves_are_independent<T: Eq + Hash + Debug>(
+    moves: &[(T, T)],
+) -> Result<()> {
+    let mut old_locations: HashSet<&T> = HashSet::with_capacity(moves.len());
+    let mut new_locations: HashSet<&T> = HashSet::with_capacity(moves.len());
+
+    for (old_location, new_location) in moves {
+        if !old_locations.insert(old_location) {
+            return Err(miette!(
+                "{:?} is moved more than once.",
+                old_location
+            ));
+        }
+
+        if !new_locations.insert(new_location) {
+            return Err(miette!(
+                "More than one album is moved to {:?}.",
+                new_location
+            ));
+        }
+    }
+
+    for (old_location, new_location) in moves {
+        if old_location != new_location && old_locations.contains(new_location) {
+            return Err(miette!(
+                "{:?} is moved to {:?}, which is itself moved elsewhere \
+                (relink chained moves in separate runs).",
+                old_location,
+                new_location
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolved source and transcoded locations of a single album move.
+struct PlannedAlbumRelink {
+    relink: AlbumRelink,
+
+    tracked_album: TrackedAlbum,
+
+    old_source_album_directory: PathBuf,
+    new_source_album_directory: PathBuf,
+
+    old_transcoded_album_directory: PathBuf,
+    new_transcoded_album_directory: PathBuf,
+
+    /// The album's `year` (see `AlbumConfiguration`) at its new location.
+    new_album_year: Option<u16>,
+}
+
+/// Check that the move can be applied and compute the old and new album directories.
+/// Nothing is modified here.
+fn plan_album_relink(
+    library: &SharedLibraryView,
+    library_state: &LibraryState,
+    relink: AlbumRelink,
+) -> Result<PlannedAlbumRelink> {
+    let old_relative_path = relink.old_relative_path();
+    let new_relative_path = relink.new_relative_path();
+
+    let tracked_album = library_state
+        .tracked_artists
+        .get(&relink.old_artist)
+        .and_then(|artist_albums| {
+            artist_albums.tracked_albums.iter().find(|album| {
+                album.album_source_relative_path == old_relative_path
+            })
+        })
+        .ok_or_else(|| {
+            miette!(
+                "\"{}\" is not tracked in the library state (has it been transcoded?).",
+                old_relative_path
+            )
+        })?
+        .clone();
+
+    let old_artist =
+        ArtistView::new(library.clone(), relink.old_artist.clone(), true)?;
+    let old_album = tracked_album.album_view(old_artist)?;
+
+    let new_artist =
+        ArtistView::new(library.clone(), relink.new_artist.clone(), true)?;
+    let new_album = new_artist
+        .read()
+        .album(relink.new_album.clone())?
+        .ok_or_else(|| {
+            miette!(
+                "\"{}\" does not exist in the source library (move the source album first).",
+                new_relative_path
+            )
+        })?;
+
+    let (old_source_album_directory, old_transcoded_album_directory) = {
+        let old_album_locked = old_album.read();
+        (
+            old_album_locked.album_directory_in_source_library(),
+            old_album_locked.album_directory_in_transcoded_library(),
+        )
+    };
+
+    let (
+        new_source_album_directory,
+        new_transcoded_album_directory,
+        new_album_year,
+    ) = {
+        let new_album_locked = new_album.read();
+        (
+            new_album_locked.album_directory_in_source_library(),
+            new_album_locked.album_directory_in_transcoded_library(),
+            new_album_locked.album_year(),
+        )
+    };
+
+    if old_transcoded_album_directory != new_transcoded_album_directory
+        && new_transcoded_album_directory.exists()
+    {
+        return Err(miette!(
+            "Transcoded album directory {:?} (for \"{}\") already exists.",
+            new_transcoded_album_directory,
+            new_relative_path
+        ));
+    }
+
+    Ok(PlannedAlbumRelink {
+        relink,
+        tracked_album,
+        old_source_album_directory,
+        new_source_album_directory,
+        old_transcoded_album_directory,
+        new_transcoded_album_directory,
+        new_album_year,
+    })
+}
+
+/// Move `from` to `to`, creating any missing parent directories of `to`.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent_directory) = to.parent() {
+        fs::create_dir_all(parent_directory)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not create directory {:?}.",
+                    parent_directory
+                )
+            })?;
+    }
+
+    fs::rename(from, to)
+        .into_diagnostic()
+        .wrap_err_with(|| miette!("Could not move {:?} to {:?}.", from, to))
+}
+
+/// Remove `directory_path` if it exists and is empty (e.g. an artist directory
+/// whose last album was moved elsewhere).
+fn remove_directory_if_empty(directory_path: &Path) -> Result<()> {
+    let Ok(mut directory_entries) = fs::read_dir(directory_path) else {
+        return Ok(());
+    };
+
+    if directory_entries.next().is_none() {
+        fs::remove_dir(directory_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not remove empty directory {:?}.",
+                    directory_path
+                )
+            })?;
+    }
+
+    Ok(())
+}
+
+/// Move the album's entry in the library state to its new location.
+fn relink_tracked_album(
+    planned_relink: &PlannedAlbumRelink,
+    library_state: &mut LibraryState,
+) {
+    if let Some(old_artist_albums) = library_state
+        .tracked_artists
+        .get_mut(&planned_relink.relink.old_artist)
+    {
+        old_artist_albums
+            .tracked_albums
+            .retain(|album| album != &planned_relink.tracked_album);
+
+        if old_artist_albums.tracked_albums.is_empty() {
+            library_state
+                .tracked_artists
+                .remove(&planned_relink.relink.old_artist);
+        }
+    }
+
+    library_state
+        .tracked_artists
+        .entry(planned_relink.relink.new_artist.clone())
+        .or_insert_with(|| TrackedArtistAlbums {
+            tracked_albums: Vec::new(),
+        })
+        .tracked_albums
+        .push(TrackedAlbum {
+            album_title: planned_relink.relink.new_album.clone(),
+            album_source_relative_path: planned_relink
+                .relink
+                .new_relative_path(),
+            album_year: planned_relink.new_album_year,
+        });
+}
+
+/// Move the album's transcoded directory and (if it was left behind) its source state file
+/// to the new location and update its entry in the library state.
+///
+/// The library state is updated as soon as the transcoded directory has been moved, so it matches
+/// the transcoded library even if a later step fails (the caller is expected to save it either way).
+fn apply_album_relink(
+    planned_relink: PlannedAlbumRelink,
+    library_state: &mut LibraryState,
+    terminal: &SimpleTerminal,
+) -> Result<()> {
+    let old_relative_path = planned_relink.relink.old_relative_path();
+    let new_relative_path = planned_relink.relink.new_relative_path();
+
+    let move_transcoded_album_directory = planned_relink
+        .old_transcoded_album_directory
+        != planned_relink.new_transcoded_album_directory
+        && planned_relink.old_transcoded_album_directory.is_dir();
+
+    if move_transcoded_album_directory {
+        move_path(
+            &planned_relink.old_transcoded_album_directory,
+            &planned_relink.new_transcoded_album_directory,
+        )?;
+    }
+
+    relink_tracked_album(&planned_relink, library_state);
+
+    if move_transcoded_album_directory {
+        if let Some(old_transcoded_artist_directory) =
+            planned_relink.old_transcoded_album_directory.parent()
+        {
+            remove_directory_if_empty(old_transcoded_artist_directory)?;
+        }
+
+        if is_verbose_enabled() {
+            terminal.log_println(format!(
+                "  Moved {:?} to {:?}.",
+                planned_relink.old_transcoded_album_directory,
+                planned_relink.new_transcoded_album_directory
+            ));
+        }
+    }
+
+    let old_source_state_file_path =
+        SourceAlbumState::get_state_file_path_for_directory(
+            &planned_relink.old_source_album_directory,
+        );
+    let new_source_state_file_path =
+        SourceAlbumState::get_state_file_path_for_directory(
+            &planned_relink.new_source_album_directory,
+        );
+
+    if old_source_state_file_path.is_file()
+        && !new_source_state_file_path.exists()
+    {
+        move_path(
+            &old_source_state_file_path,
+            &new_source_state_file_path,
+        )?;
+
+        // The state file may have been the only thing left in the old source directories.
+        remove_directory_if_empty(&planned_relink.old_source_album_directory)?;
+        if let Some(old_source_artist_directory) =
+            planned_relink.old_source_album_directory.parent()
+        {
+            remove_directory_if_empty(old_source_artist_directory)?;
+        }
+    }
+
+    terminal.log_println(format!(
+        "  Relinked \"{}\" to \"{}\".",
+        old_relative_path, new_relative_path
+    ));
+
+    Ok(())
+}
+
+/// Associated with the `relink` command.
+///
+/// After albums have been moved or renamed in the source library, moves their transcoded
+/// directories (including the transcoded album states) to match and updates the library state,
+/// so the next transcode doesn't delete and re-transcode them. The mapping file lists the
+/// old and new album paths, relative to the library root (see `load_relink_mapping`).
+///
+/// All moves are checked before anything is modified. The library state is saved after each
+/// album (even if moving it failed partway), so it always matches the albums moved so far.
+pub fn cmd_relink(
+    configuration: &Configuration,
+    library_name: &str,
+    mapping_file_path: &Path,
+    terminal: &mut SimpleTerminal,
+) -> Result<()> {
+    terminal.log_println(
+        "Command: relink moved albums to their existing transcodes."
+            .cyan()
+            .bold(),
+    );
+
+    let library_configuration = configuration
+        .get_library_by_full_name(library_name)
+        .or_else(|| configuration.libraries.get(library_name))
+        .ok_or_else(|| miette!("No such library: \"{}\".", library_name))?;
+
+    let library = LibraryView::from_library_configuration(
+        configuration,
+        library_configuration,
+    )?;
+    let library_root_directory =
+        library.read().root_directory_in_source_library();
+
+    let mut library_state = LibraryState::load_from_directory(
+        &library_root_directory,
+    )
+    .wrap_err_with(|| {
+        miette!(
+            "Could not load the library state of \"{}\" (relinking requires a previous transcode).",
+            library_configuration.name
+        )
+    })?;
+
+    let album_relinks = load_relink_mapping(mapping_file_path)?;
+
+    check_moves_are_independent(
+        &album_relinks
+            .iter()
+            .map(|relink| {
+                (
+                    relink.old_relative_path(),
+                    relink.new_relative_path(),
+                )
+            })
+            .collect::<Vec<_>>(),
+    )
+    .wrap_err_with(|| {
+        miette!("Mapping file {:?} is invalid.", mapping_file_path)
+    })?;
+
+    let planned_relinks = album_relinks
+        .into_iter()
+        .map(|relink| plan_album_relink(&library, &library_state, relink))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Different source albums may still end up in the same transcoded directory
+    // (e.g. if `album_directory_template` leaves out what sets them apart).
+    check_moves_are_independent(
+        &planned_relinks
+            .iter()
+            .map(|planned_relink| {
+                (
+                    &planned_relink.old_transcoded_album_directory,
+                    &planned_relink.new_transcoded_album_directory,
+                )
+            })
+            .collect::<Vec<_>>(),
+    )
+    .wrap_err_with(|| {
+        miette!("Moved albums would clash in the transcoded library.")
+    })?;
+
+    let total_relinked_albums = planned_relinks.len();
+
+    for planned_relink in planned_relinks {
+        let relink_result =
+            apply_album_relink(planned_relink, &mut library_state, terminal);
+
+        // Saved even if the album failed to move, since its transcoded directory may already
+        // have been moved (see `apply_album_relink`) - otherwise the next transcode would delete it.
+        library_state.save_to_directory(&library_root_directory, true)?;
+        relink_result?;
+    }
+
+    terminal.log_println(
+        format!("Relinked {} albums.", total_relinked_albums)
+            .green()
+            .bold(),
+    );
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::check_moves_are_independent;
+
+    fn moves(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(old, new)| (old.to_string(), new.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn independent_moves_are_accepted() {
+        check_moves_are_independent(&moves(&[
+            ("Artist/A", "Artist/B"),
+            ("Artist/C", "Other Artist/C"),
+            ("Artist/D", "Artist/D"),
+        ]))
+        .unwrap();
+    }
+
+    #[test]
+    fn duplicate_targets_are_rejected() {
+        let error = check_moves_are_independent(&moves(&[
+            ("Artist/A", "Artist/B"),
+            ("Artist/C", "Artist/B"),
+        ]))
+        .unwrap_err();
+        assert!(error.to_string().contains("More than one album"));
+    }
+
+    #[test]
+    fn duplicate_sources_are_rejected() {
+        let error = check_moves_are_independent(&moves(&[
+            ("Artist/A", "Artist/B"),
+            ("Artist/A", "Artist/C"),
+        ]))
+        .unwrap_err();
+        assert!(error.to_string().contains("more than once"));
+    }
+
+    #[test]
+    fn chained_and_swapped_moves_are_rejected() {
+        let error = check_moves_are_independent(&moves(&[
+            ("Artist/A", "Artist/B"),
+            ("Artist/B", "Artist/C"),
+        ]))
+        .unwrap_err();
+        assert!(error.to_string().contains("itself moved elsewhere"));
+
+        let error = check_moves_are_independent(&moves(&[
+            ("Artist/A", "Artist/B"),
+            ("Artist/B", "Artist/A"),
+        ]))
+        .unwrap_err();
+        assert!(error.to_string().contains("itself moved elsewhere"));
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crossterm::style::Stylize;
+use euphony_configuration::Configuration;
+use euphony_library::state::transcoded::{
+    TranscodedAlbumState,
+    TranscodedAlbumStateLoadError,
+};
+use euphony_library::view::library::LibraryViewError;
+use euphony_library::view::{AlbumSourceFileList, LibraryView};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Serialize;
+
+use crate::commands::log_optional_library_unavailable;
+use crate::console::frontends::SimpleTerminal;
+use crate::console::LogBackend;
+
+
+/// Collection statistics of a single library (or, in `CollectionStatistics`, of all of them).
+#[derive(Serialize, Default)]
+struct LibraryStatistics {
+    artists: usize,
+    albums: usize,
+
+    /// Number of tracked audio files (see `transcoding.audio_file_extensions`).
+    audio_files: usize,
+
+    /// Number of tracked data files (see `transcoding.other_file_extensions`).
+    data_files: usize,
+
+    /// Total size of all tracked source files.
+    source_bytes: u64,
+
+    /// Total size of the tracked source files that have a transcoded counterpart.
+    source_bytes_with_transcoded_counterpart: u64,
+
+    /// Total size of those transcoded counterparts.
+    transcoded_bytes: u64,
+
+    /// Number of albums per genre tag (only collected if `aggregated_library.read_tags` is enabled,
+    /// see `AlbumView::read_album_tags`). Albums without a genre tag are not counted.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    albums_by_genre: BTreeMap<String, usize>,
+}
+
+impl LibraryStatistics {
+    fn add(&mut self, other: &LibraryStatistics) {
+        self.artists += other.artists;
+        self.albums += other.albums;
+        self.audio_files += other.audio_files;
+        self.data_files += other.data_files;
+        self.source_bytes += other.source_bytes;
+        self.source_bytes_with_transcoded_counterpart +=
+            other.source_bytes_with_transcoded_counterpart;
+        self.transcoded_bytes += other.transcoded_bytes;
+
+        for (genre, num_albums) in &other.albums_by_genre {
+            *self.albums_by_genre.entry(genre.clone()).or_default() +=
+                num_albums;
+        }
+    }
+
+    /// Returns the percentage of space saved by the transcoded files compared
+    /// to their source files, or `None` if nothing has been transcoded yet.
+    fn space_saved_percentage(&self) -> Option<f64> {
+        if self.source_bytes_with_transcoded_counterpart == 0 {
+            return None;
+        }
+
+        Some(
+            (1.0 - self.transcoded_bytes as f64
+                / self.source_bytes_with_transcoded_counterpart as f64)
+                * 100.0,
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct NamedLibraryStatistics {
+    library_name: String,
+
+    #[serde(flatten)]
+    statistics: LibraryStatistics,
+
+    space_saved_percentage: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct CollectionStatistics {
+    libraries: Vec<NamedLibraryStatistics>,
+
+    total: LibraryStatistics,
+
+    total_space_saved_percentage: Option<f64>,
+}
+
+fn file_size<P: AsRef<Path>>(file_path: P) -> Result<u64> {
+    let file_path = file_path.as_ref();
+
+    Ok(fs::metadata(file_path)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!("Could not read metadata of {:?}.", file_path)
+        })?
+        .len())
+}
+
+/// Formats the byte count as a human-readable size (e.g. `12.34 GiB`).
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit_index = 0;
+
+    while size >= 1024.0 && unit_index < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit_index += 1;
+    }
+
+    if unit_index == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.2} {}", size, UNITS[unit_index])
+    }
+}
+
+fn format_percentage(percentage: Option<f64>) -> String {
+    match percentage {
+        Some(percentage) => format!("{:.1} %", percentage),
+        None => "-".to_string(),
+    }
+}
+
+/// Walks the entire library and collects its statistics. Source files are the files
+/// tracked by euphony (see `AlbumSourceFileList`), while their transcoded counterparts
+/// are taken from the saved transcoded album state (see `TranscodedAlbumState`).
+fn collect_library_statistics(
+    library: &LibraryView,
+) -> Result<LibraryStatistics> {
+    let mut statistics = LibraryStatistics::default();
+
+    for artist in library.artists()?.values() {
+        statistics.artists += 1;
+
+        for album in artist.read().albums()?.into_values() {
+            statistics.albums += 1;
+
+            if let Some(genre) = album
+                .read()
+                .read_album_tags()
+                .and_then(|album_tags| album_tags.genre.clone())
+            {
+                *statistics.albums_by_genre.entry(genre).or_default() += 1;
+            }
+
+            let source_album_directory =
+                album.read().album_directory_in_source_library();
+            let transcoded_album_directory =
+                album.read().album_directory_in_transcoded_library();
+
+            let source_file_list =
+                AlbumSourceFileList::from_album_view(album.clone())?;
+
+            statistics.audio_files += source_file_list.audio_files.len();
+            statistics.data_files += source_file_list.data_files.len();
+
+            for source_file in source_file_list
+                .audio_files
+                .iter()
+                .chain(source_file_list.data_files.iter())
+            {
+                statistics.source_bytes +=
+                    file_size(source_album_directory.join(source_file))?;
+            }
+
+            let transcoded_state =
+                match TranscodedAlbumState::load_from_directory(
+                    &transcoded_album_directory,
+                ) {
+                    Ok(state) => state,
+                    Err(TranscodedAlbumStateLoadError::NotFound)
+                    | Err(
+                        TranscodedAlbumStateLoadError::SchemaVersionMismatch(_),
+                    ) => continue,
+                    Err(error) => return Err(error.into()),
+                };
+
+            for (transcoded_file, source_file) in transcoded_state
+                .transcoded_to_original_file_paths
+                .audio
+                .iter()
+                .chain(
+                    transcoded_state
+                        .transcoded_to_original_file_paths
+                        .data
+                        .iter(),
+                )
+            {
+                let Some(transcoded_file_metadata) = transcoded_state
+                    .transcoded_files
+                    .audio_files
+                    .get(transcoded_file)
+                    .or_else(|| {
+                        transcoded_state
+                            .transcoded_files
+                            .data_files
+                            .get(transcoded_file)
+                    })
+                else {
+                    continue;
+                };
+
+                let source_file_path = source_album_directory.join(source_file);
+                if !source_file_path.is_file() {
+                    continue;
+                }
+
+                statistics.source_bytes_with_transcoded_counterpart +=
+                    file_size(source_file_path)?;
+                statistics.transcoded_bytes +=
+                    transcoded_file_metadata.size_bytes;
+            }
+        }
+    }
+
+    Ok(statistics)
+}
+
+/// Associated with the `stats` command.
+///
+/// Walks each library and prints the number of artists, albums and tracked audio and data files,
+/// along with their total size and the space saved by their transcoded counterparts.
+/// Nothing is modified. If `output_json` is `true`, the statistics are printed as JSON instead.
+pub fn cmd_stats(
+    configuration: &Configuration,
+    output_json: bool,
+    terminal: &mut SimpleTerminal,
+) -> Result<()> {
+    let mut library_statistics: Vec<NamedLibraryStatistics> = Vec::new();
+    let mut total = LibraryStatistics::default();
+
+    for library_configuration in configuration.libraries.values() {
+        let library = match LibraryView::from_library_configuration(
+            configuration,
+            library_configuration,
+        ) {
+            Ok(library) => library,
+            Err(LibraryViewError::NoSuchDirectory(path))
+                if library_configuration.optional =>
+            {
+                if !output_json {
+                    log_optional_library_unavailable(
+                        terminal,
+                        &library_configuration.name,
+                        &path,
+                    );
+                }
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let statistics = collect_library_statistics(&library.read())
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not collect statistics of library {}.",
+                    library_configuration.name
+                )
+            })?;
+
+        total.add(&statistics);
+
+        library_statistics.push(NamedLibraryStatistics {
+            library_name: library_configuration.name.clone(),
+            space_saved_percentage: statistics.space_saved_percentage(),
+            statistics,
+        });
+    }
+
+    library_statistics.sort_unstable_by(|first, second| {
+        first.library_name.cmp(&second.library_name)
+    });
+
+    let collection_statistics = CollectionStatistics {
+        libraries: library_statistics,
+        total_space_saved_percentage: total.space_saved_percentage(),
+        total,
+    };
+
+    if output_json {
+        let serialized_statistics =
+            serde_json::to_string_pretty(&collection_statistics)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!("Could not serialize statistics into JSON.")
+                })?;
+
+        terminal.log_println(serialized_statistics);
+        return Ok(());
+    }
+
+    terminal.log_println("Command: collection statistics.".cyan().bold());
+    terminal.log_println(format!(
+        "{:<24} {:>8} {:>8} {:>8} {:>8} {:>12} {:>12} {:>8}",
+        "Library",
+        "Artists",
+        "Albums",
+        "Audio",
+        "Data",
+        "Source",
+        "Transcoded",
+        "Saved",
+    ));
+
+    let table_rows = collection_statistics
+        .libraries
+        .iter()
+        .map(|library| {
+            (
+                library.library_name.as_str(),
+                &library.statistics,
+                library.space_saved_percentage,
+            )
+        })
+        .chain([(
+            "Total",
+            &collection_statistics.total,
+            collection_statistics.total_space_saved_percentage,
+        )]);
+
+    for (name, statistics, space_saved_percentage) in table_rows {
+        terminal.log_println(format!(
+            "{:<24} {:>8} {:>8} {:>8} {:>8} {:>12} {:>12} {:>8}",
+            name,
+            statistics.artists,
+            statistics.albums,
+            statistics.audio_files,
+            statistics.data_files,
+            format_bytes(statistics.source_bytes),
+            format_bytes(statistics.transcoded_bytes),
+            format_percentage(space_saved_percentage),
+        ));
+    }
+
+    terminal.log_println(
+        "Space saved is computed only over source files that have a transcoded counterpart."
+            .dark_grey(),
+    );
+
+    if !collection_statistics.total.albums_by_genre.is_empty() {
+        terminal.log_newline();
+        terminal.log_println("Albums by genre (from tags):".bold());
+
+        for (genre, num_albums) in &collection_statistics.total.albums_by_genre {
+            terminal.log_println(format!("  {:<32} {:>8}", genre, num_albums));
+        }
+    }
+
+    Ok(())
+}
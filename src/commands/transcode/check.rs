@@ -0,0 +1,151 @@
+use crossterm::style::Stylize;
+use euphony_configuration::Configuration;
+use miette::{miette, Result};
+
+use super::{
+    collect_changes,
+    collect_full_library_states,
+    collect_libraries_sorted,
+    CollectedChanges,
+    TranscodeOptions,
+};
+use crate::console::frontends::TranscodeTerminal;
+use crate::console::LogBackend;
+
+
+/// Returned by `cmd_transcode_check`.
+pub struct TranscodeCheckSummary {
+    /// The number of albums the next transcode would process (changed and removed ones).
+    pub num_pending_albums: usize,
+
+    /// The number of albums that could not be scanned (see `UnscannableAlbum`),
+    /// meaning their state is unknown.
+    pub num_unscannable_albums: usize,
+}
+
+
+/// Associated with `transcode --check`.
+///
+/// Scans all libraries for changes (just like a transcode does), logs a short summary of the pending changes
+/// of each library and returns the number of pending and unscannable albums.
+/// Nothing is transcoded, copied, deleted or saved, so `options.dry_run` must be set
+/// (otherwise the scan cache would be saved).
+pub fn cmd_transcode_check<'config: 'scope, 'scope>(
+    configuration: &'config Configuration,
+    options: &TranscodeOptions,
+    terminal: &TranscodeTerminal<'config, 'scope>,
+) -> Result<TranscodeCheckSummary> {
+    if !options.dry_run {
+        return Err(miette!(
+            "BUG: transcode --check must not save the scan cache (dry_run is not set)."
+        ));
+    }
+
+    terminal.log_println(
+        "Command: check whether the transcoded collection is up to date."
+            .cyan()
+            .bold(),
+    );
+
+    let libraries = collect_libraries_sorted(configuration, options, terminal)?;
+    let fresh_library_states = collect_full_library_states(&libraries)?;
+    let CollectedChanges {
+        sorted_libraries_with_changes: libraries_with_changes,
+        num_unscannable_albums,
+    } = collect_changes(&fresh_library_states, options, terminal)?;
+
+    let num_total_discovered_albums = fresh_library_states
+        .iter()
+        .flat_map(|(_, library_state)| library_state.tracked_artists.values())
+        .map(|artist| artist.tracked_albums.len())
+        .sum::<usize>();
+
+    if libraries_with_changes.is_empty() {
+        if num_unscannable_albums > 0 {
+            terminal.log_println(
+                format!(
+                    "Found no changes, but {num_unscannable_albums} of {num_total_discovered_albums} \
+                    albums could not be scanned (see above)."
+                )
+                .yellow()
+                .bold(),
+            );
+        } else if num_total_discovered_albums == 0 {
+            terminal.log_eprintln(format!(
+                "{} Found 0 albums across {} libraries \
+                (check the library paths in your configuration).",
+                "WARNING:".yellow(),
+                libraries.len()
+            ));
+        } else {
+            terminal.log_println(
+                format!(
+                    "Found {num_total_discovered_albums} albums, all are up to date."
+                )
+                .green()
+                .bold(),
+            );
+        }
+
+        return Ok(TranscodeCheckSummary {
+            num_pending_albums: 0,
+            num_unscannable_albums,
+        });
+    }
+
+    let mut num_total_pending_albums: usize = 0;
+
+    for library in &libraries_with_changes {
+        let num_changed_albums = library
+            .sorted_changed_artists
+            .iter()
+            .map(|artist| artist.sorted_changed_albums.len())
+            .sum::<usize>();
+        let num_removed_albums = library
+            .sorted_changed_artists
+            .iter()
+            .map(|artist| artist.sorted_removed_albums.len())
+            .sum::<usize>();
+        let num_changed_files = library
+            .sorted_changed_artists
+            .iter()
+            .flat_map(|artist| {
+                artist
+                    .sorted_changed_albums
+                    .iter()
+                    .map(|album| &album.changes)
+                    .chain(
+                        artist
+                            .sorted_removed_albums
+                            .iter()
+                            .map(|album| &album.changes),
+                    )
+            })
+            .map(|changes| changes.number_of_changed_files())
+            .sum::<usize>();
+
+        terminal.log_println(format!(
+            "  Library \"{}\": {} changed albums, {} removed albums ({} files to process).",
+            library.library_name,
+            num_changed_albums.to_string().bold(),
+            num_removed_albums.to_string().bold(),
+            num_changed_files,
+        ));
+
+        num_total_pending_albums += num_changed_albums + num_removed_albums;
+    }
+
+    terminal.log_println(
+        format!(
+            "{num_total_pending_albums} of {num_total_discovered_albums} albums are not up to date \
+            (run transcode to process them, or --dry-run to see the individual files)."
+        )
+        .yellow()
+        .bold(),
+    );
+
+    Ok(TranscodeCheckSummary {
+        num_pending_albums: num_total_pending_albums,
+        num_unscannable_albums,
+    })
+}
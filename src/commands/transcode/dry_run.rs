@@ -0,0 +1,139 @@
+use std::cell::Cell;
+
+use crossterm::style::Stylize;
+use miette::Result;
+
+use super::state::changes::{FileJobContext, FileProcessingAction};
+use super::state::generate_jobs::GenerateChanges;
+use super::{QueuedAlbumJobType, QueuedLibrary};
+use crate::console::frontends::shared::queue::{
+    AlbumQueueItemFinishedResult,
+    QueueItemID,
+};
+use crate::console::frontends::TranscodeTerminal;
+use crate::console::{LogBackend, TranscodeBackend};
+
+
+/// Number of planned file actions (per action type) in a dry run.
+#[derive(Default)]
+struct PlannedActionCounts {
+    transcode: Cell<usize>,
+    copy: Cell<usize>,
+    delete: Cell<usize>,
+}
+
+impl PlannedActionCounts {
+    fn increment(counter: &Cell<usize>) {
+        counter.set(counter.get() + 1);
+    }
+}
+
+/// Log a single planned file action and count it.
+fn log_planned_action(
+    context: &FileJobContext,
+    counts: &PlannedActionCounts,
+    terminal: &TranscodeTerminal,
+) {
+    match &context.action {
+        FileProcessingAction::Transcode {
+            source_path,
+            target_path,
+            reason,
+        } => {
+            PlannedActionCounts::increment(&counts.transcode);
+
+            terminal.log_println(format!(
+                "  {} {:?} -> {:?} ({:?})",
+                "[transcode]".cyan(),
+                source_path,
+                target_path,
+                reason
+            ));
+        }
+        FileProcessingAction::Copy {
+            source_path,
+            target_path,
+            reason,
+        } => {
+            PlannedActionCounts::increment(&counts.copy);
+
+            terminal.log_println(format!(
+                "  {} {:?} -> {:?} ({:?})",
+                "[copy]".cyan(),
+                source_path,
+                target_path,
+                reason
+            ));
+        }
+        FileProcessingAction::DeleteInTranscoded {
+            target_path,
+            reason,
+        } => {
+            PlannedActionCounts::increment(&counts.delete);
+
+            terminal.log_println(format!(
+                "  {} {:?} ({:?})",
+                "[delete]".yellow(),
+                target_path,
+                reason
+            ));
+        }
+    }
+}
+
+/// Associated with the `transcode --dry-run` flag.
+///
+/// Instead of processing the queued albums, this only logs the action (transcode, copy or delete)
+/// that would be performed for each file, followed by a summary of the counts per action type.
+/// Nothing is written to or removed from the filesystem (this includes all state files).
+pub fn log_planned_changes<'config>(
+    queued_libraries: Vec<QueuedLibrary<'config>>,
+    terminal: &TranscodeTerminal<'config, '_>,
+) -> Result<()> {
+    let counts = PlannedActionCounts::default();
+
+    for queued_library in queued_libraries {
+        for queued_album in queued_library.queued_albums {
+            terminal.queue_album_item_start(queued_album.queue_id)?;
+
+            {
+                let album_view = queued_album.album.read();
+
+                terminal.log_println(format!(
+                    "↳ Album \"{} - {}\"{}",
+                    album_view.read_lock_artist().name,
+                    album_view.title,
+                    match queued_album.job_type {
+                        QueuedAlbumJobType::NormalProcessing => "",
+                        QueuedAlbumJobType::FullyRemoving =>
+                            " (removed from source library)",
+                    }
+                ));
+            }
+
+            // The generated jobs are never run, we only need their contexts.
+            queued_album.changes.generate_file_jobs(|context| {
+                log_planned_action(&context, &counts, terminal);
+                Ok(QueueItemID::new_random())
+            })?;
+
+            terminal.queue_album_item_finish(
+                queued_album.queue_id,
+                AlbumQueueItemFinishedResult::new_ok(),
+            )?;
+        }
+    }
+
+    terminal.log_println(
+        format!(
+            "Dry run finished, nothing was changed. Would transcode {} files, copy {} files and delete {} files.",
+            counts.transcode.get(),
+            counts.copy.get(),
+            counts.delete.get(),
+        )
+        .green()
+        .bold(),
+    );
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use std::fs::OpenOptions;
+use std::io::{stdout, BufWriter, Write};
+use std::path::PathBuf;
+
+use chrono::{Local, SecondsFormat};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use super::state::changes::FileType;
+
+
+/// Where the transcode event stream is written to (see `--events-jsonl`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TranscodeEventStreamTarget {
+    /// Standard output (passed as `-`).
+    Stdout,
+
+    /// A file (appended to if it already exists).
+    File(PathBuf),
+}
+
+impl TranscodeEventStreamTarget {
+    /// Parse the `--events-jsonl` argument: `-` means standard output, anything else is a file path.
+    pub fn from_argument(argument: &str) -> Self {
+        if argument == "-" {
+            Self::Stdout
+        } else {
+            Self::File(PathBuf::from(argument))
+        }
+    }
+}
+
+/// A single event of the transcode event stream.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TranscodeEvent<'a> {
+    AlbumStarted {
+        library: &'a str,
+        artist: &'a str,
+        album: &'a str,
+    },
+    AlbumFinished {
+        library: &'a str,
+        artist: &'a str,
+        album: &'a str,
+
+        /// `false` if the album was cancelled or aborted (e.g. with `--fail-fast`).
+        ok: bool,
+
+        elapsed_seconds: f64,
+    },
+    FileStarted {
+        file_type: FileType,
+
+        /// Target file path (in the transcoded library).
+        path: &'a str,
+    },
+    FileFinished {
+        file_type: FileType,
+
+        /// Target file path (in the transcoded library).
+        path: &'a str,
+
+        ok: bool,
+
+        /// Why the file failed to process (only present if `ok` is `false`).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<&'a str>,
+    },
+    FileCancelled {
+        file_type: FileType,
+
+        /// Target file path (in the transcoded library).
+        path: &'a str,
+    },
+}
+
+#[derive(Serialize)]
+struct TranscodeEventLine<'a> {
+    timestamp: String,
+
+    #[serde(flatten)]
+    event: TranscodeEvent<'a>,
+}
+
+/// A machine-parseable stream of transcode events (album and file starts and finishes),
+/// written as one JSON object per line (see `--events-jsonl`). It is independent of the human-readable log.
+pub struct TranscodeEventStream {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl TranscodeEventStream {
+    /// Open the event stream for writing. Files are created if missing and appended to otherwise.
+    pub fn open(target: &TranscodeEventStreamTarget) -> Result<Self> {
+        let writer: Box<dyn Write + Send> = match target {
+            TranscodeEventStreamTarget::Stdout => Box::new(stdout()),
+            TranscodeEventStreamTarget::File(file_path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(file_path)
+                    .into_diagnostic()
+                    .wrap_err_with(|| {
+                        miette!(
+                            "Could not open event stream file {:?}.",
+                            file_path
+                        )
+                    })?;
+
+                Box::new(BufWriter::new(file))
+            }
+        };
+
+        Ok(Self {
+            writer: Mutex::new(writer),
+        })
+    }
+
+    /// Write a single event (with the current time) into the stream. Each line is flushed immediately,
+    /// so the stream can be followed live (e.g. with `tail -f`).
+    pub fn emit(&self, event: TranscodeEvent) -> Result<()> {
+        let line = serde_json::to_string(&TranscodeEventLine {
+            timestamp: Local::now()
+                .to_rfc3339_opts(SecondsFormat::Millis, false),
+            event,
+        })
+        .into_diagnostic()
+        .wrap_err_with(|| miette!("Could not serialize transcode event."))?;
+
+        let mut writer = self.writer.lock();
+
+        writeln!(writer, "{line}")
+            .and_then(|_| writer.flush())
+            .into_diagnostic()
+            .wrap_err_with(|| miette!("Could not write transcode event."))
+    }
+}
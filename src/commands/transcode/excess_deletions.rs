@@ -0,0 +1,159 @@
+use std::io::{stdin, IsTerminal};
+use std::path::PathBuf;
+
+use crossterm::style::Stylize;
+use euphony_library::utilities::ExtendedSortedFileList;
+use miette::{miette, Context, Result};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::LibraryWithChanges;
+use crate::console::frontends::TranscodeTerminal;
+use crate::console::{LogBackend, UserControlMessage};
+use crate::globals::is_quiet_enabled;
+use crate::signals;
+
+
+/// Ask the user whether the given excess files may be deleted. Returns `true` if the user confirmed.
+///
+/// With the fancy terminal UI, the user confirms with `y` (or keeps the files with `q`).
+/// With the bare terminal, the answer is read from the standard input. If it can't be asked
+/// (quiet mode or non-interactive standard input), this returns `false` with a warning.
+fn ask_for_deletion_confirmation(
+    num_excess_files: usize,
+    terminal: &TranscodeTerminal,
+    terminal_user_input: &mut broadcast::Receiver<UserControlMessage>,
+) -> Result<bool> {
+    match terminal {
+        TranscodeTerminal::Bare(_) => {
+            // The prompt wouldn't be visible in quiet mode.
+            if is_quiet_enabled() || !stdin().is_terminal() {
+                terminal.log_eprintln(format!(
+                    "{} can't ask for confirmation (quiet mode or non-interactive standard input), \
+                    skipping the deletion of {} excess files. Pass --yes to delete them.",
+                    "WARNING:".yellow(),
+                    num_excess_files
+                ));
+                return Ok(false);
+            }
+
+            terminal.log_println("Delete them? [y/N]");
+
+            // Ctrl+C must still work while waiting for the answer.
+            let answer = signals::read_line_unless_terminated()
+                .wrap_err_with(|| miette!("Failed to read confirmation."))?;
+
+            Ok(answer.trim().eq_ignore_ascii_case("y"))
+        }
+        TranscodeTerminal::Fancy(_) => {
+            terminal.log_println("Press y to delete them or q to keep them.");
+
+            loop {
+                match terminal_user_input.blocking_recv() {
+                    Ok(UserControlMessage::Confirm) => return Ok(true),
+                    Ok(UserControlMessage::Exit) => return Ok(false),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return Ok(false),
+                }
+            }
+        }
+    }
+}
+
+/// Used with `--confirm-deletions`: list the files in the transcoded library that don't belong
+/// to any transcode (`AlbumFileChangesV2::excess_in_transcoded`) and ask the user
+/// to confirm their deletion once for the whole run.
+///
+/// If the deletion is not confirmed, the excess files are removed from the changes,
+/// so they are left alone (and found again on the next run). With `skip_confirmation` (`--yes`),
+/// the files are only listed.
+pub fn confirm_excess_deletions(
+    libraries_with_changes: &mut [LibraryWithChanges],
+    skip_confirmation: bool,
+    terminal: &TranscodeTerminal,
+    terminal_user_input: &mut broadcast::Receiver<UserControlMessage>,
+) -> Result<()> {
+    let mut excess_files_per_album: Vec<(String, Vec<PathBuf>)> = Vec::new();
+
+    for library in libraries_with_changes.iter() {
+        for artist in &library.sorted_changed_artists {
+            for changed_album in &artist.sorted_changed_albums {
+                let excess_in_transcoded =
+                    &changed_album.changes.excess_in_transcoded;
+
+                if excess_in_transcoded.is_empty() {
+                    continue;
+                }
+
+                let mut excess_files = excess_in_transcoded
+                    .audio
+                    .iter()
+                    .chain(excess_in_transcoded.data.iter())
+                    .chain(excess_in_transcoded.unknown.iter())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                excess_files.sort_unstable();
+
+                excess_files_per_album.push((
+                    format!(
+                        "{} - {}",
+                        artist.artist_name, changed_album.album_title
+                    ),
+                    excess_files,
+                ));
+            }
+        }
+    }
+
+    if excess_files_per_album.is_empty() {
+        return Ok(());
+    }
+
+    let num_excess_files = excess_files_per_album
+        .iter()
+        .map(|(_, excess_files)| excess_files.len())
+        .sum::<usize>();
+
+    terminal.log_println(format!(
+        "{} {} files in the transcoded library don't belong to any transcode and would be deleted:",
+        "NOTE:".cyan().bold(),
+        num_excess_files.to_string().bold(),
+    ));
+
+    for (album_name, excess_files) in &excess_files_per_album {
+        terminal.log_println(format!("  Album \"{}\":", album_name));
+
+        for excess_file in excess_files {
+            terminal.log_println(format!("    {:?}", excess_file));
+        }
+    }
+
+    if skip_confirmation {
+        terminal.log_println("Deleting them (--yes).");
+        return Ok(());
+    }
+
+    if ask_for_deletion_confirmation(
+        num_excess_files,
+        terminal,
+        terminal_user_input,
+    )? {
+        return Ok(());
+    }
+
+    terminal.log_println(format!(
+        "Keeping {} excess files in the transcoded library.",
+        num_excess_files
+    ));
+
+    for changed_album in libraries_with_changes
+        .iter_mut()
+        .flat_map(|library| library.sorted_changed_artists.iter_mut())
+        .flat_map(|artist| artist.sorted_changed_albums.iter_mut())
+    {
+        changed_album.changes.excess_in_transcoded =
+            ExtendedSortedFileList::default();
+    }
+
+    Ok(())
+}
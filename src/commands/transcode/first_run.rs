@@ -0,0 +1,135 @@
+use std::io::{stdin, IsTerminal};
+
+use crossterm::style::Stylize;
+use euphony_library::state::source::SourceAlbumState;
+use euphony_library::view::SharedLibraryView;
+use miette::{miette, Context, Result};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::library_state::{LibraryState, LIBRARY_STATE_FILE_NAME};
+use super::LibraryWithChanges;
+use crate::console::frontends::TranscodeTerminal;
+use crate::console::{LogBackend, UserControlMessage};
+use crate::globals::is_quiet_enabled;
+use crate::signals;
+
+
+/// Returns `true` if none of the libraries has ever been transcoded, i.e. there is no saved
+/// library state in any of them and none of the albums that need processing has a saved album state.
+///
+/// Albums without changes always have a saved album state, so only the changed ones need checking.
+pub fn is_first_run(
+    sorted_libraries_with_fresh_states: &[(SharedLibraryView, LibraryState)],
+    libraries_with_changes: &[LibraryWithChanges],
+) -> bool {
+    let any_library_state_exists = sorted_libraries_with_fresh_states
+        .iter()
+        .any(|(library, _)| {
+            library
+                .read()
+                .root_directory_in_source_library()
+                .join(LIBRARY_STATE_FILE_NAME)
+                .is_file()
+        });
+
+    if any_library_state_exists {
+        return false;
+    }
+
+    let any_album_state_exists = libraries_with_changes
+        .iter()
+        .flat_map(|library| &library.sorted_changed_artists)
+        .flat_map(|artist| &artist.sorted_changed_albums)
+        .any(|changed_album| {
+            SourceAlbumState::get_state_file_path_for_directory(
+                changed_album
+                    .album
+                    .read()
+                    .album_directory_in_source_library(),
+            )
+            .is_file()
+        });
+
+    !any_album_state_exists
+}
+
+/// Print a summary of the work a first run is about to do and ask the user to confirm it.
+/// Returns `true` if the user confirmed.
+///
+/// With the fancy terminal UI, the user confirms with `y` (or aborts with `q`).
+/// With the bare terminal, the answer is read from the standard input (which must be a terminal,
+/// otherwise this returns `false` - use `--yes` for non-interactive runs).
+pub fn confirm_first_run(
+    libraries_with_changes: &[LibraryWithChanges],
+    terminal: &TranscodeTerminal,
+    terminal_user_input: &mut broadcast::Receiver<UserControlMessage>,
+) -> Result<bool> {
+    let changed_albums = libraries_with_changes
+        .iter()
+        .flat_map(|library| &library.sorted_changed_artists)
+        .flat_map(|artist| &artist.sorted_changed_albums)
+        .collect::<Vec<_>>();
+
+    let num_audio_files = changed_albums
+        .iter()
+        .map(|album| album.changes.number_of_changed_audio_files())
+        .sum::<usize>();
+    let num_data_files = changed_albums
+        .iter()
+        .map(|album| album.changes.number_of_changed_data_files())
+        .sum::<usize>();
+
+    terminal.log_println(format!(
+        "{} no saved state was found in any library, this appears to be the first transcode.",
+        "NOTE:".cyan().bold()
+    ));
+    terminal.log_println(format!(
+        "Planned work: {} albums in {} libraries - {} audio files to transcode and {} data files to copy.",
+        changed_albums.len().to_string().bold(),
+        libraries_with_changes.len(),
+        num_audio_files.to_string().bold(),
+        num_data_files.to_string().bold(),
+    ));
+
+    match terminal {
+        TranscodeTerminal::Bare(_) => {
+            // The prompt wouldn't be visible in quiet mode.
+            if is_quiet_enabled() {
+                terminal.log_eprintln(format!(
+                    "{} can't ask for confirmation in quiet mode, pass --yes to confirm the first transcode.",
+                    "ERROR:".red()
+                ));
+                return Ok(false);
+            }
+
+            if !stdin().is_terminal() {
+                terminal.log_eprintln(format!(
+                    "{} standard input is not interactive, pass --yes to confirm the first transcode.",
+                    "ERROR:".red()
+                ));
+                return Ok(false);
+            }
+
+            terminal.log_println("Proceed? [y/N]");
+
+            // Ctrl+C must still work while waiting for the answer.
+            let answer = signals::read_line_unless_terminated()
+                .wrap_err_with(|| miette!("Failed to read confirmation."))?;
+
+            Ok(answer.trim().eq_ignore_ascii_case("y"))
+        }
+        TranscodeTerminal::Fancy(_) => {
+            terminal.log_println("Press y to proceed or q to abort.");
+
+            loop {
+                match terminal_user_input.blocking_recv() {
+                    Ok(UserControlMessage::Confirm) => return Ok(true),
+                    Ok(UserControlMessage::Exit) => return Ok(false),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return Ok(false),
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::io;
+use std::path::Path;
 use std::sync::atomic::AtomicBool;
 
 use crossbeam::channel::Sender;
@@ -40,11 +42,55 @@ impl<C: Send> CancellableTask<C> {
 pub trait IntoCancellableTask<C: Send> {
     fn into_cancellable_task(self) -> CancellableTask<C>;
 }
+/// Size of a processed file before (source) and after (output) processing.
+#[derive(Debug, Copy, Clone)]
+pub struct FileJobSizes {
+    pub source_size_bytes: u64,
+    pub output_size_bytes: u64,
+}
+
+impl FileJobSizes {
+    /// Read the sizes of the given source and output files from the filesystem.
+    /// Returns `None` if the metadata of either file could not be read.
+    pub fn from_file_paths<S: AsRef<Path>, O: AsRef<Path>>(
+        source_file_path: S,
+        output_file_path: O,
+    ) -> Option<Self> {
+        let source_size_bytes = source_file_path.as_ref().metadata().ok()?.len();
+        let output_size_bytes = output_file_path.as_ref().metadata().ok()?.len();
+
+        Some(Self {
+            source_size_bytes,
+            output_size_bytes,
+        })
+    }
+}
+
+/// Removes the file at `target_file_path`, if it exists.
+///
+/// Jobs call this before writing their output: the existing file may be a hard link
+/// (e.g. in staging mode, see `StagingDirectory`), and writing into it directly
+/// would also modify the file it is linked to.
+pub fn remove_existing_target_file<P: AsRef<Path>>(
+    target_file_path: P,
+) -> io::Result<()> {
+    let target_file_path = target_file_path.as_ref();
+
+    if target_file_path.is_file() {
+        std::fs::remove_file(target_file_path)?;
+    }
+
+    Ok(())
+}
+
 /// Task state for completed `FileJob`s.
 #[derive(Debug)]
 pub enum FileJobResult {
     Okay {
         verbose_info: Option<String>,
+
+        /// Source and output file sizes, if the job produced an output file.
+        sizes: Option<FileJobSizes>,
     },
     Errored {
         error: String,
@@ -59,7 +105,13 @@ pub enum FileJobMessage {
         file_type: FileType,
         file_path: String,
     },
-    // TODO Some sort of progress message?
+    /// Intra-file progress of a running job (currently only sent by `TranscodeAudioFileJob`).
+    Progress {
+        queue_item: QueueItemID,
+
+        /// How much of the file has been processed, from 0 to 100.
+        percent: u8,
+    },
     Finished {
         queue_item: QueueItemID,
         file_type: FileType,
@@ -89,6 +141,13 @@ impl FileJobMessage {
         }
     }
 
+    pub fn new_progress(queue_item: QueueItemID, percent: u8) -> Self {
+        Self::Progress {
+            queue_item,
+            percent,
+        }
+    }
+
     pub fn new_finished<P: Into<String>>(
         queue_item: QueueItemID,
         file_type: FileType,
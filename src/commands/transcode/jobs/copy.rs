@@ -1,25 +1,36 @@
 use std::fs;
-use std::path::PathBuf;
-use std::sync::atomic::AtomicBool;
+use std::fs::{File, FileTimes};
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crossbeam::channel::Sender;
+use euphony_configuration::aggregated_library::DataFileLinkMode;
 use euphony_configuration::get_path_extension_or_empty;
 use euphony_library::view::SharedAlbumView;
 use miette::{miette, Context, IntoDiagnostic, Result};
 
 use crate::commands::transcode::jobs::common::{
+    remove_existing_target_file,
     FileJob,
     FileJobMessage,
     FileJobResult,
+    FileJobSizes,
 };
 use crate::commands::transcode::state::changes::FileType;
 use crate::console::frontends::shared::queue::QueueItemID;
 use crate::globals::is_verbose_enabled;
 
+/// Whether we've already logged that a link couldn't be created and a data file was copied instead
+/// (the note is only logged once per run to avoid flooding the log, e.g. when linking across filesystems).
+static LINK_FALLBACK_NOTED: AtomicBool = AtomicBool::new(false);
+
 /// One of multiple file jobs.
 ///
 /// `CopyFileJob` simply copies a file (usually data/other files, not audio files) into the
-/// album directory in the aggregated library.
+/// album directory in the aggregated library. Depending on `aggregated_library.data_file_link_mode`,
+/// the file is hard-linked or symlinked instead (falling back to a copy if the link can't be created).
 pub struct CopyFileJob {
     /// File to copy from.
     source_file_path: PathBuf,
@@ -30,6 +41,19 @@ pub struct CopyFileJob {
     /// For missing directory creation purposes, the directory `target_file_path` is in.
     target_file_directory_path: PathBuf,
 
+    /// Whether to set the modification and access times of the copy to those of the source file
+    /// (see `aggregated_library.preserve_timestamps`).
+    preserve_timestamps: bool,
+
+    /// Size of the copy buffer in bytes (see `aggregated_library.copy_buffer_kib`).
+    copy_buffer_size: usize,
+
+    /// Whether to flush the copy to disk before finishing (see `aggregated_library.fsync_after_copy`).
+    fsync_after_copy: bool,
+
+    /// Whether to link the file instead of copying it (see `aggregated_library.data_file_link_mode`).
+    link_mode: DataFileLinkMode,
+
     /// `QueueItemID` this job belongs to.
     queue_item: QueueItemID,
 }
@@ -66,19 +90,124 @@ impl CopyFileJob {
             .parent()
             .ok_or_else(|| miette!("Could not get target file directory."))?;
 
+        let aggregated_library_config =
+            &album_locked.euphony_configuration().aggregated_library;
+
         Ok(Self {
             target_file_directory_path: target_file_directory.to_path_buf(),
             source_file_path,
             target_file_path,
+            preserve_timestamps: aggregated_library_config.preserve_timestamps,
+            copy_buffer_size: aggregated_library_config.copy_buffer_kib * 1024,
+            fsync_after_copy: aggregated_library_config.fsync_after_copy,
+            link_mode: aggregated_library_config.data_file_link_mode,
             queue_item,
         })
     }
+
+    /// Copy the source file to the target path, returning the amount of bytes copied.
+    /// If `preserve_timestamps` is enabled, the copy also gets the source file's modification and access times.
+    ///
+    /// The cancellation flag is checked between buffer writes: if the copy is cancelled,
+    /// the partially copied file is removed and `None` is returned.
+    fn copy_source_to_target(
+        &self,
+        cancellation_flag: &AtomicBool,
+    ) -> io::Result<Option<u64>> {
+        // The times are read before copying, as reading the file can update its access time.
+        let source_file_times = if self.preserve_timestamps {
+            Some(read_file_times(&self.source_file_path)?)
+        } else {
+            None
+        };
+
+        let mut source_file = File::open(&self.source_file_path)?;
+        let source_file_permissions = source_file.metadata()?.permissions();
+
+        let mut target_file = File::create(&self.target_file_path)?;
+
+        let mut buffer = vec![0u8; self.copy_buffer_size];
+        let mut bytes_copied: u64 = 0;
+
+        loop {
+            if cancellation_flag.load(Ordering::SeqCst) {
+                drop(target_file);
+                fs::remove_file(&self.target_file_path)?;
+
+                return Ok(None);
+            }
+
+            let bytes_read = match source_file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(bytes_read) => bytes_read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+
+            target_file.write_all(&buffer[..bytes_read])?;
+            bytes_copied += bytes_read as u64;
+        }
+
+        if let Some(source_file_times) = source_file_times {
+            target_file.set_times(source_file_times)?;
+        }
+
+        if self.fsync_after_copy {
+            target_file.sync_all()?;
+        }
+
+        // Like `fs::copy`, the copy gets the permissions of the source file.
+        drop(target_file);
+        fs::set_permissions(&self.target_file_path, source_file_permissions)?;
+
+        Ok(Some(bytes_copied))
+    }
+
+    /// Create a hard link or a symbolic link (depending on `link_mode`) to the source file at the target path.
+    /// Symbolic links point to the canonical (absolute) source file path.
+    fn link_source_to_target(&self) -> io::Result<()> {
+        match self.link_mode {
+            DataFileLinkMode::Copy => Err(io::Error::other(
+                "BUG: linking is disabled (data_file_link_mode is \"copy\")",
+            )),
+            DataFileLinkMode::Hardlink => {
+                fs::hard_link(&self.source_file_path, &self.target_file_path)
+            }
+            DataFileLinkMode::Symlink => {
+                let canonical_source_file_path =
+                    dunce::canonicalize(&self.source_file_path)?;
+
+                #[cfg(unix)]
+                return std::os::unix::fs::symlink(
+                    canonical_source_file_path,
+                    &self.target_file_path,
+                );
+
+                #[cfg(windows)]
+                return std::os::windows::fs::symlink_file(
+                    canonical_source_file_path,
+                    &self.target_file_path,
+                );
+            }
+        }
+    }
+}
+
+/// Read the modification and access times of the given file.
+pub(crate) fn read_file_times(file_path: &Path) -> io::Result<FileTimes> {
+    let metadata = fs::metadata(file_path)?;
+
+    Ok(FileTimes::new()
+        .set_modified(metadata.modified()?)
+        .set_accessed(metadata.accessed()?))
 }
 
 impl FileJob for CopyFileJob {
     fn run(
         &mut self,
-        _cancellation_flag: &AtomicBool,
+        cancellation_flag: &AtomicBool,
         message_sender: &Sender<FileJobMessage>,
     ) -> Result<()> {
         message_sender
@@ -112,16 +241,96 @@ impl FileJob for CopyFileJob {
             return Ok(());
         }
 
+        /*
+         * Step 1.5: remove the existing target file (it might be a hard link).
+         */
+        if let Err(error) = remove_existing_target_file(&self.target_file_path) {
+            let verbose_info = is_verbose_enabled()
+                .then(|| format!("fs::remove_file error: {error}"));
+
+            message_sender
+                .send(FileJobMessage::new_finished(
+                    self.queue_item,
+                    FileType::Data,
+                    self.target_file_path.to_string_lossy(),
+                    FileJobResult::Errored {
+                        error: "Could not remove existing target file."
+                            .to_string(),
+                        verbose_info,
+                    },
+                ))
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!("Could not send FileJobMessage::Finished")
+                })?;
+
+            return Ok(());
+        }
+
         /*
          * Step 2: copy the file.
          */
-        // TODO Find out a way to create cancellable file copies.
-        //      (Make sure to handle the half-copied edge-case - we should delete such a file)
-        let copy_result =
-            fs::copy(&self.source_file_path, &self.target_file_path);
+        if self.link_mode != DataFileLinkMode::Copy {
+            match self.link_source_to_target() {
+                Ok(()) => {
+                    let verbose_info = is_verbose_enabled().then(|| {
+                        format!("Link operation OK ({:?}).", self.link_mode)
+                    });
+
+                    return message_sender
+                        .send(FileJobMessage::new_finished(
+                            self.queue_item,
+                            FileType::Data,
+                            self.target_file_path.to_string_lossy(),
+                            FileJobResult::Okay {
+                                verbose_info,
+                                sizes: FileJobSizes::from_file_paths(
+                                    &self.source_file_path,
+                                    &self.target_file_path,
+                                ),
+                            },
+                        ))
+                        .into_diagnostic()
+                        .wrap_err_with(|| {
+                            miette!("Could not send FileJobMessage::Finished.")
+                        });
+                }
+                Err(error) => {
+                    // E.g. a hard link across filesystems or a symlink without the required privileges.
+                    if !LINK_FALLBACK_NOTED.swap(true, Ordering::SeqCst)
+                        || is_verbose_enabled()
+                    {
+                        message_sender
+                            .send(FileJobMessage::new_log(format!(
+                                "NOTE: could not link {:?} ({:?}: {}), copying it instead \
+                                (further fallbacks are only logged with --verbose).",
+                                self.source_file_path, self.link_mode, error
+                            )))
+                            .into_diagnostic()
+                            .wrap_err_with(|| {
+                                miette!("Could not send FileJobMessage::Log.")
+                            })?;
+                    }
+                }
+            }
+        }
+
+        let copy_result = self.copy_source_to_target(cancellation_flag);
 
         let processing_result = match copy_result {
-            Ok(bytes_copied) => {
+            Ok(None) => {
+                return message_sender
+                    .send(FileJobMessage::new_cancelled(
+                        self.queue_item,
+                        FileType::Data,
+                        self.target_file_path.to_string_lossy(),
+                    ))
+                    .into_diagnostic()
+                    .wrap_err_with(|| {
+                        miette!("Could not send FileJobMessage::Cancelled.")
+                    });
+            }
+            Ok(Some(bytes_copied)) => {
                 let verbose_info = is_verbose_enabled().then(|| {
                     format!(
                         "Copy operation OK. Copied {} bytes.",
@@ -129,7 +338,13 @@ impl FileJob for CopyFileJob {
                     )
                 });
 
-                FileJobResult::Okay { verbose_info }
+                FileJobResult::Okay {
+                    verbose_info,
+                    sizes: FileJobSizes::from_file_paths(
+                        &self.source_file_path,
+                        &self.target_file_path,
+                    ),
+                }
             }
             Err(error) => {
                 let verbose_info = is_verbose_enabled().then(|| {
@@ -161,3 +376,134 @@ impl FileJob for CopyFileJob {
         Ok(())
     }
 }
+
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use crossbeam::channel;
+    use euphony_configuration::aggregated_library::DataFileLinkMode;
+
+    use super::{CopyFileJob, LINK_FALLBACK_NOTED};
+    use crate::commands::transcode::jobs::common::{
+        FileJob,
+        FileJobMessage,
+        FileJobResult,
+    };
+    use crate::console::frontends::shared::queue::QueueItemID;
+    use crate::globals::VERBOSE;
+    use crate::test_utilities::TestDirectory;
+
+    /// A directory that is usually on a different filesystem than the temporary directory.
+    const OTHER_FILESYSTEM_DIRECTORY: &str = "/dev/shm";
+
+    fn hardlink_job(
+        source_file_path: PathBuf,
+        target_directory: &Path,
+    ) -> CopyFileJob {
+        CopyFileJob {
+            source_file_path,
+            target_file_path: target_directory.join("cover.jpg"),
+            target_file_directory_path: target_directory.to_path_buf(),
+            preserve_timestamps: false,
+            copy_buffer_size: 1024,
+            fsync_after_copy: false,
+            link_mode: DataFileLinkMode::Hardlink,
+            queue_item: QueueItemID::new_random(),
+        }
+    }
+
+    /// Run the job and return the result it finished with, along with any logged messages.
+    fn run_job(job: &mut CopyFileJob) -> (FileJobResult, Vec<String>) {
+        // Normally set on startup (fails harmlessly if another test already set it).
+        let _ = VERBOSE.set(false);
+
+        let (message_sender, message_receiver) = channel::unbounded();
+        job.run(&AtomicBool::new(false), &message_sender).unwrap();
+
+        let mut processing_result = None;
+        let mut logs = Vec::new();
+        for message in message_receiver.try_iter() {
+            match message {
+                FileJobMessage::Finished {
+                    processing_result: result,
+                    ..
+                } => processing_result = Some(result),
+                FileJobMessage::Log { content } => logs.push(content),
+                _ => {}
+            }
+        }
+
+        (
+            processing_result
+                .expect("job did not send FileJobMessage::Finished"),
+            logs,
+        )
+    }
+
+    #[test]
+    fn hard_link_on_the_same_filesystem_is_created() {
+        let test_directory = TestDirectory::new("same-filesystem");
+        let source_file_path = test_directory.join("source.jpg");
+        fs::write(&source_file_path, "jpg").unwrap();
+
+        let mut job = hardlink_job(
+            source_file_path.clone(),
+            &test_directory.join("target"),
+        );
+        let (result, _) = run_job(&mut job);
+
+        assert!(matches!(result, FileJobResult::Okay { .. }));
+        assert_eq!(
+            fs::metadata(&source_file_path).unwrap().ino(),
+            fs::metadata(&job.target_file_path).unwrap().ino()
+        );
+    }
+
+    #[test]
+    fn hard_link_across_filesystems_falls_back_to_copy() {
+        let source_directory = TestDirectory::new("cross-device-source");
+
+        let other_filesystem_directory = Path::new(OTHER_FILESYSTEM_DIRECTORY);
+        let is_other_filesystem = other_filesystem_directory.is_dir()
+            && fs::metadata(other_filesystem_directory).unwrap().dev()
+                != fs::metadata(&source_directory).unwrap().dev();
+        if !is_other_filesystem {
+            eprintln!(
+                "Skipping: {OTHER_FILESYSTEM_DIRECTORY} is not on a different filesystem."
+            );
+            return;
+        }
+
+        let target_directory = TestDirectory::new_in(
+            other_filesystem_directory,
+            "cross-device-target",
+        );
+
+        let source_file_path = source_directory.join("source.jpg");
+        fs::write(&source_file_path, "jpg").unwrap();
+
+        LINK_FALLBACK_NOTED.store(false, Ordering::SeqCst);
+
+        let mut job = hardlink_job(source_file_path.clone(), &target_directory);
+        let (result, logs) = run_job(&mut job);
+
+        assert!(matches!(result, FileJobResult::Okay { .. }));
+        assert_eq!(
+            fs::read_to_string(&job.target_file_path).unwrap(),
+            "jpg"
+        );
+        assert_eq!(
+            fs::metadata(&source_file_path).unwrap().nlink(),
+            1
+        );
+        assert!(
+            logs.iter().any(|log| log.contains("copying it instead")),
+            "{logs:?}"
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::channel::Sender;
+use euphony_library::view::SharedAlbumView;
+use miette::{miette, Context, IntoDiagnostic, Result};
+
+use crate::commands::transcode::jobs::common::{
+    remove_existing_target_file,
+    FileJob,
+    FileJobMessage,
+    FileJobResult,
+    FileJobSizes,
+};
+use crate::commands::transcode::jobs::copy::read_file_times;
+use crate::commands::transcode::state::changes::FileType;
+use crate::console::frontends::shared::queue::QueueItemID;
+use crate::globals::is_verbose_enabled;
+
+const FFMPEG_COVER_ART_CANCELLATION_CHECK_INTERVAL: Duration =
+    Duration::from_millis(20);
+
+/// One of multiple file jobs.
+///
+/// `ProcessCoverArtJob` is used instead of `CopyFileJob` for cover art images
+/// (see `aggregated_library.cover_art_max_dimension`): it uses ffmpeg to downscale the image
+/// (if needed) and re-encode it into the album directory of the aggregated library.
+pub struct ProcessCoverArtJob {
+    /// Path to the source image.
+    source_file_path: PathBuf,
+
+    /// Path to the processed image that will be created.
+    target_file_path: PathBuf,
+
+    /// For missing directory creation purposes, the directory `target_file_path` is in.
+    target_file_directory_path: PathBuf,
+
+    /// Path to the ffmpeg binary.
+    ffmpeg_binary_path: String,
+
+    /// Arguments to ffmpeg that will downscale and re-encode the image.
+    ffmpeg_arguments: Vec<String>,
+
+    /// Whether to set the modification and access times of the processed image to those of the source file
+    /// (see `aggregated_library.preserve_timestamps`).
+    preserve_timestamps: bool,
+
+    /// `QueueItemID` this job belongs to.
+    queue_item: QueueItemID,
+}
+
+impl ProcessCoverArtJob {
+    /// Initialize a new `ProcessCoverArtJob`.
+    pub fn new(
+        album: SharedAlbumView,
+        source_file_path: PathBuf,
+        target_file_path: PathBuf,
+        queue_item: QueueItemID,
+    ) -> Result<Self> {
+        let album_locked = album.read();
+
+        let config = album_locked.euphony_configuration();
+        let ffmpeg_config = config.ffmpeg_configuration_for_library(
+            album_locked.library_configuration(),
+        );
+
+        let max_dimension = config
+            .aggregated_library
+            .cover_art_max_dimension
+            .ok_or_else(|| {
+                miette!("Cover art processing is disabled (no cover_art_max_dimension).")
+            })?;
+
+        let target_file_directory = target_file_path
+            .parent()
+            .ok_or_else(|| miette!("Could not get target file directory."))?;
+
+        let source_file_path_str = source_file_path
+            .to_str()
+            .ok_or_else(|| miette!("Source file path is not valid UTF-8."))?;
+        let target_file_path_str = target_file_path
+            .to_str()
+            .ok_or_else(|| miette!("Target file path is not valid UTF-8."))?;
+
+        // The image is scaled to fit into a `max_dimension` square (keeping its aspect ratio),
+        // but never upscaled. The output format is picked by ffmpeg from the (unchanged) extension.
+        let ffmpeg_arguments = vec![
+            "-y".to_string(),
+            "-hide_banner".to_string(),
+            "-loglevel".to_string(),
+            "error".to_string(),
+            "-i".to_string(),
+            source_file_path_str.to_string(),
+            "-vf".to_string(),
+            format!(
+                "scale='min(iw,{max_dimension})':'min(ih,{max_dimension})':force_original_aspect_ratio=decrease"
+            ),
+            "-frames:v".to_string(),
+            "1".to_string(),
+            "-update".to_string(),
+            "1".to_string(),
+            "-q:v".to_string(),
+            "2".to_string(),
+            target_file_path_str.to_string(),
+        ];
+
+        Ok(Self {
+            target_file_directory_path: target_file_directory.to_path_buf(),
+            source_file_path,
+            target_file_path,
+            ffmpeg_binary_path: ffmpeg_config.binary.clone(),
+            ffmpeg_arguments,
+            preserve_timestamps: config.aggregated_library.preserve_timestamps,
+            queue_item,
+        })
+    }
+
+    /// Set the modification and access times of the processed image to those of the source file,
+    /// if `preserve_timestamps` is enabled.
+    fn apply_preserved_timestamps(&self) -> std::io::Result<()> {
+        if !self.preserve_timestamps {
+            return Ok(());
+        }
+
+        let source_file_times = read_file_times(&self.source_file_path)?;
+
+        File::options()
+            .write(true)
+            .open(&self.target_file_path)?
+            .set_times(source_file_times)
+    }
+
+    fn send_finished(
+        &self,
+        message_sender: &Sender<FileJobMessage>,
+        processing_result: FileJobResult,
+    ) -> Result<()> {
+        message_sender
+            .send(FileJobMessage::new_finished(
+                self.queue_item,
+                FileType::Data,
+                self.target_file_path.to_string_lossy(),
+                processing_result,
+            ))
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!("Could not send FileJobMessage::Finished.")
+            })
+    }
+}
+
+impl FileJob for ProcessCoverArtJob {
+    fn run(
+        &mut self,
+        cancellation_flag: &AtomicBool,
+        message_sender: &Sender<FileJobMessage>,
+    ) -> Result<()> {
+        message_sender
+            .send(FileJobMessage::new_starting(
+                self.queue_item,
+                FileType::Data,
+                self.target_file_path.to_string_lossy(),
+            ))
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!("Could not send FileJobMessage::Starting.")
+            })?;
+
+        /*
+         * Step 1: create parent directories if missing.
+         */
+        if let Err(error) = fs::create_dir_all(&self.target_file_directory_path)
+        {
+            let verbose_info = is_verbose_enabled()
+                .then(|| format!("fs::create_dir_all error: {error}"));
+
+            return self.send_finished(
+                message_sender,
+                FileJobResult::Errored {
+                    error: "Could not create target file's missing parent directory."
+                        .to_string(),
+                    verbose_info,
+                },
+            );
+        }
+
+        /*
+         * Step 1.5: remove the existing target file (it might be a hard link).
+         */
+        if let Err(error) = remove_existing_target_file(&self.target_file_path) {
+            let verbose_info = is_verbose_enabled()
+                .then(|| format!("fs::remove_file error: {error}"));
+
+            return self.send_finished(
+                message_sender,
+                FileJobResult::Errored {
+                    error: "Could not remove existing target file.".to_string(),
+                    verbose_info,
+                },
+            );
+        }
+
+        /*
+         * Step 2: run ffmpeg (downscales and re-encodes the image).
+         */
+        let mut ffmpeg_command = Command::new(&self.ffmpeg_binary_path);
+        ffmpeg_command
+            .args(&self.ffmpeg_arguments)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+
+        // See `TranscodeAudioFileJob` - cancellation is handled by killing ffmpeg below.
+        #[cfg(unix)]
+        ffmpeg_command.process_group(0);
+
+        let mut ffmpeg_child_process =
+            ffmpeg_command.spawn().into_diagnostic().wrap_err_with(|| {
+                miette!("Could not spawn ffmpeg for processing cover art.")
+            })?;
+
+        while ffmpeg_child_process
+            .try_wait()
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!("Could not wait or get process exit code.")
+            })?
+            .is_none()
+        {
+            if cancellation_flag.load(Ordering::SeqCst) {
+                ffmpeg_child_process
+                    .kill()
+                    .into_diagnostic()
+                    .wrap_err_with(|| {
+                        miette!("Could not kill ffmpeg process.")
+                    })?;
+
+                break;
+            }
+
+            thread::sleep(FFMPEG_COVER_ART_CANCELLATION_CHECK_INTERVAL);
+        }
+
+        let ffmpeg_exit_status =
+            ffmpeg_child_process.wait().into_diagnostic()?;
+
+        // With `-loglevel error`, ffmpeg only writes errors to stderr.
+        let mut ffmpeg_stderr = String::new();
+        if let Some(mut stderr) = ffmpeg_child_process.stderr.take() {
+            let _ = stderr.read_to_string(&mut ffmpeg_stderr);
+        }
+
+        if cancellation_flag.load(Ordering::SeqCst) {
+            if self.target_file_path.is_file() {
+                fs::remove_file(&self.target_file_path)
+                    .into_diagnostic()
+                    .wrap_err_with(|| {
+                        miette!(
+                            "Could not remove partially processed cover art at {:?}.",
+                            self.target_file_path
+                        )
+                    })?;
+            }
+
+            return message_sender
+                .send(FileJobMessage::new_cancelled(
+                    self.queue_item,
+                    FileType::Data,
+                    self.target_file_path.to_string_lossy(),
+                ))
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!("Could not send FileJobMessage::Cancelled.")
+                });
+        }
+
+        let processing_result = if !ffmpeg_exit_status.success() {
+            let verbose_info = is_verbose_enabled().then(|| {
+                format!(
+                    "ffmpeg exited ({}). Binary={:?} Arguments={:?}",
+                    ffmpeg_exit_status,
+                    &self.ffmpeg_binary_path,
+                    &self.ffmpeg_arguments
+                )
+            });
+
+            FileJobResult::Errored {
+                error: format!(
+                    "ffmpeg could not process cover art.\nStderr: {}",
+                    ffmpeg_stderr
+                ),
+                verbose_info,
+            }
+        } else if let Err(error) = self.apply_preserved_timestamps() {
+            FileJobResult::Errored {
+                error: format!(
+                    "Could not preserve source file timestamps: {error}"
+                ),
+                verbose_info: None,
+            }
+        } else {
+            let verbose_info = is_verbose_enabled().then(|| {
+                format!(
+                    "ffmpeg exited (exit code 0). Binary={:?} Arguments={:?}",
+                    &self.ffmpeg_binary_path, &self.ffmpeg_arguments
+                )
+            });
+
+            FileJobResult::Okay {
+                verbose_info,
+                sizes: FileJobSizes::from_file_paths(
+                    &self.source_file_path,
+                    &self.target_file_path,
+                ),
+            }
+        };
+
+        self.send_finished(message_sender, processing_result)
+    }
+}
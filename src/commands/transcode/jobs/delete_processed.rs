@@ -3,6 +3,7 @@ use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 
 use crossbeam::channel::Sender;
+use euphony_configuration::library::LibraryConfiguration;
 use euphony_configuration::Configuration;
 use miette::{miette, Context, IntoDiagnostic, Result};
 
@@ -37,6 +38,7 @@ impl DeleteProcessedFileJob {
     /// If the file is missing
     pub fn new(
         configuration: &Configuration,
+        library_configuration: &LibraryConfiguration,
         target_file_path: PathBuf,
         file_type: FileType,
         ignore_if_missing: bool,
@@ -45,8 +47,9 @@ impl DeleteProcessedFileJob {
         /*
          * 1. Sanity checks
          */
-        if !target_file_path.starts_with(&configuration.aggregated_library.path)
-        {
+        if !target_file_path.starts_with(
+            configuration.transcoded_library_path(library_configuration),
+        ) {
             return Err(miette!(
                 "Suspicious path (not in transcoded directory): {:?}",
                 target_file_path
@@ -92,7 +95,10 @@ impl FileJob for DeleteProcessedFileJob {
                 let verbose_info = is_verbose_enabled()
                     .then(|| "File did not exist, but ignore_if_missing==true - skipping.".to_string());
 
-                FileJobResult::Okay { verbose_info }
+                FileJobResult::Okay {
+                    verbose_info,
+                    sizes: None,
+                }
             } else {
                 FileJobResult::Errored {
                     error: "File did not exist and ignore_if_missing != true!"
@@ -104,7 +110,10 @@ impl FileJob for DeleteProcessedFileJob {
             let removal_result = fs::remove_file(&self.target_file_path);
 
             match removal_result {
-                Ok(_) => FileJobResult::Okay { verbose_info: None },
+                Ok(_) => FileJobResult::Okay {
+                    verbose_info: None,
+                    sizes: None,
+                },
                 Err(error) => FileJobResult::Errored {
                     error: error.to_string(),
                     verbose_info: None,
@@ -1,11 +1,15 @@
 pub mod common;
 pub mod copy;
+pub mod cover_art;
 pub mod delete_processed;
+pub mod retry;
 pub mod thread_pool;
 pub mod transcode;
 
 pub use common::*;
 pub use copy::*;
+pub use cover_art::*;
 pub use delete_processed::*;
+pub use retry::*;
 pub use thread_pool::*;
 pub use transcode::*;
@@ -0,0 +1,187 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use miette::{miette, Context, IntoDiagnostic, Result};
+
+use crate::commands::transcode::jobs::common::{
+    FileJob,
+    FileJobMessage,
+    FileJobResult,
+};
+
+const RETRY_DELAY_CANCELLATION_CHECK_INTERVAL: Duration =
+    Duration::from_millis(50);
+
+/// Wraps another `FileJob` and runs it again (after a delay) if it fails, up to `max_retries` times
+/// (see `aggregated_library.failure_max_retries` and `aggregated_library.failure_delay_seconds`).
+///
+/// Messages of the wrapped job are forwarded as usual, except that the main thread only sees
+/// the first `FileJobMessage::Starting` and the final `FileJobMessage::Finished`, which notes
+/// the number of attempts in its `verbose_info`.
+pub struct RetryingFileJob<Job: FileJob> {
+    job: Job,
+
+    max_retries: u16,
+
+    retry_delay: Duration,
+}
+
+impl<Job: FileJob> RetryingFileJob<Job> {
+    pub fn new(job: Job, max_retries: u16, retry_delay: Duration) -> Self {
+        Self {
+            job,
+            max_retries,
+            retry_delay,
+        }
+    }
+
+    /// Forwards the messages of a single attempt to `message_sender`. A failed `Finished` message
+    /// is held back and returned (unless this is the last attempt) so the job can be retried.
+    fn forward_attempt_messages(
+        attempt_receiver: Receiver<FileJobMessage>,
+        message_sender: &Sender<FileJobMessage>,
+        attempt: usize,
+        max_attempts: usize,
+    ) -> Result<Option<FileJobMessage>> {
+        let mut held_back_failure: Option<FileJobMessage> = None;
+
+        for message in attempt_receiver {
+            let message = match message {
+                // The main thread already knows the file has started.
+                FileJobMessage::Starting { .. } if attempt > 1 => continue,
+                FileJobMessage::Finished {
+                    processing_result: FileJobResult::Errored { .. },
+                    ..
+                } if attempt < max_attempts => {
+                    held_back_failure = Some(message);
+                    continue;
+                }
+                FileJobMessage::Finished { .. } if max_attempts > 1 => {
+                    with_attempt_count(message, attempt, max_attempts)
+                }
+                message => message,
+            };
+
+            message_sender
+                .send(message)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!("Could not forward FileJobMessage.")
+                })?;
+        }
+
+        Ok(held_back_failure)
+    }
+
+    /// Sleeps for `retry_delay`, returning early (with `false`) if the job is cancelled.
+    fn wait_before_retrying(&self, cancellation_flag: &AtomicBool) -> bool {
+        let delay_start_time = Instant::now();
+
+        while delay_start_time.elapsed() < self.retry_delay {
+            if cancellation_flag.load(Ordering::SeqCst) {
+                return false;
+            }
+
+            thread::sleep(RETRY_DELAY_CANCELLATION_CHECK_INTERVAL);
+        }
+
+        !cancellation_flag.load(Ordering::SeqCst)
+    }
+}
+
+/// Notes the number of attempts in the `verbose_info` of a `FileJobMessage::Finished` message.
+fn with_attempt_count(
+    message: FileJobMessage,
+    attempt: usize,
+    max_attempts: usize,
+) -> FileJobMessage {
+    let FileJobMessage::Finished {
+        queue_item,
+        file_type,
+        file_path,
+        mut processing_result,
+    } = message
+    else {
+        return message;
+    };
+
+    let verbose_info = match &mut processing_result {
+        FileJobResult::Okay { verbose_info, .. } => verbose_info,
+        FileJobResult::Errored { verbose_info, .. } => verbose_info,
+    };
+
+    let attempt_note =
+        format!("Finished after {attempt} of at most {max_attempts} attempts.");
+
+    *verbose_info = Some(match verbose_info.take() {
+        Some(existing_info) => format!("{existing_info} {attempt_note}"),
+        None => attempt_note,
+    });
+
+    FileJobMessage::Finished {
+        queue_item,
+        file_type,
+        file_path,
+        processing_result,
+    }
+}
+
+impl<Job: FileJob> FileJob for RetryingFileJob<Job> {
+    fn run(
+        &mut self,
+        cancellation_flag: &AtomicBool,
+        message_sender: &Sender<FileJobMessage>,
+    ) -> Result<()> {
+        let max_attempts = self.max_retries as usize + 1;
+
+        for attempt in 1..=max_attempts {
+            let (attempt_sender, attempt_receiver) =
+                unbounded::<FileJobMessage>();
+
+            let held_back_failure = thread::scope(|scope| {
+                let forwarder = scope.spawn(|| {
+                    Self::forward_attempt_messages(
+                        attempt_receiver,
+                        message_sender,
+                        attempt,
+                        max_attempts,
+                    )
+                });
+
+                let job_result =
+                    self.job.run(cancellation_flag, &attempt_sender);
+                drop(attempt_sender);
+
+                let held_back_failure = forwarder.join().map_err(|_| {
+                    miette!("Job message forwarding thread panicked.")
+                })??;
+
+                job_result.map(|_| held_back_failure)
+            })?;
+
+            let Some(held_back_failure) = held_back_failure else {
+                return Ok(());
+            };
+
+            // If we got cancelled while waiting, the failure is final.
+            if !self.wait_before_retrying(cancellation_flag) {
+                message_sender
+                    .send(with_attempt_count(
+                        held_back_failure,
+                        attempt,
+                        max_attempts,
+                    ))
+                    .into_diagnostic()
+                    .wrap_err_with(|| {
+                        miette!("Could not send FileJobMessage::Finished.")
+                    })?;
+
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+}
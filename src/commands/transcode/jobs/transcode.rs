@@ -1,7 +1,11 @@
+use std::io::{BufRead, BufReader, Read};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{fs, thread};
 
 use crossbeam::channel::Sender;
@@ -10,14 +14,20 @@ use euphony_library::view::SharedAlbumView;
 use miette::{miette, Context, IntoDiagnostic, Result};
 
 use crate::commands::transcode::jobs::common::{
+    remove_existing_target_file,
     FileJob,
     FileJobMessage,
     FileJobResult,
+    FileJobSizes,
 };
 use crate::commands::transcode::state::changes::FileType;
 use crate::console::frontends::shared::queue::QueueItemID;
 use crate::globals::is_verbose_enabled;
 
+/// Prepended to the configured ffmpeg arguments: reports progress as `key=value` lines on stdout
+/// and disables the usual progress line on stderr.
+const FFMPEG_PROGRESS_ARGUMENTS: [&str; 3] = ["-progress", "pipe:1", "-nostats"];
+
 const FFMPEG_TASK_CANCELLATION_CHECK_INTERVAL: Duration =
     Duration::from_millis(50);
 const PARTIAL_TRANSCODED_FILE_DELETE_ATTEMPT_INTERVAL: Duration =
@@ -32,6 +42,9 @@ const PARTIAL_TRANSCODED_FILE_DELETE_ATTEMPT_INTERVAL: Duration =
 /// `TranscodeAudioFileJob` uses ffmpeg to transcode an audio file. The resulting file location
 /// is in the album directory of the aggregated library.
 pub struct TranscodeAudioFileJob {
+    /// Path to the source audio file.
+    source_file_path: PathBuf,
+
     /// Path to the target file's directory (for missing directory creation purposes).
     target_file_directory_path: PathBuf,
 
@@ -44,6 +57,10 @@ pub struct TranscodeAudioFileJob {
     /// List of arguments to ffmpeg that will transcode the audio as configured.
     ffmpeg_arguments: Vec<String>,
 
+    /// If set, ffmpeg is killed (and the file errored) if it runs for longer than this
+    /// (see `tools.ffmpeg.per_file_timeout_seconds`).
+    ffmpeg_timeout: Option<Duration>,
+
     /// `QueueItemID` this job belongs to.
     queue_item: QueueItemID,
 }
@@ -66,7 +83,9 @@ impl TranscodeAudioFileJob {
          */
         let transcoding_config =
             &album_locked.library_configuration().transcoding;
-        let ffmpeg_config = &config.tools.ffmpeg;
+        let ffmpeg_config = config.ffmpeg_configuration_for_library(
+            album_locked.library_configuration(),
+        );
 
         if !transcoding_config
             .is_path_audio_file_by_extension(&source_file_path)?
@@ -83,7 +102,7 @@ impl TranscodeAudioFileJob {
             .is_path_transcoding_output_by_extension(&target_file_path)?
         {
             let ffmpeg_output_extension =
-                &config.tools.ffmpeg.audio_transcoding_output_extension;
+                &ffmpeg_config.audio_transcoding_output_extension;
 
             return Err(miette!(
                 "Invalid ffmpeg output file extension \"{}\": expected \"{}\".",
@@ -103,30 +122,64 @@ impl TranscodeAudioFileJob {
             .to_str()
             .ok_or_else(|| miette!("Target file path is not valid UTF-8."))?;
 
-        let ffmpeg_arguments: Vec<String> = config
-            .tools
-            .ffmpeg
-            .audio_transcoding_args
+        // ffmpeg reports its progress on stdout (see `read_ffmpeg_progress`).
+        let mut ffmpeg_arguments: Vec<String> = FFMPEG_PROGRESS_ARGUMENTS
             .iter()
-            .map(|arg| {
-                arg.replace("{INPUT_FILE}", source_file_path_str)
-                    .replace("{OUTPUT_FILE}", target_file_path_str)
-            })
+            .map(|argument| argument.to_string())
             .collect();
+        ffmpeg_arguments.extend(ffmpeg_config.audio_transcoding_arguments(
+            source_file_path_str,
+            target_file_path_str,
+        ));
 
 
         // We have owned versions of data here because we want to be able to send this
         // job across threads easily.
         Ok(Self {
+            source_file_path: PathBuf::from(source_file_path_str),
             target_file_directory_path: target_file_directory.to_path_buf(),
             target_file_path: PathBuf::from(target_file_path_str),
-            ffmpeg_binary_path: config.tools.ffmpeg.binary.clone(),
+            ffmpeg_binary_path: ffmpeg_config.binary.clone(),
             ffmpeg_arguments,
+            ffmpeg_timeout: ffmpeg_config
+                .per_file_timeout_seconds
+                .map(Duration::from_secs),
             queue_item,
         })
     }
 }
 
+impl TranscodeAudioFileJob {
+    /// Delete the partially transcoded target file (if any) after ffmpeg was killed.
+    /// The file might still be locked for a short while, so this retries a few times.
+    fn remove_partial_target_file(&self) -> Result<()> {
+        if !self.target_file_path.is_file() {
+            return Ok(());
+        }
+
+        let mut retries: usize = 0;
+        while retries <= 4 {
+            match fs::remove_file(&self.target_file_path) {
+                Ok(_) => {
+                    break;
+                }
+                Err(error) => {
+                    if retries == 4 {
+                        return Err(error).into_diagnostic();
+                    }
+
+                    retries += 1;
+                    thread::sleep(
+                        PARTIAL_TRANSCODED_FILE_DELETE_ATTEMPT_INTERVAL,
+                    );
+                }
+            };
+        }
+
+        Ok(())
+    }
+}
+
 impl FileJob for TranscodeAudioFileJob {
     fn run(
         &mut self,
@@ -164,20 +217,91 @@ impl FileJob for TranscodeAudioFileJob {
             return Ok(());
         }
 
+        /*
+         * Step 1.5: remove the existing target file (it might be a hard link).
+         */
+        if let Err(error) = remove_existing_target_file(&self.target_file_path) {
+            let verbose_info = is_verbose_enabled()
+                .then(|| format!("fs::remove_file error: {error}"));
+
+            message_sender
+                .send(FileJobMessage::new_finished(
+                    self.queue_item,
+                    FileType::Audio,
+                    self.target_file_path.to_string_lossy(),
+                    FileJobResult::Errored {
+                        error: "Could not remove existing target file."
+                            .to_string(),
+                        verbose_info,
+                    },
+                ))
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!("Could not send FileJobMessage::Finished")
+                })?;
+
+            return Ok(());
+        }
+
         /*
          * Step 2: run ffmpeg (transcodes audio)
          */
-        let mut ffmpeg_child_process = Command::new(&self.ffmpeg_binary_path)
+        let mut ffmpeg_command = Command::new(&self.ffmpeg_binary_path);
+        ffmpeg_command
             .args(&self.ffmpeg_arguments)
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .into_diagnostic()
-            .wrap_err_with(|| {
+            .stderr(Stdio::piped());
+
+        // Run ffmpeg in its own process group so that a Ctrl+C in the terminal doesn't kill it directly.
+        // Instead, the signal cancels the transcode (see `signals::forward_termination_signals`),
+        // which kills ffmpeg below and removes the partial file.
+        #[cfg(unix)]
+        ffmpeg_command.process_group(0);
+
+        let mut ffmpeg_child_process =
+            ffmpeg_command.spawn().into_diagnostic().wrap_err_with(|| {
                 miette!("Could not spawn ffmpeg for transcoding.")
             })?;
 
-        // Keep checking for cancellation
+        // Both outputs are read continuously (ffmpeg would block on a full pipe otherwise):
+        // the total duration is parsed from stderr and the current position from stdout.
+        let total_duration_microseconds = Arc::new(AtomicU64::new(0));
+
+        let ffmpeg_stderr_reader = {
+            let ffmpeg_stderr = ffmpeg_child_process
+                .stderr
+                .take()
+                .ok_or_else(|| miette!("Could not capture ffmpeg stderr."))?;
+            let total_duration_microseconds =
+                total_duration_microseconds.clone();
+
+            thread::spawn(move || {
+                read_ffmpeg_stderr(ffmpeg_stderr, &total_duration_microseconds)
+            })
+        };
+
+        let ffmpeg_stdout_reader = {
+            let ffmpeg_stdout = ffmpeg_child_process
+                .stdout
+                .take()
+                .ok_or_else(|| miette!("Could not capture ffmpeg stdout."))?;
+            let message_sender = message_sender.clone();
+            let queue_item = self.queue_item;
+
+            thread::spawn(move || {
+                read_ffmpeg_progress(
+                    ffmpeg_stdout,
+                    &total_duration_microseconds,
+                    &message_sender,
+                    queue_item,
+                )
+            })
+        };
+
+        let ffmpeg_start_time = Instant::now();
+        let mut ffmpeg_timed_out = false;
+
+        // Keep checking for cancellation (and the timeout, if configured).
         while ffmpeg_child_process
             .try_wait()
             .into_diagnostic()
@@ -200,38 +324,39 @@ impl FileJob for TranscodeAudioFileJob {
                 break;
             }
 
+            if self
+                .ffmpeg_timeout
+                .is_some_and(|timeout| ffmpeg_start_time.elapsed() >= timeout)
+            {
+                ffmpeg_child_process
+                    .kill()
+                    .into_diagnostic()
+                    .wrap_err_with(|| {
+                        miette!("Could not kill timed out ffmpeg process.")
+                    })?;
+
+                ffmpeg_timed_out = true;
+                break;
+            }
+
             thread::sleep(FFMPEG_TASK_CANCELLATION_CHECK_INTERVAL);
         }
 
-        ffmpeg_child_process.wait().into_diagnostic()?;
+        let ffmpeg_exit_status =
+            ffmpeg_child_process.wait().into_diagnostic()?;
+
+        let ffmpeg_stdout = ffmpeg_stdout_reader
+            .join()
+            .map_err(|_| miette!("ffmpeg stdout reader thread panicked."))?;
+        let ffmpeg_stderr = ffmpeg_stderr_reader
+            .join()
+            .map_err(|_| miette!("ffmpeg stderr reader thread panicked."))?;
 
         // ffmpeg process is finished at this point, we should just check what the reason was.
         let final_cancellation_flag = cancellation_flag.load(Ordering::SeqCst);
         if final_cancellation_flag {
             // Process was killed because of cancellation.
-
-            // Delete the partial file.
-            if self.target_file_path.exists() && self.target_file_path.is_file()
-            {
-                let mut retries: usize = 0;
-                while retries <= 4 {
-                    match fs::remove_file(&self.target_file_path) {
-                        Ok(_) => {
-                            break;
-                        }
-                        Err(error) => {
-                            if retries == 4 {
-                                return Err(error).into_diagnostic();
-                            }
-
-                            retries += 1;
-                            thread::sleep(
-                                PARTIAL_TRANSCODED_FILE_DELETE_ATTEMPT_INTERVAL,
-                            );
-                        }
-                    };
-                }
-            }
+            self.remove_partial_target_file()?;
 
             message_sender
                 .send(FileJobMessage::new_cancelled(
@@ -245,15 +370,46 @@ impl FileJob for TranscodeAudioFileJob {
                 })?;
 
             Ok(())
-        } else {
-            // Everything was normal.
-            let ffmpeg_output = ffmpeg_child_process
-                .wait_with_output()
+        } else if ffmpeg_timed_out {
+            // Process was killed because it ran for too long.
+            self.remove_partial_target_file()?;
+
+            let timeout_seconds = self
+                .ffmpeg_timeout
+                .map(|timeout| timeout.as_secs())
+                .unwrap_or_default();
+
+            let verbose_info: Option<String> = is_verbose_enabled().then(|| {
+                format!(
+                    "ffmpeg timed out. Binary={:?} Arguments={:?}\nStderr: {}",
+                    &self.ffmpeg_binary_path,
+                    &self.ffmpeg_arguments,
+                    ffmpeg_stderr
+                )
+            });
+
+            message_sender
+                .send(FileJobMessage::new_finished(
+                    self.queue_item,
+                    FileType::Audio,
+                    self.target_file_path.to_string_lossy(),
+                    FileJobResult::Errored {
+                        error: format!(
+                            "ffmpeg did not finish within {timeout_seconds} seconds \
+                            (see tools.ffmpeg.per_file_timeout_seconds) and was killed."
+                        ),
+                        verbose_info,
+                    },
+                ))
                 .into_diagnostic()
-                .wrap_err_with(|| miette!("Could not get ffmpeg output."))?;
+                .wrap_err_with(|| {
+                    miette!("Could not send FileJobMessage::Finished.")
+                })?;
 
-            let ffmpeg_exit_code = ffmpeg_output
-                .status
+            Ok(())
+        } else {
+            // Everything was normal.
+            let ffmpeg_exit_code = ffmpeg_exit_status
                 .code()
                 .ok_or_else(|| miette!("No ffmpeg exit code?!"))?;
 
@@ -267,20 +423,14 @@ impl FileJob for TranscodeAudioFileJob {
                         )
                     });
 
-                FileJobResult::Okay { verbose_info }
+                FileJobResult::Okay {
+                    verbose_info,
+                    sizes: FileJobSizes::from_file_paths(
+                        &self.source_file_path,
+                        &self.target_file_path,
+                    ),
+                }
             } else {
-                let ffmpeg_stdout = String::from_utf8(ffmpeg_output.stdout)
-                    .into_diagnostic()
-                    .wrap_err_with(|| {
-                        miette!("Could not parse ffmpeg stdout.")
-                    })?;
-
-                let ffmpeg_stderr = String::from_utf8(ffmpeg_output.stderr)
-                    .into_diagnostic()
-                    .wrap_err_with(|| {
-                        miette!("could not parse ffmpeg stderr.")
-                    })?;
-
                 let error = format!(
                     "ffmpeg exited with non-zero exit code.\nStdout: {}\nStderr: {}",
                     ffmpeg_stdout, ffmpeg_stderr
@@ -317,3 +467,207 @@ impl FileJob for TranscodeAudioFileJob {
         }
     }
 }
+
+/// Reads ffmpeg's stderr until it closes and returns its contents.
+///
+/// Along the way, the total duration of the input file (the first `Duration: HH:MM:SS.ss` line)
+/// is stored into `total_duration_microseconds`.
+fn read_ffmpeg_stderr<R: Read>(
+    ffmpeg_stderr: R,
+    total_duration_microseconds: &AtomicU64,
+) -> String {
+    let mut stderr_contents = String::new();
+
+    for line in BufReader::new(ffmpeg_stderr).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if total_duration_microseconds.load(Ordering::SeqCst) == 0 {
+            if let Some(duration) = parse_ffmpeg_duration_line(&line) {
+                total_duration_microseconds.store(duration, Ordering::SeqCst);
+            }
+        }
+
+        stderr_contents.push_str(&line);
+        stderr_contents.push('\n');
+    }
+
+    stderr_contents
+}
+
+/// Parses the total duration (in microseconds) from an ffmpeg input information line,
+/// e.g. `  Duration: 00:03:21.45, start: 0.000000, bitrate: 912 kb/s`.
+fn parse_ffmpeg_duration_line(line: &str) -> Option<u64> {
+    let duration = line
+        .trim_start()
+        .strip_prefix("Duration: ")?
+        .split(',')
+        .next()?;
+
+    let mut components = duration.split(':');
+    let hours: u64 = components.next()?.parse().ok()?;
+    let minutes: u64 = components.next()?.parse().ok()?;
+    let seconds: f64 = components.next()?.parse().ok()?;
+
+    Some(
+        (hours * 3600 + minutes * 60) * 1_000_000
+            + (seconds * 1_000_000.0) as u64,
+    )
+}
+
+/// Reads ffmpeg's `-progress` output (`key=value` lines on stdout) until it closes and sends
+/// a `FileJobMessage::Progress` each time the transcoded percentage changes.
+/// Percentages can only be computed once the total duration is known (see `read_ffmpeg_stderr`).
+///
+/// Returns any stdout lines that weren't part of the progress output.
+fn read_ffmpeg_progress<R: Read>(
+    ffmpeg_stdout: R,
+    total_duration_microseconds: &AtomicU64,
+    message_sender: &Sender<FileJobMessage>,
+    queue_item: QueueItemID,
+) -> String {
+    let mut other_stdout_contents = String::new();
+    let mut last_sent_percent: Option<u8> = None;
+
+    for line in BufReader::new(ffmpeg_stdout).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        let Some((key, value)) = line.split_once('=') else {
+            other_stdout_contents.push_str(&line);
+            other_stdout_contents.push('\n');
+            continue;
+        };
+
+        // Despite its name, `out_time_ms` is also in microseconds (newer versions add `out_time_us`).
+        if key != "out_time_us" && key != "out_time_ms" {
+            continue;
+        }
+
+        let total_duration = total_duration_microseconds.load(Ordering::SeqCst);
+        let Ok(current_time) = value.trim().parse::<u64>() else {
+            continue;
+        };
+
+        if total_duration == 0 {
+            continue;
+        }
+
+        let percent =
+            (current_time.saturating_mul(100) / total_duration).min(100) as u8;
+
+        if last_sent_percent != Some(percent) {
+            last_sent_percent = Some(percent);
+
+            // The main thread might have stopped listening already (e.g. on cancellation),
+            // in which case there is no one left to report the progress to.
+            let _ = message_sender
+                .send(FileJobMessage::new_progress(queue_item, percent));
+        }
+    }
+
+    other_stdout_contents
+}
+
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
+
+    use crossbeam::channel;
+
+    use super::TranscodeAudioFileJob;
+    use crate::commands::transcode::jobs::common::{
+        FileJob,
+        FileJobMessage,
+        FileJobResult,
+    };
+    use crate::console::frontends::shared::queue::QueueItemID;
+    use crate::globals::VERBOSE;
+    use crate::test_utilities::TestDirectory;
+
+    /// Create a job that runs `shell_script` with `sh` instead of ffmpeg
+    /// (the target file is `target.mp3` in `test_directory`).
+    fn job_with_fake_ffmpeg(
+        test_directory: &Path,
+        shell_script: &str,
+        ffmpeg_timeout: Option<Duration>,
+    ) -> TranscodeAudioFileJob {
+        TranscodeAudioFileJob {
+            source_file_path: test_directory.join("source.flac"),
+            target_file_directory_path: test_directory.to_path_buf(),
+            target_file_path: test_directory.join("target.mp3"),
+            ffmpeg_binary_path: "sh".to_string(),
+            ffmpeg_arguments: vec!["-c".to_string(), shell_script.to_string()],
+            ffmpeg_timeout,
+            queue_item: QueueItemID::new_random(),
+        }
+    }
+
+    /// Run the job and return the result it finished with.
+    fn run_job(job: &mut TranscodeAudioFileJob) -> FileJobResult {
+        // Normally set on startup (fails harmlessly if another test already set it).
+        let _ = VERBOSE.set(false);
+
+        let (message_sender, message_receiver) = channel::unbounded();
+
+        job.run(&AtomicBool::new(false), &message_sender).unwrap();
+
+        message_receiver
+            .try_iter()
+            .find_map(|message| match message {
+                FileJobMessage::Finished {
+                    processing_result, ..
+                } => Some(processing_result),
+                _ => None,
+            })
+            .expect("job did not send FileJobMessage::Finished")
+    }
+
+    #[test]
+    fn long_running_ffmpeg_is_killed_after_timeout() {
+        let test_directory = TestDirectory::new("timeout");
+        // Writes a partial output file and then hangs (`exec`, so that killing
+        // the process doesn't leave a child behind that keeps the output pipes open).
+        let mut job = job_with_fake_ffmpeg(
+            &test_directory,
+            &format!(
+                "echo partial > '{}'; exec sleep 30",
+                test_directory.join("target.mp3").display()
+            ),
+            Some(Duration::from_millis(300)),
+        );
+
+        let start_time = Instant::now();
+        let result = run_job(&mut job);
+
+        assert!(start_time.elapsed() < Duration::from_secs(10));
+        match result {
+            FileJobResult::Errored { error, .. } => {
+                assert!(error.contains("did not finish within"), "{error}");
+            }
+            FileJobResult::Okay { .. } => panic!("timed out job did not error"),
+        }
+        assert!(!job.target_file_path.exists());
+    }
+
+    #[test]
+    fn ffmpeg_finishing_within_timeout_is_not_killed() {
+        let test_directory = TestDirectory::new("no-timeout");
+        let mut job = job_with_fake_ffmpeg(
+            &test_directory,
+            "exit 0",
+            Some(Duration::from_secs(30)),
+        );
+        fs::write(&job.source_file_path, "flac").unwrap();
+
+        let result = run_job(&mut job);
+
+        assert!(matches!(result, FileJobResult::Okay { .. }));
+    }
+}
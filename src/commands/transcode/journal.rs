@@ -0,0 +1,683 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crossterm::style::Stylize;
+use euphony_library::state::common::FileTrackedMetadata;
+use euphony_library::state::progress::AlbumTranscodeProgressWriter;
+use euphony_library::view::AlbumSourceFileList;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::console::frontends::TranscodeTerminal;
+use crate::console::LogBackend;
+
+
+/// Name of the journal file, placed in the root of the aggregated library,
+/// that tracks the albums currently being processed (as JSON lines, see `JournalEvent`).
+pub const IN_PROGRESS_JOURNAL_FILE_NAME: &str = ".euphony-inprogress.jsonl";
+
+
+/// A single album tracked in the in-progress journal.
+#[derive(Default)]
+struct InProgressAlbum {
+    /// Source directory of the album being processed.
+    album_source_directory: PathBuf,
+
+    /// Transcoded directory of the album being processed.
+    album_transcoded_directory: PathBuf,
+
+    /// Absolute target paths of all file jobs of this album that have started.
+    started_target_file_paths: BTreeSet<String>,
+
+    /// Absolute target paths of all file jobs of this album that have completed successfully.
+    completed_target_file_paths: BTreeSet<String>,
+}
+
+/// A single line of the in-progress journal (see `IN_PROGRESS_JOURNAL_FILE_NAME`).
+///
+/// Events are appended to the journal as they happen, so recording a file doesn't require
+/// rewriting the entire journal (see `TranscodeJournal`).
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum JournalEvent {
+    AlbumStarted {
+        album_source_directory: PathBuf,
+        album_transcoded_directory: PathBuf,
+    },
+    FileStarted {
+        album_transcoded_directory: PathBuf,
+        target_file_path: String,
+    },
+    FileCompleted {
+        album_transcoded_directory: PathBuf,
+        target_file_path: String,
+    },
+    AlbumFinished {
+        album_transcoded_directory: PathBuf,
+    },
+}
+
+/// The albums tracked in the in-progress journal (see `IN_PROGRESS_JOURNAL_FILE_NAME`),
+/// as reconstructed from its events.
+///
+/// If euphony is interrupted while processing albums, the journal is left behind. On the next run,
+/// every target file that was started, but never completed, is removed before scanning for changes,
+/// so no partially-written files remain in the transcoded library.
+#[derive(Default)]
+struct InProgressJournal {
+    /// Albums currently being processed (several at once with `transcode_album_concurrency`),
+    /// keyed by their transcoded album directory.
+    albums: BTreeMap<PathBuf, InProgressAlbum>,
+}
+
+impl InProgressJournal {
+    fn apply(&mut self, event: JournalEvent) -> Result<()> {
+        match event {
+            JournalEvent::AlbumStarted {
+                album_source_directory,
+                album_transcoded_directory,
+            } => {
+                self.albums.insert(
+                    album_transcoded_directory.clone(),
+                    InProgressAlbum {
+                        album_source_directory,
+                        album_transcoded_directory,
+                        ..Default::default()
+                    },
+                );
+            }
+            JournalEvent::FileStarted {
+                album_transcoded_directory,
+                target_file_path,
+            } => {
+                self.album_mut(&album_transcoded_directory)?
+                    .started_target_file_paths
+                    .insert(target_file_path);
+            }
+            JournalEvent::FileCompleted {
+                album_transcoded_directory,
+                target_file_path,
+            } => {
+                self.album_mut(&album_transcoded_directory)?
+                    .completed_target_file_paths
+                    .insert(target_file_path);
+            }
+            JournalEvent::AlbumFinished {
+                album_transcoded_directory,
+            } => {
+                self.albums.remove(&album_transcoded_directory);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn album_mut(
+        &mut self,
+        album_transcoded_directory: &Path,
+    ) -> Result<&mut InProgressAlbum> {
+        self.albums
+            .get_mut(album_transcoded_directory)
+            .ok_or_else(|| {
+                miette!(
+                    "Album {:?} is not tracked in the in-progress journal.",
+                    album_transcoded_directory
+                )
+            })
+    }
+
+    /// The events that reconstruct the current state of the journal (see `TranscodeJournal::compact`).
+    fn to_events(&self) -> Vec<JournalEvent> {
+        let mut events: Vec<JournalEvent> = Vec::new();
+
+        for album in self.albums.values() {
+            events.push(JournalEvent::AlbumStarted {
+                album_source_directory: album.album_source_directory.clone(),
+                album_transcoded_directory: album
+                    .album_transcoded_directory
+                    .clone(),
+            });
+
+            events.extend(album.started_target_file_paths.iter().map(
+                |target_file_path| JournalEvent::FileStarted {
+                    album_transcoded_directory:
+                        album.album_transcoded_directory.clone(),
+                    target_file_path: target_file_path.clone(),
+                },
+            ));
+
+            events.extend(album.completed_target_file_paths.iter().map(
+                |target_file_path| JournalEvent::FileCompleted {
+                    album_transcoded_directory:
+                        album.album_transcoded_directory.clone(),
+                    target_file_path: target_file_path.clone(),
+                },
+            ));
+        }
+
+        events
+    }
+}
+
+/// Serialize `events` as JSON lines (see `JournalEvent`).
+fn serialize_journal_events(events: &[JournalEvent]) -> Result<String> {
+    let mut serialized_events = String::new();
+
+    for event in events {
+        serialized_events.push_str(
+            &serde_json::to_string(event)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!("Could not serialize in-progress journal event.")
+                })?,
+        );
+        serialized_events.push('\n');
+    }
+
+    Ok(serialized_events)
+}
+
+/// Parse the contents of an in-progress journal (see `JournalEvent`).
+///
+/// A malformed last line is ignored, since a crash may have interrupted writing it.
+fn parse_journal(journal_contents: &str) -> Result<InProgressJournal> {
+    let mut journal = InProgressJournal::default();
+
+    let lines = journal_contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+
+    for (line_index, line) in lines.iter().enumerate() {
+        let event = match serde_json::from_str::<JournalEvent>(line) {
+            Ok(event) => event,
+            Err(_) if line_index + 1 == lines.len() => break,
+            Err(error) => {
+                return Err(error).into_diagnostic().wrap_err_with(|| {
+                    miette!("Line {} is invalid.", line_index + 1)
+                });
+            }
+        };
+
+        journal
+            .apply(event)
+            .wrap_err_with(|| miette!("Line {} is invalid.", line_index + 1))?;
+    }
+
+    Ok(journal)
+}
+
+struct TranscodeJournalState {
+    journal: InProgressJournal,
+
+    /// The journal file, opened for appending (see `TranscodeJournal::append`).
+    journal_file: Option<File>,
+}
+
+/// The in-progress journal of a single transcode run, shared by all albums being processed.
+///
+/// Events are appended to the journal file as they happen. Once an album finishes, the journal
+/// is compacted (rewritten with only the albums still in progress), so its size stays
+/// proportional to the albums in progress, and removed once no albums are left.
+pub struct TranscodeJournal {
+    journal_file_path: PathBuf,
+
+    state: Mutex<TranscodeJournalState>,
+}
+
+impl TranscodeJournal {
+    /// Create an empty journal for the given aggregated library.
+    /// Nothing is written to disk until an album starts processing.
+    pub fn new<P: AsRef<Path>>(aggregated_library_path: P) -> Self {
+        Self {
+            journal_file_path: aggregated_library_path
+                .as_ref()
+                .join(IN_PROGRESS_JOURNAL_FILE_NAME),
+            state: Mutex::new(TranscodeJournalState {
+                journal: InProgressJournal::default(),
+                journal_file: None,
+            }),
+        }
+    }
+
+    /// Apply `event` to the journal and append it to the journal file.
+    fn append(&self, event: JournalEvent) -> Result<()> {
+        let serialized_event =
+            serialize_journal_events(std::slice::from_ref(&event))?;
+
+        let mut state = self.state.lock();
+        state.journal.apply(event).wrap_err_with(|| {
+            miette!("BUG: In-progress journal event is out of order.")
+        })?;
+
+        if state.journal_file.is_none() {
+            if let Some(journal_directory) = self.journal_file_path.parent() {
+                fs::create_dir_all(journal_directory)
+                    .into_diagnostic()
+                    .wrap_err_with(|| {
+                        miette!(
+                            "Failed to create aggregated library directory at {:?}.",
+                            journal_directory
+                        )
+                    })?;
+            }
+
+            state.journal_file = Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.journal_file_path)
+                    .into_diagnostic()
+                    .wrap_err_with(|| {
+                        miette!(
+                            "Could not open in-progress journal at {:?}.",
+                            self.journal_file_path
+                        )
+                    })?,
+            );
+        }
+
+        state
+            .journal_file
+            .as_mut()
+            .expect("journal file was opened above")
+            .write_all(serialized_event.as_bytes())
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not write to in-progress journal at {:?}.",
+                    self.journal_file_path
+                )
+            })
+    }
+
+    /// Remove the given albums (keyed by their transcoded album directories) from the journal
+    /// and compact it. Used for albums whose states were saved after they finished processing
+    /// (see `AlbumJournal::finish`).
+    pub fn finish_albums(
+        &self,
+        album_transcoded_directories: &[PathBuf],
+    ) -> Result<()> {
+        let mut state = self.state.lock();
+
+        for album_transcoded_directory in album_transcoded_directories {
+            state.journal.apply(JournalEvent::AlbumFinished {
+                album_transcoded_directory: album_transcoded_directory.clone(),
+            })?;
+        }
+
+        self.compact(&mut state)
+    }
+
+    /// Rewrite the journal file with only the albums still in progress, or remove it
+    /// if no albums are in progress.
+    ///
+    /// The journal is first written into a temporary file and then renamed into place,
+    /// so an interruption can't leave behind a half-written journal.
+    fn compact(&self, state: &mut TranscodeJournalState) -> Result<()> {
+        // Appending continues on the new file, which is opened again on the next event.
+        state.journal_file = None;
+
+        if state.journal.albums.is_empty() {
+            if !self.journal_file_path.is_file() {
+                return Ok(());
+            }
+
+            return fs::remove_file(&self.journal_file_path)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!(
+                        "Failed to remove in-progress journal at {:?}.",
+                        self.journal_file_path
+                    )
+                });
+        }
+
+        let serialized_journal =
+            serialize_journal_events(&state.journal.to_events())?;
+
+        let temporary_file_path = self.journal_file_path.with_extension("tmp");
+
+        fs::write(&temporary_file_path, serialized_journal)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not write in-progress journal to {:?}.",
+                    temporary_file_path
+                )
+            })?;
+
+        fs::rename(&temporary_file_path, &self.journal_file_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not move in-progress journal into place at {:?}.",
+                    self.journal_file_path
+                )
+            })
+    }
+}
+
+/// Keeps a single album's entry in the in-progress journal up to date while it is being processed.
+pub struct AlbumJournal<'journal> {
+    transcode_journal: &'journal TranscodeJournal,
+
+    album_transcoded_directory: PathBuf,
+}
+
+impl<'journal> AlbumJournal<'journal> {
+    /// Add the given album to the journal.
+    pub fn start(
+        transcode_journal: &'journal TranscodeJournal,
+        album_source_directory: PathBuf,
+        album_transcoded_directory: PathBuf,
+    ) -> Result<Self> {
+        transcode_journal.append(JournalEvent::AlbumStarted {
+            album_source_directory,
+            album_transcoded_directory: album_transcoded_directory.clone(),
+        })?;
+
+        Ok(Self {
+            transcode_journal,
+            album_transcoded_directory,
+        })
+    }
+
+    /// Record that the job writing `target_file_path` has started.
+    pub fn mark_started(&mut self, target_file_path: &str) -> Result<()> {
+        self.transcode_journal.append(JournalEvent::FileStarted {
+            album_transcoded_directory: self.album_transcoded_directory.clone(),
+            target_file_path: target_file_path.to_string(),
+        })
+    }
+
+    /// Record that the job writing `target_file_path` has completed successfully.
+    pub fn mark_completed(&mut self, target_file_path: &str) -> Result<()> {
+        self.transcode_journal.append(JournalEvent::FileCompleted {
+            album_transcoded_directory: self.album_transcoded_directory.clone(),
+            target_file_path: target_file_path.to_string(),
+        })
+    }
+
+    /// Remove the album from the journal once it (including its state files) has been fully processed.
+    /// The journal file itself is removed once no albums are left in it.
+    ///
+    /// If the album's state files are saved later (see `state_write_concurrency`), this must not
+    /// be called - the album is removed with `TranscodeJournal::finish_albums` once they are saved instead.
+    pub fn finish(self) -> Result<()> {
+        self.transcode_journal
+            .finish_albums(&[self.album_transcoded_directory])
+    }
+}
+
+/// Appends the files of a single album to its transcode progress file as they complete
+/// (see `AlbumTranscodeProgress`), so a run that is interrupted (e.g. by a reboot) in the middle
+/// of a large album doesn't have to process the album's completed files again on the next run.
+///
+/// Unlike the in-progress journal, the progress file survives the cleanup of the next run.
+pub struct AlbumProgressRecorder {
+    progress_writer: AlbumTranscodeProgressWriter,
+
+    source_album_directory: PathBuf,
+
+    /// Maps from absolute target file paths (as reported by file jobs) to absolute source file paths.
+    target_to_source_file_paths: HashMap<String, PathBuf>,
+}
+
+impl AlbumProgressRecorder {
+    /// Open the album's progress file (keeping any records of an interrupted run).
+    pub fn start(tracked_source_files: &AlbumSourceFileList) -> Result<Self> {
+        let (source_album_directory, transcoded_album_directory) = {
+            let album = tracked_source_files.album.read();
+
+            (
+                album.album_directory_in_source_library(),
+                album.album_directory_in_transcoded_library(),
+            )
+        };
+
+        let target_to_source_file_paths = tracked_source_files
+            .map_source_file_paths_to_transcoded_file_paths_absolute()
+            .into_flattened_map()
+            .into_iter()
+            .map(|(source_file_path, target_file_path)| {
+                (
+                    target_file_path.to_string_lossy().to_string(),
+                    source_file_path,
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            progress_writer: AlbumTranscodeProgressWriter::open_in_directory(
+                transcoded_album_directory,
+            )?,
+            source_album_directory,
+            target_to_source_file_paths,
+        })
+    }
+
+    /// Record that the job writing `target_file_path` has completed successfully.
+    /// Targets without a source file (i.e. deletions) aren't recorded.
+    pub fn mark_completed(&mut self, target_file_path: &str) -> Result<()> {
+        let Some(source_file_path) =
+            self.target_to_source_file_paths.get(target_file_path)
+        else {
+            return Ok(());
+        };
+
+        let relative_source_file_path = source_file_path
+            .strip_prefix(&self.source_album_directory)
+            .into_diagnostic()?
+            .to_string_lossy()
+            .to_string();
+
+        self.progress_writer.record_completed(
+            relative_source_file_path,
+            FileTrackedMetadata::from_file_path(source_file_path)?,
+        )
+    }
+}
+
+/// If the previous transcode was interrupted (i.e. an in-progress journal exists in the aggregated
+/// library root), remove all partially-written target files of the interrupted albums and the journal.
+/// The albums themselves are then picked up again by the usual change detection.
+///
+/// If `dry_run` is `true`, the interrupted albums are only logged.
+pub fn recover_interrupted_album<P: AsRef<Path>>(
+    aggregated_library_path: P,
+    dry_run: bool,
+    terminal: &TranscodeTerminal,
+) -> Result<()> {
+    let journal_file_path = aggregated_library_path
+        .as_ref()
+        .join(IN_PROGRESS_JOURNAL_FILE_NAME);
+
+    if !journal_file_path.is_file() {
+        return Ok(());
+    }
+
+    let journal_contents = fs::read_to_string(&journal_file_path)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!(
+                "Failed to read in-progress journal at {:?}.",
+                journal_file_path
+            )
+        })?;
+
+    let journal = parse_journal(&journal_contents).wrap_err_with(|| {
+            miette!(
+                "Failed to parse in-progress journal at {:?} \
+                (remove it manually if the transcoded library is otherwise fine).",
+                journal_file_path
+            )
+        })?;
+
+    let mut total_partial_files: usize = 0;
+
+    for album in journal.albums.values() {
+        terminal.log_eprintln(format!(
+            "{} the previous transcode was interrupted while processing {:?}.",
+            "WARNING:".yellow(),
+            album.album_source_directory
+        ));
+
+        let partial_target_file_paths = album
+            .started_target_file_paths
+            .difference(&album.completed_target_file_paths)
+            .map(PathBuf::from)
+            .filter(|target_file_path| target_file_path.is_file())
+            .collect::<Vec<PathBuf>>();
+
+        if dry_run {
+            terminal.log_println(format!(
+                "  Would remove {} partially-written files in {:?}.",
+                partial_target_file_paths.len(),
+                album.album_transcoded_directory
+            ));
+            continue;
+        }
+
+        for partial_target_file_path in &partial_target_file_paths {
+            fs::remove_file(partial_target_file_path)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!(
+                        "Failed to remove partially-written file at {:?}.",
+                        partial_target_file_path
+                    )
+                })?;
+
+            terminal.log_println(format!(
+                "  Removed partially-written file {:?}.",
+                partial_target_file_path
+            ));
+        }
+
+        total_partial_files += partial_target_file_paths.len();
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+
+    fs::remove_file(&journal_file_path)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!(
+                "Failed to remove in-progress journal at {:?}.",
+                journal_file_path
+            )
+        })?;
+
+    terminal.log_println(format!(
+        "  Cleaned up {} partially-written files, the interrupted albums will be processed again \
+        (skipping their already completed files).",
+        total_partial_files
+    ));
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{
+        parse_journal,
+        AlbumJournal,
+        TranscodeJournal,
+        IN_PROGRESS_JOURNAL_FILE_NAME,
+    };
+    use crate::test_utilities::TestDirectory;
+
+    fn start_album<'journal>(
+        transcode_journal: &'journal TranscodeJournal,
+        name: &str,
+    ) -> AlbumJournal<'journal> {
+        AlbumJournal::start(
+            transcode_journal,
+            PathBuf::from("source").join(name),
+            PathBuf::from("transcoded").join(name),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn journal_is_appended_to_and_compacted_when_albums_finish() {
+        let test_directory = TestDirectory::new("journal");
+        let journal_file_path =
+            test_directory.join(IN_PROGRESS_JOURNAL_FILE_NAME);
+        let transcode_journal = TranscodeJournal::new(&test_directory);
+
+        let mut first_album = start_album(&transcode_journal, "first");
+        let mut second_album = start_album(&transcode_journal, "second");
+
+        first_album.mark_started("first/01.mp3").unwrap();
+        first_album.mark_completed("first/01.mp3").unwrap();
+        second_album.mark_started("second/01.mp3").unwrap();
+        second_album.mark_started("second/02.mp3").unwrap();
+        second_album.mark_completed("second/01.mp3").unwrap();
+
+        let journal_contents = fs::read_to_string(&journal_file_path).unwrap();
+        assert_eq!(journal_contents.lines().count(), 7);
+
+        first_album.finish().unwrap();
+
+        // Only the events of the second album remain.
+        let journal_contents = fs::read_to_string(&journal_file_path).unwrap();
+        assert_eq!(journal_contents.lines().count(), 4);
+
+        let journal = parse_journal(&journal_contents).unwrap();
+        assert_eq!(journal.albums.len(), 1);
+
+        let second_album_entry =
+            &journal.albums[&PathBuf::from("transcoded/second")];
+        assert_eq!(
+            second_album_entry
+                .started_target_file_paths
+                .difference(&second_album_entry.completed_target_file_paths)
+                .collect::<Vec<_>>(),
+            vec!["second/02.mp3"]
+        );
+
+        // Appending continues after compaction.
+        second_album.mark_completed("second/02.mp3").unwrap();
+        let journal_contents = fs::read_to_string(&journal_file_path).unwrap();
+        assert_eq!(journal_contents.lines().count(), 5);
+
+        second_album.finish().unwrap();
+        assert!(!journal_file_path.exists());
+    }
+
+    #[test]
+    fn interrupted_last_line_is_ignored() {
+        let journal_contents = "\
+            {\"event\":\"album_started\",\"album_source_directory\":\"source\",\"album_transcoded_directory\":\"transcoded\"}\n\
+            {\"event\":\"file_started\",\"album_transcoded_directory\":\"transcoded\",\"target_file_path\":\"01.mp3\"}\n\
+            {\"event\":\"file_completed\",\"album_transcoded_dir";
+
+        let journal = parse_journal(journal_contents).unwrap();
+        let album = &journal.albums[&PathBuf::from("transcoded")];
+
+        assert_eq!(album.started_target_file_paths.len(), 1);
+        assert!(album.completed_target_file_paths.is_empty());
+    }
+
+    #[test]
+    fn malformed_line_in_the_middle_is_rejected() {
+        let journal_contents = "\
+            {\"event\":\"album_started\",\"album_source_directory\":\"source\",\"album_transcoded_directory\":\"transcoded\"}\n\
+            not json\n\
+            {\"event\":\"album_finished\",\"album_transcoded_directory\":\"transcoded\"}\n";
+
+        assert!(parse_journal(journal_contents).is_err());
+    }
+}
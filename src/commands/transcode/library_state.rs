@@ -5,6 +5,12 @@ use std::io::Write;
 use std::path::Path;
 use std::{fs, io};
 
+use euphony_library::state::migration::{
+    migrate_state_json,
+    read_schema_version,
+    StateMigrationStep,
+};
+use euphony_library::view::{AlbumView, SharedAlbumView, SharedArtistView};
 use miette::{miette, Context, Diagnostic, IntoDiagnostic, Result};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -12,13 +18,17 @@ use thiserror::Error;
 pub const LIBRARY_STATE_FILE_NAME: &str = ".library.state.euphony";
 const LIBRARY_STATE_SCHEMA_VERSION: u32 = 2;
 
+/// Migration steps for older library states (see `LibraryState::migrate`).
+/// Whenever `LIBRARY_STATE_SCHEMA_VERSION` is bumped, add a step from the previous version here.
+const LIBRARY_STATE_MIGRATION_STEPS: &[StateMigrationStep] = &[];
+
 #[derive(Error, Debug, Diagnostic)]
 pub enum LibraryStateLoadError {
     #[error("no state found on disk")]
     NotFound,
 
     #[error(
-        "schema version mismatch: {0} (current is {})",
+        "schema version mismatch: {0} (current is {}, no migration is available)",
         LIBRARY_STATE_SCHEMA_VERSION
     )]
     SchemaVersionMismatch(u32),
@@ -37,6 +47,12 @@ pub struct TrackedAlbum {
 
     /// Relative path from the library root to the album.
     pub album_source_relative_path: String,
+
+    /// The album's year (see `AlbumView::album_year`) at the time of transcoding. Needed to find
+    /// its transcoded directory with `aggregated_library.album_directory_template` once the source
+    /// album (and its override file) is gone.
+    #[serde(default)]
+    pub album_year: Option<u16>,
 }
 
 impl Hash for TrackedAlbum {
@@ -45,6 +61,20 @@ impl Hash for TrackedAlbum {
     }
 }
 
+impl TrackedAlbum {
+    /// Create a view of this (possibly no longer existing) album with the same year
+    /// it was transcoded with, so it maps to the same transcoded directory.
+    pub fn album_view<'config>(
+        &self,
+        artist: SharedArtistView<'config>,
+    ) -> Result<SharedAlbumView<'config>> {
+        let album_view = AlbumView::new(artist, self.album_title.clone(), true)?;
+        album_view.write().configuration.year = self.album_year;
+
+        Ok(album_view)
+    }
+}
+
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TrackedArtistAlbums {
@@ -69,6 +99,25 @@ impl LibraryState {
         }
     }
 
+    /// Add all artists and albums from `saved_state` that are missing from this state,
+    /// so that their removal is still detected by a later transcode.
+    pub fn retain_removed_entries_from(&mut self, saved_state: &LibraryState) {
+        for (artist_name, saved_artist_albums) in &saved_state.tracked_artists {
+            let artist_albums = self
+                .tracked_artists
+                .entry(artist_name.clone())
+                .or_insert_with(|| TrackedArtistAlbums {
+                    tracked_albums: Vec::new(),
+                });
+
+            for saved_album in &saved_artist_albums.tracked_albums {
+                if !artist_albums.tracked_albums.contains(saved_album) {
+                    artist_albums.tracked_albums.push(saved_album.clone());
+                }
+            }
+        }
+    }
+
     pub fn load_from_file<P: AsRef<Path>>(
         file_path: P,
     ) -> Result<Self, LibraryStateLoadError> {
@@ -79,15 +128,34 @@ impl LibraryState {
         }
 
         let file_contents = fs::read_to_string(file_path)?;
-        let state: Self = serde_json::from_str(&file_contents)?;
-
-        if state.schema_version != LIBRARY_STATE_SCHEMA_VERSION {
-            return Err(LibraryStateLoadError::SchemaVersionMismatch(
-                state.schema_version,
-            ));
+        let mut raw_state: serde_json::Value =
+            serde_json::from_str(&file_contents)?;
+
+        if let Some(schema_version) = read_schema_version(&raw_state)
+            .filter(|version| *version != LIBRARY_STATE_SCHEMA_VERSION)
+        {
+            raw_state = Self::migrate(schema_version, raw_state).ok_or(
+                LibraryStateLoadError::SchemaVersionMismatch(schema_version),
+            )?;
         }
 
-        Ok(state)
+        Ok(serde_json::from_value(raw_state)?)
+    }
+
+    /// Upgrade the (not yet deserialized) JSON of a library state with an older schema version
+    /// to the current one (see `LIBRARY_STATE_SCHEMA_VERSION`), so that the list of tracked
+    /// artists and albums isn't lost on upgrades. Returns `None` if there is no migration path
+    /// from `from_version`.
+    pub fn migrate(
+        from_version: u32,
+        state: serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        migrate_state_json(
+            state,
+            from_version,
+            LIBRARY_STATE_SCHEMA_VERSION,
+            LIBRARY_STATE_MIGRATION_STEPS,
+        )
     }
 
     pub fn load_from_directory<P: AsRef<Path>>(
@@ -0,0 +1,162 @@
+use crossterm::style::Stylize;
+use euphony_configuration::Configuration;
+use euphony_library::state::errored::{
+    AlbumErrorRecord,
+    AlbumErrorRecordLoadError,
+    ErroredFileRecord,
+};
+use euphony_library::view::library::LibraryViewError;
+use euphony_library::view::LibraryView;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Serialize;
+
+use crate::commands::log_optional_library_unavailable;
+use crate::console::frontends::SimpleTerminal;
+use crate::console::LogBackend;
+
+
+/// Files that errored the last time an album was transcoded.
+#[derive(Serialize)]
+struct ErroredAlbum {
+    library_name: String,
+
+    artist_name: String,
+
+    album_title: String,
+
+    errored_files: Vec<ErroredFileRecord>,
+}
+
+/// Associated with the `transcode --list-errored` flag.
+///
+/// Prints the files that errored the last time each album was transcoded
+/// (from the `.album.last-errors.euphony` records in the data directory), without scanning for changes.
+/// If `output_json` is `true`, the list is printed as JSON instead.
+pub fn cmd_list_errored(
+    configuration: &Configuration,
+    output_json: bool,
+    terminal: &mut SimpleTerminal,
+) -> Result<()> {
+    let mut errored_albums: Vec<ErroredAlbum> = Vec::new();
+
+    for library_configuration in configuration.libraries.values() {
+        let library = match LibraryView::from_library_configuration(
+            configuration,
+            library_configuration,
+        ) {
+            Ok(library) => library,
+            Err(LibraryViewError::NoSuchDirectory(path))
+                if library_configuration.optional =>
+            {
+                if !output_json {
+                    log_optional_library_unavailable(
+                        terminal,
+                        &library_configuration.name,
+                        &path,
+                    );
+                }
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let library_locked = library.read();
+
+        for (artist_name, artist) in library_locked.artists()? {
+            for (album_title, album) in artist.read().albums()? {
+                let error_record_directory =
+                    album.read().album_directory_in_error_records();
+
+                let error_record = match AlbumErrorRecord::load_from_directory(
+                    &error_record_directory,
+                ) {
+                    Ok(record) => record,
+                    Err(AlbumErrorRecordLoadError::NotFound)
+                    | Err(AlbumErrorRecordLoadError::SchemaVersionMismatch(_)) =>
+                    {
+                        continue;
+                    }
+                    Err(error) => {
+                        return Err(error).into_diagnostic().wrap_err_with(
+                            || {
+                                miette!(
+                                    "Failed to load error record in {:?}.",
+                                    error_record_directory
+                                )
+                            },
+                        );
+                    }
+                };
+
+                errored_albums.push(ErroredAlbum {
+                    library_name: library_locked.name(),
+                    artist_name: artist_name.clone(),
+                    album_title,
+                    errored_files: error_record.errored_files,
+                });
+            }
+        }
+    }
+
+    errored_albums.sort_unstable_by(|first, second| {
+        (
+            &first.library_name,
+            &first.artist_name,
+            &first.album_title,
+        )
+            .cmp(&(
+                &second.library_name,
+                &second.artist_name,
+                &second.album_title,
+            ))
+    });
+
+    if output_json {
+        let serialized_albums = serde_json::to_string_pretty(&errored_albums)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!("Could not serialize errored albums into JSON.")
+            })?;
+
+        terminal.log_println(serialized_albums);
+        return Ok(());
+    }
+
+    if errored_albums.is_empty() {
+        terminal.log_println(
+            "No files errored the last time albums were transcoded."
+                .green()
+                .bold(),
+        );
+        return Ok(());
+    }
+
+    for errored_album in &errored_albums {
+        terminal.log_println(format!(
+            "{} - {} (library: {})",
+            errored_album.artist_name.clone().bold(),
+            errored_album.album_title.clone().bold(),
+            errored_album.library_name
+        ));
+
+        for errored_file in &errored_album.errored_files {
+            terminal.log_println(format!(
+                "  {} {}",
+                errored_file.file_path,
+                format!("({})", errored_file.error).red()
+            ));
+        }
+    }
+
+    let total_errored_files = errored_albums
+        .iter()
+        .map(|album| album.errored_files.len())
+        .sum::<usize>();
+
+    terminal.log_println(format!(
+        "{} files in {} albums errored the last time they were transcoded.",
+        total_errored_files,
+        errored_albums.len()
+    ));
+
+    Ok(())
+}
@@ -1,16 +1,26 @@
 use std::collections::{HashMap, HashSet};
+use std::iter;
 use std::ops::Sub;
-use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::{fs, thread};
 
 use crossbeam::channel;
 use crossbeam::channel::{Receiver, RecvTimeoutError, Sender};
 use crossterm::style::Stylize;
+use euphony_configuration::library::ChangeDetection;
 use euphony_configuration::Configuration;
+use euphony_library::state::common::FileAccessError;
+use euphony_library::state::errored::{AlbumErrorRecord, ErroredFileRecord};
+use euphony_library::state::progress::AlbumTranscodeProgress;
+use euphony_library::state::source::SourceAlbumState;
 use euphony_library::state::transcoded::TranscodedAlbumState;
 use euphony_library::state::AlbumFileChangesV2;
 use euphony_library::view::library::LibraryViewError;
 use euphony_library::view::{
+    AlbumSourceFileList,
     AlbumView,
     ArtistView,
     LibraryView,
@@ -19,7 +29,10 @@ use euphony_library::view::{
     SharedLibraryView,
 };
 use miette::{miette, Context, IntoDiagnostic, Result};
+use parking_lot::Mutex;
 
+use self::events::{TranscodeEvent, TranscodeEventStream};
+use self::journal::{AlbumJournal, AlbumProgressRecorder, TranscodeJournal};
 use self::library_state::{
     LibraryState,
     LibraryStateLoadError,
@@ -27,16 +40,34 @@ use self::library_state::{
     TrackedArtistAlbums,
     LIBRARY_STATE_FILE_NAME,
 };
+use self::reason_summary::ProcessedFileReasonCounts;
+use self::scan_cache::{
+    CachedAlbumScan,
+    LibraryScanCache,
+    LIBRARY_SCAN_CACHE_FILE_NAME,
+};
+use self::staging::StagingDirectory;
 use self::state::changes::FileType;
-use self::state::generate_jobs::GenerateChanges;
+use self::state::generate_jobs::{GenerateChanges, GeneratedFileJobs};
 use crate::commands::transcode::jobs::common::FileJobMessage;
-use crate::commands::transcode::jobs::{CancellableThreadPool, FileJobResult};
+use crate::commands::transcode::jobs::{
+    CancellableTask,
+    CancellableThreadPool,
+    FileJobResult,
+};
+use crate::commands::validation::find_inter_library_album_collisions;
+use crate::commands::{
+    log_configuration_warnings,
+    log_optional_library_unavailable,
+};
 use crate::console::frontends::shared::queue::{
     AlbumQueueItem,
     AlbumQueueItemFinishedResult,
     FileQueueItem,
     FileQueueItemErrorType,
     FileQueueItemFinishedResult,
+    GenericQueueItemState,
+    QueueItem,
     QueueItemID,
 };
 use crate::console::frontends::TranscodeTerminal;
@@ -47,10 +78,24 @@ use crate::console::{
     UserControllableBackend,
 };
 use crate::globals::is_verbose_enabled;
+use crate::signals;
 
+pub mod check;
+mod dry_run;
+pub mod events;
+mod excess_deletions;
+mod first_run;
 pub mod jobs;
+mod journal;
 pub mod library_state;
+pub mod list_errored;
+mod playlist;
+mod reason_summary;
+pub mod scan_cache;
+pub mod staging;
 pub mod state;
+mod transcode_info;
+mod verify;
 
 
 pub struct GlobalProgress {
@@ -65,18 +110,493 @@ pub struct GlobalProgress {
     pub audio_files_errored: usize,
 
     pub data_files_errored: usize,
+
+    /// Successfully processed files per action and reason, printed at the end of the transcode.
+    pub reason_counts: ProcessedFileReasonCounts,
+
+    /// Total size of the source files of successfully transcoded audio files.
+    pub audio_source_bytes_processed: u64,
+
+    /// Total size of the source files of successfully copied data files.
+    pub data_source_bytes_processed: u64,
+
+    /// Total size of the source audio files of all normally processed albums
+    /// (entire albums, not only the files processed in this run).
+    pub album_audio_source_bytes: u64,
+
+    /// Total size of the transcoded counterparts of `album_audio_source_bytes`.
+    pub album_audio_transcoded_bytes: u64,
+}
+
+impl GlobalProgress {
+    /// Log the throughput of the processing phase (source megabytes per second
+    /// and files per minute, separately for audio and data files).
+    fn log_throughput_summary(
+        &self,
+        processing_elapsed_seconds: f64,
+        terminal: &TranscodeTerminal,
+    ) {
+        // Avoids dividing by zero when there was (practically) nothing to do.
+        let elapsed_seconds = processing_elapsed_seconds.max(0.001);
+
+        terminal.log_println("Throughput:".bold());
+
+        for (file_type_name, source_bytes_processed, files_finished_ok) in [
+            (
+                "audio",
+                self.audio_source_bytes_processed,
+                self.audio_files_finished_ok,
+            ),
+            (
+                "data",
+                self.data_source_bytes_processed,
+                self.data_files_finished_ok,
+            ),
+        ] {
+            terminal.log_println(format!(
+                "  {}: {:.2} MB/s, {:.1} files/min ({:.2} MB in {} files)",
+                file_type_name,
+                source_bytes_processed as f64 / 1_000_000.0 / elapsed_seconds,
+                files_finished_ok as f64 / elapsed_seconds * 60.0,
+                source_bytes_processed as f64 / 1_000_000.0,
+                files_finished_ok,
+            ));
+        }
+    }
+
+    /// Log the storage saved by transcoding the audio files of all processed albums.
+    fn log_storage_summary(&self, terminal: &TranscodeTerminal) {
+        if self.album_audio_source_bytes == 0 {
+            return;
+        }
+
+        terminal.log_println(format!(
+            "{} {}",
+            "Storage (audio files of processed albums):".bold(),
+            format_storage_savings(
+                self.album_audio_source_bytes,
+                self.album_audio_transcoded_bytes
+            )
+        ));
+    }
+}
+
+/// Formats source and transcoded sizes as e.g. "312.00 MB → 54.00 MB (saved 82.7%)".
+fn format_storage_savings(source_bytes: u64, transcoded_bytes: u64) -> String {
+    let saved_percentage = if source_bytes == 0 {
+        0.0
+    } else {
+        (1.0 - transcoded_bytes as f64 / source_bytes as f64) * 100.0
+    };
+
+    format!(
+        "{:.2} MB → {:.2} MB (saved {:.1}%)",
+        source_bytes as f64 / 1_000_000.0,
+        transcoded_bytes as f64 / 1_000_000.0,
+        saved_percentage
+    )
+}
+
+/// Returns the total size of the album's source audio files and their transcoded counterparts.
+/// Only files that exist on both sides are counted. Data files are left out,
+/// as they are copied verbatim.
+fn album_audio_storage_sizes(
+    tracked_source_files: &AlbumSourceFileList,
+) -> (u64, u64) {
+    let mut source_bytes: u64 = 0;
+    let mut transcoded_bytes: u64 = 0;
+
+    for (source_file_path, transcoded_file_path) in tracked_source_files
+        .map_source_file_paths_to_transcoded_file_paths_absolute()
+        .audio
+    {
+        let (Ok(source_metadata), Ok(transcoded_metadata)) = (
+            source_file_path.metadata(),
+            transcoded_file_path.metadata(),
+        ) else {
+            continue;
+        };
+
+        source_bytes += source_metadata.len();
+        transcoded_bytes += transcoded_metadata.len();
+    }
+
+    (source_bytes, transcoded_bytes)
+}
+
+/// Per-run options for the `transcode` command (set through CLI flags).
+#[derive(Default)]
+pub struct TranscodeOptions {
+    /// Export mode: treat every album as new (ignoring any saved state)
+    /// and don't write any source, transcoded or library state files.
+    pub export_mode: bool,
+
+    /// In export mode, also remove any existing state files of processed albums and libraries.
+    pub remove_existing_state_files: bool,
+
+    /// If `Some`, the transcode writes into this staging directory, which is swapped into place
+    /// of the transcoded library once the entire run succeeds (see `aggregated_library.staging_mode`).
+    /// The configured `aggregated_library.path` must already point to the staging directory.
+    pub staging_directory: Option<StagingDirectory>,
+
+    /// Don't ask for confirmation before the first transcode (when no state has been saved anywhere yet)
+    /// or, with `confirm_deletions`, before deleting excess files.
+    pub skip_first_run_confirmation: bool,
+
+    /// Dry run: only log what would be transcoded, copied and deleted,
+    /// without touching the filesystem (including state files and the staging directory).
+    pub dry_run: bool,
+
+    /// If non-empty, only the libraries matching these name selectors are scanned and transcoded
+    /// (see `Configuration::get_library_by_name_selector`). Other libraries' state files are never touched.
+    pub only_library: Vec<String>,
+
+    /// Only process new files and files missing from the transcoded library,
+    /// ignoring changed and removed files (nothing is ever removed or overwritten).
+    pub only_new: bool,
+
+    /// If set, every album is processed with this many threads, overriding both
+    /// `aggregated_library.transcode_threads` and the per-library `transcoding.transcode_threads`.
+    pub transcode_threads: Option<usize>,
+
+    /// Don't use (or update) the library scan caches, scanning every album in full (see `LibraryScanCache`).
+    pub no_scan_cache: bool,
+
+    /// If set, previously transcoded albums whose source directory has nothing modified
+    /// at or after this time are skipped without scanning them for changes
+    /// (see `album_was_modified_since`).
+    pub modified_since: Option<SystemTime>,
+
+    /// Transcode even if albums from different libraries collide in the transcoded library
+    /// (see `find_inter_library_album_collisions`), only warning about them.
+    pub allow_collisions: bool,
+
+    /// Abort the entire transcode as soon as any file fails to process
+    /// (instead of continuing and reporting the amount of errored files at the end).
+    pub fail_fast: bool,
+
+    /// Abort the entire scan as soon as any album can't be scanned (because one of its files
+    /// can't be accessed), instead of skipping the album and reporting it once scanning is done
+    /// (see `UnscannableAlbum`).
+    pub strict: bool,
+
+    /// If set, only the first N changed albums (across all libraries, in sorted order) are processed
+    /// (see `limit_queued_albums`). Libraries that weren't processed in full don't get their library state saved.
+    pub album_limit: Option<usize>,
+
+    /// List excess files in the transcoded library and ask for confirmation before deleting them
+    /// (see `excess_deletions::confirm_excess_deletions`).
+    pub confirm_deletions: bool,
+
+    /// If set, album and file starts and finishes are also written into this machine-parseable
+    /// event stream (see `--events-jsonl`).
+    pub event_stream: Option<TranscodeEventStream>,
+}
+
+impl TranscodeOptions {
+    /// Write the event into the event stream, if one is enabled.
+    fn emit_event(&self, event: TranscodeEvent) -> Result<()> {
+        match &self.event_stream {
+            Some(event_stream) => event_stream.emit(event),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The state files of a single processed album (see `StateWriter::save_album_states`).
+struct AlbumStates {
+    source_album_directory: PathBuf,
+
+    source_state: SourceAlbumState,
+
+    transcoded_album_directory: PathBuf,
+
+    transcoded_state: TranscodedAlbumState,
+}
+
+impl AlbumStates {
+    /// Save the transcoded album state (and the source album state, if `save_source_state` is `true`),
+    /// then remove the album's transcode progress file, which the saved states now supersede.
+    fn save(&self, save_source_state: bool) -> Result<()> {
+        if save_source_state {
+            self.source_state
+                .save_to_directory(&self.source_album_directory, true)?;
+        }
+
+        self.transcoded_state
+            .save_to_directory(&self.transcoded_album_directory, true)?;
+
+        AlbumTranscodeProgress::remove_from_directory(
+            &self.transcoded_album_directory,
+        )
+    }
+}
+
+/// Saves state files (`.album.source-state.euphony`, `.album.transcode-state.euphony`, `.library.state.euphony`).
+///
+/// In staging mode, saving source states is deferred until the staging directory has been swapped into place,
+/// otherwise a failed run would leave behind source states for transcodes that never made it
+/// into the transcoded library.
+///
+/// With `aggregated_library.state_write_concurrency`, album states are additionally buffered while a library
+/// is being processed and saved by several concurrent writers in `flush_album_states`, which is called
+/// once all of the library's albums are done (and before its library state is saved).
+struct StateWriter {
+    defer_source_writes: bool,
+
+    album_state_write_concurrency: Option<usize>,
+
+    buffered_album_states: Vec<AlbumStates>,
+
+    pending_album_states: Vec<(PathBuf, SourceAlbumState)>,
+
+    pending_library_states: Vec<(PathBuf, LibraryState)>,
+}
+
+impl StateWriter {
+    fn new(
+        defer_source_writes: bool,
+        album_state_write_concurrency: Option<usize>,
+    ) -> Self {
+        Self {
+            defer_source_writes,
+            album_state_write_concurrency,
+            buffered_album_states: Vec::new(),
+            pending_album_states: Vec::new(),
+            pending_library_states: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the album states were saved right away, or `false` if they were
+    /// only buffered (see `flush_album_states`).
+    fn save_album_states(&mut self, album_states: AlbumStates) -> Result<bool> {
+        if self.album_state_write_concurrency.is_some() {
+            self.buffered_album_states.push(album_states);
+            return Ok(false);
+        }
+
+        album_states.save(!self.defer_source_writes)?;
+
+        if self.defer_source_writes {
+            self.pending_album_states.push((
+                album_states.source_album_directory,
+                album_states.source_state,
+            ));
+        }
+
+        Ok(true)
+    }
+
+    /// Save all buffered album states with `album_state_write_concurrency` concurrent writers.
+    /// Returns the transcoded album directories of the albums whose states were saved.
+    fn flush_album_states(&mut self) -> Result<Vec<PathBuf>> {
+        let Some(album_state_write_concurrency) =
+            self.album_state_write_concurrency
+        else {
+            return Ok(Vec::new());
+        };
+
+        let buffered_album_states =
+            std::mem::take(&mut self.buffered_album_states);
+        let save_source_states = !self.defer_source_writes;
+
+        let album_states_queue = Mutex::new(buffered_album_states.iter());
+        let num_writers =
+            album_state_write_concurrency.min(buffered_album_states.len());
+
+        thread::scope::<'_, _, Result<()>>(|scope| {
+            let album_states_queue = &album_states_queue;
+
+            let writer_handles = (0..num_writers)
+                .map(|_| {
+                    scope.spawn(move || -> Result<()> {
+                        while let Some(album_states) =
+                            album_states_queue.lock().next()
+                        {
+                            album_states.save(save_source_states)?;
+                        }
+
+                        Ok(())
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            // Wait for all writers to finish and return the first error, if any.
+            let mut first_error: Option<miette::Report> = None;
+
+            for writer_handle in writer_handles {
+                let writer_result =
+                    writer_handle.join().expect("State writer thread panicked.");
+
+                if let Err(error) = writer_result {
+                    first_error.get_or_insert(error);
+                }
+            }
+
+            match first_error {
+                Some(error) => Err(error),
+                None => Ok(()),
+            }
+        })?;
+
+        let saved_album_directories = buffered_album_states
+            .iter()
+            .map(|album_states| album_states.transcoded_album_directory.clone())
+            .collect();
+
+        if self.defer_source_writes {
+            self.pending_album_states.extend(
+                buffered_album_states.into_iter().map(|album_states| {
+                    (
+                        album_states.source_album_directory,
+                        album_states.source_state,
+                    )
+                }),
+            );
+        }
+
+        Ok(saved_album_directories)
+    }
+
+    fn save_library_state(
+        &mut self,
+        library_directory: PathBuf,
+        state: LibraryState,
+    ) -> Result<()> {
+        if self.defer_source_writes {
+            self.pending_library_states.push((library_directory, state));
+            return Ok(());
+        }
+
+        state.save_to_directory(library_directory, true)
+    }
+
+    /// Save all deferred source states.
+    fn flush(self) -> Result<()> {
+        for (album_directory, state) in self.pending_album_states {
+            state.save_to_directory(album_directory, true)?;
+        }
+
+        for (library_directory, state) in self.pending_library_states {
+            state.save_to_directory(library_directory, true)?;
+        }
+
+        Ok(())
+    }
 }
 
 
+/// Removes the file at the given path, if it exists.
+/// Returns `true` if a file was removed.
+fn remove_file_if_exists<P: AsRef<Path>>(file_path: P) -> Result<bool> {
+    let file_path = file_path.as_ref();
+
+    if !file_path.is_file() {
+        return Ok(false);
+    }
+
+    fs::remove_file(file_path)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!("Failed to remove state file at {:?}", file_path)
+        })?;
+
+    Ok(true)
+}
+
+
+/// Runs the configured `aggregated_library.post_album_command` (if any)
+/// for an album that has just been successfully transcoded.
+///
+/// Failures are logged as warnings, unless `aggregated_library.post_album_command_failure_is_fatal`
+/// is enabled, in which case an error is returned.
+fn run_post_album_command(
+    album: &AlbumView,
+    album_artist_name: &str,
+    album_title: &str,
+    terminal: &TranscodeTerminal,
+) -> Result<()> {
+    let aggregated_library_configuration =
+        &album.euphony_configuration().aggregated_library;
+
+    let Some(post_album_command) =
+        &aggregated_library_configuration.post_album_command
+    else {
+        return Ok(());
+    };
+
+    let transcoded_album_directory =
+        album.album_directory_in_transcoded_library();
+    let transcoded_album_directory_str =
+        transcoded_album_directory.to_str().ok_or_else(|| {
+            miette!("Transcoded album directory path is not valid UTF-8.")
+        })?;
+
+    let (command_binary, command_arguments) = post_album_command
+        .split_first()
+        .expect("post_album_command should not be empty.");
+
+    let command_arguments: Vec<String> = command_arguments
+        .iter()
+        .map(|arg| {
+            arg.replace(
+                "{ALBUM_DIRECTORY}",
+                transcoded_album_directory_str,
+            )
+        })
+        .collect();
+
+    if is_verbose_enabled() {
+        terminal.log_println(format!(
+            "  Running post-album command: {command_binary} {command_arguments:?}"
+        ));
+    }
+
+    let command_result = Command::new(command_binary)
+        .args(&command_arguments)
+        .env(
+            "EUPHONY_ALBUM_DIRECTORY",
+            transcoded_album_directory_str,
+        )
+        .env("EUPHONY_ALBUM_ARTIST", album_artist_name)
+        .env("EUPHONY_ALBUM_TITLE", album_title)
+        .output();
+
+    let failure_reason = match command_result {
+        Ok(output) if output.status.success() => return Ok(()),
+        Ok(output) => format!(
+            "exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(error) => format!("could not be started: {error}"),
+    };
+
+    if aggregated_library_configuration.post_album_command_failure_is_fatal {
+        return Err(miette!(
+            "Post-album command for album \"{album_artist_name} - {album_title}\" {failure_reason}"
+        ));
+    }
+
+    terminal.log_eprintln(format!(
+        "  {} post-album command for album \"{album_artist_name} - {album_title}\" {failure_reason}",
+        "WARNING:".yellow(),
+    ));
+
+    Ok(())
+}
+
 fn process_album<'config>(
     queued_album: QueuedAlbum<'config>,
-    progress: &mut GlobalProgress,
+    options: &TranscodeOptions,
+    transcode_journal: &TranscodeJournal,
+    state_writer: &Mutex<StateWriter>,
+    progress: &Mutex<GlobalProgress>,
     terminal: &TranscodeTerminal<'config, '_>,
     terminal_user_input_receiver: &mut tokio::sync::broadcast::Receiver<
         UserControlMessage,
     >,
 ) -> Result<()> {
-    // TODO A percentage of storage saved after each file finishes would be cool.
     let time_album_start = Instant::now();
 
     let (album_artist_name, album_title, album_library_name) = {
@@ -92,6 +612,11 @@ fn process_album<'config>(
     };
 
     terminal.queue_album_item_start(queued_album.queue_id)?;
+    options.emit_event(TranscodeEvent::AlbumStarted {
+        library: &album_library_name,
+        artist: &album_artist_name,
+        album: &album_title,
+    })?;
     terminal.log_println(format!(
         "↳ Transcoding album \"{album_artist_name} - {album_title}\" (library: {album_library_name})"
     ));
@@ -103,12 +628,58 @@ fn process_album<'config>(
         ));
     }
 
+    // Track the album in the in-progress journal, so an interrupted run can be cleaned up
+    // on the next one (see `journal::recover_interrupted_album`). Export mode leaves no state behind.
+    let mut album_journal = if options.export_mode {
+        None
+    } else {
+        let album_view = queued_album.album.read();
+
+        Some(AlbumJournal::start(
+            transcode_journal,
+            album_view.album_directory_in_source_library(),
+            album_view.album_directory_in_transcoded_library(),
+        )?)
+    };
+
+    // Also record completed files in the album's transcode progress file, so that an interrupted run
+    // doesn't have to process them again on the next one (see `AlbumTranscodeProgress`).
+    // In staging mode, the files of an interrupted run are discarded along with the staging directory.
+    let mut album_progress_recorder =
+        match &queued_album.changes.tracked_source_files {
+            Some(tracked_source_files)
+                if !options.export_mode
+                    && options.staging_directory.is_none()
+                    && queued_album.job_type
+                        == QueuedAlbumJobType::NormalProcessing =>
+            {
+                Some(AlbumProgressRecorder::start(
+                    tracked_source_files,
+                )?)
+            }
+            _ => None,
+        };
+
     let (worker_tx, worker_rx) = channel::unbounded::<FileJobMessage>();
     let (processing_control_tx, processing_control_rx) =
         channel::unbounded::<MainThreadMessage>();
 
     let mut user_requested_cancellation = false;
 
+    // With `--fail-fast`, the first file that errored (processing is stopped once this is set).
+    let mut fail_fast_errored_file: Option<ErroredFileRecord> = None;
+
+    // Total source and output sizes of all files in this album that produced an output file.
+    let mut album_source_size_bytes: u64 = 0;
+    let mut album_output_size_bytes: u64 = 0;
+
+    // Files that errored while processing this album (see `AlbumErrorRecord`).
+    let mut album_errored_files: Vec<ErroredFileRecord> = Vec::new();
+
+    // File queue items of this album, removed from the file queue once the album is done
+    // (other albums may be processing at the same time, so the queue can't just be cleared).
+    let mut album_file_queue_items: Vec<QueueItemID> = Vec::new();
+
     thread::scope::<'_, _, Result<()>>(|scope| {
         // Spawn a thread that will manage the following:
         // - initialize the thread pool
@@ -118,6 +689,7 @@ fn process_album<'config>(
             process_changes(
                 &queued_album.changes,
                 queued_album.album.clone(),
+                options.transcode_threads,
                 terminal,
                 worker_tx,
                 processing_control_rx,
@@ -148,6 +720,16 @@ fn process_album<'config>(
                         }
 
                         terminal.queue_file_item_start(queue_item)?;
+                        options.emit_event(TranscodeEvent::FileStarted {
+                            file_type,
+                            path: &file_path,
+                        })?;
+
+                        if let Some(album_journal) = &mut album_journal {
+                            album_journal.mark_started(&file_path)?;
+                        }
+
+                        let mut progress = progress.lock();
 
                         match file_type {
                             FileType::Audio => {
@@ -165,6 +747,13 @@ fn process_album<'config>(
                             progress.data_files_currently_processing,
                         )?;
                     }
+                    FileJobMessage::Progress {
+                        queue_item,
+                        percent,
+                    } => {
+                        terminal
+                            .queue_file_item_set_progress(queue_item, percent)?;
+                    }
                     FileJobMessage::Finished {
                         queue_item,
                         file_type,
@@ -177,6 +766,25 @@ fn process_album<'config>(
                             ));
                         }
 
+                        album_file_queue_items.push(queue_item);
+
+                        options.emit_event(TranscodeEvent::FileFinished {
+                            file_type,
+                            path: &file_path,
+                            ok: matches!(
+                                processing_result,
+                                FileJobResult::Okay { .. }
+                            ),
+                            error: match &processing_result {
+                                FileJobResult::Errored { error, .. } => {
+                                    Some(error)
+                                }
+                                FileJobResult::Okay { .. } => None,
+                            },
+                        })?;
+
+                        let mut progress = progress.lock();
+
                         match file_type {
                             FileType::Audio => {
                                 progress.audio_files_currently_processing -= 1;
@@ -194,13 +802,47 @@ fn process_album<'config>(
                         )?;
 
                         let item_result = match processing_result {
-                            FileJobResult::Okay { verbose_info } => {
+                            FileJobResult::Okay {
+                                verbose_info,
+                                sizes,
+                            } => {
                                 if let Some(verbose_info) = verbose_info {
                                     if is_verbose_enabled() {
                                         terminal.log_println(verbose_info);
                                     }
                                 }
 
+                                if let Some(sizes) = sizes {
+                                    album_source_size_bytes +=
+                                        sizes.source_size_bytes;
+                                    album_output_size_bytes +=
+                                        sizes.output_size_bytes;
+
+                                    match file_type {
+                                        FileType::Audio => {
+                                            progress
+                                                .audio_source_bytes_processed +=
+                                                sizes.source_size_bytes;
+                                        }
+                                        FileType::Data | FileType::Unknown => {
+                                            progress
+                                                .data_source_bytes_processed +=
+                                                sizes.source_size_bytes;
+                                        }
+                                    }
+                                }
+
+                                if let Some(album_journal) = &mut album_journal {
+                                    album_journal.mark_completed(&file_path)?;
+                                }
+
+                                if let Some(album_progress_recorder) =
+                                    &mut album_progress_recorder
+                                {
+                                    album_progress_recorder
+                                        .mark_completed(&file_path)?;
+                                }
+
                                 match file_type {
                                     FileType::Audio => {
                                         progress.audio_files_finished_ok += 1;
@@ -250,18 +892,50 @@ fn process_album<'config>(
                                     }
                                 };
 
+                                let errored_file_record = ErroredFileRecord {
+                                    file_path: file_path.clone(),
+                                    error: error.clone(),
+                                };
+
+                                if options.fail_fast
+                                    && fail_fast_errored_file.is_none()
+                                    && !user_requested_cancellation
+                                {
+                                    terminal.log_println(
+                                        "A file failed to process, cancelling transcode (--fail-fast).",
+                                    );
+
+                                    processing_control_tx
+                                        .send(MainThreadMessage::StopProcessing)
+                                        .into_diagnostic()?;
+
+                                    fail_fast_errored_file =
+                                        Some(errored_file_record.clone());
+                                }
+
+                                album_errored_files.push(errored_file_record);
+
                                 FileQueueItemFinishedResult::Failed(
                                     FileQueueItemErrorType::Errored { error },
                                 )
                             }
                         };
 
-                        // TODO File that fail once should retry (see the configuration).
-                        // TODO Errored files should stop the transcode.
                         terminal
                             .queue_file_item_finish(queue_item, item_result)?;
                     }
-                    FileJobMessage::Cancelled { queue_item, .. } => {
+                    FileJobMessage::Cancelled {
+                        queue_item,
+                        file_type,
+                        file_path,
+                    } => {
+                        album_file_queue_items.push(queue_item);
+
+                        options.emit_event(TranscodeEvent::FileCancelled {
+                            file_type,
+                            path: &file_path,
+                        })?;
+
                         let item_result = FileQueueItemFinishedResult::Failed(
                             FileQueueItemErrorType::Cancelled,
                         );
@@ -319,12 +993,22 @@ fn process_album<'config>(
     })?;
 
 
+    if user_requested_cancellation || fail_fast_errored_file.is_some() {
+        options.emit_event(TranscodeEvent::AlbumFinished {
+            library: &album_library_name,
+            artist: &album_artist_name,
+            album: &album_title,
+            ok: false,
+            elapsed_seconds: time_album_start.elapsed().as_secs_f64(),
+        })?;
+    }
+
     if user_requested_cancellation {
         let album_view = queued_album.album.read();
 
-        terminal.log_println(format!(
+        terminal.log_eprintln(format!(
             "{} A partially-transcoded album ({} - {}) has been potentially left behind \
-            in the transcoded library.",
+            in the transcoded library (it will be cleaned up and processed again on the next run).",
             "WARNING:".red(),
             album_view.read_lock_artist().name,
             album_view.title,
@@ -333,12 +1017,37 @@ fn process_album<'config>(
         return Err(miette!("User aborted transcoding."));
     }
 
+    if let Some(errored_file) = fail_fast_errored_file {
+        let album_view = queued_album.album.read();
+
+        terminal.log_eprintln(format!(
+            "{} {} failed to process: {}",
+            "ERROR:".red(),
+            errored_file.file_path,
+            errored_file.error,
+        ));
+        terminal.log_eprintln(format!(
+            "{} A partially-transcoded album ({} - {}) has been potentially left behind \
+            in the transcoded library (it will be cleaned up and processed again on the next run).",
+            "WARNING:".red(),
+            album_view.read_lock_artist().name,
+            album_view.title,
+        ));
+
+        return Err(miette!(
+            "Aborted transcoding because a file failed to process (--fail-fast)."
+        ));
+    }
+
 
     // There are now two possibilities:
     // - if the album was being processed normally, we should save the states (see below - `.album.source-state.euphony`, ...)
     // - but if the transcoded album was being deleted (e.g. when the source album is fully deleted),
     //   we need to remove those state files and possibly delete the empty directory that has now been left behind
 
+    // Buffered album states (see `state_write_concurrency`) are only saved by `process_library`.
+    let mut album_states_buffered = false;
+
     if queued_album.job_type == QueuedAlbumJobType::NormalProcessing {
         // The entire album is not up-to-date, so we generate two state structs that are then
         // saved as JSON:
@@ -347,37 +1056,167 @@ fn process_album<'config>(
         // - `.album.transcode-state.euphony` is saved in the transcoded album directory
         //   and contains a mapping from transcoded files back to their originals
         //   as well as metadata of the tracked *transcoded* files.
+        //
+        // Additionally, if any files errored, `.album.last-errors.euphony` is saved in the album's
+        // directory inside the data directory (and removed if the album processed cleanly).
+        //
+        // In export mode, no state is saved at all (and existing state files may be removed instead).
+        if !options.export_mode {
+            let source_album_state =
+                queued_album.changes.generate_source_album_state()?;
+            let mut transcoded_album_state =
+                queued_album.changes.generate_transcoded_album_state()?;
 
-        let source_album_state =
-            queued_album.changes.generate_source_album_state()?;
-        let transcoded_album_state =
-            queued_album.changes.generate_transcoded_album_state()?;
+            let album_view = queued_album.album.read();
 
-        {
+            if album_view
+                .euphony_configuration()
+                .aggregated_library
+                .write_transcode_info
+            {
+                transcode_info::write_transcode_info(
+                    &album_view,
+                    &mut transcoded_album_state,
+                )?;
+            }
+
+            if album_view
+                .euphony_configuration()
+                .aggregated_library
+                .generate_playlists
+            {
+                playlist::write_album_playlist(
+                    &album_view,
+                    &mut transcoded_album_state,
+                )?;
+            }
+
+            // The album's state files cover all of its files, so the progress file is removed
+            // once they are saved (which might happen later, see `state_write_concurrency`).
+            drop(album_progress_recorder);
+            album_states_buffered =
+                !state_writer.lock().save_album_states(AlbumStates {
+                    source_album_directory: album_view
+                        .album_directory_in_source_library(),
+                    source_state: source_album_state,
+                    transcoded_album_directory: album_view
+                        .album_directory_in_transcoded_library(),
+                    transcoded_state: transcoded_album_state,
+                })?;
+
+            if album_errored_files.is_empty() {
+                remove_file_if_exists(
+                    AlbumErrorRecord::get_record_file_path_for_directory(
+                        album_view.album_directory_in_error_records(),
+                    ),
+                )?;
+            } else {
+                AlbumErrorRecord::new(album_errored_files.clone())
+                    .save_to_directory(
+                        album_view.album_directory_in_error_records(),
+                    )?;
+            }
+        } else if options.remove_existing_state_files {
             let album_view = queued_album.album.read();
 
-            source_album_state.save_to_directory(
-                album_view.album_directory_in_source_library(),
-                true,
+            let removed_source_state = remove_file_if_exists(
+                SourceAlbumState::get_state_file_path_for_directory(
+                    album_view.album_directory_in_source_library(),
+                ),
             )?;
-
-            transcoded_album_state.save_to_directory(
-                album_view.album_directory_in_transcoded_library(),
-                true,
+            let removed_transcoded_state = remove_file_if_exists(
+                TranscodedAlbumState::get_state_file_path_for_directory(
+                    album_view.album_directory_in_transcoded_library(),
+                ),
             )?;
+
+            if is_verbose_enabled()
+                && (removed_source_state || removed_transcoded_state)
+            {
+                terminal.log_println("  Removed existing album state files.");
+            }
         }
 
-        // Mark the album as finished in the album queue and clear the file queue.
+        // Mark the album as finished in the album queue and remove its files from the file queue.
         terminal.queue_album_item_finish(
             queued_album.queue_id,
             AlbumQueueItemFinishedResult::new_ok(),
         )?;
-        terminal.queue_file_clear()?;
+
+        let mut locked_progress = progress.lock();
+
+        for file_queue_item in album_file_queue_items {
+            let file_queue_item =
+                terminal.queue_file_item_remove(file_queue_item)?;
+
+            if file_queue_item.get_state()
+                == (GenericQueueItemState::Finished { ok: true })
+            {
+                locked_progress
+                    .reason_counts
+                    .record(&file_queue_item.context.action);
+            }
+        }
+
+        drop(locked_progress);
 
         let time_album_elapsed = time_album_start.elapsed().as_secs_f64();
         terminal.log_println(format!(
             "  Album transcoded in {time_album_elapsed:.2} seconds."
         ));
+
+        if let Some(tracked_source_files) =
+            &queued_album.changes.tracked_source_files
+        {
+            let (album_audio_source_bytes, album_audio_transcoded_bytes) =
+                album_audio_storage_sizes(tracked_source_files);
+
+            if album_audio_source_bytes > 0 {
+                let mut locked_progress = progress.lock();
+                locked_progress.album_audio_source_bytes +=
+                    album_audio_source_bytes;
+                locked_progress.album_audio_transcoded_bytes +=
+                    album_audio_transcoded_bytes;
+                drop(locked_progress);
+
+                terminal.log_println(format!(
+                    "  Album \"{album_artist_name} - {album_title}\": {}",
+                    format_storage_savings(
+                        album_audio_source_bytes,
+                        album_audio_transcoded_bytes
+                    )
+                ));
+            }
+        }
+
+        let warn_on_size_increase = queued_album
+            .album
+            .read()
+            .euphony_configuration()
+            .aggregated_library
+            .warn_on_size_increase;
+
+        if warn_on_size_increase
+            && album_output_size_bytes > album_source_size_bytes
+        {
+            terminal.log_eprintln(format!(
+                "  {} processed files of album \"{album_artist_name} - {album_title}\" \
+                are larger than their sources ({} bytes -> {} bytes).",
+                "WARNING:".yellow(),
+                album_source_size_bytes,
+                album_output_size_bytes,
+            ));
+        }
+
+        // The post-album command only makes sense for albums whose files all made it through.
+        if album_errored_files.is_empty() {
+            run_post_album_command(
+                &queued_album.album.read(),
+                &album_artist_name,
+                &album_title,
+                terminal,
+            )?;
+        }
     } else if queued_album.job_type == QueuedAlbumJobType::FullyRemoving {
         // The transcoded album was fully deleted, meaning we need to delete the state (`.*.euphony`) files
         // and potentially remove the now-empty album directory.
@@ -411,6 +1250,12 @@ fn process_album<'config>(
             }
         }
 
+        remove_file_if_exists(
+            AlbumErrorRecord::get_record_file_path_for_directory(
+                album_view.album_directory_in_error_records(),
+            ),
+        )?;
+
         // Now remove the album directory if it is empty.
         // `std::fs::remove_dir` already guarantees that it will only remove empty directories.
         if album_transcoded_directory_path
@@ -443,24 +1288,124 @@ fn process_album<'config>(
         }
     }
 
+    // Once the album's state has been saved, it no longer needs to be tracked as in-progress.
+    // Buffered states haven't been saved yet, so their albums stay in the journal until
+    // `process_library` has flushed them.
+    if let Some(album_journal) = album_journal {
+        if !album_states_buffered {
+            album_journal.finish()?;
+        }
+    }
+
+    options.emit_event(TranscodeEvent::AlbumFinished {
+        library: &album_library_name,
+        artist: &album_artist_name,
+        album: &album_title,
+        ok: true,
+        elapsed_seconds: time_album_start.elapsed().as_secs_f64(),
+    })?;
+
     Ok(())
 }
 
 fn process_library<'config>(
     queued_library: QueuedLibrary<'config>,
-    progress: &mut GlobalProgress,
+    options: &TranscodeOptions,
+    transcode_journal: &TranscodeJournal,
+    state_writer: &Mutex<StateWriter>,
+    progress: &Mutex<GlobalProgress>,
     terminal: &TranscodeTerminal<'config, '_>,
     terminal_user_input_receiver: &mut tokio::sync::broadcast::Receiver<
         UserControlMessage,
     >,
 ) -> Result<()> {
-    for album in queued_library.queued_albums {
-        process_album(
-            album,
-            progress,
-            terminal,
-            terminal_user_input_receiver,
-        )?;
+    let album_concurrency = queued_library
+        .library
+        .read()
+        .euphony_configuration
+        .aggregated_library
+        .transcode_album_concurrency;
+
+    // Albums are processed by `transcode_album_concurrency` workers, each of which takes
+    // the next queued album as soon as it is done with its current one.
+    // After the first error, the workers don't start any new albums.
+    let album_queue = Mutex::new(queued_library.queued_albums.into_iter());
+    let stop_processing = AtomicBool::new(false);
+
+    // Each worker needs its own receiver, so that every album in progress sees user control messages.
+    let mut additional_user_input_receivers = (1..album_concurrency)
+        .map(|_| terminal.get_user_control_receiver())
+        .collect::<Result<Vec<_>>>()?;
+
+    let processing_result = thread::scope::<'_, _, Result<()>>(|scope| {
+        let album_queue = &album_queue;
+        let stop_processing = &stop_processing;
+
+        let worker_handles = iter::once(terminal_user_input_receiver)
+            .chain(additional_user_input_receivers.iter_mut())
+            .map(|user_input_receiver| {
+                scope.spawn(move || -> Result<()> {
+                    while !stop_processing.load(Ordering::SeqCst) {
+                        let Some(queued_album) = album_queue.lock().next()
+                        else {
+                            break;
+                        };
+
+                        let album_result = process_album(
+                            queued_album,
+                            options,
+                            transcode_journal,
+                            state_writer,
+                            progress,
+                            terminal,
+                            user_input_receiver,
+                        );
+
+                        if album_result.is_err() {
+                            stop_processing.store(true, Ordering::SeqCst);
+                            return album_result;
+                        }
+                    }
+
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Wait for all workers to finish and return the first error, if any.
+        let mut first_error: Option<miette::Report> = None;
+
+        for worker_handle in worker_handles {
+            let worker_result =
+                worker_handle.join().expect("Album worker thread panicked.");
+
+            if let Err(error) = worker_result {
+                first_error.get_or_insert(error);
+            }
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    });
+
+    // Buffered album states (see `state_write_concurrency`) are saved even if an album failed,
+    // so the albums that did finish don't need to be processed again.
+    let flush_result = state_writer.lock().flush_album_states();
+    processing_result?;
+
+    let saved_album_directories = flush_result?;
+    if !saved_album_directories.is_empty() {
+        // The buffered albums have been kept in the in-progress journal until now.
+        transcode_journal.finish_albums(&saved_album_directories)?;
+
+        if is_verbose_enabled() {
+            terminal.log_println(format!(
+                "Saved buffered album states for {} albums.",
+                saved_album_directories.len()
+            ));
+        }
     }
 
 
@@ -509,9 +1454,43 @@ fn process_library<'config>(
     let library_view = queued_library.library.read();
     let library_directory = library_view.root_directory_in_source_library();
 
-    queued_library
-        .fresh_artist_album_list_state
-        .save_to_directory(library_directory, true)?;
+    // With `--limit`, some changed albums of this library were left out, so the saved library state must stay
+    // as it was - otherwise e.g. removed albums that weren't processed yet wouldn't be detected as removed anymore.
+    if !queued_library.is_complete {
+        terminal.log_println(format!(
+            "Not saving library state for library {}, as only some of its changed albums were processed (--limit).",
+            library_view.name()
+        ));
+
+        return Ok(());
+    }
+
+    if options.export_mode {
+        if options.remove_existing_state_files
+            && remove_file_if_exists(
+                library_directory.join(LIBRARY_STATE_FILE_NAME),
+            )?
+            && is_verbose_enabled()
+        {
+            terminal.log_println(format!(
+                "Removed existing library state for library {}.",
+                library_view.name()
+            ));
+        }
+
+        if options.remove_existing_state_files {
+            remove_file_if_exists(
+                library_directory.join(LIBRARY_SCAN_CACHE_FILE_NAME),
+            )?;
+        }
+
+        return Ok(());
+    }
+
+    state_writer.lock().save_library_state(
+        library_directory,
+        queued_library.fresh_artist_album_list_state,
+    )?;
 
     if is_verbose_enabled() {
         terminal.log_println(format!(
@@ -527,15 +1506,112 @@ fn process_library<'config>(
 
 pub fn cmd_transcode_all<'config: 'scope, 'scope, 'scope_env: 'scope_env>(
     configuration: &'config Configuration,
+    options: &TranscodeOptions,
     terminal: &TranscodeTerminal<'config, 'scope>,
 ) -> Result<()> {
     let time_full_processing_start = Instant::now();
 
-    terminal.log_println(
-        "Command: transcode entire collection (skip unchanged)."
-            .cyan()
-            .bold(),
-    );
+    if options.export_mode {
+        terminal.log_println(
+            "Command: export entire collection (transcode everything, save no state)."
+                .cyan()
+                .bold(),
+        );
+    } else {
+        terminal.log_println(
+            "Command: transcode entire collection (skip unchanged)."
+                .cyan()
+                .bold(),
+        );
+    }
+
+    if options.dry_run {
+        terminal.log_println(
+            "Dry run: nothing will be transcoded, copied, deleted or saved."
+                .cyan(),
+        );
+    }
+
+    log_configuration_warnings(terminal, configuration);
+
+    match options.transcode_threads {
+        Some(transcode_threads) => terminal.log_println(format!(
+            "Using {} threads per album (set with --jobs).",
+            transcode_threads.to_string().bold()
+        )),
+        None => terminal.log_println(format!(
+            "Using {} threads per album (aggregated_library.transcode_threads, \
+            unless a library sets its own transcoding.transcode_threads).",
+            configuration
+                .aggregated_library
+                .transcode_threads
+                .to_string()
+                .bold()
+        )),
+    }
+
+    let libraries: Vec<SharedLibraryView<'config>> =
+        collect_libraries_sorted(configuration, options, terminal)?;
+
+    // Albums from different libraries that map to the same transcoded directory
+    // would overwrite each other's files.
+    let album_collisions = find_inter_library_album_collisions(&libraries)?;
+    if !album_collisions.is_empty() {
+        terminal.log_eprintln(format!(
+            "{} {} albums exist in more than one library and would be transcoded into the same directory:",
+            if options.allow_collisions {
+                "WARNING:".yellow()
+            } else {
+                "ERROR:".red()
+            },
+            album_collisions.len()
+        ));
+        for album_collision in &album_collisions {
+            terminal.log_eprintln(format!("  {album_collision}"));
+        }
+
+        if !options.allow_collisions {
+            terminal.log_eprintln(
+                "Nothing was transcoded. Rename or remove the colliding albums \
+                or pass --allow-collisions to transcode anyway.",
+            );
+
+            return Err(miette!(
+                "Found {} inter-library album collisions.",
+                album_collisions.len()
+            ));
+        }
+    }
+
+    if let Some(staging_directory) = &options.staging_directory {
+        terminal.log_println(format!(
+            "Preparing staging directory at {:?}...",
+            staging_directory.staging_directory_path()
+        ));
+        staging_directory.prepare()?;
+
+        // Only the aggregated transcoded library is staged, libraries with their own
+        // `output_path` are still transcoded in place.
+        for library in configuration.libraries.values() {
+            if let Some(output_path) = &library.output_path {
+                terminal.log_eprintln(format!(
+                    "{} library \"{}\" has its own output_path ({}), which is not staged \
+                    (its changes are written in place).",
+                    "WARNING:".yellow(),
+                    library.name,
+                    output_path
+                ));
+            }
+        }
+    }
+
+    // Clean up after a previous run that was interrupted in the middle of an album.
+    journal::recover_interrupted_album(
+        &configuration.aggregated_library.path,
+        options.dry_run,
+        terminal,
+    )?;
+
     terminal.log_println("Scanning albums for changes...");
 
     // The user may send control messages via the selected backend (such as an abort message).
@@ -543,24 +1619,59 @@ pub fn cmd_transcode_all<'config: 'scope, 'scope, 'scope_env: 'scope_env>(
     // The terminal UI backend for example implements the "q" keybind that sends UserControlMessage::Exit.
     let mut terminal_user_input = terminal.get_user_control_receiver()?;
 
-
-    let libraries: Vec<SharedLibraryView<'config>> =
-        collect_libraries_sorted(configuration, terminal)?;
-
     let fresh_library_states = collect_full_library_states(&libraries)?;
-    let libraries_with_changes =
-        collect_changes(&fresh_library_states, terminal)?;
+    let mut libraries_with_changes =
+        collect_changes(&fresh_library_states, options, terminal)?
+            .sorted_libraries_with_changes;
+
+    let num_total_discovered_albums = fresh_library_states
+        .iter()
+        .flat_map(|(_, library_state)| library_state.tracked_artists.values())
+        .map(|artist| artist.tracked_albums.len())
+        .sum::<usize>();
 
     // It is possible that no changes have been detected, in which case we should just exit.
     if libraries_with_changes.is_empty() {
-        terminal.log_println(
-            "All albums are up to date, no transcoding needed."
+        if num_total_discovered_albums == 0 {
+            // Most likely a misconfiguration - saying that everything is up to date would be misleading.
+            terminal.log_eprintln(format!(
+                "{} Found 0 albums across {} libraries, nothing to transcode \
+                (check the library paths in your configuration).",
+                "WARNING:".yellow(),
+                libraries.len()
+            ));
+        } else {
+            terminal.log_println(
+                format!(
+                    "Found {num_total_discovered_albums} albums, all are up to date, \
+                    no transcoding needed."
+                )
                 .green()
                 .bold(),
-        );
+            );
+        }
+
+        if let Some(staging_directory) = &options.staging_directory {
+            staging_directory.discard()?;
+        }
+
         return Ok(());
     }
 
+    // Excess files in the transcoded library were most likely put there by hand,
+    // so with `--confirm-deletions` the user has to agree to their deletion first.
+    if options.confirm_deletions && !options.dry_run {
+        excess_deletions::confirm_excess_deletions(
+            &mut libraries_with_changes,
+            options.skip_first_run_confirmation,
+            terminal,
+            &mut terminal_user_input,
+        )?;
+
+        // Declining the deletion with `q` continues, but a termination signal must not.
+        signals::check_for_termination()?;
+    }
+
     let num_total_changed_files = libraries_with_changes
         .iter()
         .flat_map(|library| &library.sorted_changed_artists)
@@ -586,6 +1697,30 @@ pub fn cmd_transcode_all<'config: 'scope, 'scope, 'scope_env: 'scope_env>(
         num_total_changed_files.to_string().bold()
     ));
 
+    // The first transcode of an existing collection can take many hours, so we make sure
+    // it isn't started by accident (export mode ignores saved state, so it is exempt).
+    if !options.export_mode
+        && !options.dry_run
+        && !options.skip_first_run_confirmation
+        && first_run::is_first_run(
+            &fresh_library_states,
+            &libraries_with_changes,
+        )
+        && !first_run::confirm_first_run(
+            &libraries_with_changes,
+            terminal,
+            &mut terminal_user_input,
+        )?
+    {
+        terminal.log_println("First transcode was not confirmed, aborting.");
+
+        if let Some(staging_directory) = &options.staging_directory {
+            staging_directory.discard()?;
+        }
+
+        return Ok(());
+    }
+
 
     // Queue the entire workload - this way we'll generate `QueueItemID`s
     // for each item, enabling us to interact with the terminal backend
@@ -594,17 +1729,55 @@ pub fn cmd_transcode_all<'config: 'scope, 'scope, 'scope_env: 'scope_env>(
     terminal.queue_file_enable();
     terminal.progress_enable();
 
-    let queued_libraries =
+    let mut queued_libraries =
         queue_all_changed_albums(terminal, libraries_with_changes)?;
 
+    let mut num_total_changed_files = num_total_changed_files;
+
+    if let Some(album_limit) = options.album_limit {
+        let num_queued_albums = queued_libraries
+            .iter()
+            .map(|library| library.queued_albums.len())
+            .sum::<usize>();
+
+        if num_queued_albums > album_limit {
+            queued_libraries =
+                limit_queued_albums(queued_libraries, album_limit, terminal)?;
+
+            num_total_changed_files = queued_libraries
+                .iter()
+                .flat_map(|library| &library.queued_albums)
+                .map(|album| album.changes.number_of_changed_files())
+                .sum::<usize>();
+
+            terminal.log_println(format!(
+                "{} only processing the first {} of {} changed albums ({} files). \
+                Libraries that aren't processed in full won't have their library state saved.",
+                "--limit:".yellow(),
+                album_limit,
+                num_queued_albums,
+                num_total_changed_files,
+            ));
+        }
+    }
+
+    if options.dry_run {
+        return dry_run::log_planned_changes(queued_libraries, terminal);
+    }
+
     // Set up progress bar tracking.
-    let mut global_progress = GlobalProgress {
+    let global_progress = GlobalProgress {
         audio_files_currently_processing: 0,
         data_files_currently_processing: 0,
         audio_files_finished_ok: 0,
         data_files_finished_ok: 0,
         audio_files_errored: 0,
         data_files_errored: 0,
+        reason_counts: ProcessedFileReasonCounts::default(),
+        audio_source_bytes_processed: 0,
+        data_source_bytes_processed: 0,
+        album_audio_source_bytes: 0,
+        album_audio_transcoded_bytes: 0,
     };
 
     terminal.progress_set_audio_files_currently_processing(
@@ -626,16 +1799,37 @@ pub fn cmd_transcode_all<'config: 'scope, 'scope, 'scope_env: 'scope_env>(
 
     terminal.progress_set_total(num_total_changed_files)?;
 
+    // Shared by all albums processed at the same time (see `transcode_album_concurrency`).
+    let global_progress = Mutex::new(global_progress);
+    let state_writer = Mutex::new(StateWriter::new(
+        options.staging_directory.is_some(),
+        configuration.aggregated_library.state_write_concurrency,
+    ));
+    let transcode_journal =
+        TranscodeJournal::new(&configuration.aggregated_library.path);
+
+    // Throughput only covers the processing phase, not scanning.
+    let time_processing_start = Instant::now();
 
     for queued_library in queued_libraries {
         process_library(
             queued_library,
-            &mut global_progress,
+            options,
+            &transcode_journal,
+            &state_writer,
+            &global_progress,
             terminal,
             &mut terminal_user_input,
         )?;
     }
 
+    let time_processing_elapsed = time_processing_start.elapsed().as_secs_f64();
+
+    let global_progress = global_progress.into_inner();
+    global_progress.reason_counts.log_summary(terminal);
+    global_progress.log_throughput_summary(time_processing_elapsed, terminal);
+    global_progress.log_storage_summary(terminal);
+
     let time_full_processing_elapsed =
         time_full_processing_start.elapsed().as_secs_f64();
 
@@ -643,6 +1837,19 @@ pub fn cmd_transcode_all<'config: 'scope, 'scope, 'scope_env: 'scope_env>(
         "All changes successfully processed in {time_full_processing_elapsed:.2} seconds."
     ));
 
+    // In staging mode, this checks the staging directory (i.e. what is about to be swapped in).
+    if configuration.aggregated_library.verify_after_run {
+        verify::verify_transcoded_audio_files(&libraries, terminal)?;
+    }
+
+    // In staging mode, the transcoded library is only updated now that the entire run has succeeded.
+    if let Some(staging_directory) = &options.staging_directory {
+        staging_directory.swap_into_place()?;
+        terminal.log_println("Swapped staging directory into place.");
+    }
+
+    state_writer.into_inner().flush()?;
+
     Ok(())
 }
 
@@ -653,16 +1860,52 @@ pub fn cmd_transcode_all<'config: 'scope, 'scope, 'scope_env: 'scope_env>(
 
 fn collect_libraries_sorted<'config>(
     configuration: &'config Configuration,
+    options: &TranscodeOptions,
     terminal: &TranscodeTerminal<'config, '_>,
 ) -> Result<Vec<SharedLibraryView<'config>>> {
-    // `LibraryView` is the root abstraction here - we use it to discover artists and their albums.
-    let mut libraries = configuration
-        .libraries
-        .values()
-        .map(|library| {
-            LibraryView::from_library_configuration(configuration, library)
+    let selected_library_names = options
+        .only_library
+        .iter()
+        .map(|library_name_selector| {
+            configuration
+                .get_library_by_name_selector(library_name_selector)
+                .map(|library| library.name.as_str())
         })
-        .collect::<Result<Vec<SharedLibraryView>, LibraryViewError>>()?;
+        .collect::<Result<HashSet<&str>>>()?;
+
+    // `LibraryView` is the root abstraction here - we use it to discover artists and their albums.
+    let mut libraries: Vec<SharedLibraryView> =
+        Vec::with_capacity(configuration.libraries.len());
+
+    for library in configuration.libraries.values() {
+        if !selected_library_names.is_empty()
+            && !selected_library_names.contains(library.name.as_str())
+        {
+            continue;
+        }
+
+        // Configuration loading already refuses overlapping library and transcoded library paths,
+        // but scanning a transcoded library as a source would re-transcode its transcodes, so we double-check.
+        if configuration.is_transcoded_library_root(&library.path) {
+            terminal.log_eprintln(format!(
+                "{} library \"{}\" ({}) is a transcoded library, skipping it.",
+                "WARNING:".yellow(),
+                library.name,
+                library.path
+            ));
+            continue;
+        }
+
+        match LibraryView::from_library_configuration(configuration, library) {
+            Ok(library_view) => libraries.push(library_view),
+            Err(LibraryViewError::NoSuchDirectory(path)) if library.optional => {
+                // Optional libraries that are currently unavailable are skipped entirely - this also
+                // means their library state isn't loaded, so their transcoded albums are left untouched.
+                log_optional_library_unavailable(terminal, &library.name, &path);
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
 
     libraries.sort_unstable_by_key(|library| library.read().name());
 
@@ -701,6 +1944,8 @@ fn collect_full_library_states<'config>(
                             .read()
                             .directory_path_relative_to_library_root();
 
+                        let album_year = album_view.read().album_year();
+
                         tracked_albums.push(TrackedAlbum {
                             album_title,
                             album_source_relative_path: dunce::simplified(
@@ -708,6 +1953,7 @@ fn collect_full_library_states<'config>(
                             )
                             .to_string_lossy()
                             .to_string(),
+                            album_year,
                         })
                     }
 
@@ -763,26 +2009,380 @@ pub struct LibraryWithChanges<'view> {
     pub fully_removed_artists: Vec<SharedArtistView<'view>>,
 }
 
+/// Returned by `collect_changes`.
+pub struct CollectedChanges<'view> {
+    /// Libraries with at least one change, sorted by name.
+    pub sorted_libraries_with_changes: Vec<LibraryWithChanges<'view>>,
+
+    /// The number of albums that were skipped because they could not be scanned
+    /// (see `UnscannableAlbum`). These were already reported by `collect_changes`.
+    pub num_unscannable_albums: usize,
+}
+
+
+/// Returns `false` if the album has been transcoded before and nothing in its source directory
+/// (including the directory itself) has been modified at or after `modified_since`.
+///
+/// This relies on modification times only, so e.g. files replaced with older copies are missed.
+fn album_was_modified_since(
+    album: &AlbumView,
+    modified_since: SystemTime,
+) -> Result<bool> {
+    // Albums that have never been transcoded must always be scanned,
+    // no matter how old their files are.
+    if !SourceAlbumState::get_state_file_path_for_directory(
+        album.album_directory_in_source_library(),
+    )
+    .is_file()
+    {
+        return Ok(true);
+    }
+
+    let modified_since_seconds = modified_since
+        .duration_since(UNIX_EPOCH)
+        .into_diagnostic()?
+        .as_secs_f64();
+
+    Ok(album.newest_source_time_modified()? >= modified_since_seconds)
+}
+
+/// Increase the number of scanned albums (shared between all scanning threads, see `collect_changes`)
+/// and update the scanning progress.
+fn mark_album_scanned(
+    num_albums_scanned: &AtomicUsize,
+    terminal: &TranscodeTerminal,
+) -> Result<()> {
+    let num_albums_scanned =
+        num_albums_scanned.fetch_add(1, Ordering::SeqCst) + 1;
+    terminal.progress_set_scan_albums_scanned(num_albums_scanned)
+}
 
 fn collect_artist_changes<'config>(
-    artist: SharedArtistView<'config>,
-    saved_tracked_album_list: Option<&TrackedArtistAlbums>,
-    fresh_tracked_album_list: &TrackedArtistAlbums,
+    artist_to_scan: ArtistToScan<'config, '_>,
+    options: &TranscodeOptions,
+    scan_cache: Option<&Mutex<LibraryScanCache>>,
+    num_albums_scanned: &AtomicUsize,
+    unscannable_albums: &Mutex<Vec<UnscannableAlbum>>,
     terminal: &TranscodeTerminal<'config, '_>,
 ) -> Result<Option<ArtistWithChanges<'config>>> {
+    let ArtistToScan {
+        artist,
+        saved_artist_album_list: saved_tracked_album_list,
+        fresh_artist_album_list: fresh_tracked_album_list,
+    } = artist_to_scan;
+
     let artist_locked = artist.read();
 
-    let mut changed_albums: Vec<ChangedAlbum> = artist_locked
-        .scan_for_albums_with_changes()?
-        .into_iter()
-        .map(
-            |(album_title, (album_view, album_changes))| ChangedAlbum {
+    // This is `ArtistView::scan_for_albums_with_changes`, except that we also need to look at
+    // albums without changes to warn about any skipped broken symbolic links.
+    let mut changed_albums: Vec<ChangedAlbum> = Vec::new();
+
+    for (album_title, album_view) in artist_locked.albums()? {
+        // Nothing listens for `UserControlMessage::Exit` until processing starts,
+        // so a termination signal has to be checked for here.
+        signals::check_for_termination()?;
+
+        // Skipped albums remain in the library state (so they aren't treated as removed),
+        // but are never scanned or transcoded.
+        if album_view.read().configuration.skip_transcode {
+            if is_verbose_enabled() {
+                terminal.log_println(format!(
+                    "Album \"{} - {}\" has skip_transcode set, skipping it.",
+                    artist_locked.name, album_title,
+                ));
+            }
+
+            mark_album_scanned(num_albums_scanned, terminal)?;
+            continue;
+        }
+
+        if let Some(modified_since) = options.modified_since {
+            let modified_result =
+                album_was_modified_since(&album_view.read(), modified_since);
+            let Some(was_modified) = skip_if_unscannable(
+                modified_result,
+                &album_view.read(),
+                options,
+                unscannable_albums,
+            )?
+            else {
+                mark_album_scanned(num_albums_scanned, terminal)?;
+                continue;
+            };
+
+            if !was_modified {
+                if is_verbose_enabled() {
+                    terminal.log_println(format!(
+                        "Album \"{} - {}\" has not been modified since the --since cutoff, skipping it.",
+                        artist_locked.name, album_title,
+                    ));
+                }
+
+                mark_album_scanned(num_albums_scanned, terminal)?;
+                continue;
+            }
+        }
+
+        // Albums whose directories haven't changed since the previous run don't need a full scan.
+        let fresh_album_scan = match &scan_cache {
+            Some(_) => {
+                let snapshot_result =
+                    CachedAlbumScan::from_album_view(&album_view.read());
+                let Some(fresh_album_scan) = skip_if_unscannable(
+                    snapshot_result,
+                    &album_view.read(),
+                    options,
+                    unscannable_albums,
+                )?
+                else {
+                    mark_album_scanned(num_albums_scanned, terminal)?;
+                    continue;
+                };
+
+                fresh_album_scan
+            }
+            None => None,
+        };
+
+        if let (Some(scan_cache), Some(fresh_album_scan)) =
+            (scan_cache, &fresh_album_scan)
+        {
+            let album_locked = album_view.read();
+            let mut scan_cache_locked = scan_cache.lock();
+
+            if scan_cache_locked.is_unchanged(&album_locked, fresh_album_scan) {
+                scan_cache_locked
+                    .insert_unchanged(&album_locked, fresh_album_scan.clone());
+
+                drop(scan_cache_locked);
+                mark_album_scanned(num_albums_scanned, terminal)?;
+                continue;
+            }
+        }
+
+        let scan_result =
+            album_view.read().scan_for_changes(options.export_mode);
+        let Some(mut album_changes) = skip_if_unscannable(
+            scan_result,
+            &album_view.read(),
+            options,
+            unscannable_albums,
+        )?
+        else {
+            mark_album_scanned(num_albums_scanned, terminal)?;
+            continue;
+        };
+
+        if let (Some(scan_cache), Some(fresh_album_scan)) =
+            (scan_cache, fresh_album_scan)
+        {
+            if !album_changes.has_changes() {
+                scan_cache
+                    .lock()
+                    .insert_unchanged(&album_view.read(), fresh_album_scan);
+            }
+        }
+
+        // The album is unchanged, but its saved directory times were outdated (see `scan_skip_strategy`).
+        if let Some(refreshed_source_state) = album_changes
+            .source_state_with_refreshed_directory_times
+            .take()
+        {
+            if !options.dry_run {
+                refreshed_source_state.save_to_directory(
+                    album_view.read().album_directory_in_source_library(),
+                    true,
+                )?;
+            }
+        }
+
+        if options.only_new {
+            album_changes.retain_only_new_files();
+        }
+
+        // Files that were completed before the previous run was interrupted don't need
+        // to be processed again (export mode ignores any saved progress, like it does saved state).
+        if !options.export_mode {
+            let album_progress = AlbumTranscodeProgress::load_from_directory(
+                album_view.read().album_directory_in_transcoded_library(),
+            )?;
+
+            if let Some(album_progress) = album_progress {
+                let retain_result = album_changes
+                    .retain_files_not_completed_in_previous_run(&album_progress);
+                let Some(num_completed_files) = skip_if_unscannable(
+                    retain_result,
+                    &album_view.read(),
+                    options,
+                    unscannable_albums,
+                )?
+                else {
+                    mark_album_scanned(num_albums_scanned, terminal)?;
+                    continue;
+                };
+
+                if num_completed_files > 0 {
+                    terminal.log_println(format!(
+                        "Album \"{} - {}\" was interrupted in the previous run, \
+                        skipping its {} already completed files.",
+                        artist_locked.name, album_title, num_completed_files,
+                    ));
+                }
+            }
+        }
+
+        for (state_file_path, schema_version) in
+            &album_changes.unmigratable_saved_states
+        {
+            terminal.log_eprintln(format!(
+                "{} saved state {:?} of album \"{} - {}\" has schema version {} \
+                and can't be migrated, the album will be processed as if it was never transcoded.",
+                "WARNING:".yellow(),
+                state_file_path,
+                artist_locked.name,
+                album_title,
+                schema_version,
+            ));
+        }
+
+        if let Some(tracked_source_files) = &album_changes.tracked_source_files {
+            for broken_symlink in &tracked_source_files.broken_symlinks {
+                terminal.log_eprintln(format!(
+                    "{} {:?} in album \"{} - {}\" is a broken symbolic link, skipping it.",
+                    "WARNING:".yellow(),
+                    broken_symlink,
+                    artist_locked.name,
+                    album_title,
+                ));
+            }
+
+            if tracked_source_files.audio_files.is_empty() {
+                let contents_description =
+                    if tracked_source_files.data_files.is_empty() {
+                        "has no tracked files at all".to_string()
+                    } else {
+                        format!(
+                            "has no audio files (only {} data files)",
+                            tracked_source_files.data_files.len()
+                        )
+                    };
+
+                terminal.log_eprintln(format!(
+                    "{} album \"{} - {}\" {}, nothing will be transcoded.",
+                    "WARNING:".yellow(),
+                    artist_locked.name,
+                    album_title,
+                    contents_description,
+                ));
+            }
+        }
+
+        if album_changes.has_changes() {
+            changed_albums.push(ChangedAlbum {
                 album: album_view,
                 album_title,
                 changes: album_changes,
-            },
-        )
-        .collect::<Vec<ChangedAlbum>>();
+            });
+        }
+
+        mark_album_scanned(num_albums_scanned, terminal)?;
+    }
+
+    // Refuse to process albums whose transcoded directory overlaps with the source directory,
+    // otherwise we could end up overwriting the source files.
+    changed_albums.retain(|changed_album| {
+        let album_locked = changed_album.album.read();
+
+        if !album_locked.has_overlapping_source_and_transcoded_directories() {
+            return true;
+        }
+
+        terminal.log_eprintln(format!(
+            "{} album \"{} - {}\" has overlapping source ({:?}) and transcoded ({:?}) directories, \
+            refusing to process it (check your library and aggregated library paths).",
+            "ERROR:".red(),
+            artist_locked.name,
+            changed_album.album_title,
+            album_locked.album_directory_in_source_library(),
+            album_locked.album_directory_in_transcoded_library(),
+        ));
+
+        false
+    });
+
+    // Refuse to process albums with files whose transcoded paths only differ in case, as they would
+    // silently overwrite each other on case-insensitive filesystems (e.g. `Cover.jpg` and `cover.jpg`).
+    // The same goes for files whose names are sanitized into the same name (see `filename_sanitization`).
+    changed_albums.retain(|changed_album| {
+        let Some(tracked_source_files) =
+            &changed_album.changes.tracked_source_files
+        else {
+            return true;
+        };
+
+        let sanitization_collisions =
+            tracked_source_files.find_sanitization_collisions();
+
+        if !sanitization_collisions.is_empty() {
+            for (first_colliding_file, second_colliding_file) in
+                &sanitization_collisions
+            {
+                terminal.log_eprintln(format!(
+                    "{} files {:?} and {:?} in album \"{} - {}\" have the same name after sanitization \
+                    (see aggregated_library.filename_sanitization) and would overwrite each other, \
+                    refusing to process the album (rename one of them).",
+                    "ERROR:".red(),
+                    first_colliding_file,
+                    second_colliding_file,
+                    artist_locked.name,
+                    changed_album.album_title,
+                ));
+            }
+
+            return false;
+        }
+
+        let case_insensitive_collisions =
+            tracked_source_files.find_case_insensitive_collisions();
+
+        for (first_colliding_file, second_colliding_file) in
+            &case_insensitive_collisions
+        {
+            terminal.log_eprintln(format!(
+                "{} files {:?} and {:?} in album \"{} - {}\" only differ in letter case \
+                and would overwrite each other on case-insensitive filesystems, refusing to process the album \
+                (rename one of them).",
+                "ERROR:".red(),
+                first_colliding_file,
+                second_colliding_file,
+                artist_locked.name,
+                changed_album.album_title,
+            ));
+        }
+
+        case_insensitive_collisions.is_empty()
+    });
+
+    // Renaming (see `rename_audio_files_pattern`) might map several files to the same name,
+    // in which case the colliding files keep their original names.
+    for changed_album in &changed_albums {
+        let Some(tracked_source_files) =
+            &changed_album.changes.tracked_source_files
+        else {
+            continue;
+        };
+
+        for colliding_file in tracked_source_files.find_renaming_collisions() {
+            terminal.log_eprintln(format!(
+                "{} renamed file name of {:?} in album \"{} - {}\" collides with another file, \
+                keeping its original name.",
+                "WARNING:".yellow(),
+                colliding_file,
+                artist_locked.name,
+                changed_album.album_title,
+            ));
+        }
+    }
 
     if is_verbose_enabled() {
         terminal.log_println(format!(
@@ -799,6 +2399,10 @@ fn collect_artist_changes<'config>(
         ));
     }
 
+    // With `--only-new`, transcodes of removed albums are left alone.
+    let saved_tracked_album_list =
+        saved_tracked_album_list.filter(|_| !options.only_new);
+
     let mut removed_albums = if let Some(saved_album_list) =
         saved_tracked_album_list
     {
@@ -807,7 +2411,36 @@ fn collect_artist_changes<'config>(
         let fresh_album_set: HashSet<&TrackedAlbum> =
             HashSet::from_iter(fresh_tracked_album_list.tracked_albums.iter());
 
-        let fully_removed_album_set = saved_album_set.sub(&fresh_album_set);
+        // Albums are matched by the transcoded directory they map to. With `normalize_whitespace`,
+        // an album whose directory name only changed in whitespace maps to the same transcoded directory,
+        // so it must not be treated as removed. On the other hand, an album whose `year` changed
+        // (with `album_directory_template`) moves to a new directory and the old one must be removed.
+        let aggregated_library = &artist_locked
+            .read_lock_library()
+            .euphony_configuration
+            .aggregated_library;
+        let transcoded_album_directory_name = |album: &TrackedAlbum| {
+            aggregated_library.album_directory_name(
+                &artist_locked.name,
+                &album.album_title,
+                album.album_year,
+            )
+        };
+
+        let fresh_transcoded_album_directory_names: HashSet<String> =
+            fresh_album_set
+                .iter()
+                .map(|album| transcoded_album_directory_name(album))
+                .collect();
+
+        let fully_removed_album_set: HashSet<&TrackedAlbum> = saved_album_set
+            .sub(&fresh_album_set)
+            .into_iter()
+            .filter(|album| {
+                !fresh_transcoded_album_directory_names
+                    .contains(&transcoded_album_directory_name(album))
+            })
+            .collect();
 
         if is_verbose_enabled() && !fully_removed_album_set.is_empty() {
             terminal.log_println(format!(
@@ -819,11 +2452,7 @@ fn collect_artist_changes<'config>(
         fully_removed_album_set
             .into_iter()
             .filter_map(|album| {
-                let album_view = match AlbumView::new(
-                    artist.clone(),
-                    album.album_title.clone(),
-                    true,
-                ) {
+                let album_view = match album.album_view(artist.clone()) {
                     Ok(view) => view,
                     Err(error) => return Some(Err(error))
                 };
@@ -835,7 +2464,6 @@ fn collect_artist_changes<'config>(
 
                 let changes = match AlbumFileChangesV2::generate_entire_transcoded_album_deletion(
                     album_view,
-                    &album.album_source_relative_path
                 ) {
                     Ok(changes) => changes,
                     Err(error) => return Some(Err(error))
@@ -870,13 +2498,154 @@ fn collect_artist_changes<'config>(
     }
 }
 
+/// An album that was skipped while scanning for changes, because one of its files or directories
+/// could not be accessed (an I/O or permission error, see `FileAccessError`). Without `--strict`,
+/// such albums are collected and reported once scanning is done instead of aborting the entire scan.
+/// Any other error (e.g. a saved state file that can't be parsed) still aborts the scan.
+///
+/// The entire album is skipped (instead of just the unreadable files), since files missing
+/// from the scan would otherwise look like they were removed, deleting their transcodes.
+struct UnscannableAlbum {
+    album_directory_path: PathBuf,
+
+    /// Why the album could not be scanned (the entire error chain).
+    reason: String,
+}
+
+/// Unless `--strict` is set, record the album in `unscannable_albums` if the given scan `result`
+/// is an error caused by a `FileAccessError` and return `Ok(None)`, so the album can be skipped.
+/// All other errors are returned as-is.
+fn skip_if_unscannable<T>(
+    result: Result<T>,
+    album_view: &AlbumView,
+    options: &TranscodeOptions,
+    unscannable_albums: &Mutex<Vec<UnscannableAlbum>>,
+) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(error)
+            if !options.strict && FileAccessError::is_cause_of(&error) =>
+        {
+            unscannable_albums.lock().push(UnscannableAlbum {
+                album_directory_path: album_view
+                    .album_directory_in_source_library(),
+                reason: error
+                    .chain()
+                    .map(|cause| cause.to_string())
+                    .collect::<Vec<String>>()
+                    .join(": "),
+            });
+
+            Ok(None)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// An artist waiting to be scanned by `scan_artists_in_parallel`,
+/// along with its saved and fresh album lists (see `collect_artist_changes`).
+struct ArtistToScan<'config, 'state> {
+    artist: SharedArtistView<'config>,
+
+    saved_artist_album_list: Option<&'state TrackedArtistAlbums>,
+
+    fresh_artist_album_list: &'state TrackedArtistAlbums,
+}
+
+/// Run `collect_artist_changes` on each of the given artists (of a single library)
+/// using up to `scan_threads` threads, each of which takes the next artist as soon as it is done
+/// with its current one. After the first error, the threads don't start scanning any new artists
+/// (albums that can't be scanned are only errors with `--strict`, see `UnscannableAlbum`).
+///
+/// The order of the returned artists is unspecified, the caller is expected to sort them.
+///
+/// Scanning only ever takes read locks on the shared library, artist and album views,
+/// so several threads can safely scan artists of the same library at once.
+fn scan_artists_in_parallel<'config>(
+    artists_to_scan: Vec<ArtistToScan<'config, '_>>,
+    scan_threads: usize,
+    options: &TranscodeOptions,
+    scan_cache: Option<&Mutex<LibraryScanCache>>,
+    num_albums_scanned: &AtomicUsize,
+    unscannable_albums: &Mutex<Vec<UnscannableAlbum>>,
+    terminal: &TranscodeTerminal<'config, '_>,
+) -> Result<Vec<ArtistWithChanges<'config>>> {
+    let num_threads = scan_threads.min(artists_to_scan.len()).max(1);
+
+    let artist_queue = Mutex::new(artists_to_scan.into_iter());
+    let artists_with_changes: Mutex<Vec<ArtistWithChanges>> =
+        Mutex::new(Vec::new());
+    let stop_scanning = AtomicBool::new(false);
+
+    thread::scope::<'_, _, Result<()>>(|scope| {
+        let artist_queue = &artist_queue;
+        let artists_with_changes = &artists_with_changes;
+        let stop_scanning = &stop_scanning;
+
+        let worker_handles = (0..num_threads)
+            .map(|_| {
+                scope.spawn(move || -> Result<()> {
+                    while !stop_scanning.load(Ordering::SeqCst) {
+                        let Some(artist_to_scan) = artist_queue.lock().next()
+                        else {
+                            break;
+                        };
+
+                        let changes = collect_artist_changes(
+                            artist_to_scan,
+                            options,
+                            scan_cache,
+                            num_albums_scanned,
+                            unscannable_albums,
+                            terminal,
+                        );
+
+                        match changes {
+                            Ok(Some(changes)) => {
+                                artists_with_changes.lock().push(changes)
+                            }
+                            Ok(None) => {}
+                            Err(error) => {
+                                stop_scanning.store(true, Ordering::SeqCst);
+                                return Err(error);
+                            }
+                        }
+                    }
+
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Wait for all workers to finish and return the first error, if any.
+        let mut first_error: Option<miette::Report> = None;
+
+        for worker_handle in worker_handles {
+            let worker_result =
+                worker_handle.join().expect("Artist scan thread panicked.");
+
+            if let Err(error) = worker_result {
+                first_error.get_or_insert(error);
+            }
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    })?;
+
+    Ok(artists_with_changes.into_inner())
+}
+
 fn collect_changes<'config>(
     sorted_libraries_with_fresh_states: &Vec<(
         SharedLibraryView<'config>,
         LibraryState,
     )>,
+    options: &TranscodeOptions,
     terminal: &TranscodeTerminal<'config, '_>,
-) -> Result<Vec<LibraryWithChanges<'config>>> {
+) -> Result<CollectedChanges<'config>> {
     // We perform a scan on each library: for each artist in the library, we scan each
     // of their albums for changes (this includes untranscoded albums in addition to
     // albums changed since last transcode).
@@ -887,6 +2656,18 @@ fn collect_changes<'config>(
     let mut libraries_with_changes: Vec<LibraryWithChanges> =
         Vec::with_capacity(sorted_libraries_with_fresh_states.len());
 
+    // The fresh library states already list every album, so the total is known before scanning.
+    let num_total_albums = sorted_libraries_with_fresh_states
+        .iter()
+        .flat_map(|(_, library_state)| library_state.tracked_artists.values())
+        .map(|artist| artist.tracked_albums.len())
+        .sum::<usize>();
+
+    terminal.progress_set_scan_total(num_total_albums);
+    let num_albums_scanned = AtomicUsize::new(0);
+    let unscannable_albums: Mutex<Vec<UnscannableAlbum>> =
+        Mutex::new(Vec::new());
+
     for (library_view, fresh_tracked_artist_album_list) in
         sorted_libraries_with_fresh_states
     {
@@ -899,17 +2680,32 @@ fn collect_changes<'config>(
             ));
         }
 
-        let saved_tracked_artist_album_list =
+        // In export mode, we act as if nothing was ever transcoded (so nothing is ever removed either).
+        let saved_tracked_artist_album_list = if options.export_mode {
+            None
+        } else {
             match LibraryState::load_from_directory(
                 library.root_directory_in_source_library(),
             ) {
                 Ok(state) => Some(state),
                 Err(error) => match error {
                     LibraryStateLoadError::NotFound => None,
-                    LibraryStateLoadError::SchemaVersionMismatch(_) => None,
+                    LibraryStateLoadError::SchemaVersionMismatch(
+                        schema_version,
+                    ) => {
+                        terminal.log_eprintln(format!(
+                            "{} saved library state of library \"{}\" has schema version {} \
+                            and can't be migrated, removed artists and albums won't be detected in this run.",
+                            "WARNING:".yellow(),
+                            library.name(),
+                            schema_version,
+                        ));
+                        None
+                    }
                     _ => return Err(error.into()),
                 },
-            };
+            }
+        };
 
         if is_verbose_enabled() {
             terminal.log_println(format!(
@@ -929,22 +2725,58 @@ fn collect_changes<'config>(
                 HashSet::new()
             };
 
-        let mut artists_with_changes: Vec<ArtistWithChanges> = Vec::new();
+        let aggregated_library =
+            &library.euphony_configuration.aggregated_library;
+
+        // The scan cache relies on modification times only, so it is not used when changes
+        // are detected by other means (tags or content hashes). In export and staging mode,
+        // the saved snapshots never match anyway.
+        let use_scan_cache = !options.no_scan_cache
+            && !options.export_mode
+            && options.staging_directory.is_none()
+            && !aggregated_library.retranscode_on_tag_change
+            && library.library_configuration.transcoding.change_detection
+                == ChangeDetection::Mtime;
+
+        let scan_cache = if use_scan_cache {
+            Some(Mutex::new(LibraryScanCache::load_from_directory(
+                library.root_directory_in_source_library(),
+                library.euphony_configuration,
+            )?))
+        } else {
+            None
+        };
+
+        // With `normalize_whitespace`, artists are matched against the saved state
+        // by their normalized names (see `normalized_directory_name`).
+        let mut present_normalized_artist_names: HashSet<String> =
+            HashSet::new();
+
+        let mut artists_to_scan: Vec<ArtistToScan> = Vec::new();
         for (artist_name, artist_view) in library.artists()? {
+            let normalized_artist_name =
+                aggregated_library.normalized_directory_name(&artist_name);
+
             let saved_artist_album_list = match &saved_tracked_artist_album_list
             {
                 Some(saved_state) => {
-                    match saved_state.tracked_artists.get(&artist_name) {
-                        Some(album_list) => {
-                            remaining_saved_tracked_artists.remove(&artist_name);
-                            Some(album_list)
-                        }
-                        None => None,
-                    }
+                    saved_state.tracked_artists.get(&artist_name).or_else(|| {
+                        saved_state
+                            .tracked_artists
+                            .iter()
+                            .find(|(saved_artist_name, _)| {
+                                aggregated_library
+                                    .normalized_directory_name(saved_artist_name)
+                                    == normalized_artist_name
+                            })
+                            .map(|(_, album_list)| album_list)
+                    })
                 }
                 None => None,
             };
 
+            present_normalized_artist_names.insert(normalized_artist_name);
+
             let fresh_artist_album_list = fresh_tracked_artist_album_list
                 .tracked_artists
                 .get(&artist_name)
@@ -955,20 +2787,45 @@ fn collect_changes<'config>(
                     )
                 })?;
 
-            let changes = collect_artist_changes(
-                artist_view.clone(),
+            artists_to_scan.push(ArtistToScan {
+                artist: artist_view,
                 saved_artist_album_list,
                 fresh_artist_album_list,
-                terminal,
-            )?;
+            });
+        }
+
+        let mut artists_with_changes = scan_artists_in_parallel(
+            artists_to_scan,
+            aggregated_library.scan_threads,
+            options,
+            scan_cache.as_ref(),
+            &num_albums_scanned,
+            &unscannable_albums,
+            terminal,
+        )?;
 
-            if let Some(changes) = changes {
-                artists_with_changes.push(changes);
+        // The cache only contains albums without changes, so it can be saved right away.
+        if let Some(scan_cache) = &scan_cache {
+            if !options.dry_run {
+                scan_cache.lock().save_to_directory(
+                    library.root_directory_in_source_library(),
+                )?;
             }
         }
 
+        remaining_saved_tracked_artists.retain(|saved_artist_name| {
+            !present_normalized_artist_names.contains(
+                &aggregated_library.normalized_directory_name(saved_artist_name),
+            )
+        });
+
         // Any artists left in `remaining_saved_tracked_artists` are those that were entirely removed
-        // since the last transcode, meaning we should remove all transcodes of their albums.
+        // since the last transcode, meaning we should remove all transcodes of their albums
+        // (unless we're only processing new files).
+        if options.only_new {
+            remaining_saved_tracked_artists.clear();
+        }
+
         let mut fully_removed_artists: Vec<SharedArtistView> =
             Vec::with_capacity(remaining_saved_tracked_artists.len());
 
@@ -994,15 +2851,10 @@ fn collect_changes<'config>(
                 .tracked_albums
                 .iter()
                 .map(|album| {
-                    let album_view = AlbumView::new(
-                        artist_view.clone(),
-                        album.album_title.clone(),
-                        true,
-                    )?;
+                    let album_view = album.album_view(artist_view.clone())?;
 
                     let album_changes = AlbumFileChangesV2::generate_entire_transcoded_album_deletion(
                         album_view,
-                        &album.album_source_relative_path
                     )?;
 
                     Ok(FullyRemovedAlbum {
@@ -1040,22 +2892,60 @@ fn collect_changes<'config>(
 
         if !artists_with_changes.is_empty() || !fully_removed_artists.is_empty()
         {
+            let mut fresh_artist_album_list_state =
+                fresh_tracked_artist_album_list.clone();
+
+            // Removed artists and albums were skipped, so we keep tracking them
+            // in order for the next (full) transcode to still remove them.
+            if let (true, Some(saved_tracked_artist_album_list)) =
+                (options.only_new, &saved_tracked_artist_album_list)
+            {
+                fresh_artist_album_list_state.retain_removed_entries_from(
+                    saved_tracked_artist_album_list,
+                );
+            }
+
             libraries_with_changes.push(LibraryWithChanges {
                 library: library_view.clone(),
                 library_name: library.name(),
-                fresh_artist_album_list_state: fresh_tracked_artist_album_list
-                    .clone(),
+                fresh_artist_album_list_state,
                 sorted_changed_artists: artists_with_changes,
                 fully_removed_artists,
             })
         }
     }
 
+    terminal.progress_set_scan_finished();
+
+    let mut unscannable_albums = unscannable_albums.into_inner();
+    if !unscannable_albums.is_empty() {
+        unscannable_albums.sort_unstable_by(|first, second| {
+            first.album_directory_path.cmp(&second.album_directory_path)
+        });
+
+        terminal.log_eprintln(format!(
+            "{} {} albums could not be scanned (because some of their files or directories can't be accessed) \
+            and were skipped in this run (pass --strict to abort instead):",
+            "WARNING:".yellow(),
+            unscannable_albums.len()
+        ));
+
+        for unscannable_album in &unscannable_albums {
+            terminal.log_eprintln(format!(
+                "  {:?}: {}",
+                unscannable_album.album_directory_path, unscannable_album.reason
+            ));
+        }
+    }
+
     libraries_with_changes.sort_unstable_by(|first, second| {
         first.library_name.cmp(&second.library_name)
     });
 
-    Ok(libraries_with_changes)
+    Ok(CollectedChanges {
+        sorted_libraries_with_changes: libraries_with_changes,
+        num_unscannable_albums: unscannable_albums.len(),
+    })
 }
 
 
@@ -1083,6 +2973,10 @@ pub struct QueuedLibrary<'view> {
     pub queued_albums: Vec<QueuedAlbum<'view>>,
 
     pub fully_removed_artists: Vec<SharedArtistView<'view>>,
+
+    /// `false` if some of the library's changed albums were left out of this run (see `--limit`),
+    /// in which case the library state must not be saved (the next run has to see the rest as changed).
+    pub is_complete: bool,
 }
 
 
@@ -1154,12 +3048,48 @@ fn queue_all_changed_albums<'config: 'scope, 'scope>(
                 .fresh_artist_album_list_state,
             queued_albums,
             fully_removed_artists: changed_library.fully_removed_artists,
+            is_complete: true,
         });
     }
 
     Ok(queued_libraries)
 }
 
+/// Keep only the first `album_limit` queued albums (`--limit`), in the order they were queued
+/// (libraries, artists and albums are all sorted by name), and remove the rest from the album queue.
+///
+/// Libraries left without any queued albums are dropped entirely, while libraries that only kept some
+/// of their albums are marked as incomplete, so their library state isn't saved (see `process_library`).
+/// The album states of albums that were left out are never touched, so the next run picks them up again.
+fn limit_queued_albums<'config: 'scope, 'scope>(
+    queued_libraries: Vec<QueuedLibrary<'config>>,
+    album_limit: usize,
+    terminal: &TranscodeTerminal<'config, 'scope>,
+) -> Result<Vec<QueuedLibrary<'config>>> {
+    let mut num_remaining_albums = album_limit;
+    let mut limited_queued_libraries: Vec<QueuedLibrary> =
+        Vec::with_capacity(queued_libraries.len());
+
+    for mut queued_library in queued_libraries {
+        let num_kept_albums =
+            queued_library.queued_albums.len().min(num_remaining_albums);
+        num_remaining_albums -= num_kept_albums;
+
+        for left_out_album in
+            queued_library.queued_albums.drain(num_kept_albums..)
+        {
+            terminal.queue_album_item_remove(left_out_album.queue_id)?;
+            queued_library.is_complete = false;
+        }
+
+        if !queued_library.queued_albums.is_empty() {
+            limited_queued_libraries.push(queued_library);
+        }
+    }
+
+    Ok(limited_queued_libraries)
+}
+
 
 /// A message type to send from the main processing thread to `process_album_changes`.
 /// Currently the only possible message is for the worker threads to stop.
@@ -1169,6 +3099,8 @@ enum MainThreadMessage {
 
 /// Process an entire album (given its `AlbumFileChangesV2`).
 ///
+/// `transcode_threads_override` is the thread count set with `--jobs` (if any).
+///
 /// `worker_progress_sender` is the `Sender` part of a channel that individual file workers
 /// can use to send `FileJobMessage`s back to the main thread.
 ///
@@ -1179,23 +3111,29 @@ enum MainThreadMessage {
 fn process_changes<'config>(
     album_changes: &AlbumFileChangesV2,
     album: SharedAlbumView<'config>,
+    transcode_threads_override: Option<usize>,
     terminal: &TranscodeTerminal<'config, '_>,
     worker_progress_sender: Sender<FileJobMessage>,
     main_thread_receiver: Receiver<MainThreadMessage>,
 ) -> Result<()> {
-    let thread_pool_size = {
+    let (transcode_threads, delete_threads) = {
         let album_locked = album.read();
+        let aggregated_library_configuration =
+            &album_locked.euphony_configuration().aggregated_library;
 
-        album_locked
-            .euphony_configuration()
-            .aggregated_library
-            .transcode_threads
+        // `--jobs` takes precedence over everything, then the library's own `transcode_threads` (if set)
+        // takes precedence over the global one.
+        (
+            transcode_threads_override
+                .or(album_locked
+                    .library_configuration()
+                    .transcoding
+                    .transcode_threads)
+                .unwrap_or(aggregated_library_configuration.transcode_threads),
+            aggregated_library_configuration.delete_threads,
+        )
     };
 
-    let mut thread_pool =
-        CancellableThreadPool::new(thread_pool_size, worker_progress_sender);
-    thread_pool.start()?;
-
     if is_verbose_enabled() {
         terminal.log_println(format!(
             "absolute_source_file_paths_to_transcoded_file_paths_map={:?}",
@@ -1231,12 +3169,71 @@ fn process_changes<'config>(
         Ok(queued_file_item_id)
     })?;
 
+    let GeneratedFileJobs {
+        processing_jobs,
+        deletion_jobs,
+    } = jobs;
+
+    match delete_threads {
+        Some(delete_threads) => {
+            // Deletions have their own concurrency setting, so they run as a separate phase
+            // (before any transcoding and copying).
+            let was_cancelled = run_jobs_in_thread_pool(
+                deletion_jobs,
+                delete_threads,
+                worker_progress_sender.clone(),
+                &main_thread_receiver,
+            )?;
+
+            if was_cancelled {
+                return Ok(());
+            }
+
+            run_jobs_in_thread_pool(
+                processing_jobs,
+                transcode_threads,
+                worker_progress_sender,
+                &main_thread_receiver,
+            )?;
+        }
+        None => {
+            let all_jobs = processing_jobs.into_iter().chain(deletion_jobs);
+
+            run_jobs_in_thread_pool(
+                all_jobs,
+                transcode_threads,
+                worker_progress_sender,
+                &main_thread_receiver,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the given jobs in a new thread pool of the given size and wait for them to finish.
+///
+/// Returns `true` if the main thread requested cancellation before all jobs finished.
+fn run_jobs_in_thread_pool<
+    I: IntoIterator<Item = CancellableTask<FileJobMessage>>,
+>(
+    jobs: I,
+    thread_pool_size: usize,
+    worker_progress_sender: Sender<FileJobMessage>,
+    main_thread_receiver: &Receiver<MainThreadMessage>,
+) -> Result<bool> {
+    let mut thread_pool =
+        CancellableThreadPool::new(thread_pool_size, worker_progress_sender);
+    thread_pool.start()?;
+
     // Could flatten this into `generate_file_jobs`, but this is cleaner.
     for job in jobs {
         // This does not block! The thread pool has an internal job queue.
         thread_pool.queue_task(job);
     }
 
+    let mut was_cancelled = false;
+
     // All jobs have been queued, now we wait for tasks to complete.
     while thread_pool.has_tasks_left() && thread_pool.is_running() {
         // Keep checking for a user exit message.
@@ -1248,6 +3245,7 @@ fn process_changes<'config>(
                 MainThreadMessage::StopProcessing => {
                     // Any exit from this while loop will mean the
                     // cancellation flag will be set to true, so a break is enough.
+                    was_cancelled = true;
                     break;
                 }
             },
@@ -1266,5 +3264,5 @@ fn process_changes<'config>(
         .set_cancellation_and_join()
         .wrap_err_with(|| miette!("Thread pool exited abnormally."))?;
 
-    Ok(())
+    Ok(was_cancelled)
 }
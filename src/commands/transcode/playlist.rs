@@ -0,0 +1,85 @@
+use std::fs;
+
+use euphony_library::state::transcoded::TranscodedAlbumState;
+use euphony_library::view::AlbumView;
+use miette::{miette, Context, IntoDiagnostic, Result};
+
+use super::jobs::common::remove_existing_target_file;
+
+
+/// Extension of the playlists written when `aggregated_library.generate_playlists` is enabled.
+pub const PLAYLIST_FILE_EXTENSION: &str = "m3u8";
+
+/// Used in place of the album directory name if it can't be determined.
+const FALLBACK_PLAYLIST_FILE_STEM: &str = "playlist";
+
+
+/// Write an `.m3u8` playlist (named after the transcoded album directory) into the transcoded
+/// album directory, listing the transcoded audio files (taken from `transcoded_album_state`)
+/// sorted by their transcoded paths. Albums without audio files don't get a playlist.
+///
+/// Any existing playlist is removed first instead of being overwritten in place
+/// (see `remove_existing_target_file`).
+///
+/// The file name is then recorded in `transcoded_album_state`, so that the file is removed
+/// along with the rest of the transcoded album.
+pub fn write_album_playlist(
+    album: &AlbumView,
+    transcoded_album_state: &mut TranscodedAlbumState,
+) -> Result<()> {
+    let mut transcoded_audio_files = transcoded_album_state
+        .transcoded_to_original_file_paths
+        .audio
+        .keys()
+        .collect::<Vec<_>>();
+
+    if transcoded_audio_files.is_empty() {
+        return Ok(());
+    }
+
+    transcoded_audio_files.sort_unstable();
+
+    let transcoded_album_directory =
+        album.album_directory_in_transcoded_library();
+
+    let playlist_file_name = format!(
+        "{}.{}",
+        transcoded_album_directory
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| FALLBACK_PLAYLIST_FILE_STEM.to_string()),
+        PLAYLIST_FILE_EXTENSION
+    );
+
+    let mut playlist_lines: Vec<String> = vec!["#EXTM3U".to_string()];
+    playlist_lines.extend(transcoded_audio_files.into_iter().cloned());
+
+    let playlist_file_path =
+        transcoded_album_directory.join(&playlist_file_name);
+
+    // The existing playlist may be hard-linked to the live transcoded library (in staging mode).
+    remove_existing_target_file(&playlist_file_path)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!(
+                "Failed to remove existing playlist at {:?}.",
+                playlist_file_path
+            )
+        })?;
+
+    fs::write(
+        &playlist_file_path,
+        playlist_lines.join("\n") + "\n",
+    )
+    .into_diagnostic()
+    .wrap_err_with(|| {
+        miette!(
+            "Failed to write playlist to {:?}.",
+            playlist_file_path
+        )
+    })?;
+
+    transcoded_album_state.playlist_file = Some(playlist_file_name);
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use crossterm::style::Stylize;
+
+use super::state::changes::{
+    CopyProcessingReason,
+    DeleteInTranscodedProcessingReason,
+    FileProcessingAction,
+    TranscodeProcessingReason,
+};
+use crate::console::frontends::TranscodeTerminal;
+use crate::console::LogBackend;
+
+
+/// Number of files per reason (new, changed or missing in the transcoded library).
+#[derive(Default, Clone, Copy)]
+struct SourceReasonCounts {
+    added_in_source: usize,
+    changed_in_source: usize,
+    missing_in_transcoded: usize,
+}
+
+impl SourceReasonCounts {
+    fn total(&self) -> usize {
+        self.added_in_source
+            + self.changed_in_source
+            + self.missing_in_transcoded
+    }
+}
+
+/// Number of successfully processed files of a transcode, per action and reason
+/// (see `FileProcessingAction`). Printed at the end of the transcode to help tell apart
+/// runs caused by actual changes from ones caused by e.g. modification time churn.
+#[derive(Default)]
+pub struct ProcessedFileReasonCounts {
+    transcoded: SourceReasonCounts,
+    copied: SourceReasonCounts,
+    deleted_removed_from_source: usize,
+    deleted_excess_in_transcoded: usize,
+}
+
+impl ProcessedFileReasonCounts {
+    /// Count a single successfully processed file.
+    pub fn record(&mut self, action: &FileProcessingAction) {
+        match action {
+            FileProcessingAction::Transcode { reason, .. } => match reason {
+                TranscodeProcessingReason::AddedInSourceLibrary => {
+                    self.transcoded.added_in_source += 1
+                }
+                TranscodeProcessingReason::ChangedInSourceLibrary => {
+                    self.transcoded.changed_in_source += 1
+                }
+                TranscodeProcessingReason::MissingInTranscodedLibrary => {
+                    self.transcoded.missing_in_transcoded += 1
+                }
+            },
+            FileProcessingAction::Copy { reason, .. } => match reason {
+                CopyProcessingReason::AddedInSourceLibrary => {
+                    self.copied.added_in_source += 1
+                }
+                CopyProcessingReason::ChangedInSourceLibrary => {
+                    self.copied.changed_in_source += 1
+                }
+                CopyProcessingReason::MissingInTranscodedLibrary => {
+                    self.copied.missing_in_transcoded += 1
+                }
+            },
+            FileProcessingAction::DeleteInTranscoded { reason, .. } => {
+                match reason {
+                    DeleteInTranscodedProcessingReason::RemovedFromSourceLibrary => {
+                        self.deleted_removed_from_source += 1
+                    }
+                    DeleteInTranscodedProcessingReason::ExcessInTranscodedLibrary => {
+                        self.deleted_excess_in_transcoded += 1
+                    }
+                }
+            }
+        }
+    }
+
+    /// Log the breakdown of processed files per action and reason.
+    pub fn log_summary(&self, terminal: &TranscodeTerminal) {
+        terminal.log_println("Processed files by reason:".bold());
+
+        for (action_name, counts) in
+            [("transcoded", &self.transcoded), ("copied", &self.copied)]
+        {
+            terminal.log_println(format!(
+                "  {}: {} (new: {}, changed: {}, missing in transcoded library: {})",
+                action_name,
+                counts.total(),
+                counts.added_in_source,
+                counts.changed_in_source,
+                counts.missing_in_transcoded,
+            ));
+        }
+
+        terminal.log_println(format!(
+            "  deleted: {} (removed from source library: {}, excess in transcoded library: {})",
+            self.deleted_removed_from_source + self.deleted_excess_in_transcoded,
+            self.deleted_removed_from_source,
+            self.deleted_excess_in_transcoded,
+        ));
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use euphony_configuration::aggregated_library::ScanSkipStrategy;
+use euphony_configuration::Configuration;
+use euphony_library::state::common::AlbumDirectoryTimes;
+use euphony_library::state::source::SourceAlbumState;
+use euphony_library::state::transcoded::TranscodedAlbumState;
+use euphony_library::view::AlbumView;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::EUPHONY_VERSION;
+
+pub const LIBRARY_SCAN_CACHE_FILE_NAME: &str = ".library.scan-cache.euphony";
+const LIBRARY_SCAN_CACHE_SCHEMA_VERSION: u32 = 1;
+
+
+/// Snapshots of an album's source and transcoded directories, taken before scanning it.
+///
+/// Snapshots always include the newest file modification time and the number of entries
+/// (as with `ScanSkipStrategy::DirectoryAndNewestFileMtime`), regardless of the configured
+/// `aggregated_library.scan_skip_strategy`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CachedAlbumScan {
+    source_album_directory: AlbumDirectoryTimes,
+
+    transcoded_album_directory: AlbumDirectoryTimes,
+}
+
+impl CachedAlbumScan {
+    /// Take snapshots of the album's source and transcoded directories.
+    ///
+    /// Returns `None` if the album hasn't been fully transcoded before
+    /// (i.e. either of its directories or state files is missing), as such albums can't be cached.
+    pub fn from_album_view(album: &AlbumView) -> Result<Option<Self>> {
+        let source_album_directory = album.album_directory_in_source_library();
+        let transcoded_album_directory =
+            album.album_directory_in_transcoded_library();
+
+        if !SourceAlbumState::get_state_file_path_for_directory(
+            &source_album_directory,
+        )
+        .is_file()
+            || !TranscodedAlbumState::get_state_file_path_for_directory(
+                &transcoded_album_directory,
+            )
+            .is_file()
+        {
+            return Ok(None);
+        }
+
+        Self::from_directory_paths(
+            &source_album_directory,
+            &transcoded_album_directory,
+            album.configuration.scan.depth as usize,
+        )
+        .map(Some)
+    }
+
+    /// Take snapshots of the given source and transcoded album directories,
+    /// looking `scan_depth` directories deep (see `scan.depth` in the album configuration).
+    fn from_directory_paths(
+        source_album_directory: &Path,
+        transcoded_album_directory: &Path,
+        scan_depth: usize,
+    ) -> Result<Self> {
+        let take_snapshot = |album_directory: &Path| {
+            AlbumDirectoryTimes::from_directory_path(
+                album_directory,
+                ScanSkipStrategy::DirectoryAndNewestFileMtime,
+                scan_depth,
+            )?
+            .ok_or_else(|| {
+                miette!("BUG: Album directory snapshot was not taken.")
+            })
+        };
+
+        Ok(Self {
+            source_album_directory: take_snapshot(source_album_directory)?,
+            transcoded_album_directory: take_snapshot(
+                transcoded_album_directory,
+            )?,
+        })
+    }
+
+    fn matches(&self, other: &Self) -> bool {
+        self.source_album_directory
+            .matches(&other.source_album_directory)
+            && self
+                .transcoded_album_directory
+                .matches(&other.transcoded_album_directory)
+    }
+}
+
+
+/// Per-library cache of albums that had no changes in the previous run, saved next to
+/// the library state (see `LibraryState`). If an album's directories still match their
+/// snapshots from the previous run, its full change scan can be skipped.
+///
+/// The cache is tied to the exact configuration file contents (and euphony version)
+/// it was created with - any change invalidates it entirely.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LibraryScanCache {
+    schema_version: u32,
+
+    configuration_fingerprint: String,
+
+    /// Maps from the album directory path (relative to the library root) to its snapshots.
+    /// Only albums found to be unchanged in the current run end up here.
+    albums: HashMap<String, CachedAlbumScan>,
+
+    /// Albums cached in the previous run (loaded from disk).
+    #[serde(skip)]
+    previous_albums: HashMap<String, CachedAlbumScan>,
+}
+
+impl LibraryScanCache {
+    /// Create an empty cache for the given configuration.
+    pub fn new(configuration: &Configuration) -> Result<Self> {
+        Ok(Self {
+            schema_version: LIBRARY_SCAN_CACHE_SCHEMA_VERSION,
+            configuration_fingerprint: configuration_fingerprint(configuration)?,
+            albums: HashMap::new(),
+            previous_albums: HashMap::new(),
+        })
+    }
+
+    /// Load the cache of the previous run from the library root directory. As this is only a cache,
+    /// a missing, unreadable or outdated cache file simply results in an empty cache.
+    pub fn load_from_directory<P: AsRef<Path>>(
+        directory_path: P,
+        configuration: &Configuration,
+    ) -> Result<Self> {
+        let empty_cache = Self::new(configuration)?;
+
+        let saved_cache = fs::read_to_string(
+            directory_path.as_ref().join(LIBRARY_SCAN_CACHE_FILE_NAME),
+        )
+        .ok()
+        .and_then(|file_contents| {
+            serde_json::from_str::<Self>(&file_contents).ok()
+        });
+
+        match saved_cache {
+            Some(saved_cache)
+                if saved_cache.schema_version
+                    == LIBRARY_SCAN_CACHE_SCHEMA_VERSION
+                    && saved_cache.configuration_fingerprint
+                        == empty_cache.configuration_fingerprint =>
+            {
+                Ok(Self {
+                    previous_albums: saved_cache.albums,
+                    ..empty_cache
+                })
+            }
+            _ => Ok(empty_cache),
+        }
+    }
+
+    /// Check whether the album's current snapshots match the ones cached in the previous run.
+    pub fn is_unchanged(
+        &self,
+        album: &AlbumView,
+        fresh_album_scan: &CachedAlbumScan,
+    ) -> bool {
+        self.previous_albums
+            .get(&album_cache_key(album))
+            .is_some_and(|cached_album_scan| {
+                cached_album_scan.matches(fresh_album_scan)
+            })
+    }
+
+    /// Record an album that had no changes in this run, so the next run can skip its scan.
+    /// The snapshots must have been taken *before* scanning the album.
+    pub fn insert_unchanged(
+        &mut self,
+        album: &AlbumView,
+        fresh_album_scan: CachedAlbumScan,
+    ) {
+        self.albums.insert(album_cache_key(album), fresh_album_scan);
+    }
+
+    pub fn save_to_directory<P: AsRef<Path>>(
+        &self,
+        output_directory_path: P,
+    ) -> Result<()> {
+        let output_file_path = output_directory_path
+            .as_ref()
+            .join(LIBRARY_SCAN_CACHE_FILE_NAME);
+
+        let serialized_cache = serde_json::to_string(self)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!("Could not serialize library scan cache to string.")
+            })?;
+
+        fs::write(&output_file_path, serialized_cache)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not write library scan cache to {:?}.",
+                    output_file_path
+                )
+            })
+    }
+}
+
+fn album_cache_key(album: &AlbumView) -> String {
+    dunce::simplified(&album.directory_path_relative_to_library_root())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Hash of the configuration file contents and the euphony version. Anything that could change
+/// the outcome of a scan (extensions, paths, ffmpeg arguments, ...) lives in the configuration file.
+fn configuration_fingerprint(configuration: &Configuration) -> Result<String> {
+    let configuration_file_contents =
+        fs::read(&configuration.configuration_file_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Could not read configuration file {:?}.",
+                    configuration.configuration_file_path
+                )
+            })?;
+
+    let mut hasher = DefaultHasher::new();
+    EUPHONY_VERSION.hash(&mut hasher);
+    configuration_file_contents.hash(&mut hasher);
+
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+
+    use super::CachedAlbumScan;
+    use crate::test_utilities::TestDirectory;
+
+    /// Create a source album (with a disc subdirectory) and its transcoded counterpart.
+    fn create_album(test_directory: &Path) -> (PathBuf, PathBuf) {
+        let source_album = test_directory.join("source");
+        let transcoded_album = test_directory.join("transcoded");
+
+        fs::create_dir_all(source_album.join("Disc 1")).unwrap();
+        fs::write(source_album.join("01 Track.flac"), "flac").unwrap();
+        fs::write(
+            source_album.join("Disc 1").join("01 Track.flac"),
+            "flac",
+        )
+        .unwrap();
+        fs::write(
+            source_album.join(".album.source-state.euphony"),
+            "{}",
+        )
+        .unwrap();
+
+        fs::create_dir_all(&transcoded_album).unwrap();
+        fs::write(transcoded_album.join("01 Track.mp3"), "mp3").unwrap();
+
+        (source_album, transcoded_album)
+    }
+
+    fn directory_time_modified(directory: &Path) -> SystemTime {
+        directory.metadata().unwrap().modified().unwrap()
+    }
+
+    /// Rewrite the file in place (which doesn't change its directory's modification time)
+    /// and give it a modification time newer than anything else in the album.
+    fn modify_file_in_place(file_path: &Path) {
+        let directory_time_before =
+            directory_time_modified(file_path.parent().unwrap());
+
+        fs::write(file_path, "modified").unwrap();
+        File::options()
+            .write(true)
+            .open(file_path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+
+        assert_eq!(
+            directory_time_modified(file_path.parent().unwrap()),
+            directory_time_before
+        );
+    }
+
+    fn take_snapshot(
+        source_album: &Path,
+        transcoded_album: &Path,
+    ) -> CachedAlbumScan {
+        CachedAlbumScan::from_directory_paths(source_album, transcoded_album, 1)
+            .unwrap()
+    }
+
+    #[test]
+    fn unchanged_album_matches() {
+        let test_directory = TestDirectory::new("unchanged");
+        let (source_album, transcoded_album) = create_album(&test_directory);
+
+        let cached_scan = take_snapshot(&source_album, &transcoded_album);
+        let fresh_scan = take_snapshot(&source_album, &transcoded_album);
+
+        assert!(cached_scan.matches(&fresh_scan));
+    }
+
+    #[test]
+    fn source_file_modified_without_directory_change_does_not_match() {
+        let test_directory = TestDirectory::new("modified-source-file");
+        let (source_album, transcoded_album) = create_album(&test_directory);
+
+        let cached_scan = take_snapshot(&source_album, &transcoded_album);
+        modify_file_in_place(&source_album.join("01 Track.flac"));
+        let fresh_scan = take_snapshot(&source_album, &transcoded_album);
+
+        assert!(!cached_scan.matches(&fresh_scan));
+    }
+
+    #[test]
+    fn nested_source_file_modified_without_directory_change_does_not_match() {
+        let test_directory = TestDirectory::new("modified-nested-source-file");
+        let (source_album, transcoded_album) = create_album(&test_directory);
+
+        let cached_scan = take_snapshot(&source_album, &transcoded_album);
+        modify_file_in_place(&source_album.join("Disc 1").join("01 Track.flac"));
+        let fresh_scan = take_snapshot(&source_album, &transcoded_album);
+
+        assert!(!cached_scan.matches(&fresh_scan));
+    }
+
+    #[test]
+    fn transcoded_file_modified_without_directory_change_does_not_match() {
+        let test_directory = TestDirectory::new("modified-transcoded-file");
+        let (source_album, transcoded_album) = create_album(&test_directory);
+
+        let cached_scan = take_snapshot(&source_album, &transcoded_album);
+        modify_file_in_place(&transcoded_album.join("01 Track.mp3"));
+        let fresh_scan = take_snapshot(&source_album, &transcoded_album);
+
+        assert!(!cached_scan.matches(&fresh_scan));
+    }
+
+    #[test]
+    fn modified_state_file_still_matches() {
+        let test_directory = TestDirectory::new("modified-state-file");
+        let (source_album, transcoded_album) = create_album(&test_directory);
+
+        let cached_scan = take_snapshot(&source_album, &transcoded_album);
+        modify_file_in_place(&source_album.join(".album.source-state.euphony"));
+        let fresh_scan = take_snapshot(&source_album, &transcoded_album);
+
+        assert!(cached_scan.matches(&fresh_scan));
+    }
+}
@@ -0,0 +1,343 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use miette::{miette, Context, IntoDiagnostic, Result};
+
+
+/// Suffix of the staging directory, which is placed next to the transcoded library directory
+/// (so that the final swap is a simple rename on the same filesystem).
+const STAGING_DIRECTORY_SUFFIX: &str = ".euphony-staging";
+
+/// Suffix of the previous transcoded library directory while the staging directory is being swapped in.
+const PREVIOUS_DIRECTORY_SUFFIX: &str = ".euphony-previous";
+
+/// Extension of euphony's own state files. These are always copied into the staging directory
+/// instead of hard-linked, as they are rewritten in place when saved.
+const STATE_FILE_EXTENSION: &str = "euphony";
+
+
+/// A staging directory for the transcoded library (see `aggregated_library.staging_mode`).
+///
+/// The transcode writes into the staging directory, which starts out as a mirror of the current
+/// transcoded library (with unchanged files hard-linked instead of copied). Only when the entire run
+/// succeeds is the staging directory swapped into place of the transcoded library.
+pub struct StagingDirectory {
+    /// The real transcoded library directory.
+    final_directory_path: PathBuf,
+
+    /// The staging directory the transcode writes into.
+    staging_directory_path: PathBuf,
+}
+
+impl StagingDirectory {
+    /// Compute the staging directory path for the given transcoded library directory.
+    /// This does not touch the filesystem, see `prepare` for that.
+    pub fn for_transcoded_library<P: AsRef<Path>>(
+        transcoded_library_path: P,
+    ) -> Result<Self> {
+        let final_directory_path =
+            transcoded_library_path.as_ref().to_path_buf();
+
+        let staging_directory_path = sibling_path_with_suffix(
+            &final_directory_path,
+            STAGING_DIRECTORY_SUFFIX,
+        )?;
+
+        Ok(Self {
+            final_directory_path,
+            staging_directory_path,
+        })
+    }
+
+    pub fn staging_directory_path(&self) -> &Path {
+        &self.staging_directory_path
+    }
+
+    /// Create a fresh staging directory (removing any stale one left behind by a failed run)
+    /// that mirrors the current transcoded library using hard links.
+    ///
+    /// If a previous swap was interrupted (see `swap_into_place`), it is recovered first.
+    pub fn prepare(&self) -> Result<()> {
+        self.recover_interrupted_swap()?;
+
+        if self.staging_directory_path.exists() {
+            fs::remove_dir_all(&self.staging_directory_path)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!(
+                        "Failed to remove stale staging directory at {:?}.",
+                        self.staging_directory_path
+                    )
+                })?;
+        }
+
+        if self.final_directory_path.is_dir() {
+            mirror_directory_with_hard_links(
+                &self.final_directory_path,
+                &self.staging_directory_path,
+            )
+        } else {
+            fs::create_dir_all(&self.staging_directory_path)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!(
+                        "Failed to create staging directory at {:?}.",
+                        self.staging_directory_path
+                    )
+                })
+        }
+    }
+
+    /// Swap the staging directory into place of the transcoded library directory.
+    ///
+    /// The previous transcoded library is first renamed out of the way and only removed
+    /// after the staging directory has taken its place.
+    ///
+    /// Note that this is two renames, so if the process is killed between them, the transcoded library
+    /// directory will briefly not exist (the previous one is left at the `.euphony-previous` sibling path
+    /// and the fully transcoded one at the `.euphony-staging` sibling path). The next staged transcode
+    /// finishes such an interrupted swap in `prepare`.
+    pub fn swap_into_place(&self) -> Result<()> {
+        if !self.final_directory_path.exists() {
+            return fs::rename(
+                &self.staging_directory_path,
+                &self.final_directory_path,
+            )
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!("Failed to move staging directory into place.")
+            });
+        }
+
+        let previous_directory_path = sibling_path_with_suffix(
+            &self.final_directory_path,
+            PREVIOUS_DIRECTORY_SUFFIX,
+        )?;
+
+        if previous_directory_path.exists() {
+            fs::remove_dir_all(&previous_directory_path)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!(
+                        "Failed to remove stale previous transcoded library at {:?}.",
+                        previous_directory_path
+                    )
+                })?;
+        }
+
+        fs::rename(
+            &self.final_directory_path,
+            &previous_directory_path,
+        )
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!("Failed to move previous transcoded library out of the way.")
+        })?;
+
+        fs::rename(
+            &self.staging_directory_path,
+            &self.final_directory_path,
+        )
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!(
+                "Failed to move staging directory into place \
+                    (the previous transcoded library is at {:?}).",
+                previous_directory_path
+            )
+        })?;
+
+        fs::remove_dir_all(&previous_directory_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Failed to remove previous transcoded library at {:?}.",
+                    previous_directory_path
+                )
+            })
+    }
+
+    /// Finish a swap that was interrupted between its two renames (see `swap_into_place`).
+    ///
+    /// If the transcoded library directory is missing, the staging directory (which is only swapped in
+    /// after a successful run) is moved into its place or, if that is missing as well, the previous
+    /// transcoded library is restored. A leftover previous transcoded library is then removed.
+    fn recover_interrupted_swap(&self) -> Result<()> {
+        let previous_directory_path = sibling_path_with_suffix(
+            &self.final_directory_path,
+            PREVIOUS_DIRECTORY_SUFFIX,
+        )?;
+
+        if !previous_directory_path.exists() {
+            return Ok(());
+        }
+
+        if !self.final_directory_path.exists() {
+            let directory_to_restore = if self.staging_directory_path.is_dir() {
+                &self.staging_directory_path
+            } else {
+                &previous_directory_path
+            };
+
+            fs::rename(directory_to_restore, &self.final_directory_path)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!(
+                        "Failed to recover interrupted staging swap (moving {:?} into place).",
+                        directory_to_restore
+                    )
+                })?;
+        }
+
+        if previous_directory_path.exists() {
+            fs::remove_dir_all(&previous_directory_path)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!(
+                        "Failed to remove previous transcoded library at {:?}.",
+                        previous_directory_path
+                    )
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the staging directory without swapping it in (e.g. when there was nothing to do).
+    pub fn discard(&self) -> Result<()> {
+        if !self.staging_directory_path.exists() {
+            return Ok(());
+        }
+
+        fs::remove_dir_all(&self.staging_directory_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Failed to remove staging directory at {:?}.",
+                    self.staging_directory_path
+                )
+            })
+    }
+}
+
+/// Returns a path next to `path` with the same name, but with `suffix` appended.
+fn sibling_path_with_suffix(path: &Path, suffix: &str) -> Result<PathBuf> {
+    let directory_name = path.file_name().ok_or_else(|| {
+        miette!(
+            "Transcoded library path has no directory name: {:?}",
+            path
+        )
+    })?;
+
+    let mut sibling_name = directory_name.to_os_string();
+    sibling_name.push(suffix);
+
+    Ok(path.with_file_name(sibling_name))
+}
+
+/// Recursively recreate the `source` directory tree at `target`, hard-linking all files
+/// (except euphony's state files, which are copied).
+fn mirror_directory_with_hard_links(source: &Path, target: &Path) -> Result<()> {
+    fs::create_dir_all(target)
+        .into_diagnostic()
+        .wrap_err_with(|| miette!("Failed to create directory {:?}.", target))?;
+
+    let directory_entries = fs::read_dir(source)
+        .into_diagnostic()
+        .wrap_err_with(|| miette!("Failed to read directory {:?}.", source))?;
+
+    for entry in directory_entries {
+        let entry = entry.into_diagnostic()?;
+        let entry_path = entry.path();
+        let target_path = target.join(entry.file_name());
+
+        let file_type = entry.file_type().into_diagnostic()?;
+
+        if file_type.is_dir() {
+            mirror_directory_with_hard_links(&entry_path, &target_path)?;
+        } else if entry_path
+            .extension()
+            .is_some_and(|extension| extension == STATE_FILE_EXTENSION)
+        {
+            fs::copy(&entry_path, &target_path)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!("Failed to copy state file {:?}.", entry_path)
+                })?;
+        } else {
+            fs::hard_link(&entry_path, &target_path)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!("Failed to hard-link file {:?}.", entry_path)
+                })?;
+        }
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{
+        sibling_path_with_suffix,
+        StagingDirectory,
+        PREVIOUS_DIRECTORY_SUFFIX,
+    };
+    use crate::test_utilities::TestDirectory;
+
+    #[test]
+    fn prepare_finishes_swap_interrupted_between_renames() {
+        let test_directory = TestDirectory::new("interrupted-swap");
+        let final_directory = test_directory.join("transcoded");
+
+        let staging =
+            StagingDirectory::for_transcoded_library(&final_directory).unwrap();
+        let previous_directory = sibling_path_with_suffix(
+            &final_directory,
+            PREVIOUS_DIRECTORY_SUFFIX,
+        )
+        .unwrap();
+
+        // State after the first rename of `swap_into_place`.
+        fs::create_dir_all(&previous_directory).unwrap();
+        fs::write(previous_directory.join("old.mp3"), "old").unwrap();
+        fs::create_dir_all(staging.staging_directory_path()).unwrap();
+        fs::write(
+            staging.staging_directory_path().join("new.mp3"),
+            "new",
+        )
+        .unwrap();
+
+        staging.prepare().unwrap();
+
+        assert!(final_directory.join("new.mp3").is_file());
+        assert!(!final_directory.join("old.mp3").exists());
+        assert!(!previous_directory.exists());
+        assert!(staging.staging_directory_path().join("new.mp3").is_file());
+    }
+
+    #[test]
+    fn prepare_restores_previous_library_without_staging_directory() {
+        let test_directory = TestDirectory::new("restore-previous");
+        let final_directory = test_directory.join("transcoded");
+
+        let staging =
+            StagingDirectory::for_transcoded_library(&final_directory).unwrap();
+        let previous_directory = sibling_path_with_suffix(
+            &final_directory,
+            PREVIOUS_DIRECTORY_SUFFIX,
+        )
+        .unwrap();
+
+        fs::create_dir_all(&previous_directory).unwrap();
+        fs::write(previous_directory.join("old.mp3"), "old").unwrap();
+
+        staging.prepare().unwrap();
+
+        assert!(final_directory.join("old.mp3").is_file());
+        assert!(!previous_directory.exists());
+    }
+}
@@ -1,9 +1,11 @@
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use euphony_library::view::common::SortedFileMap;
 use euphony_library::view::SharedAlbumView;
 use miette::{miette, Context, Result};
+use serde::Serialize;
 
 // TODO Finish reorganising code into the euphony_library crate.
 // TODO Try to put things in transcode::jobs into a different crate, if possible.
@@ -11,8 +13,11 @@ use crate::commands::transcode::jobs::{
     CancellableTask,
     CopyFileJob,
     DeleteProcessedFileJob,
+    FileJob,
     FileJobMessage,
     IntoCancellableTask,
+    ProcessCoverArtJob,
+    RetryingFileJob,
     TranscodeAudioFileJob,
 };
 use crate::console::frontends::shared::queue::QueueItemID;
@@ -20,7 +25,8 @@ use crate::console::frontends::shared::queue::QueueItemID;
 
 
 /// Describes one of three possible file types (audio, data, unknown).
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Serialize, Copy, Clone, Eq, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
 pub enum FileType {
     /// Audio files, as configured per-library.
     Audio,
@@ -37,7 +43,7 @@ pub enum FileType {
 
 
 #[allow(clippy::enum_variant_names)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum TranscodeProcessingReason {
     AddedInSourceLibrary,
     ChangedInSourceLibrary,
@@ -45,7 +51,7 @@ pub enum TranscodeProcessingReason {
 }
 
 #[allow(clippy::enum_variant_names)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum CopyProcessingReason {
     AddedInSourceLibrary,
     ChangedInSourceLibrary,
@@ -54,7 +60,7 @@ pub enum CopyProcessingReason {
 
 
 #[allow(clippy::enum_variant_names)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum DeleteInTranscodedProcessingReason {
     RemovedFromSourceLibrary,
     ExcessInTranscodedLibrary,
@@ -96,6 +102,26 @@ impl FileProcessingAction {
 }
 
 
+/// Wraps the job in a `RetryingFileJob` according to `aggregated_library.failure_max_retries`
+/// and `aggregated_library.failure_delay_seconds`.
+fn with_configured_retries<Job: FileJob>(
+    job: Job,
+    album_view: &SharedAlbumView,
+) -> RetryingFileJob<Job> {
+    let album_locked = album_view.read();
+    let aggregated_library_configuration =
+        &album_locked.euphony_configuration().aggregated_library;
+
+    RetryingFileJob::new(
+        job,
+        aggregated_library_configuration.failure_max_retries,
+        Duration::from_secs(
+            aggregated_library_configuration.failure_delay_seconds as u64,
+        ),
+    )
+}
+
+
 #[derive(Clone)]
 pub struct FileJobContext {
     pub file_type: FileType,
@@ -143,7 +169,10 @@ pub fn add_transcode_job<
     )
     .wrap_err_with(|| miette!("Could not create TranscodeAudioFileJob."))?;
 
-    global_job_array.push(transcoding_job.into_cancellable_task());
+    global_job_array.push(
+        with_configured_retries(transcoding_job, album_view)
+            .into_cancellable_task(),
+    );
 
     Ok(())
 }
@@ -188,7 +217,58 @@ pub fn add_file_copy_job<
     )
     .wrap_err_with(|| miette!("Could not create CopyFileJob."))?;
 
-    global_job_array.push(copy_job.into_cancellable_task());
+    global_job_array.push(
+        with_configured_retries(copy_job, album_view).into_cancellable_task(),
+    );
+
+    Ok(())
+}
+
+/// Like `add_file_copy_job`, but the data file is processed as cover art (see `ProcessCoverArtJob`).
+/// Queue items still show the file as being copied.
+pub fn add_cover_art_processing_job<
+    F: Fn(FileJobContext) -> Result<QueueItemID>,
+    P: Into<PathBuf>,
+>(
+    global_job_array: &mut Vec<CancellableTask<FileJobMessage>>,
+    album_view: &SharedAlbumView,
+    queue_item_id_generator: &F,
+    absolute_source_to_target_path_map: &SortedFileMap<PathBuf, PathBuf>,
+    source_path: P,
+    copy_reason: CopyProcessingReason,
+) -> Result<()> {
+    let source_path = source_path.into();
+
+    let target_path = absolute_source_to_target_path_map
+        .get(&source_path)
+        .ok_or_else(|| {
+            miette!(
+                "BUG(add_cover_art_processing_job): Map is missing data file entry: {:?}.",
+                source_path
+            )
+        })?;
+
+    let queue_item_id = queue_item_id_generator(FileJobContext {
+        file_type: FileType::Data,
+        action: FileProcessingAction::Copy {
+            source_path: source_path.clone(),
+            target_path: target_path.to_path_buf(),
+            reason: copy_reason,
+        },
+    })?;
+
+    let cover_art_job = ProcessCoverArtJob::new(
+        album_view.clone(),
+        source_path,
+        target_path.to_path_buf(),
+        queue_item_id,
+    )
+    .wrap_err_with(|| miette!("Could not create ProcessCoverArtJob."))?;
+
+    global_job_array.push(
+        with_configured_retries(cover_art_job, album_view)
+            .into_cancellable_task(),
+    );
 
     Ok(())
 }
@@ -223,6 +303,7 @@ pub fn add_aggregated_file_deletion_job<
 
     let copy_job = DeleteProcessedFileJob::new(
         album_view.read().euphony_configuration(),
+        album_view.read().library_configuration(),
         target_path,
         file_type,
         true,
@@ -1,10 +1,13 @@
 use std::path::PathBuf;
 
 use euphony_library::state::AlbumFileChangesV2;
+use euphony_library::view::common::SortedFileMap;
+use euphony_library::view::SharedAlbumView;
 use miette::{miette, Result};
 
 use super::changes::{
     add_aggregated_file_deletion_job,
+    add_cover_art_processing_job,
     add_file_copy_job,
     add_transcode_job,
     CopyProcessingReason,
@@ -28,16 +31,64 @@ fn sort_pathbuf_iterator<'a, I: IntoIterator<Item = &'a PathBuf>>(
     vector
 }
 
+/// Adds a job for the given data file: cover art is processed with `add_cover_art_processing_job`
+/// (see `aggregated_library.cover_art_max_dimension`), other data files are copied with `add_file_copy_job`.
+fn add_data_file_job<F: Fn(FileJobContext) -> Result<QueueItemID>>(
+    jobs: &mut Vec<CancellableTask<FileJobMessage>>,
+    album_view: &SharedAlbumView,
+    queue_item_id_generator: &F,
+    absolute_source_to_target_path_map: &SortedFileMap<PathBuf, PathBuf>,
+    path: &PathBuf,
+    copy_reason: CopyProcessingReason,
+) -> Result<()> {
+    let is_cover_art = album_view
+        .read()
+        .euphony_configuration()
+        .aggregated_library
+        .is_cover_art_file(path);
+
+    if is_cover_art {
+        add_cover_art_processing_job(
+            jobs,
+            album_view,
+            queue_item_id_generator,
+            absolute_source_to_target_path_map,
+            path,
+            copy_reason,
+        )
+    } else {
+        add_file_copy_job(
+            jobs,
+            album_view,
+            queue_item_id_generator,
+            absolute_source_to_target_path_map,
+            path,
+            FileType::Data,
+            copy_reason,
+        )
+    }
+}
+
+
+/// File jobs generated from a set of album changes.
+pub struct GeneratedFileJobs {
+    /// Transcoding and copying jobs.
+    pub processing_jobs: Vec<CancellableTask<FileJobMessage>>,
+
+    /// Jobs that delete files in the transcoded library.
+    pub deletion_jobs: Vec<CancellableTask<FileJobMessage>>,
+}
 
 pub trait GenerateChanges {
     fn generate_file_jobs<F: Fn(FileJobContext) -> Result<QueueItemID>>(
         &self,
         queue_item_id_generator: F,
-    ) -> Result<Vec<CancellableTask<FileJobMessage>>>;
+    ) -> Result<GeneratedFileJobs>;
 }
 
 impl<'view> GenerateChanges for AlbumFileChangesV2<'view> {
-    /// This method will generate and return a list of cancellable tasks.
+    /// This method will generate and return cancellable tasks
+    /// (transcoding and copying jobs separately from deletion jobs).
     ///
     /// The `queue_item_id_generator` parameter should be a closure that will take two parameters:
     /// - `FileType`, which is the type of the file (audio or data) and
@@ -48,9 +99,10 @@ impl<'view> GenerateChanges for AlbumFileChangesV2<'view> {
     fn generate_file_jobs<F: Fn(FileJobContext) -> Result<QueueItemID>>(
         &self,
         queue_item_id_generator: F,
-    ) -> Result<Vec<CancellableTask<FileJobMessage>>> {
+    ) -> Result<GeneratedFileJobs> {
         let mut jobs: Vec<CancellableTask<FileJobMessage>> =
             Vec::with_capacity(self.number_of_changed_files());
+        let mut deletion_jobs: Vec<CancellableTask<FileJobMessage>> = Vec::new();
 
         let absolute_source_to_target_path_map =
             self.tracked_source_files.as_ref().map(|files| {
@@ -127,13 +179,12 @@ impl<'view> GenerateChanges for AlbumFileChangesV2<'view> {
                 return Err(miette!("Can't map source paths to transcoded paths, no tracked files."));
             };
 
-            add_file_copy_job(
+            add_data_file_job(
                 &mut jobs,
                 &self.album_view,
                 &queue_item_id_generator,
                 source_to_target_path_map,
                 path,
-                FileType::Data,
                 CopyProcessingReason::AddedInSourceLibrary,
             )?;
         }
@@ -147,13 +198,12 @@ impl<'view> GenerateChanges for AlbumFileChangesV2<'view> {
                 return Err(miette!("Can't map source paths to transcoded paths, no tracked files."));
             };
 
-            add_file_copy_job(
+            add_data_file_job(
                 &mut jobs,
                 &self.album_view,
                 &queue_item_id_generator,
                 source_to_target_path_map,
                 path,
-                FileType::Data,
                 CopyProcessingReason::ChangedInSourceLibrary,
             )?;
         }
@@ -165,13 +215,12 @@ impl<'view> GenerateChanges for AlbumFileChangesV2<'view> {
                 return Err(miette!("Can't map source paths to transcoded paths, no tracked files."));
             };
 
-            add_file_copy_job(
+            add_data_file_job(
                 &mut jobs,
                 &self.album_view,
                 &queue_item_id_generator,
                 source_to_target_path_map,
                 path,
-                FileType::Data,
                 CopyProcessingReason::MissingInTranscodedLibrary,
             )?;
         }
@@ -182,7 +231,7 @@ impl<'view> GenerateChanges for AlbumFileChangesV2<'view> {
             &self.removed_from_source_since_last_transcode.audio,
         ) {
             add_aggregated_file_deletion_job(
-                &mut jobs,
+                &mut deletion_jobs,
                 &self.album_view,
                 &queue_item_id_generator,
                 target_path,
@@ -195,7 +244,7 @@ impl<'view> GenerateChanges for AlbumFileChangesV2<'view> {
             &self.removed_from_source_since_last_transcode.data,
         ) {
             add_aggregated_file_deletion_job(
-                &mut jobs,
+                &mut deletion_jobs,
                 &self.album_view,
                 &queue_item_id_generator,
                 target_path,
@@ -207,7 +256,7 @@ impl<'view> GenerateChanges for AlbumFileChangesV2<'view> {
 
         for path in sort_pathbuf_iterator(&self.excess_in_transcoded.audio) {
             add_aggregated_file_deletion_job(
-                &mut jobs,
+                &mut deletion_jobs,
                 &self.album_view,
                 &queue_item_id_generator,
                 path,
@@ -218,7 +267,7 @@ impl<'view> GenerateChanges for AlbumFileChangesV2<'view> {
 
         for path in sort_pathbuf_iterator(&self.excess_in_transcoded.data) {
             add_aggregated_file_deletion_job(
-                &mut jobs,
+                &mut deletion_jobs,
                 &self.album_view,
                 &queue_item_id_generator,
                 path,
@@ -229,7 +278,7 @@ impl<'view> GenerateChanges for AlbumFileChangesV2<'view> {
 
         for path in sort_pathbuf_iterator(&self.excess_in_transcoded.unknown) {
             add_aggregated_file_deletion_job(
-                &mut jobs,
+                &mut deletion_jobs,
                 &self.album_view,
                 &queue_item_id_generator,
                 path,
@@ -238,6 +287,9 @@ impl<'view> GenerateChanges for AlbumFileChangesV2<'view> {
             )?;
         }
 
-        Ok(jobs)
+        Ok(GeneratedFileJobs {
+            processing_jobs: jobs,
+            deletion_jobs,
+        })
     }
 }
@@ -0,0 +1,119 @@
+use std::fs;
+
+use chrono::Local;
+use euphony_library::state::transcoded::TranscodedAlbumState;
+use euphony_library::view::AlbumView;
+use miette::{miette, Context, IntoDiagnostic, Result};
+
+use super::jobs::common::remove_existing_target_file;
+use crate::EUPHONY_VERSION;
+
+
+/// Name of the human-readable transcode manifest placed in each transcoded album directory
+/// when `aggregated_library.write_transcode_info` is enabled.
+pub const TRANSCODE_INFO_FILE_NAME: &str = "TRANSCODE_INFO.txt";
+
+
+/// Write `TRANSCODE_INFO.txt` (see `TRANSCODE_INFO_FILE_NAME`) into the transcoded album directory,
+/// noting the euphony version, the ffmpeg arguments, the current date and the source to transcoded
+/// file name mapping (taken from `transcoded_album_state`).
+///
+/// Any existing file is removed first instead of being overwritten in place
+/// (see `remove_existing_target_file`).
+///
+/// The file name is then recorded in `transcoded_album_state`, so that the file is removed
+/// along with the rest of the transcoded album.
+pub fn write_transcode_info(
+    album: &AlbumView,
+    transcoded_album_state: &mut TranscodedAlbumState,
+) -> Result<()> {
+    let configuration = album.euphony_configuration();
+    let ffmpeg_configuration = configuration
+        .ffmpeg_configuration_for_library(album.library_configuration());
+
+    let transcoded_album_directory =
+        album.album_directory_in_transcoded_library();
+
+    let mut transcode_info_lines: Vec<String> = vec![
+        format!(
+            "Transcoded by euphony {} on {}.",
+            EUPHONY_VERSION,
+            Local::now().format("%Y-%m-%d %H:%M:%S")
+        ),
+        String::new(),
+        format!(
+            "Album: {} - {}",
+            album.read_lock_artist().name,
+            album.title
+        ),
+        format!("Library: {}", album.library_configuration().name),
+        format!(
+            "Source directory: {}",
+            album.album_directory_in_source_library().display()
+        ),
+        format!(
+            "Audio transcoding: ffmpeg {} (output extension: {})",
+            ffmpeg_configuration.audio_transcoding_args.join(" "),
+            ffmpeg_configuration.audio_transcoding_output_extension
+        ),
+    ];
+
+    for (header, file_map) in [
+        (
+            "Audio files",
+            &transcoded_album_state
+                .transcoded_to_original_file_paths
+                .audio,
+        ),
+        (
+            "Data files",
+            &transcoded_album_state
+                .transcoded_to_original_file_paths
+                .data,
+        ),
+    ] {
+        let mut sorted_file_pairs = file_map
+            .iter()
+            .map(|(transcoded_path, source_path)| (source_path, transcoded_path))
+            .collect::<Vec<_>>();
+        sorted_file_pairs.sort_unstable();
+
+        transcode_info_lines.push(String::new());
+        transcode_info_lines.push(format!("{header} (source -> transcoded):"));
+
+        for (source_path, transcoded_path) in sorted_file_pairs {
+            transcode_info_lines
+                .push(format!("  {source_path} -> {transcoded_path}"));
+        }
+    }
+
+    let transcode_info_file_path =
+        transcoded_album_directory.join(TRANSCODE_INFO_FILE_NAME);
+
+    // The existing file may be hard-linked to the live transcoded library (in staging mode).
+    remove_existing_target_file(&transcode_info_file_path)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!(
+                "Failed to remove existing transcode info at {:?}.",
+                transcode_info_file_path
+            )
+        })?;
+
+    fs::write(
+        &transcode_info_file_path,
+        transcode_info_lines.join("\n") + "\n",
+    )
+    .into_diagnostic()
+    .wrap_err_with(|| {
+        miette!(
+            "Failed to write transcode info to {:?}.",
+            transcode_info_file_path
+        )
+    })?;
+
+    transcoded_album_state.transcode_info_file =
+        Some(TRANSCODE_INFO_FILE_NAME.to_string());
+
+    Ok(())
+}
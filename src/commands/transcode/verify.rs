@@ -0,0 +1,82 @@
+use crossterm::style::Stylize;
+use euphony_library::view::{AlbumSourceFileList, SharedLibraryView};
+use miette::Result;
+
+use crate::console::frontends::TranscodeTerminal;
+use crate::console::LogBackend;
+
+
+/// Associated with the `aggregated_library.verify_after_run` option.
+///
+/// Checks that every tracked source audio file of every album has a corresponding file
+/// in the transcoded library and logs a warning for each one that is missing
+/// (e.g. because its job failed without being reported). Returns the number of missing files.
+pub fn verify_transcoded_audio_files<'config>(
+    libraries: &[SharedLibraryView<'config>],
+    terminal: &TranscodeTerminal<'config, '_>,
+) -> Result<usize> {
+    terminal.log_println("Verifying transcoded audio files...");
+
+    let mut num_missing_files: usize = 0;
+
+    for library in libraries {
+        let library_locked = library.read();
+
+        for (artist_name, artist) in library_locked.artists()? {
+            for (album_title, album) in artist.read().albums()? {
+                // Such albums are never processed (see `collect_artist_changes`).
+                {
+                    let album_locked = album.read();
+
+                    if album_locked.configuration.skip_transcode
+                        || album_locked
+                            .has_overlapping_source_and_transcoded_directories()
+                    {
+                        continue;
+                    }
+                }
+
+                let tracked_source_files =
+                    AlbumSourceFileList::from_album_view(album)?;
+
+                let mut missing_files = tracked_source_files
+                    .map_source_file_paths_to_transcoded_file_paths_absolute()
+                    .audio
+                    .into_iter()
+                    .filter(|(_, transcoded_file_path)| {
+                        !transcoded_file_path.is_file()
+                    })
+                    .collect::<Vec<_>>();
+
+                missing_files.sort_unstable();
+
+                for (source_file_path, transcoded_file_path) in missing_files {
+                    terminal.log_eprintln(format!(
+                        "{} album \"{} - {}\" has no transcoded file for {:?} (expected at {:?}).",
+                        "WARNING:".yellow(),
+                        artist_name,
+                        album_title,
+                        source_file_path,
+                        transcoded_file_path,
+                    ));
+
+                    num_missing_files += 1;
+                }
+            }
+        }
+    }
+
+    if num_missing_files == 0 {
+        terminal.log_println(
+            "Verified transcoded audio files, none are missing.".green(),
+        );
+    } else {
+        terminal.log_eprintln(format!(
+            "{} {} source audio files have no transcoded counterpart.",
+            "WARNING:".yellow(),
+            num_missing_files
+        ));
+    }
+
+    Ok(num_missing_files)
+}
@@ -3,15 +3,34 @@ use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 
 use crossterm::style::Stylize;
-use euphony_configuration::library::LibraryConfiguration;
+use euphony_configuration::library::{
+    LibraryConfiguration,
+    UnexpectedFileCategory,
+};
 use euphony_configuration::{Configuration, ALBUM_OVERRIDE_FILE_NAME};
+use euphony_library::state::common::FileTrackedMetadata;
 use euphony_library::state::source::SOURCE_ALBUM_STATE_FILE_NAME;
-use euphony_library::view::LibraryView;
-use miette::{miette, Context, Result};
-
+use euphony_library::view::library::LibraryViewError;
+use euphony_library::view::{
+    AlbumSourceFileList,
+    LibraryView,
+    SharedLibraryView,
+};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::commands::log_optional_library_unavailable;
 use crate::commands::transcode::library_state::LIBRARY_STATE_FILE_NAME;
+use crate::commands::transcode::scan_cache::LIBRARY_SCAN_CACHE_FILE_NAME;
 use crate::console::frontends::ValidationTerminal;
-use crate::console::{LogBackend, ValidationBackend, ValidationErrorInfo};
+use crate::console::{
+    LogBackend,
+    UserControlMessage,
+    UserControllableBackend,
+    ValidationBackend,
+    ValidationErrorInfo,
+};
 
 /// Implemented by concrete validation errors to allow a standardised way of displaying the error.
 pub trait ValidationErrorDisplay {
@@ -20,10 +39,24 @@ pub trait ValidationErrorDisplay {
     fn get_error_info(&self) -> Result<ValidationErrorInfo>;
 }
 
+/// How serious a validation error is. Only errors cause `validate` to fail,
+/// warnings are just reported (see `validation.warning_categories` in the library configuration).
+#[derive(Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationSeverity {
+    Error,
+    Warning,
+}
+
 /// Describes all possible validation errors.
 pub enum ValidationError<'a> {
     UnexpectedFile(UnexpectedFile<'a>),
     AlbumCollision(AlbumCollision<'a>),
+    FileNameCollision(FileNameCollision<'a>),
+    UnscannableLibrary(UnscannableLibrary<'a>),
+    AlbumWithoutAudioFiles(AlbumWithoutAudioFiles<'a>),
+    EmptyDirectory(EmptyDirectory<'a>),
+    UnreadableFile(UnreadableFile<'a>),
 }
 
 impl<'a> ValidationError<'a> {
@@ -46,20 +79,283 @@ impl<'a> ValidationError<'a> {
         )?))
     }
 
+    /// Initialize a new validation error: two files in an album whose transcoded paths
+    /// only differ in letter case.
+    pub fn new_file_name_collision(
+        library: &'a LibraryConfiguration,
+        album_directory_path: PathBuf,
+        colliding_files: (PathBuf, PathBuf),
+    ) -> Self {
+        Self::FileNameCollision(FileNameCollision::new(
+            library,
+            album_directory_path,
+            colliding_files,
+        ))
+    }
+
+    /// Initialize a new validation error: a library that could not be scanned.
+    pub fn new_unscannable_library(
+        library: &'a LibraryConfiguration,
+        error: miette::Report,
+    ) -> Self {
+        Self::UnscannableLibrary(UnscannableLibrary::new(library, error))
+    }
+
+    /// Initialize a new validation error: an album directory without any tracked audio files.
+    pub fn new_album_without_audio_files(
+        library: &'a LibraryConfiguration,
+        album_directory_path: PathBuf,
+        num_data_files: usize,
+    ) -> Self {
+        Self::AlbumWithoutAudioFiles(AlbumWithoutAudioFiles::new(
+            library,
+            album_directory_path,
+            num_data_files,
+        ))
+    }
+
+    /// Initialize a new validation error: an empty artist or album directory.
+    pub fn new_empty_directory(
+        library: &'a LibraryConfiguration,
+        directory_path: PathBuf,
+        location: EmptyDirectoryLocation,
+    ) -> Self {
+        Self::EmptyDirectory(EmptyDirectory::new(
+            library,
+            directory_path,
+            location,
+        ))
+    }
+
+    /// Initialize a new validation error: a tracked file whose metadata can't be read.
+    pub fn new_unreadable_file(
+        library: &'a LibraryConfiguration,
+        file_path: PathBuf,
+        error: miette::Report,
+    ) -> Self {
+        Self::UnreadableFile(UnreadableFile::new(library, file_path, error))
+    }
+
+    /// Return the severity of this validation error. Unexpected files are downgraded to warnings
+    /// if their library lists their category in `validation.warning_categories`
+    /// and albums without audio files, empty directories and unreadable files are always warnings.
+    /// Everything else is always an error.
+    pub fn severity(&self) -> ValidationSeverity {
+        match self {
+            ValidationError::UnexpectedFile(unexpected_file)
+                if unexpected_file
+                    .library
+                    .validation
+                    .warning_categories
+                    .contains(&unexpected_file.location.category()) =>
+            {
+                ValidationSeverity::Warning
+            }
+            ValidationError::AlbumWithoutAudioFiles(_)
+            | ValidationError::EmptyDirectory(_)
+            | ValidationError::UnreadableFile(_) => ValidationSeverity::Warning,
+            _ => ValidationSeverity::Error,
+        }
+    }
+
+    /// Return the machine-readable report of this validation error (see `ValidationErrorReport`).
+    pub fn to_report(&self) -> Result<ValidationErrorReport> {
+        let severity = self.severity();
+
+        match self {
+            ValidationError::UnexpectedFile(unexpected_file) => {
+                Ok(ValidationErrorReport::UnexpectedFile {
+                    severity,
+                    library: unexpected_file.library.name.clone(),
+                    relative_path: unexpected_file
+                        .relative_file_path()?
+                        .to_string_lossy()
+                        .to_string(),
+                    location: unexpected_file.location,
+                })
+            }
+            ValidationError::AlbumCollision(album_collision) => {
+                let mut colliding_libraries =
+                    album_collision.colliding_library_names();
+                colliding_libraries.sort_unstable();
+
+                Ok(ValidationErrorReport::AlbumCollision {
+                    severity,
+                    artist: album_collision.artist_name(),
+                    album: album_collision.album_title(),
+                    colliding_libraries,
+                })
+            }
+            ValidationError::FileNameCollision(file_name_collision) => {
+                Ok(ValidationErrorReport::FileNameCollision {
+                    severity,
+                    library: file_name_collision.library.name.clone(),
+                    album_directory: file_name_collision
+                        .relative_album_directory_path()?
+                        .to_string_lossy()
+                        .to_string(),
+                    colliding_files: file_name_collision.colliding_file_names(),
+                })
+            }
+            ValidationError::UnscannableLibrary(unscannable_library) => {
+                Ok(ValidationErrorReport::UnscannableLibrary {
+                    severity,
+                    library: unscannable_library.library.name.clone(),
+                    path: unscannable_library.library.path.clone(),
+                    reason: unscannable_library.reason.clone(),
+                })
+            }
+            ValidationError::AlbumWithoutAudioFiles(
+                album_without_audio_files,
+            ) => Ok(ValidationErrorReport::AlbumWithoutAudioFiles {
+                severity,
+                library: album_without_audio_files.library.name.clone(),
+                album_directory: album_without_audio_files
+                    .relative_album_directory_path()?
+                    .to_string_lossy()
+                    .to_string(),
+                num_data_files: album_without_audio_files.num_data_files,
+            }),
+            ValidationError::EmptyDirectory(empty_directory) => {
+                Ok(ValidationErrorReport::EmptyDirectory {
+                    severity,
+                    library: empty_directory.library.name.clone(),
+                    directory: empty_directory
+                        .relative_directory_path()?
+                        .to_string_lossy()
+                        .to_string(),
+                    location: empty_directory.location,
+                })
+            }
+            ValidationError::UnreadableFile(unreadable_file) => {
+                Ok(ValidationErrorReport::UnreadableFile {
+                    severity,
+                    library: unreadable_file.library.name.clone(),
+                    relative_path: unreadable_file
+                        .relative_file_path()?
+                        .to_string_lossy()
+                        .to_string(),
+                    reason: unreadable_file.reason.clone(),
+                })
+            }
+        }
+    }
+
     /// Consume the enum instance and return the `ValidationErrorInfo` that its variant returns.
+    /// The header of warnings is prefixed with "Warning:".
     pub fn into_validation_error_info(self) -> Result<ValidationErrorInfo> {
-        match self {
+        let severity = self.severity();
+
+        let mut error_info = match self {
             ValidationError::UnexpectedFile(unexpected_file) => {
                 unexpected_file.get_error_info()
             }
             ValidationError::AlbumCollision(album_collision) => {
                 album_collision.get_error_info()
             }
+            ValidationError::FileNameCollision(file_name_collision) => {
+                file_name_collision.get_error_info()
+            }
+            ValidationError::UnscannableLibrary(unscannable_library) => {
+                unscannable_library.get_error_info()
+            }
+            ValidationError::AlbumWithoutAudioFiles(
+                album_without_audio_files,
+            ) => album_without_audio_files.get_error_info(),
+            ValidationError::EmptyDirectory(empty_directory) => {
+                empty_directory.get_error_info()
+            }
+            ValidationError::UnreadableFile(unreadable_file) => {
+                unreadable_file.get_error_info()
+            }
+        }?;
+
+        if severity == ValidationSeverity::Warning {
+            error_info.header = format!("Warning: {}", error_info.header);
         }
+
+        Ok(error_info)
     }
 }
 
+/// Machine-readable version of a `ValidationError`, printed by `validate --format json`.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ValidationErrorReport {
+    UnexpectedFile {
+        severity: ValidationSeverity,
+
+        library: String,
+
+        /// File path, relative to the library root.
+        relative_path: String,
+
+        location: UnexpectedFileLocation,
+    },
+    AlbumCollision {
+        severity: ValidationSeverity,
+
+        artist: String,
+
+        album: String,
+
+        colliding_libraries: Vec<String>,
+    },
+    FileNameCollision {
+        severity: ValidationSeverity,
+
+        library: String,
+
+        /// Album directory path, relative to the library root.
+        album_directory: String,
+
+        /// File paths, relative to the album directory.
+        colliding_files: Vec<String>,
+    },
+    UnscannableLibrary {
+        severity: ValidationSeverity,
+
+        library: String,
+
+        path: String,
+
+        reason: String,
+    },
+    AlbumWithoutAudioFiles {
+        severity: ValidationSeverity,
+
+        library: String,
+
+        /// Album directory path, relative to the library root.
+        album_directory: String,
+
+        num_data_files: usize,
+    },
+    EmptyDirectory {
+        severity: ValidationSeverity,
+
+        library: String,
+
+        /// Directory path, relative to the library root.
+        directory: String,
+
+        location: EmptyDirectoryLocation,
+    },
+    UnreadableFile {
+        severity: ValidationSeverity,
+
+        library: String,
+
+        /// File path, relative to the library root.
+        relative_path: String,
+
+        reason: String,
+    },
+}
+
 /// Describes the type of the "unexpected file type" validation error.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
 pub enum UnexpectedFileLocation {
     LibraryRoot,
     ArtistDirectory,
@@ -67,6 +363,26 @@ pub enum UnexpectedFileLocation {
     AlbumDirectoryOther,
 }
 
+impl UnexpectedFileLocation {
+    /// The configuration counterpart of this location (see `validation.warning_categories`).
+    pub fn category(self) -> UnexpectedFileCategory {
+        match self {
+            UnexpectedFileLocation::LibraryRoot => {
+                UnexpectedFileCategory::LibraryRoot
+            }
+            UnexpectedFileLocation::ArtistDirectory => {
+                UnexpectedFileCategory::ArtistDirectory
+            }
+            UnexpectedFileLocation::AlbumDirectoryAudio => {
+                UnexpectedFileCategory::AlbumDirectoryAudio
+            }
+            UnexpectedFileLocation::AlbumDirectoryOther => {
+                UnexpectedFileCategory::AlbumDirectoryOther
+            }
+        }
+    }
+}
+
 /// This validation error happens when the contents of a library do not match what is configured
 /// in the library configuration table in `configuration.toml`.
 pub struct UnexpectedFile<'a> {
@@ -92,6 +408,64 @@ impl<'a> UnexpectedFile<'a> {
             location: reason,
         }
     }
+
+    /// Get the unexpected file's path relative to its library root.
+    fn relative_file_path(&self) -> Result<PathBuf> {
+        pathdiff::diff_paths(&self.file_path, &self.library.path).ok_or_else(
+            || miette!("Could not make file path relative to library base!"),
+        )
+    }
+
+    /// Render a shortened file tree, rooted at the library directory, that only descends
+    /// along the path to the unexpected file. Each directory is annotated with its role.
+    fn render_shortened_file_tree(&self) -> Result<String> {
+        let relative_file_path = self.relative_file_path()?;
+
+        let path_components = relative_file_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+
+        let Some((file_name, directory_names)) = path_components.split_last()
+        else {
+            return Err(miette!("Unexpected file path is empty."));
+        };
+
+        let is_in_album_directory = matches!(
+            self.location,
+            UnexpectedFileLocation::AlbumDirectoryAudio
+                | UnexpectedFileLocation::AlbumDirectoryOther
+        );
+
+        let mut tree_lines: Vec<String> = vec![self.library.path.clone()];
+
+        for (depth, directory_name) in directory_names.iter().enumerate() {
+            let is_last_directory = depth + 1 == directory_names.len();
+
+            let directory_role = if is_in_album_directory && is_last_directory {
+                "album directory"
+            } else if depth == 0 {
+                "artist directory"
+            } else {
+                "directory"
+            };
+
+            tree_lines.push(format!(
+                "{}|-- {} ({})",
+                "|   ".repeat(depth),
+                directory_name,
+                directory_role
+            ));
+        }
+
+        tree_lines.push(format!(
+            "{}|-> {}",
+            "|   ".repeat(directory_names.len()),
+            file_name
+        ));
+
+        Ok(tree_lines.join("\n"))
+    }
 }
 
 impl<'a> ValidationErrorDisplay for UnexpectedFile<'a> {
@@ -103,17 +477,11 @@ impl<'a> ValidationErrorDisplay for UnexpectedFile<'a> {
         // Library: Standard
         // File: Aindulmedir/some_unexpected_file.zip
         //
-        // C:/StandardLibrary
-        // |-- Aindulmedir (album directory)
+        // Location: C:/StandardLibrary
+        // |-- Aindulmedir (artist directory)
         // |   |-> some_unexpected_file.zip
 
-        // TODO Render a shortened file tree as in the example above.
-
-        let relative_file_path =
-            pathdiff::diff_paths(&self.file_path, &self.library.path)
-                .ok_or_else(|| {
-                    miette!("Could not make file path relative to library base!")
-                })?;
+        let relative_file_path = self.relative_file_path()?;
 
         let attributes = vec![
             ("Library".to_string(), self.library.name.clone()),
@@ -121,6 +489,10 @@ impl<'a> ValidationErrorDisplay for UnexpectedFile<'a> {
                 "File".to_string(),
                 relative_file_path.to_string_lossy().to_string(),
             ),
+            (
+                "Location".to_string(),
+                self.render_shortened_file_tree()?,
+            ),
         ];
 
         Ok(ValidationErrorInfo::new(
@@ -144,6 +516,343 @@ impl<'a> ValidationErrorDisplay for UnexpectedFile<'a> {
 }
 
 
+/// This validation error happens when two tracked files in the same album would end up with
+/// transcoded paths that only differ in letter case (e.g. `Cover.jpg` and `cover.jpg`).
+/// On case-insensitive filesystems (the default on Windows and macOS), one would overwrite the other,
+/// so `transcode` refuses to process such albums.
+pub struct FileNameCollision<'a> {
+    /// The library the album is part of.
+    library: &'a LibraryConfiguration,
+
+    /// Absolute path of the album directory.
+    album_directory_path: PathBuf,
+
+    /// The colliding files, relative to the album directory.
+    colliding_files: (PathBuf, PathBuf),
+}
+
+impl<'a> FileNameCollision<'a> {
+    pub fn new(
+        library: &'a LibraryConfiguration,
+        album_directory_path: PathBuf,
+        colliding_files: (PathBuf, PathBuf),
+    ) -> Self {
+        Self {
+            library,
+            album_directory_path,
+            colliding_files,
+        }
+    }
+
+    /// Get the album directory path relative to its library root.
+    fn relative_album_directory_path(&self) -> Result<PathBuf> {
+        pathdiff::diff_paths(&self.album_directory_path, &self.library.path)
+            .ok_or_else(|| {
+                miette!("Could not make album path relative to library base!")
+            })
+    }
+
+    fn colliding_file_names(&self) -> Vec<String> {
+        vec![
+            self.colliding_files.0.to_string_lossy().to_string(),
+            self.colliding_files.1.to_string_lossy().to_string(),
+        ]
+    }
+}
+
+impl<'a> ValidationErrorDisplay for FileNameCollision<'a> {
+    fn get_error_info(&self) -> Result<ValidationErrorInfo> {
+        // (FileNameCollision validation error display example)
+        //
+        // # Case-insensitive File Name Collision
+        //
+        // Library: Lossless
+        // Album: Aindulmedir/The Lunar Lexicon
+        // Colliding files: Cover.jpg, cover.jpg
+
+        let attributes = vec![
+            ("Library".to_string(), self.library.name.clone()),
+            (
+                "Album".to_string(),
+                self.relative_album_directory_path()?
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            (
+                "Colliding files".to_string(),
+                self.colliding_file_names().join(", "),
+            ),
+        ];
+
+        Ok(ValidationErrorInfo::new(
+            "Case-insensitive File Name Collision",
+            attributes,
+        ))
+    }
+}
+
+
+/// This validation error happens when a library can't be scanned at all
+/// (e.g. its directory is missing or can't be read). The rest of the libraries are still validated.
+pub struct UnscannableLibrary<'a> {
+    /// The library that could not be scanned.
+    library: &'a LibraryConfiguration,
+
+    /// Why the library could not be scanned (the entire error chain).
+    reason: String,
+}
+
+impl<'a> UnscannableLibrary<'a> {
+    pub fn new(
+        library: &'a LibraryConfiguration,
+        error: miette::Report,
+    ) -> Self {
+        Self {
+            library,
+            reason: error
+                .chain()
+                .map(|cause| cause.to_string())
+                .collect::<Vec<String>>()
+                .join(": "),
+        }
+    }
+}
+
+impl<'a> ValidationErrorDisplay for UnscannableLibrary<'a> {
+    fn get_error_info(&self) -> Result<ValidationErrorInfo> {
+        // (UnscannableLibrary validation error display example)
+        //
+        // # Library could not be scanned.
+        //
+        // Library: Standard
+        // Path: C:/StandardLibrary
+        // Reason: No such directory: C:/StandardLibrary
+
+        let attributes = vec![
+            ("Library".to_string(), self.library.name.clone()),
+            ("Path".to_string(), self.library.path.clone()),
+            ("Reason".to_string(), self.reason.clone()),
+        ];
+
+        Ok(ValidationErrorInfo::new(
+            "Library could not be scanned.",
+            attributes,
+        ))
+    }
+}
+
+
+/// This validation warning happens when an album directory doesn't contain any tracked audio files,
+/// but does contain data files, so there is nothing to transcode. Albums without any tracked files
+/// at all are reported as `EmptyDirectory` instead.
+pub struct AlbumWithoutAudioFiles<'a> {
+    /// The library the album is part of.
+    library: &'a LibraryConfiguration,
+
+    /// Absolute path of the album directory.
+    album_directory_path: PathBuf,
+
+    /// Number of tracked data files in the album directory.
+    num_data_files: usize,
+}
+
+impl<'a> AlbumWithoutAudioFiles<'a> {
+    pub fn new(
+        library: &'a LibraryConfiguration,
+        album_directory_path: PathBuf,
+        num_data_files: usize,
+    ) -> Self {
+        Self {
+            library,
+            album_directory_path,
+            num_data_files,
+        }
+    }
+
+    /// Get the album directory path relative to its library root.
+    fn relative_album_directory_path(&self) -> Result<PathBuf> {
+        pathdiff::diff_paths(&self.album_directory_path, &self.library.path)
+            .ok_or_else(|| {
+                miette!("Could not make album path relative to library base!")
+            })
+    }
+}
+
+impl<'a> ValidationErrorDisplay for AlbumWithoutAudioFiles<'a> {
+    fn get_error_info(&self) -> Result<ValidationErrorInfo> {
+        // (AlbumWithoutAudioFiles validation warning display example)
+        //
+        // # Album has no audio files.
+        //
+        // Library: Lossless
+        // Album: Aindulmedir/The Lunar Lexicon
+        // Data files: 2
+
+        let attributes = vec![
+            ("Library".to_string(), self.library.name.clone()),
+            (
+                "Album".to_string(),
+                self.relative_album_directory_path()?
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            (
+                "Data files".to_string(),
+                self.num_data_files.to_string(),
+            ),
+        ];
+
+        Ok(ValidationErrorInfo::new(
+            "Album has no audio files.",
+            attributes,
+        ))
+    }
+}
+
+
+/// Describes which kind of directory the "empty directory" validation warning is about.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyDirectoryLocation {
+    /// An artist directory without any album directories.
+    ArtistDirectory,
+
+    /// An album directory without any tracked (audio or data) files.
+    AlbumDirectory,
+}
+
+/// This validation warning happens when an artist directory doesn't contain any album directories
+/// or an album directory doesn't contain any tracked files. Such directories are usually
+/// leftovers from moving or deleting music.
+pub struct EmptyDirectory<'a> {
+    /// The library the directory is part of.
+    library: &'a LibraryConfiguration,
+
+    /// Absolute path of the empty directory.
+    directory_path: PathBuf,
+
+    /// Whether this is an artist or an album directory.
+    location: EmptyDirectoryLocation,
+}
+
+impl<'a> EmptyDirectory<'a> {
+    pub fn new(
+        library: &'a LibraryConfiguration,
+        directory_path: PathBuf,
+        location: EmptyDirectoryLocation,
+    ) -> Self {
+        Self {
+            library,
+            directory_path,
+            location,
+        }
+    }
+
+    /// Get the directory path relative to its library root.
+    fn relative_directory_path(&self) -> Result<PathBuf> {
+        pathdiff::diff_paths(&self.directory_path, &self.library.path)
+            .ok_or_else(|| {
+                miette!(
+                    "Could not make directory path relative to library base!"
+                )
+            })
+    }
+}
+
+impl<'a> ValidationErrorDisplay for EmptyDirectory<'a> {
+    fn get_error_info(&self) -> Result<ValidationErrorInfo> {
+        // (EmptyDirectory validation warning display example)
+        //
+        // # Empty album directory.
+        //
+        // Library: Lossless
+        // Directory: Aindulmedir/The Lunar Lexicon
+
+        let header = match self.location {
+            EmptyDirectoryLocation::ArtistDirectory => "Empty artist directory.",
+            EmptyDirectoryLocation::AlbumDirectory => "Empty album directory.",
+        };
+
+        let attributes = vec![
+            ("Library".to_string(), self.library.name.clone()),
+            (
+                "Directory".to_string(),
+                self.relative_directory_path()?
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+        ];
+
+        Ok(ValidationErrorInfo::new(header, attributes))
+    }
+}
+
+
+/// This validation warning happens when the metadata (size and times) of a tracked file can't be read
+/// (e.g. because of missing permissions). `transcode` skips albums with such files (unless `--strict` is set).
+pub struct UnreadableFile<'a> {
+    /// The library the file is part of.
+    library: &'a LibraryConfiguration,
+
+    /// Absolute path of the unreadable file.
+    file_path: PathBuf,
+
+    /// Why the file could not be read (the entire error chain).
+    reason: String,
+}
+
+impl<'a> UnreadableFile<'a> {
+    pub fn new(
+        library: &'a LibraryConfiguration,
+        file_path: PathBuf,
+        error: miette::Report,
+    ) -> Self {
+        Self {
+            library,
+            file_path,
+            reason: error
+                .chain()
+                .map(|cause| cause.to_string())
+                .collect::<Vec<String>>()
+                .join(": "),
+        }
+    }
+
+    /// Get the file path relative to its library root.
+    fn relative_file_path(&self) -> Result<PathBuf> {
+        pathdiff::diff_paths(&self.file_path, &self.library.path).ok_or_else(
+            || miette!("Could not make file path relative to library base!"),
+        )
+    }
+}
+
+impl<'a> ValidationErrorDisplay for UnreadableFile<'a> {
+    fn get_error_info(&self) -> Result<ValidationErrorInfo> {
+        // (UnreadableFile validation warning display example)
+        //
+        // # File could not be read.
+        //
+        // Library: Lossless
+        // File: Aindulmedir/The Lunar Lexicon/01 Aindulmedir - Relic.flac
+        // Reason: Could not retrieve metadata for file: ...: Permission denied (os error 13)
+
+        let attributes = vec![
+            ("Library".to_string(), self.library.name.clone()),
+            (
+                "File".to_string(),
+                self.relative_file_path()?.to_string_lossy().to_string(),
+            ),
+            ("Reason".to_string(), self.reason.clone()),
+        ];
+
+        Ok(ValidationErrorInfo::new(
+            "File could not be read.",
+            attributes,
+        ))
+    }
+}
+
+
 /// Represents an album belonging to a specific artist in a specific library.
 /// Used by `LibraryValidator` to keep track of all available albums.
 pub struct ValidationAlbumEntry<'a> {
@@ -276,36 +985,62 @@ impl<'a> ValidationErrorDisplay for AlbumCollision<'a> {
 /// - instantiate an empty `CollectionCollisionValidator`,
 /// - call `add_album_entry` with all your albums from all your libraries,
 /// - when finished, call `find_collisions` to receive information about potential collisions.
+///
+/// Only albums that are transcoded into the same transcoded library can collide
+/// (libraries with their own `output_path` are transcoded separately).
 struct CollectionCollisionValidator<'a> {
-    /// A nested map from artist names to album names to sets of individual (colliding) albums.
-    artist_to_albums:
+    configuration: &'a Configuration,
+
+    /// A nested map from transcoded library paths to artist names to album names
+    /// to sets of individual (colliding) albums.
+    transcoded_library_to_artist_albums: HashMap<
+        String,
         HashMap<String, HashMap<String, HashSet<ValidationAlbumEntry<'a>>>>,
+    >,
 }
 
 impl<'a> CollectionCollisionValidator<'a> {
     /// Create a new empty `LibraryValidator`.
-    pub fn new() -> Self {
+    pub fn new(configuration: &'a Configuration) -> Self {
         Self {
-            artist_to_albums: HashMap::new(),
+            configuration,
+            transcoded_library_to_artist_albums: HashMap::new(),
         }
     }
 
     /// Add a new album entry into the validator by providing the album's title, artist name
     /// and the library is it in. This method returns `Err` only in the case of this exact combination
     /// (including library) already existing in the validator (which is a bug, not a collision).
+    // `ValidationAlbumEntry` only hashes the library name, not the (compiled regex inside the)
+    // library configuration, so the key can't change while it is in the set.
+    #[allow(clippy::mutable_key_type)]
     pub fn add_album_entry<S: Into<String>>(
         &mut self,
         artist_name: S,
         album_title: S,
         library: &'a LibraryConfiguration,
     ) -> Result<()> {
-        let artist_name = artist_name.into();
-        let album_title = album_title.into();
+        // Albums are matched by their (whitespace-normalized, see `normalize_whitespace`) names,
+        // which is also what gets recorded in the entry.
+        let artist_name = self
+            .configuration
+            .aggregated_library
+            .normalized_directory_name(&artist_name.into());
+        let album_title = self
+            .configuration
+            .aggregated_library
+            .normalized_directory_name(&album_title.into());
 
         let entry = ValidationAlbumEntry::new(artist_name, album_title, library);
 
         let artist_albums = self
-            .artist_to_albums
+            .transcoded_library_to_artist_albums
+            .entry(
+                self.configuration
+                    .transcoded_library_path(library)
+                    .to_string(),
+            )
+            .or_default()
             .entry(entry.artist_name.clone())
             .or_default();
 
@@ -316,7 +1051,11 @@ impl<'a> CollectionCollisionValidator<'a> {
 
         // It is possible (but would be a bug) that the exact same entry from the same library
         // would be inserted multiple times. In that case we return early.
-        if exact_entry_already_existed {
+        // With `normalize_whitespace`, however, two albums from the same library can legitimately
+        // normalize to the same names (they are transcoded into the same directory).
+        if exact_entry_already_existed
+            && !self.configuration.aggregated_library.normalize_whitespace
+        {
             return Err(miette!("Exact entry already exists in set."));
         }
 
@@ -327,8 +1066,9 @@ impl<'a> CollectionCollisionValidator<'a> {
     /// Get a list of album collisions in this validator. A single collision represents two or more
     /// of the same album colliding in multiple different libraries.
     pub fn find_collisions(&'a self) -> Result<Vec<AlbumCollision<'a>>> {
-        self.artist_to_albums
+        self.transcoded_library_to_artist_albums
             .values()
+            .flat_map(|artist_to_albums| artist_to_albums.values())
             .flatten()
             .filter_map(|(_, album_set)| {
                 if album_set.len() > 1 {
@@ -347,11 +1087,340 @@ impl<'a> CollectionCollisionValidator<'a> {
     }
 }
 
+/// Finds inter-library album collisions among the given libraries: albums from different libraries
+/// that would be transcoded into the same directory (see `album_directory_in_transcoded_library`,
+/// which also takes e.g. `album_directory_template` into account). Albums with `skip_transcode` are ignored.
+///
+/// Returns a sorted description of each collision
+/// (e.g. `"/transcoded/Artist/Album" (Lossless: "Artist/Album", Standard: "Artist/Album")`).
+pub fn find_inter_library_album_collisions(
+    libraries: &[SharedLibraryView],
+) -> Result<Vec<String>> {
+    // Maps from transcoded album directories to the library names and album paths transcoded into them.
+    let mut transcoded_album_directories: HashMap<
+        PathBuf,
+        Vec<(String, String)>,
+    > = HashMap::new();
+
+    for library_view in libraries {
+        let library_name = library_view.read().name();
+
+        for (artist_name, artist_view) in library_view.read().artists()? {
+            for (album_title, album_view) in artist_view.read().albums()? {
+                let album_view_locked = album_view.read();
+                if album_view_locked.configuration.skip_transcode {
+                    continue;
+                }
+
+                transcoded_album_directories
+                    .entry(
+                        album_view_locked
+                            .album_directory_in_transcoded_library(),
+                    )
+                    .or_default()
+                    .push((
+                        library_name.clone(),
+                        format!("{artist_name}/{album_title}"),
+                    ));
+            }
+        }
+    }
+
+    let mut collision_descriptions = transcoded_album_directories
+        .into_iter()
+        .filter_map(|(transcoded_album_directory, mut albums)| {
+            let num_libraries = albums
+                .iter()
+                .map(|(library_name, _)| library_name)
+                .collect::<HashSet<_>>()
+                .len();
+            if num_libraries < 2 {
+                return None;
+            }
+
+            albums.sort_unstable();
+
+            Some(format!(
+                "{:?} ({})",
+                transcoded_album_directory,
+                albums
+                    .iter()
+                    .map(|(library_name, album_path)| {
+                        format!("{library_name}: \"{album_path}\"")
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ))
+        })
+        .collect::<Vec<String>>();
+    collision_descriptions.sort_unstable();
+
+    Ok(collision_descriptions)
+}
+
+/// Validates a single library: looks for unexpected files in its root, artist and album directories
+/// (adding them to `validation_errors`) and registers its albums with `collision_validator`.
+fn validate_library<'a>(
+    config: &'a Configuration,
+    library_config: &'a LibraryConfiguration,
+    library_view: &SharedLibraryView<'a>,
+    collision_validator: &mut CollectionCollisionValidator<'a>,
+    validation_errors: &mut Vec<ValidationError<'a>>,
+) -> Result<()> {
+    let is_any_audio_file = |file_path: &Path| {
+        let file_extension = file_path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_ascii_lowercase();
+
+        config
+            .validation
+            .extensions_considered_audio_files
+            .contains(&file_extension)
+    };
+
+    let library_view_locked = library_view.read();
+
+    let ignored_directories_in_base_directory: HashSet<&String> =
+        match &library_config.ignored_directories_in_base_directory {
+            Some(ignored_dirs) => HashSet::from_iter(ignored_dirs.iter()),
+            None => HashSet::new(),
+        };
+
+    let allowed_audio_file_extensions =
+        &library_config.validation.allowed_audio_file_extensions;
+    let allowed_other_file_extensions =
+        &library_config.validation.allowed_other_file_extensions;
+    let allowed_other_files_by_name =
+        &library_config.validation.allowed_other_files_by_name;
+
+    // Handy closures for repeated file validity checks.
+    let is_valid_library_audio_file = |file_path: &Path| {
+        let file_extension = file_path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_ascii_lowercase();
+
+        allowed_audio_file_extensions.contains(&file_extension)
+    };
+
+    let is_valid_library_non_audio_file = |file_path: &Path| {
+        let file_name = file_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        let file_extension = file_path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_ascii_lowercase();
+
+        allowed_other_file_extensions.contains(&file_extension)
+            || allowed_other_files_by_name.contains(&file_name)
+    };
+
+    // Check for unexpected files in the root library directory.
+    let root_library_files_to_check =
+        library_view_locked.library_root_validation_files()?;
+    for root_file in root_library_files_to_check {
+        let root_file_name = root_file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        // Allow `.library.state.euphony` and `.library.scan-cache.euphony`.
+        if root_file_name.eq(LIBRARY_STATE_FILE_NAME)
+            || root_file_name.eq(LIBRARY_SCAN_CACHE_FILE_NAME)
+        {
+            continue;
+        }
+
+        if !is_valid_library_non_audio_file(root_file.as_path()) {
+            validation_errors.push(ValidationError::new_unexpected_file(
+                root_file,
+                library_config,
+                UnexpectedFileLocation::LibraryRoot,
+            ))
+        }
+    }
+
+    // Check for unexpected files in each artist directory.
+    for (artist_name, artist_view) in library_view_locked.artists()? {
+        if ignored_directories_in_base_directory.contains(&artist_name) {
+            continue;
+        }
+
+        let artist_view_locked = artist_view.read();
+
+        let artist_files =
+            artist_view_locked.artist_directory_validation_files()?;
+        for artist_dir_file_path in artist_files {
+            if !is_valid_library_non_audio_file(artist_dir_file_path.as_path()) {
+                validation_errors.push(ValidationError::new_unexpected_file(
+                    artist_dir_file_path,
+                    library_config,
+                    UnexpectedFileLocation::ArtistDirectory,
+                ))
+            }
+        }
+
+        // Artist directories without any albums are usually leftovers.
+        let artist_albums = artist_view_locked.albums()?;
+        if artist_albums.is_empty() {
+            validation_errors.push(ValidationError::new_empty_directory(
+                library_config,
+                artist_view_locked.artist_directory_in_source_library(),
+                EmptyDirectoryLocation::ArtistDirectory,
+            ));
+        }
+
+        // Iterate over each of their albums and validate those as well.
+        for (album_title, album_view) in artist_albums {
+            collision_validator
+                .add_album_entry(&artist_name, &album_title, library_config)
+                .wrap_err_with(|| miette!("BUG: Duplicate album entry."))?;
+
+            let album_view_locked = album_view.read();
+
+            // Albums excluded from transcoding (see `skip_transcode` in the album override file)
+            // still take part in the collision check above, but their contents aren't validated.
+            if album_view_locked.configuration.skip_transcode {
+                continue;
+            }
+
+            let album_files = album_view_locked.album_validation_files()?;
+            for album_dir_file_path in album_files {
+                let album_dir_file_name = album_dir_file_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+
+                if album_dir_file_name.eq(SOURCE_ALBUM_STATE_FILE_NAME)
+                    || album_dir_file_name.eq(ALBUM_OVERRIDE_FILE_NAME)
+                {
+                    continue;
+                }
+
+                let is_any_audio =
+                    is_any_audio_file(album_dir_file_path.as_path());
+                let is_valid_audio =
+                    is_valid_library_audio_file(album_dir_file_path.as_path());
+                let is_valid_non_audio = is_valid_library_non_audio_file(
+                    album_dir_file_path.as_path(),
+                );
+
+                if is_any_audio && !is_valid_audio {
+                    // File was an audio file, but not the kind that we allow in this library.
+                    validation_errors.push(
+                        ValidationError::new_unexpected_file(
+                            &album_dir_file_path,
+                            library_config,
+                            UnexpectedFileLocation::AlbumDirectoryAudio,
+                        ),
+                    );
+                } else if !is_any_audio && !is_valid_non_audio {
+                    // File was not an audio file nor a valid non-audio (data) file in this library.
+                    validation_errors.push(
+                        ValidationError::new_unexpected_file(
+                            &album_dir_file_path,
+                            library_config,
+                            UnexpectedFileLocation::AlbumDirectoryOther,
+                        ),
+                    );
+                }
+            }
+
+            // Tracked files whose transcoded paths only differ in case would overwrite
+            // each other on case-insensitive filesystems.
+            let album_directory_path =
+                album_view_locked.album_directory_in_source_library();
+            drop(album_view_locked);
+
+            let album_source_files =
+                AlbumSourceFileList::from_album_view(album_view.clone())?;
+
+            for colliding_files in
+                album_source_files.find_case_insensitive_collisions()
+            {
+                validation_errors.push(
+                    ValidationError::new_file_name_collision(
+                        library_config,
+                        album_directory_path.clone(),
+                        colliding_files,
+                    ),
+                );
+            }
+
+            // Tracked files whose metadata can't be read would make `transcode` skip the album.
+            for tracked_file_path in album_source_files
+                .audio_files
+                .iter()
+                .chain(album_source_files.data_files.iter())
+            {
+                let absolute_file_path =
+                    album_directory_path.join(tracked_file_path);
+
+                if let Err(error) =
+                    FileTrackedMetadata::from_file_path(&absolute_file_path)
+                {
+                    validation_errors.push(
+                        ValidationError::new_unreadable_file(
+                            library_config,
+                            absolute_file_path,
+                            error,
+                        ),
+                    );
+                }
+            }
+
+            // Albums without any tracked files are usually leftovers, and albums
+            // without any audio files have nothing to transcode, which is usually a mistake.
+            if album_source_files.audio_files.is_empty()
+                && album_source_files.data_files.is_empty()
+            {
+                validation_errors.push(ValidationError::new_empty_directory(
+                    library_config,
+                    album_directory_path,
+                    EmptyDirectoryLocation::AlbumDirectory,
+                ));
+            } else if album_source_files.audio_files.is_empty() {
+                validation_errors.push(
+                    ValidationError::new_album_without_audio_files(
+                        library_config,
+                        album_directory_path,
+                        album_source_files.data_files.len(),
+                    ),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Number of validation errors and warnings found (see `ValidationSeverity`).
+#[derive(Clone, Copy, Default)]
+pub struct ValidationCounts {
+    pub errors: usize,
+
+    pub warnings: usize,
+}
+
 /// Runs the validation process over the entire collection (all registered libraries).
+///
+/// If `output_json` is `true`, the validation errors are printed as a JSON array
+/// (see `ValidationErrorReport`) instead. Returns the number of validation errors and warnings.
 fn validate_entire_collection(
     config: &Configuration,
+    output_json: bool,
     terminal: &mut ValidationTerminal,
-) -> Result<()> {
+) -> Result<ValidationCounts> {
     // As explained in the README and configuration template, library structure
     // is expected to be the following:
     //
@@ -372,7 +1441,8 @@ fn validate_entire_collection(
     // |   |   |     (settings for other files (see below) apply here as well)
     // |   |   |
     // |   |   | ... <possibly other directories that don't really matter for transcoding>
-    // |   |   |     (album subdirectories are ignored by default, see `depth` in per-album configuration)
+    // |   |   |     (album subdirectories are ignored by default, see `depth` in per-album configuration
+    // |   |   |      and `recurse_album_subdirs` in the validation configuration)
     // |
     // |-- <any directory (directly in the library directory) that has been ignored>
     // |   (it is sometimes useful to have additional directories inside your library that are
@@ -419,11 +1489,11 @@ fn validate_entire_collection(
     // ```
     //
     // NOTE: Specifying the files to transcode or copy is not directly linked to validation! See
-    // `tracked_audio_extensions` and `tracked_other_extensions`, which dictate which
-    // extensions are transcoded and which are copied when running the `transcode` command.
+    // each library's `transcoding.audio_file_extensions` and `transcoding.other_file_extensions`,
+    // which dictate which extensions are transcoded and which are copied when running the `transcode` command.
 
     let mut validation_errors: Vec<ValidationError> = Vec::new();
-    let mut collision_validator = CollectionCollisionValidator::new();
+    let mut collision_validator = CollectionCollisionValidator::new(config);
 
     // For each library, check the following:
     //  1. Unexpected files in the root library directory,
@@ -433,163 +1503,51 @@ fn validate_entire_collection(
     // As we're validating albums we're also performing an artist-album collision check
     // between all registered libraries.
 
-    let is_any_audio_file = |file_path: &Path| {
-        let file_extension = file_path
-            .extension()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_ascii_lowercase();
-
-        config
-            .validation
-            .extensions_considered_audio_files
-            .contains(&file_extension)
-    };
-
     for library_config in config.libraries.values() {
-        let library_view =
-            LibraryView::from_library_configuration(config, library_config)?;
-        let library_view_locked = library_view.read();
-
-        let ignored_directories_in_base_directory: HashSet<&String> =
-            match &library_config.ignored_directories_in_base_directory {
-                Some(ignored_dirs) => HashSet::from_iter(ignored_dirs.iter()),
-                None => HashSet::new(),
-            };
-
-        let allowed_audio_file_extensions =
-            &library_config.validation.allowed_audio_file_extensions;
-        let allowed_other_file_extensions =
-            &library_config.validation.allowed_other_file_extensions;
-        let allowed_other_files_by_name =
-            &library_config.validation.allowed_other_files_by_name;
-
-        // Handy closures for repeated file validity checks.
-        let is_valid_library_audio_file = |file_path: &Path| {
-            let file_extension = file_path
-                .extension()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_ascii_lowercase();
-
-            allowed_audio_file_extensions.contains(&file_extension)
-        };
-
-        let is_valid_library_non_audio_file = |file_path: &Path| {
-            let file_name = file_path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
-
-            let file_extension = file_path
-                .extension()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_ascii_lowercase();
-
-            allowed_other_file_extensions.contains(&file_extension)
-                || allowed_other_files_by_name.contains(&file_name)
-        };
-
-        // Check for unexpected files in the root library directory.
-        let root_library_files_to_check =
-            library_view_locked.library_root_validation_files()?;
-        for root_file in root_library_files_to_check {
-            let root_file_name = root_file
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
-
-            // Allow `.library.state.euphony`.
-            if root_file_name.eq(LIBRARY_STATE_FILE_NAME) {
+        let library_view = match LibraryView::from_library_configuration(
+            config,
+            library_config,
+        ) {
+            Ok(library_view) => library_view,
+            Err(LibraryViewError::NoSuchDirectory(_))
+                if library_config.optional && output_json =>
+            {
                 continue;
             }
-
-            if !is_valid_library_non_audio_file(root_file.as_path()) {
-                validation_errors.push(ValidationError::new_unexpected_file(
-                    root_file,
-                    library_config,
-                    UnexpectedFileLocation::LibraryRoot,
-                ))
-            }
-        }
-
-        // Check for unexpected files in each artist directory.
-        for (artist_name, artist_view) in library_view_locked.artists()? {
-            if ignored_directories_in_base_directory.contains(&artist_name) {
+            Err(LibraryViewError::NoSuchDirectory(path))
+                if library_config.optional =>
+            {
+                log_optional_library_unavailable(
+                    terminal,
+                    &library_config.name,
+                    &path,
+                );
                 continue;
             }
-
-            let artist_view_locked = artist_view.read();
-
-            let artist_files =
-                artist_view_locked.artist_directory_validation_files()?;
-            for artist_dir_file_path in artist_files {
-                if !is_valid_library_non_audio_file(
-                    artist_dir_file_path.as_path(),
-                ) {
-                    validation_errors.push(ValidationError::new_unexpected_file(
-                        artist_dir_file_path,
+            Err(error) => {
+                validation_errors.push(
+                    ValidationError::new_unscannable_library(
                         library_config,
-                        UnexpectedFileLocation::ArtistDirectory,
-                    ))
-                }
+                        error.into(),
+                    ),
+                );
+                continue;
             }
+        };
 
-            // Iterate over each of their albums and validate those as well.
-            for (album_title, album_view) in artist_view_locked.albums()? {
-                collision_validator
-                    .add_album_entry(&artist_name, &album_title, library_config)
-                    .wrap_err_with(|| miette!("BUG: Duplicate album entry."))?;
-
-                let album_view_locked = album_view.read();
-
-                let album_files = album_view_locked.album_validation_files()?;
-                for album_dir_file_path in album_files {
-                    let album_dir_file_name = album_dir_file_path
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string();
-
-                    if album_dir_file_name.eq(SOURCE_ALBUM_STATE_FILE_NAME)
-                        || album_dir_file_name.eq(ALBUM_OVERRIDE_FILE_NAME)
-                    {
-                        continue;
-                    }
-
-                    let is_any_audio =
-                        is_any_audio_file(album_dir_file_path.as_path());
-                    let is_valid_audio = is_valid_library_audio_file(
-                        album_dir_file_path.as_path(),
-                    );
-                    let is_valid_non_audio = is_valid_library_non_audio_file(
-                        album_dir_file_path.as_path(),
-                    );
-
-                    if is_any_audio && !is_valid_audio {
-                        // File was an audio file, but not the kind that we allow in this library.
-                        validation_errors.push(
-                            ValidationError::new_unexpected_file(
-                                &album_dir_file_path,
-                                library_config,
-                                UnexpectedFileLocation::AlbumDirectoryAudio,
-                            ),
-                        );
-                    } else if !is_any_audio && !is_valid_non_audio {
-                        // File was not an audio file nor a valid non-audio (data) file in this library.
-                        validation_errors.push(
-                            ValidationError::new_unexpected_file(
-                                &album_dir_file_path,
-                                library_config,
-                                UnexpectedFileLocation::AlbumDirectoryOther,
-                            ),
-                        );
-                    }
-                }
-            }
+        // A library that can't be scanned (e.g. due to a permission error) is reported
+        // as a validation error of its own instead of aborting the entire validation.
+        if let Err(error) = validate_library(
+            config,
+            library_config,
+            &library_view,
+            &mut collision_validator,
+            &mut validation_errors,
+        ) {
+            validation_errors.push(ValidationError::new_unscannable_library(
+                library_config,
+                error,
+            ));
         }
     }
 
@@ -603,6 +1561,32 @@ fn validate_entire_collection(
 
 
     // We've completed the validation process, we'll now display the results.
+    let num_validation_warnings = validation_errors
+        .iter()
+        .filter(|error| error.severity() == ValidationSeverity::Warning)
+        .count();
+    let validation_counts = ValidationCounts {
+        errors: validation_errors.len() - num_validation_warnings,
+        warnings: num_validation_warnings,
+    };
+
+    if output_json {
+        let validation_error_reports = validation_errors
+            .iter()
+            .map(|error| error.to_report())
+            .collect::<Result<Vec<ValidationErrorReport>>>()?;
+
+        let serialized_reports =
+            serde_json::to_string_pretty(&validation_error_reports)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!("Could not serialize validation errors into JSON.")
+                })?;
+
+        terminal.log_println(serialized_reports);
+        return Ok(validation_counts);
+    }
+
     let validation_errors_vec: Vec<ValidationErrorInfo> = validation_errors
         .into_iter()
         .map(|error| error.into_validation_error_info())
@@ -611,31 +1595,71 @@ fn validate_entire_collection(
     if validation_errors_vec.is_empty() {
         terminal.log_println("All libraries validated, no errors.".green());
     } else {
-        terminal.log_println(
-            format!(
-                "{} validation errors!",
-                validation_errors_vec.len()
-            )
-            .red(),
-        );
+        if validation_counts.errors > 0 {
+            terminal.log_println(
+                format!(
+                    "{} validation errors and {} warnings!",
+                    validation_counts.errors, validation_counts.warnings
+                )
+                .red(),
+            );
+        } else {
+            terminal.log_println(
+                format!(
+                    "No validation errors, but {} warnings.",
+                    validation_counts.warnings
+                )
+                .yellow(),
+            );
+        }
 
         for error in validation_errors_vec {
             terminal.validation_add_error(error);
         }
     }
 
-    Ok(())
+    Ok(validation_counts)
 }
 
 /// Associated with the `validate` command.
 ///
 /// Validates the entire collection for unexpected files and album collisions.
+/// If `output_json` is `true`, the results are printed as JSON instead.
+/// Returns the number of validation errors and warnings.
+///
+/// With the fancy terminal UI, any validation errors (and warnings) are kept on screen until the user exits.
 pub fn cmd_validate(
     config: &Configuration,
+    output_json: bool,
     terminal: &mut ValidationTerminal,
-) -> Result<()> {
-    terminal.log_println("Command: validate entire collection.".cyan().bold());
+) -> Result<ValidationCounts> {
+    if !output_json {
+        terminal
+            .log_println("Command: validate entire collection.".cyan().bold());
+    }
 
-    validate_entire_collection(config, terminal)?;
-    Ok(())
+    let validation_counts =
+        validate_entire_collection(config, output_json, terminal)?;
+
+    if validation_counts.errors > 0 || validation_counts.warnings > 0 {
+        if let ValidationTerminal::Fancy(_) = terminal {
+            let mut terminal_user_input =
+                terminal.get_user_control_receiver()?;
+
+            terminal.log_println(
+                "Press q to exit (scroll through the errors with the up and down keys).",
+            );
+
+            loop {
+                match terminal_user_input.blocking_recv() {
+                    Ok(UserControlMessage::Exit) | Err(RecvError::Closed) => {
+                        break
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                }
+            }
+        }
+    }
+
+    Ok(validation_counts)
 }
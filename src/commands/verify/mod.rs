@@ -0,0 +1,331 @@
+use crossterm::style::Stylize;
+use euphony_configuration::Configuration;
+use euphony_library::state::common::FileTrackedMetadata;
+use euphony_library::state::transcoded::{
+    TranscodedAlbumState,
+    TranscodedAlbumStateLoadError,
+};
+use euphony_library::view::library::LibraryViewError;
+use euphony_library::view::{AlbumSourceFileList, LibraryView, SharedAlbumView};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Serialize;
+
+use crate::commands::log_optional_library_unavailable;
+use crate::console::frontends::SimpleTerminal;
+use crate::console::LogBackend;
+
+
+/// A problem found by `verify` (printed as JSON with `--format json`).
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum VerificationError {
+    /// The file is tracked in the transcoded album state, but doesn't exist anymore.
+    MissingFile {
+        library: String,
+
+        artist: String,
+
+        album: String,
+
+        /// File path, relative to the transcoded album directory.
+        relative_path: String,
+    },
+
+    /// The file exists, but its size or times differ from the ones in the transcoded album state.
+    ModifiedFile {
+        library: String,
+
+        artist: String,
+
+        album: String,
+
+        /// File path, relative to the transcoded album directory.
+        relative_path: String,
+    },
+
+    /// The album's source directory has a different number of audio files than the transcoded
+    /// album state, even though no source audio files changed since the last transcode
+    /// (usually because a track failed to transcode).
+    TrackCountMismatch {
+        library: String,
+
+        artist: String,
+
+        album: String,
+
+        /// Number of tracked audio files in the source album directory.
+        expected_audio_files: usize,
+
+        /// Number of audio files in the transcoded album state.
+        actual_audio_files: usize,
+    },
+}
+
+/// Compare each file tracked in the album's saved transcoded state against the transcoded
+/// album directory. Albums without a (compatible) saved transcoded state are skipped,
+/// as they haven't been transcoded yet.
+///
+/// Additionally, if no source audio files were added, changed or removed since the last transcode,
+/// the number of tracked source audio files must match the number of transcoded audio files.
+fn verify_album(
+    library_name: &str,
+    artist_name: &str,
+    album_title: &str,
+    album: SharedAlbumView,
+) -> Result<Vec<VerificationError>> {
+    let (transcoded_album_directory, change_detection_fields) = {
+        let album_locked = album.read();
+
+        (
+            album_locked.album_directory_in_transcoded_library(),
+            album_locked
+                .library_configuration()
+                .transcoding
+                .change_detection_fields
+                .clone(),
+        )
+    };
+
+    let transcoded_state = match TranscodedAlbumState::load_from_directory(
+        &transcoded_album_directory,
+    ) {
+        Ok(state) => state,
+        Err(TranscodedAlbumStateLoadError::NotFound)
+        | Err(TranscodedAlbumStateLoadError::SchemaVersionMismatch(_)) => {
+            return Ok(Vec::new());
+        }
+        Err(error) => return Err(error.into()),
+    };
+
+    let mut tracked_files = transcoded_state
+        .transcoded_files
+        .audio_files
+        .iter()
+        .chain(transcoded_state.transcoded_files.data_files.iter())
+        .collect::<Vec<_>>();
+    tracked_files.sort_unstable_by(|first, second| first.0.cmp(second.0));
+
+    let mut verification_errors: Vec<VerificationError> = Vec::new();
+
+    for (relative_path, saved_metadata) in tracked_files {
+        let file_path = transcoded_album_directory.join(relative_path);
+
+        if !file_path.is_file() {
+            verification_errors.push(VerificationError::MissingFile {
+                library: library_name.to_string(),
+                artist: artist_name.to_string(),
+                album: album_title.to_string(),
+                relative_path: relative_path.clone(),
+            });
+
+            continue;
+        }
+
+        let current_metadata = FileTrackedMetadata::from_file_path(&file_path)
+            .wrap_err_with(|| {
+            miette!("Could not read metadata of {:?}.", file_path)
+        })?;
+
+        if !saved_metadata.matches(&current_metadata, &change_detection_fields) {
+            verification_errors.push(VerificationError::ModifiedFile {
+                library: library_name.to_string(),
+                artist: artist_name.to_string(),
+                album: album_title.to_string(),
+                relative_path: relative_path.clone(),
+            });
+        }
+    }
+
+    // Pending source changes (e.g. a newly added track) naturally change the track count,
+    // so we only compare the counts when the source audio files are unchanged.
+    let album_changes = album.read().scan_for_changes(false)?;
+    let has_pending_source_changes = !album_changes
+        .added_in_source_since_last_transcode
+        .audio
+        .is_empty()
+        || !album_changes
+            .changed_in_source_since_last_transcode
+            .audio
+            .is_empty()
+        || !album_changes
+            .removed_from_source_since_last_transcode
+            .audio
+            .is_empty();
+
+    if !has_pending_source_changes {
+        let expected_audio_files = AlbumSourceFileList::from_album_view(album)?
+            .audio_files
+            .len();
+        let actual_audio_files =
+            transcoded_state.transcoded_files.audio_files.len();
+
+        if expected_audio_files != actual_audio_files {
+            verification_errors.push(VerificationError::TrackCountMismatch {
+                library: library_name.to_string(),
+                artist: artist_name.to_string(),
+                album: album_title.to_string(),
+                expected_audio_files,
+                actual_audio_files,
+            });
+        }
+    }
+
+    Ok(verification_errors)
+}
+
+/// Associated with the `verify` command.
+///
+/// Loads the saved transcoded state of each album and checks that every tracked transcoded file
+/// still exists and matches its saved metadata (size and creation/modification time), and that
+/// the number of transcoded audio files matches the source album (see `verify_album`).
+/// Nothing is modified. If `output_json` is `true`, the errors are printed as a JSON array instead.
+///
+/// Returns the number of verification errors.
+pub fn cmd_verify(
+    configuration: &Configuration,
+    output_json: bool,
+    terminal: &mut SimpleTerminal,
+) -> Result<usize> {
+    if !output_json {
+        terminal.log_println(
+            "Command: verify the transcoded libraries against their saved state."
+                .cyan()
+                .bold(),
+        );
+    }
+
+    let mut library_configurations =
+        configuration.libraries.values().collect::<Vec<_>>();
+    library_configurations
+        .sort_unstable_by(|first, second| first.name.cmp(&second.name));
+
+    let mut verification_errors: Vec<VerificationError> = Vec::new();
+
+    for library_configuration in library_configurations {
+        let library = match LibraryView::from_library_configuration(
+            configuration,
+            library_configuration,
+        ) {
+            Ok(library) => library,
+            Err(LibraryViewError::NoSuchDirectory(path))
+                if library_configuration.optional =>
+            {
+                if !output_json {
+                    log_optional_library_unavailable(
+                        terminal,
+                        &library_configuration.name,
+                        &path,
+                    );
+                }
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut artists =
+            library.read().artists()?.into_iter().collect::<Vec<_>>();
+        artists.sort_unstable_by(|first, second| first.0.cmp(&second.0));
+
+        for (artist_name, artist) in artists {
+            let mut albums =
+                artist.read().albums()?.into_iter().collect::<Vec<_>>();
+            albums.sort_unstable_by(|first, second| first.0.cmp(&second.0));
+
+            for (album_title, album) in albums {
+                verification_errors.extend(
+                    verify_album(
+                        &library_configuration.name,
+                        &artist_name,
+                        &album_title,
+                        album,
+                    )
+                    .wrap_err_with(|| {
+                        miette!(
+                            "Could not verify album {} - {} in library {}.",
+                            artist_name,
+                            album_title,
+                            library_configuration.name
+                        )
+                    })?,
+                );
+            }
+        }
+    }
+
+    if output_json {
+        let serialized_errors =
+            serde_json::to_string_pretty(&verification_errors)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    miette!(
+                        "Could not serialize verification results into JSON."
+                    )
+                })?;
+
+        terminal.log_println(serialized_errors);
+        return Ok(verification_errors.len());
+    }
+
+    for verification_error in &verification_errors {
+        let formatted_error = match verification_error {
+            VerificationError::MissingFile {
+                library,
+                artist,
+                album,
+                relative_path,
+            } => format!(
+                "{} [{}] {} - {}: {}",
+                "MISSING:".red(),
+                library,
+                artist,
+                album,
+                relative_path
+            ),
+            VerificationError::ModifiedFile {
+                library,
+                artist,
+                album,
+                relative_path,
+            } => format!(
+                "{} [{}] {} - {}: {}",
+                "MODIFIED:".yellow(),
+                library,
+                artist,
+                album,
+                relative_path
+            ),
+            VerificationError::TrackCountMismatch {
+                library,
+                artist,
+                album,
+                expected_audio_files,
+                actual_audio_files,
+            } => format!(
+                "{} [{}] {} - {}: expected {} transcoded audio files, found {} \
+                (a track may have failed to transcode).",
+                "TRACK COUNT:".red(),
+                library,
+                artist,
+                album,
+                expected_audio_files,
+                actual_audio_files
+            ),
+        };
+
+        terminal.log_println(formatted_error);
+    }
+
+    if verification_errors.is_empty() {
+        terminal.log_println(
+            "All tracked transcoded files are present and unchanged.".green(),
+        );
+    } else {
+        terminal.log_println(format!(
+            "Found {} problems in the transcoded libraries \
+            (missing files are transcoded again on the next `transcode`).",
+            verification_errors.len()
+        ));
+    }
+
+    Ok(verification_errors.len())
+}
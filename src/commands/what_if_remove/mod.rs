@@ -0,0 +1,255 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crossterm::style::Stylize;
+use euphony_configuration::Configuration;
+use euphony_library::state::transcoded::TranscodedAlbumState;
+use euphony_library::state::AlbumFileChangesV2;
+use euphony_library::view::library::LibraryViewError;
+use euphony_library::view::{ArtistView, LibraryView};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Serialize;
+
+use crate::commands::log_optional_library_unavailable;
+use crate::commands::transcode::library_state::{
+    LibraryState,
+    LibraryStateLoadError,
+};
+use crate::console::frontends::SimpleTerminal;
+use crate::console::LogBackend;
+
+
+/// Transcoded files and directories of a single library that would be removed
+/// along with the artist.
+#[derive(Serialize)]
+struct LibraryRemovalReport {
+    library_name: String,
+
+    /// Absolute paths of transcoded files (including transcoded state files) that would be removed.
+    removed_files: Vec<PathBuf>,
+
+    /// Absolute paths of transcoded album and artist directories that would be left empty and removed.
+    removed_directories: Vec<PathBuf>,
+}
+
+/// Returns `true` if the directory would be empty after removing `removed_paths`
+/// (i.e. a normal transcode would remove the directory as well).
+fn directory_would_be_emptied(
+    directory_path: &Path,
+    removed_paths: &HashSet<PathBuf>,
+) -> Result<bool> {
+    let directory_entries = fs::read_dir(directory_path)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            miette!(
+                "Failed to read directory contents of {:?}",
+                directory_path
+            )
+        })?;
+
+    for entry in directory_entries {
+        let entry = entry.into_diagnostic()?;
+
+        if !removed_paths.contains(&entry.path()) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Associated with the `what-if-remove` command.
+///
+/// Using the saved library and transcoded album states, lists every transcoded file and directory
+/// a normal transcode would remove if the given artist were removed from the source libraries.
+/// Nothing is actually removed. If `output_json` is `true`, the report is printed as JSON instead.
+pub fn cmd_what_if_remove(
+    configuration: &Configuration,
+    artist_name: &str,
+    output_json: bool,
+    terminal: &mut SimpleTerminal,
+) -> Result<()> {
+    let mut library_reports: Vec<LibraryRemovalReport> = Vec::new();
+
+    for library_configuration in configuration.libraries.values() {
+        let library = match LibraryView::from_library_configuration(
+            configuration,
+            library_configuration,
+        ) {
+            Ok(library) => library,
+            Err(LibraryViewError::NoSuchDirectory(path))
+                if library_configuration.optional =>
+            {
+                if !output_json {
+                    log_optional_library_unavailable(
+                        terminal,
+                        &library_configuration.name,
+                        &path,
+                    );
+                }
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let saved_library_state = match LibraryState::load_from_directory(
+            library.read().root_directory_in_source_library(),
+        ) {
+            Ok(state) => state,
+            Err(LibraryStateLoadError::NotFound)
+            | Err(LibraryStateLoadError::SchemaVersionMismatch(_)) => continue,
+            Err(error) => return Err(error.into()),
+        };
+
+        let Some(tracked_artist_albums) =
+            saved_library_state.tracked_artists.get(artist_name)
+        else {
+            continue;
+        };
+
+        // This mirrors how `transcode` handles a fully-removed artist,
+        // except that the changes are only collected, never processed.
+        let artist =
+            ArtistView::new(library.clone(), artist_name.to_string(), true)?;
+
+        let artist_transcoded_directory =
+            artist.read().artist_directory_in_transcoded_library();
+        if !artist_transcoded_directory.is_dir() {
+            continue;
+        }
+
+        let mut removed_files: Vec<PathBuf> = Vec::new();
+        let mut removed_directories: Vec<PathBuf> = Vec::new();
+
+        for tracked_album in &tracked_artist_albums.tracked_albums {
+            let album = tracked_album.album_view(artist.clone())?;
+
+            let album_transcoded_directory =
+                album.read().album_directory_in_transcoded_library();
+            if !album_transcoded_directory.is_dir() {
+                continue;
+            }
+
+            let changes =
+                AlbumFileChangesV2::generate_entire_transcoded_album_deletion(
+                    album,
+                )?;
+
+            let mut removed_album_files: Vec<PathBuf> = changes
+                .removed_from_source_since_last_transcode
+                .audio
+                .into_iter()
+                .chain(changes.removed_from_source_since_last_transcode.data)
+                .collect();
+
+            let transcoded_state_file_path =
+                TranscodedAlbumState::get_state_file_path_for_directory(
+                    &album_transcoded_directory,
+                );
+            if transcoded_state_file_path.is_file() {
+                removed_album_files.push(transcoded_state_file_path);
+            }
+
+            let removed_album_file_set: HashSet<PathBuf> =
+                removed_album_files.iter().cloned().collect();
+
+            if directory_would_be_emptied(
+                &album_transcoded_directory,
+                &removed_album_file_set,
+            )? {
+                removed_directories.push(album_transcoded_directory);
+            }
+
+            removed_files.extend(removed_album_files);
+        }
+
+        let removed_album_directory_set: HashSet<PathBuf> =
+            removed_directories.iter().cloned().collect();
+
+        if directory_would_be_emptied(
+            &artist_transcoded_directory,
+            &removed_album_directory_set,
+        )? {
+            removed_directories.push(artist_transcoded_directory);
+        }
+
+        removed_files.sort_unstable();
+
+        library_reports.push(LibraryRemovalReport {
+            library_name: library_configuration.name.clone(),
+            removed_files,
+            removed_directories,
+        });
+    }
+
+    library_reports.sort_unstable_by(|first, second| {
+        first.library_name.cmp(&second.library_name)
+    });
+
+    if output_json {
+        let serialized_reports = serde_json::to_string_pretty(&library_reports)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!("Could not serialize removal report into JSON.")
+            })?;
+
+        terminal.log_println(serialized_reports);
+        return Ok(());
+    }
+
+    terminal.log_println(
+        format!(
+            "Command: what would be removed from the transcoded library if \"{}\" \
+            were removed (nothing is removed).",
+            artist_name
+        )
+        .cyan()
+        .bold(),
+    );
+
+    if library_reports.is_empty() {
+        terminal.log_println(format!(
+            "Artist \"{}\" has no transcoded albums tracked in any library.",
+            artist_name
+        ));
+        return Ok(());
+    }
+
+    for library_report in &library_reports {
+        terminal.log_println(format!(
+            "Library: {}",
+            library_report.library_name.clone().bold()
+        ));
+
+        for removed_file in &library_report.removed_files {
+            terminal.log_println(format!(
+                "  {} {:?}",
+                "[delete file]".red(),
+                removed_file
+            ));
+        }
+
+        for removed_directory in &library_report.removed_directories {
+            terminal.log_println(format!(
+                "  {} {:?}",
+                "[delete directory]".red(),
+                removed_directory
+            ));
+        }
+    }
+
+    terminal.log_println(format!(
+        "{} files and {} directories would be removed.",
+        library_reports
+            .iter()
+            .map(|report| report.removed_files.len())
+            .sum::<usize>(),
+        library_reports
+            .iter()
+            .map(|report| report.removed_directories.len())
+            .sum::<usize>(),
+    ));
+
+    Ok(())
+}
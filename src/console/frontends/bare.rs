@@ -3,24 +3,35 @@ use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 use std::thread::Scope;
+use std::time::{Duration, Instant};
 
 use crossterm::style::{Color, Stylize};
+use euphony_configuration::logging::LogFileFormat;
 use miette::{miette, Context, IntoDiagnostic, Result};
 use parking_lot::{Mutex, RwLock};
 use tokio::sync::broadcast;
 
-use super::shared::logging::initialize_log_file_for_log_output;
+use super::shared::logging::{
+    format_jsonl_log_line,
+    initialize_log_file_for_log_output,
+    LogLevel,
+};
 use crate::console::frontends::shared::queue::{
     AlbumQueueItem,
     AlbumQueueItemFinishedResult,
     FileQueueItem,
+    FileQueueItemErrorType,
     FileQueueItemFinishedResult,
     Queue,
     QueueItem,
     QueueItemID,
     RenderableQueueItem,
 };
-use crate::console::frontends::shared::Progress;
+use crate::console::frontends::shared::{
+    format_time_estimate,
+    Progress,
+    ScanProgress,
+};
 use crate::console::traits::{
     LogToFileBackend,
     UserControllableBackend,
@@ -33,6 +44,11 @@ use crate::console::{
     TranscodeBackend,
     UserControlMessage,
 };
+use crate::globals::{is_quiet_enabled, is_verbose_enabled};
+
+/// Default minimum time between two progress summary lines
+/// (see `BareTerminalBackend::with_progress_summary_interval`).
+const DEFAULT_PROGRESS_SUMMARY_INTERVAL: Duration = Duration::from_secs(2);
 
 
 pub struct QueueAndProgressState<'config> {
@@ -46,6 +62,13 @@ pub struct QueueAndProgressState<'config> {
 
     /// When the progress bar is active, this contains the progress bar state.
     progress: Option<Progress>,
+
+    /// While albums are being scanned for changes, this contains the scanning progress.
+    scan_progress: Option<ScanProgress>,
+
+    /// When the last progress summary line was printed (see `progress_summary_interval`).
+    /// Shared between the scanning and the transcoding progress, which are never active at the same time.
+    last_progress_summary_time: Option<Instant>,
 }
 
 impl<'config> QueueAndProgressState<'config> {
@@ -54,6 +77,8 @@ impl<'config> QueueAndProgressState<'config> {
             album_queue: None,
             file_queue: None,
             progress: None,
+            scan_progress: None,
+            last_progress_summary_time: None,
         }
     }
 }
@@ -63,11 +88,22 @@ impl<'config> QueueAndProgressState<'config> {
 ///
 /// Any log output simply goes to stdout. More complex features, such as queues, are not displayed
 /// dynamically as a UI, but with simple one-line status updates about the queue (e.g. "New item in queue: ...").
+/// Most of these are only printed in verbose mode, while progress updates are coalesced into
+/// a periodic summary line (see `with_progress_summary_interval`).
 pub struct BareTerminalBackend<'config> {
     state: RwLock<QueueAndProgressState<'config>>,
 
-    /// If log file output is enabled, this contains the mutex in front of the file writer.
-    log_file_output: Mutex<Option<BufWriter<strip_ansi_escapes::Writer<File>>>>,
+    /// Minimum time between two progress summary lines.
+    progress_summary_interval: Duration,
+
+    /// If log file output is enabled, this contains the mutex in front of the file writer
+    /// (along with the format of the log file).
+    log_file_output: Mutex<
+        Option<(
+            BufWriter<strip_ansi_escapes::Writer<File>>,
+            LogFileFormat,
+        )>,
+    >,
 
     broadcast_sender: Mutex<broadcast::Sender<UserControlMessage>>,
 }
@@ -78,10 +114,124 @@ impl<'config> BareTerminalBackend<'config> {
 
         Self {
             state: RwLock::new(QueueAndProgressState::new()),
+            progress_summary_interval: DEFAULT_PROGRESS_SUMMARY_INTERVAL,
             log_file_output: Mutex::new(None),
             broadcast_sender: Mutex::new(broadcast_sender),
         }
     }
+
+    /// Set the minimum time between two progress summary lines
+    /// (see `ui.bare.progress_summary_interval_seconds`).
+    pub fn with_progress_summary_interval(
+        mut self,
+        progress_summary_interval: Duration,
+    ) -> Self {
+        self.progress_summary_interval = progress_summary_interval;
+        self
+    }
+
+    /// Write a line into the log file, if logging to file is enabled.
+    fn write_line_to_log_file(&self, level: LogLevel, content: &str) {
+        if let Some((writer, log_file_format)) =
+            self.log_file_output.lock().as_mut()
+        {
+            let line = match log_file_format {
+                LogFileFormat::Text => content.to_string(),
+                LogFileFormat::Jsonl => format_jsonl_log_line(level, content),
+            };
+
+            writer
+                .write_all(line.as_bytes())
+                .expect("Could not write to logfile.");
+            writer
+                .write_all("\n".as_bytes())
+                .expect("Could not write to logfile (newline).");
+        }
+    }
+
+    /// Print a line only when verbose mode is enabled (used for the per-item queue messages).
+    fn log_println_verbose<D: Display>(&self, content: D) {
+        if is_verbose_enabled() {
+            self.log_println(content);
+        }
+    }
+
+    /// Print a progress summary line (e.g. "Progress: 120/3400 files done, 2 errored."),
+    /// unless one was already printed less than `progress_summary_interval` ago and `force` is `false`.
+    fn print_progress_summary(
+        &self,
+        locked_state: &mut QueueAndProgressState<'config>,
+        force: bool,
+    ) {
+        let Some(progress) = locked_state.progress else {
+            return;
+        };
+
+        let is_due = locked_state
+            .last_progress_summary_time
+            .map(|last_summary_time| {
+                last_summary_time.elapsed() >= self.progress_summary_interval
+            })
+            .unwrap_or(true);
+
+        if !is_due && !force {
+            return;
+        }
+
+        locked_state.last_progress_summary_time = Some(Instant::now());
+
+        let time_estimate = match progress.estimated_time_remaining() {
+            Some(time_remaining)
+                if progress.total_finished_or_errored()
+                    < progress.total_files =>
+            {
+                format!(
+                    " About {} remaining.",
+                    format_time_estimate(time_remaining)
+                )
+            }
+            _ => String::new(),
+        };
+
+        self.log_println(format!(
+            "Progress: {}/{} files done ({:.1} %), {} errored.{}",
+            progress.total_finished_or_errored(),
+            progress.total_files,
+            progress.completion_ratio() * 100.0,
+            progress.audio_files_errored + progress.data_files_errored,
+            time_estimate,
+        ));
+    }
+
+    /// Print a scanning progress line (e.g. "Scanned 120/800 albums."),
+    /// unless one was already printed less than `progress_summary_interval` ago and `force` is `false`.
+    fn print_scan_progress_summary(
+        &self,
+        locked_state: &mut QueueAndProgressState<'config>,
+        force: bool,
+    ) {
+        let Some(scan_progress) = locked_state.scan_progress else {
+            return;
+        };
+
+        let is_due = locked_state
+            .last_progress_summary_time
+            .map(|last_summary_time| {
+                last_summary_time.elapsed() >= self.progress_summary_interval
+            })
+            .unwrap_or(true);
+
+        if !is_due && !force {
+            return;
+        }
+
+        locked_state.last_progress_summary_time = Some(Instant::now());
+
+        self.log_println(format!(
+            "Scanned {}/{} albums.",
+            scan_progress.albums_scanned, scan_progress.total_albums,
+        ));
+    }
 }
 
 impl<'config, 'scope, 'scope_env: 'scope> TerminalBackend<'scope, 'scope_env>
@@ -92,6 +242,18 @@ impl<'config, 'scope, 'scope_env: 'scope> TerminalBackend<'scope, 'scope_env>
     }
 
     fn destroy(self) -> Result<()> {
+        // Always end with the final state of the progress bar, if it was still active.
+        {
+            let mut locked_state = self.state.write();
+
+            if locked_state
+                .progress
+                .is_some_and(|progress| progress.total_finished_or_errored() > 0)
+            {
+                self.print_progress_summary(&mut locked_state, true);
+            }
+        }
+
         // If logging to file was enabled, we should disable it before this backend is dropped,
         // otherwise we risk failing to flush to file.
         self.disable_saving_logs_to_file()?;
@@ -102,9 +264,14 @@ impl<'config, 'scope, 'scope_env: 'scope> TerminalBackend<'scope, 'scope_env>
 
 impl<'config> LogBackend for BareTerminalBackend<'config> {
     fn log_newline(&self) {
-        println!();
+        if !is_quiet_enabled() {
+            println!();
+        }
 
-        if let Some(writer) = self.log_file_output.lock().as_mut() {
+        // Empty lines are only spacing, so they are left out of `jsonl` log files.
+        if let Some((writer, LogFileFormat::Text)) =
+            self.log_file_output.lock().as_mut()
+        {
             writer
                 .write_all("\n".as_bytes())
                 .expect("Could not write to logfile.");
@@ -114,16 +281,23 @@ impl<'config> LogBackend for BareTerminalBackend<'config> {
     fn log_println<D: Display>(&self, content: D) {
         let content_string = content.to_string();
 
-        println!("{content_string}");
-
-        if let Some(writer) = self.log_file_output.lock().as_mut() {
-            writer
-                .write_all(content_string.as_bytes())
-                .expect("Could not write to logfile.");
-            writer
-                .write_all("\n".as_bytes())
-                .expect("Could not write to logfile (newline).");
+        // In quiet mode, informational lines only end up in the log file (if enabled).
+        if !is_quiet_enabled() {
+            println!("{content_string}");
         }
+
+        self.write_line_to_log_file(LogLevel::Info, &content_string);
+    }
+
+    fn log_eprintln<D: Display>(&self, content: D) {
+        let content_string = content.to_string();
+
+        eprintln!("{content_string}");
+
+        self.write_line_to_log_file(
+            LogLevel::of_error_output(&content_string),
+            &content_string,
+        );
     }
 }
 
@@ -132,21 +306,21 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
      * Album queue
      */
     fn queue_album_enable(&self) {
-        self.log_println("Album queue enabled.");
+        self.log_println_verbose("Album queue enabled.");
 
         let mut locked_state = self.state.write();
         locked_state.album_queue = Some(Queue::new());
     }
 
     fn queue_album_disable(&self) {
-        self.log_println("Album queue disabled.");
+        self.log_println_verbose("Album queue disabled.");
 
         let mut locked_state = self.state.write();
         locked_state.album_queue = None;
     }
 
     fn queue_album_clear(&self) -> Result<()> {
-        self.log_println("Album queue cleared.");
+        self.log_println_verbose("Album queue cleared.");
 
 
         let mut locked_state = self.state.write();
@@ -165,12 +339,22 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
     ) -> Result<QueueItemID> {
         let item_id = item.get_id();
 
-        self.log_println(format!(
+        self.log_println_verbose(format!(
             "Album queue item added: {}",
             item.render()
         ));
 
         let mut locked_state = self.state.write();
+
+        debug_assert!(
+            !locked_state
+                .file_queue
+                .as_ref()
+                .is_some_and(|file_queue| file_queue.contains_item(item_id)),
+            "BUG: album queue item ID {} is already used in the file queue.",
+            *item_id
+        );
+
         locked_state
             .album_queue
             .as_mut()
@@ -190,9 +374,7 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
 
         album_queue.start_item(item_id)?;
 
-        let item = album_queue
-            .item(item_id)
-            .ok_or_else(|| miette!("Invalid item_id, no such item."))?;
+        let item = album_queue.existing_item(item_id)?;
         let item_rendered = item.render();
 
         self.log_println(format!(
@@ -215,9 +397,7 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
 
         album_queue.finish_item(item_id, result)?;
 
-        let item = album_queue
-            .item(item_id)
-            .ok_or_else(|| miette!("Invalid item_id, no such item."))?;
+        let item = album_queue.existing_item(item_id)?;
         let item_rendered = item.render();
 
         self.log_println(format!(
@@ -244,21 +424,21 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
      * File queue
      */
     fn queue_file_enable(&self) {
-        self.log_println("File queue enabled.");
+        self.log_println_verbose("File queue enabled.");
 
         let mut locked_state = self.state.write();
         locked_state.file_queue = Some(Queue::new());
     }
 
     fn queue_file_disable(&self) {
-        self.log_println("File queue disabled.");
+        self.log_println_verbose("File queue disabled.");
 
         let mut locked_state = self.state.write();
         locked_state.file_queue = None;
     }
 
     fn queue_file_clear(&self) -> Result<()> {
-        self.log_println("File queue cleared.");
+        self.log_println_verbose("File queue cleared.");
 
         let mut locked_state = self.state.write();
         locked_state
@@ -276,12 +456,22 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
     ) -> Result<QueueItemID> {
         let item_id = item.get_id();
 
-        self.log_println(format!(
+        self.log_println_verbose(format!(
             "File queue item added: {}",
             item.render()
         ));
 
         let mut locked_state = self.state.write();
+
+        debug_assert!(
+            !locked_state
+                .album_queue
+                .as_ref()
+                .is_some_and(|album_queue| album_queue.contains_item(item_id)),
+            "BUG: file queue item ID {} is already used in the album queue.",
+            *item_id
+        );
+
         locked_state
             .file_queue
             .as_mut()
@@ -301,18 +491,26 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
 
         file_queue.start_item(item_id)?;
 
-        let item = file_queue
-            .item(item_id)
-            .ok_or_else(|| miette!("Invalid item_id, no such item."))?;
+        let item = file_queue.existing_item(item_id)?;
         let item_rendered = item.render();
 
-        self.log_println(format!(
+        self.log_println_verbose(format!(
             "File queue item started: {item_rendered}"
         ));
 
         Ok(())
     }
 
+    fn queue_file_item_set_progress(
+        &self,
+        _item_id: QueueItemID,
+        _percent: u8,
+    ) -> Result<()> {
+        // Printing a line for each percent would flood the console, so the bare backend
+        // only reports overall progress (see `print_progress_summary`).
+        Ok(())
+    }
+
     fn queue_file_item_finish(
         &self,
         item_id: QueueItemID,
@@ -326,17 +524,27 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
 
 
         let result_string = format!("{result:?}");
+        let is_errored = matches!(
+            result,
+            FileQueueItemFinishedResult::Failed(
+                FileQueueItemErrorType::Errored { .. }
+            )
+        );
 
         file_queue.finish_item(item_id, result)?;
 
-        let item = file_queue
-            .item(item_id)
-            .ok_or_else(|| miette!("Invalid item_id, no such item."))?;
+        let item = file_queue.existing_item(item_id)?;
         let item_rendered = item.render();
 
-        self.log_println(format!(
+        let message = format!(
             "File queue item finished: {item_rendered} (result: {result_string})"
-        ));
+        );
+
+        if is_errored {
+            self.log_eprintln(message);
+        } else {
+            self.log_println(message);
+        }
 
         Ok(())
     }
@@ -358,17 +566,28 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
      * Progress
      */
     fn progress_enable(&self) {
-        println!("Progress bar enabled.");
+        self.log_println_verbose("Progress bar enabled.");
 
         let mut locked_state = self.state.write();
         locked_state.progress = Some(Progress::default());
+        locked_state.last_progress_summary_time = Some(Instant::now());
     }
 
     fn progress_disable(&self) {
-        println!("Progress bar disabled.");
+        self.log_println_verbose("Progress bar disabled.");
 
         let mut locked_state = self.state.write();
+
+        // Always end with the final state of the progress bar.
+        if locked_state
+            .progress
+            .is_some_and(|progress| progress.total_finished_or_errored() > 0)
+        {
+            self.print_progress_summary(&mut locked_state, true);
+        }
+
         locked_state.progress = None;
+        locked_state.last_progress_summary_time = None;
     }
 
     fn progress_set_total(&self, num_total: usize) -> Result<()> {
@@ -376,7 +595,7 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
 
         match locked_state.progress.as_mut() {
             Some(progress) => {
-                progress.total_files = num_total;
+                progress.set_total_files(num_total);
                 Ok(())
             }
             None => Err(miette!(
@@ -430,6 +649,7 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
         match locked_state.progress.as_mut() {
             Some(progress) => {
                 progress.audio_files_finished_ok = num_audio_files_finished_ok;
+                self.print_progress_summary(&mut locked_state, false);
                 Ok(())
             }
             None => Err(miette!(
@@ -447,6 +667,7 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
         match locked_state.progress.as_mut() {
             Some(progress) => {
                 progress.data_files_finished_ok = num_data_files_finished_ok;
+                self.print_progress_summary(&mut locked_state, false);
                 Ok(())
             }
             None => Err(miette!(
@@ -464,6 +685,7 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
         match locked_state.progress.as_mut() {
             Some(progress) => {
                 progress.audio_files_errored = num_audio_files_errored;
+                self.print_progress_summary(&mut locked_state, false);
                 Ok(())
             }
             None => Err(miette!(
@@ -481,6 +703,7 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
         match locked_state.progress.as_mut() {
             Some(progress) => {
                 progress.data_files_errored = num_data_files_errored;
+                self.print_progress_summary(&mut locked_state, false);
                 Ok(())
             }
             None => Err(miette!(
@@ -488,6 +711,48 @@ impl<'config> TranscodeBackend<'config> for BareTerminalBackend<'config> {
             )),
         }
     }
+
+    fn progress_set_scan_total(&self, num_total_albums: usize) {
+        let mut locked_state = self.state.write();
+        locked_state.scan_progress = Some(ScanProgress {
+            total_albums: num_total_albums,
+            albums_scanned: 0,
+        });
+        locked_state.last_progress_summary_time = Some(Instant::now());
+    }
+
+    fn progress_set_scan_albums_scanned(
+        &self,
+        num_albums_scanned: usize,
+    ) -> Result<()> {
+        let mut locked_state = self.state.write();
+
+        match locked_state.scan_progress.as_mut() {
+            Some(scan_progress) => {
+                scan_progress.albums_scanned = num_albums_scanned;
+                self.print_scan_progress_summary(&mut locked_state, false);
+                Ok(())
+            }
+            None => Err(miette!(
+                "Scanning progress is disabled, can't set albums scanned."
+            )),
+        }
+    }
+
+    fn progress_set_scan_finished(&self) {
+        let mut locked_state = self.state.write();
+
+        // Always end with the final state of the scan.
+        if locked_state
+            .scan_progress
+            .is_some_and(|scan_progress| scan_progress.total_albums > 0)
+        {
+            self.print_scan_progress_summary(&mut locked_state, true);
+        }
+
+        locked_state.scan_progress = None;
+        locked_state.last_progress_summary_time = None;
+    }
 }
 
 impl<'config> ValidationBackend for BareTerminalBackend<'config> {
@@ -521,6 +786,12 @@ impl<'config> UserControllableBackend for BareTerminalBackend<'config> {
     ) -> Result<broadcast::Receiver<UserControlMessage>> {
         Ok(self.broadcast_sender.lock().subscribe())
     }
+
+    fn get_user_control_sender(
+        &self,
+    ) -> Result<broadcast::Sender<UserControlMessage>> {
+        Ok(self.broadcast_sender.lock().clone())
+    }
 }
 
 impl<'config, 'scope, 'scope_env: 'scope> LogToFileBackend<'scope, 'scope_env>
@@ -529,16 +800,19 @@ impl<'config, 'scope, 'scope_env: 'scope> LogToFileBackend<'scope, 'scope_env>
     fn enable_saving_logs_to_file<P: AsRef<Path>>(
         &self,
         log_output_file_path: P,
+        log_file_format: LogFileFormat,
         _scope: &'scope Scope<'scope, 'scope_env>,
     ) -> Result<()> {
-        let buf_writer =
-            initialize_log_file_for_log_output(log_output_file_path.as_ref())
-                .wrap_err_with(|| {
-                    miette!("Failed to initialize log file for log output.")
-                })?;
+        let buf_writer = initialize_log_file_for_log_output(
+            log_output_file_path.as_ref(),
+            log_file_format,
+        )
+        .wrap_err_with(|| {
+            miette!("Failed to initialize log file for log output.")
+        })?;
 
         let mut locked_self_log_output = self.log_file_output.lock();
-        *locked_self_log_output = Some(buf_writer);
+        *locked_self_log_output = Some((buf_writer, log_file_format));
 
         Ok(())
     }
@@ -546,7 +820,7 @@ impl<'config, 'scope, 'scope_env: 'scope> LogToFileBackend<'scope, 'scope_env>
     fn disable_saving_logs_to_file(&self) -> Result<()> {
         let mut locked_log_output = self.log_file_output.lock();
 
-        if let Some(writer) = locked_log_output.take() {
+        if let Some((writer, _)) = locked_log_output.take() {
             let mut inner_writer = writer
                 .into_inner()
                 .map_err(|_| miette!("Failed to unwrap the BufWriter."))?
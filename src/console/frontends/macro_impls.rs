@@ -127,6 +127,12 @@ macro_rules! enumdispatch_impl_log {
                     $($variant(terminal) => terminal.log_println(content)),+
                 }
             }
+
+            fn log_eprintln<D: Display>(&self, content: D) {
+                match self {
+                    $($variant(terminal) => terminal.log_eprintln(content)),+
+                }
+            }
         }
     }
 }
@@ -147,10 +153,11 @@ macro_rules! enumdispatch_impl_log_to_file {
             fn enable_saving_logs_to_file<P: AsRef<Path>>(
                 &self,
                 log_file_path: P,
+                log_file_format: euphony_configuration::logging::LogFileFormat,
                 scope: &'scope Scope<'scope, 'scope_env>
             ) -> miette::Result<()> {
                 match self {
-                    $($variant(terminal) => terminal.enable_saving_logs_to_file(log_file_path, scope)),+
+                    $($variant(terminal) => terminal.enable_saving_logs_to_file(log_file_path, log_file_format, scope)),+
                 }
             }
 
@@ -279,6 +286,16 @@ macro_rules! enumdispatch_impl_transcode {
                 }
             }
 
+            fn queue_file_item_set_progress(
+                &self,
+                item_id: QueueItemID,
+                percent: u8,
+            ) -> miette::Result<()> {
+                match self {
+                    $($variant(terminal) => terminal.queue_file_item_set_progress(item_id, percent)),+
+                }
+            }
+
             fn queue_file_item_finish(
                 &self,
                 item_id: QueueItemID,
@@ -375,6 +392,28 @@ macro_rules! enumdispatch_impl_transcode {
                         terminal.progress_set_data_files_errored(num_data_files_errored)),+
                 }
             }
+
+            fn progress_set_scan_total(&self, num_total_albums: usize) {
+                match self {
+                    $($variant(terminal) => terminal.progress_set_scan_total(num_total_albums)),+
+                }
+            }
+
+            fn progress_set_scan_albums_scanned(
+                &self,
+                num_albums_scanned: usize,
+            ) -> miette::Result<()> {
+                match self {
+                    $($variant(terminal) =>
+                        terminal.progress_set_scan_albums_scanned(num_albums_scanned)),+
+                }
+            }
+
+            fn progress_set_scan_finished(&self) {
+                match self {
+                    $($variant(terminal) => terminal.progress_set_scan_finished()),+
+                }
+            }
         }
     };
 }
@@ -396,6 +435,12 @@ macro_rules! enumdispatch_impl_user_controllable {
                     $($variant(terminal) => terminal.get_user_control_receiver()),+
                 }
             }
+
+            fn get_user_control_sender(&self) -> miette::Result<tokio::sync::broadcast::Sender<UserControlMessage>> {
+                match self {
+                    $($variant(terminal) => terminal.get_user_control_sender()),+
+                }
+            }
         }
     };
 }
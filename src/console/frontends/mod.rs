@@ -20,13 +20,13 @@
 //! ---
 //!
 //! **The second is `ValidationTerminal`** (`TerminalTrait` + `LogBackend` + `LogToFileBackend`
-//! + `ValidationBackend` traits).
+//! + `ValidationBackend` + `UserControllableBackend` traits).
 //!
-//! Backends that implement those four traits and are added as a variant to `ValidationTerminal` can be used
+//! Backends that implement those five traits and are added as a variant to `ValidationTerminal` can be used
 //! for the following commands:
 //! - `validate`
 //!
-//! Only the `BareTerminalBackend` is available here for the moment.
+//! Both `BareTerminalBackend` and `TUITerminalBackend` are available here.
 //!
 //! ---
 //!
@@ -199,46 +199,61 @@ enumdispatch_impl_log_to_file!(
 
 
 
-pub enum ValidationTerminal<'config> {
+#[allow(clippy::large_enum_variant)]
+pub enum ValidationTerminal<'config: 'scope, 'scope> {
     Bare(BareTerminalBackend<'config>),
+    Fancy(FancyTerminalBackend<'scope, 'config>),
 }
 
 terminal_impl_direct_from!(
     on
-        ValidationTerminal<'config>,
+        ValidationTerminal<'config, 'scope>,
     do conversions
-        BareTerminalBackend<'config> => ValidationTerminal::Bare
+        BareTerminalBackend<'config> => ValidationTerminal::Bare,
+        FancyTerminalBackend<'scope, 'config> => ValidationTerminal::Fancy
 );
 
 enumdispatch_impl_terminal!(
-    lifetimes: 'config, 'scope, 'scope_env: 'scope,
+    lifetimes: 'config: 'scope, 'scope, 'scope_env: 'scope,
     TerminalBackend lifetimes: 'scope, 'scope_env,
     on
-        ValidationTerminal<'config>,
+        ValidationTerminal<'config, 'scope>,
     implement variants
-        ValidationTerminal::Bare
+        ValidationTerminal::Bare,
+        ValidationTerminal::Fancy
 );
 enumdispatch_impl_log!(
-    lifetimes: 'config,
+    lifetimes: 'config, 'scope,
     on
-        ValidationTerminal<'config>,
+        ValidationTerminal<'config, 'scope>,
     implement variants
-        ValidationTerminal::Bare
+        ValidationTerminal::Bare,
+        ValidationTerminal::Fancy
 );
 enumdispatch_impl_log_to_file!(
-    lifetimes: 'config, 'scope, 'scope_env: 'scope,
+    lifetimes: 'config: 'scope, 'scope, 'scope_env: 'scope,
     LogToFileBackend lifetimes: 'scope, 'scope_env,
     on
-        ValidationTerminal<'config>,
+        ValidationTerminal<'config, 'scope>,
     implement variants
-        ValidationTerminal::Bare
+        ValidationTerminal::Bare,
+        ValidationTerminal::Fancy
+);
+enumdispatch_impl_user_controllable!(
+    lifetimes: 'config, 'scope,
+    on
+        ValidationTerminal<'config, 'scope>,
+    implement variants
+        ValidationTerminal::Bare,
+        ValidationTerminal::Fancy
 );
 enumdispatch_impl_validation!(
-    lifetimes: 'config,
+    lifetimes: 'config, 'scope,
     on
-        ValidationTerminal<'config>,
+        ValidationTerminal<'config, 'scope>,
     implement variants
-        ValidationTerminal::Bare
+        ValidationTerminal::Bare,
+        ValidationTerminal::Fancy
 );
 
 
@@ -5,22 +5,72 @@ use std::{
     path::Path,
 };
 
-use chrono::Local;
+use chrono::{Local, SecondsFormat};
+use euphony_configuration::logging::LogFileFormat;
 use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Serialize;
 use strip_ansi_escapes::Writer as StripAnsiWriter;
 
 use crate::EUPHONY_VERSION;
 
+
+/// Level of a log line, as written into `jsonl` log files (see `LogFileFormat`).
+#[derive(Serialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// Determine the level of a line logged with `log_eprintln`, which is used for both
+    /// warnings (prefixed with "WARNING:") and errors.
+    pub fn of_error_output(message: &str) -> Self {
+        if strip_ansi_escapes::strip_str(message)
+            .trim_start()
+            .starts_with("WARNING")
+        {
+            Self::Warn
+        } else {
+            Self::Error
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonLogEntry<'a> {
+    timestamp: String,
+    level: LogLevel,
+    message: &'a str,
+}
+
+/// Format a single line of a `jsonl` log file (without the trailing newline).
+/// Any ANSI escape codes (colours) are removed from the message.
+pub fn format_jsonl_log_line(level: LogLevel, message: &str) -> String {
+    let message = strip_ansi_escapes::strip_str(message);
+
+    let entry = JsonLogEntry {
+        timestamp: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+        level,
+        message: &message,
+    };
+
+    // Serializing a struct of strings can't fail.
+    serde_json::to_string(&entry).expect("Could not serialize log entry.")
+}
+
 // TODO Extract code from enable_saving_logs_to_file.
 /// Prepares the log file for log output.
 /// This involves opening the file for writing
 /// (creating it if necessary). If the file already exists,
 /// is is opened in append mode.
 ///
-/// A small invocation header is written to the log file before the writer
-/// handle is returned.
+/// A small invocation header (in the given `log_file_format`) is written to the log file
+/// before the writer handle is returned.
 pub fn initialize_log_file_for_log_output(
     log_output_file_path: &Path,
+    log_file_format: LogFileFormat,
 ) -> Result<BufWriter<StripAnsiWriter<File>>> {
     let log_output_directory_path = log_output_file_path
         .parent()
@@ -70,19 +120,29 @@ pub fn initialize_log_file_for_log_output(
     let mut buf_writer = BufWriter::with_capacity(1024, ansi_escaping_writer);
 
     // Write an "invocation header", marking the start of euphony.
-    let time_now = Local::now();
-    let formatted_time_now = time_now.format("%Y-%m-%d %H:%M:%S%.3f");
+    let header_message = format!(
+        "Hello from euphony {}. Started with arguments: {:?}",
+        EUPHONY_VERSION,
+        args()
+    );
 
-    buf_writer
-        .write_all(
+    let header = match log_file_format {
+        LogFileFormat::Text => {
+            let time_now = Local::now();
+            let formatted_time_now = time_now.format("%Y-%m-%d %H:%M:%S%.3f");
+
+            format!("{} {}", formatted_time_now, header_message)
+        }
+        LogFileFormat::Jsonl => {
             format!(
-                "{} Hello from euphony {}. Started with arguments: {:?}",
-                formatted_time_now,
-                EUPHONY_VERSION,
-                args()
+                "{}\n",
+                format_jsonl_log_line(LogLevel::Info, &header_message)
             )
-            .as_bytes(),
-        )
+        }
+    };
+
+    buf_writer
+        .write_all(header.as_bytes())
         .into_diagnostic()
         .wrap_err_with(|| {
             miette!("Could not write invocation header to file.")
@@ -1,8 +1,13 @@
+use std::time::{Duration, Instant};
+
 /// A small progress bar abstraction that contains just two fields: `current` out of `total` progress.
 #[derive(Default, Copy, Clone, Eq, PartialEq)]
 pub struct Progress {
     pub total_files: usize,
 
+    /// When the total was first set (see `set_total_files`), used for the time estimate.
+    pub started_at: Option<Instant>,
+
     pub audio_files_currently_processing: usize,
     pub data_files_currently_processing: usize,
 
@@ -14,6 +19,16 @@ pub struct Progress {
 }
 
 impl Progress {
+    /// Set the total amount of files. The first call also starts the clock
+    /// for `estimated_time_remaining`.
+    pub fn set_total_files(&mut self, num_total: usize) {
+        self.total_files = num_total;
+
+        if self.started_at.is_none() {
+            self.started_at = Some(Instant::now());
+        }
+    }
+
     #[inline]
     pub fn total_not_pending(&self) -> usize {
         self.audio_files_currently_processing
@@ -46,4 +61,64 @@ impl Progress {
             self.total_finished_or_errored() as f64 / self.total_files as f64
         }
     }
+
+    /// Estimate the remaining time from the average throughput (files per second) since
+    /// the total was first set. Returns `None` until at least one file has finished.
+    ///
+    /// Audio and data files are weighted equally, so the estimate tends to be optimistic
+    /// while data files (which are only copied) are being processed.
+    pub fn estimated_time_remaining(&self) -> Option<Duration> {
+        let started_at = self.started_at?;
+
+        let num_done = self.total_finished_or_errored();
+        if num_done == 0 {
+            return None;
+        }
+
+        let seconds_per_file =
+            started_at.elapsed().as_secs_f64() / num_done as f64;
+        let num_remaining = self.total_files.saturating_sub(num_done);
+
+        Some(Duration::from_secs_f64(
+            seconds_per_file * num_remaining as f64,
+        ))
+    }
+}
+
+/// Progress of the album scanning phase that precedes transcoding:
+/// `albums_scanned` out of `total_albums` albums have been scanned for changes.
+#[derive(Default, Copy, Clone, Eq, PartialEq)]
+pub struct ScanProgress {
+    pub total_albums: usize,
+
+    pub albums_scanned: usize,
+}
+
+impl ScanProgress {
+    /// Get progress percentage.
+    #[inline]
+    pub fn completion_ratio(&self) -> f64 {
+        if self.total_albums == 0 {
+            0f64
+        } else {
+            self.albums_scanned as f64 / self.total_albums as f64
+        }
+    }
+}
+
+/// Format a time estimate as e.g. `1h 05m 09s`, `5m 09s` or `9s`.
+pub fn format_time_estimate(duration: Duration) -> String {
+    let total_seconds = duration.as_secs();
+
+    let hours = total_seconds / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    let seconds = total_seconds % 60;
+
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
 }
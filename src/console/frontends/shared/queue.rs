@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::ops::Deref;
@@ -366,6 +367,10 @@ pub struct Queue<Item: QueueItem<FinishedResult>, FinishedResult: Debug> {
     /// and also be able to quickly get specific items by their keys.
     items: LinkedHashMap<QueueItemID, Item>,
 
+    /// IDs of items that have been removed from the queue (including by `clear`).
+    /// Only used to make errors about missing items more useful (see `no_such_item_error`).
+    removed_item_ids: HashSet<QueueItemID>,
+
     /// Couldn't make the compiler ignore that `R` is unused, so I added `PhantomData`.
     /// Maybe I'm just stupid? We need that R in `impl` below.
     _phantom_data: PhantomData<FinishedResult>,
@@ -376,6 +381,7 @@ impl<I: QueueItem<R>, R: Debug> Queue<I, R> {
     pub fn new() -> Self {
         Self {
             items: LinkedHashMap::new(),
+            removed_item_ids: HashSet::new(),
             _phantom_data: PhantomData,
         }
     }
@@ -385,7 +391,7 @@ impl<I: QueueItem<R>, R: Debug> Queue<I, R> {
     /// If an item with the given ID exists, `Some(&mut queue_item)` is returned.
     ///
     /// If no such item exists, `None` is returned.
-    pub fn item(&mut self, item_id: QueueItemID) -> Option<&I> {
+    pub fn item(&self, item_id: QueueItemID) -> Option<&I> {
         self.items.get(&item_id)
     }
 
@@ -398,15 +404,60 @@ impl<I: QueueItem<R>, R: Debug> Queue<I, R> {
         self.items.get_mut(&item_id)
     }
 
+    /// Get a reference to the item with the given `QueueItemID`,
+    /// or an error explaining why there is no such item (see `no_such_item_error`).
+    pub fn existing_item(&self, item_id: QueueItemID) -> Result<&I> {
+        self.item(item_id)
+            .ok_or_else(|| self.no_such_item_error(item_id))
+    }
+
+    /// Get a mutable reference to the item with the given `QueueItemID`,
+    /// or an error explaining why there is no such item (see `no_such_item_error`).
+    pub fn existing_item_mut(&mut self, item_id: QueueItemID) -> Result<&mut I> {
+        if !self.contains_item(item_id) {
+            return Err(self.no_such_item_error(item_id));
+        }
+
+        Ok(self
+            .item_mut(item_id)
+            .expect("BUG: queue item disappeared while the queue was borrowed."))
+    }
+
+    /// Returns `true` if an item with the given `QueueItemID` is currently in the queue.
+    pub fn contains_item(&self, item_id: QueueItemID) -> bool {
+        self.items.contains_key(&item_id)
+    }
+
     pub fn items(&self) -> Iter<QueueItemID, I> {
         self.items.iter()
     }
 
+    /// Build the error for an operation on an item that is not in the queue,
+    /// telling apart IDs that were never added from IDs of already removed items.
+    fn no_such_item_error(&self, item_id: QueueItemID) -> miette::Report {
+        let reason = if self.removed_item_ids.contains(&item_id) {
+            "the item has already been removed from the queue"
+        } else {
+            "no item with this ID was ever added to the queue"
+        };
+
+        miette!(
+            "Invalid queue item ID {}: {} (the queue currently has {} items).",
+            *item_id,
+            reason,
+            self.items.len()
+        )
+    }
+
     /// Adds an item to the queue.
     pub fn queue_item(&mut self, mut item: I) -> Result<()> {
         let item_id = item.get_id();
         if self.items.contains_key(&item_id) {
-            return Err(miette!("This queue item already exists."));
+            return Err(miette!(
+                "Queue item with ID {} already exists (the queue currently has {} items).",
+                *item_id,
+                self.items.len()
+            ));
         }
 
         item.on_item_enqueued();
@@ -420,16 +471,16 @@ impl<I: QueueItem<R>, R: Debug> Queue<I, R> {
         let removed_item = self
             .items
             .remove(&item_id)
-            .ok_or_else(|| miette!("No such queue item."))?;
+            .ok_or_else(|| self.no_such_item_error(item_id))?;
+
+        self.removed_item_ids.insert(item_id);
 
         Ok(removed_item)
     }
 
     /// Put the given item into its "in-progress" state by calling its `start` method.
     pub fn start_item(&mut self, item_id: QueueItemID) -> Result<()> {
-        let item = self
-            .item_mut(item_id)
-            .ok_or_else(|| miette!("No such queue item."))?;
+        let item = self.existing_item_mut(item_id)?;
 
         item.on_item_started();
 
@@ -444,9 +495,7 @@ impl<I: QueueItem<R>, R: Debug> Queue<I, R> {
         item_id: QueueItemID,
         result: R,
     ) -> Result<()> {
-        let item = self
-            .item_mut(item_id)
-            .ok_or_else(|| miette!("No such queue item."))?;
+        let item = self.existing_item_mut(item_id)?;
 
         item.on_item_finished(result);
 
@@ -458,6 +507,7 @@ impl<I: QueueItem<R>, R: Debug> Queue<I, R> {
     /// Note that this does not free up the existing allocated memory of the `Vec` backing this queue
     /// (same behaviour as `Vec` - the existing capacity remains).
     pub fn clear(&mut self) {
+        self.removed_item_ids.extend(self.items.keys().copied());
         self.items.clear();
     }
 }
@@ -185,6 +185,9 @@ pub struct FancyFileQueueItem<'config> {
     pub item: FileQueueItem<'config>,
 
     pub spinner: Option<AnimatedSpinner>,
+
+    /// How much of the file has been processed (0 to 100), if the job reports its progress.
+    pub progress_percent: Option<u8>,
 }
 
 impl<'config> FancyFileQueueItem<'config> {
@@ -192,6 +195,7 @@ impl<'config> FancyFileQueueItem<'config> {
         Self {
             item: queue_item,
             spinner: None,
+            progress_percent: None,
         }
     }
 
@@ -345,6 +349,16 @@ impl<'config, 'text> RenderableQueueItem<Text<'text>>
             primary_line.push(Span::raw(" "));
             primary_line.push(Span::styled(result_str, explainer_style));
         } else {
+            if let (FileQueueItemState::InProgress, Some(progress_percent)) =
+                (&self.item.state, self.progress_percent)
+            {
+                primary_line.push(Span::raw(" "));
+                primary_line.push(Span::styled(
+                    format!("{progress_percent:>3}%"),
+                    content_style,
+                ));
+            }
+
             let reason_str: &'static str = match self.item.context.action {
                 FileProcessingAction::Transcode { reason, .. } => match reason {
                     TranscodeProcessingReason::AddedInSourceLibrary => "(why: newly added)",
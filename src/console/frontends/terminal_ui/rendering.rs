@@ -4,7 +4,7 @@ use std::time::{Duration, Instant};
 
 use ansi_to_tui::IntoText;
 use crossterm::event::{Event, KeyCode};
-use euphony_configuration::ui::TranscodingUiConfiguration;
+use euphony_configuration::ui::UiConfiguration;
 use miette::Result;
 use miette::{miette, IntoDiagnostic, WrapErr};
 use parking_lot::{Mutex, RwLock};
@@ -31,14 +31,17 @@ use crate::console::colours::{
     X066_PALE_TURQUOISE4,
     X100_YELLOW4,
     X109_LIGHT_SKY_BLUE3,
+    X124_RED3,
     X136_DARK_GOLDENROD,
     X143_DARK_KHAKI,
     X160_RED3,
+    X167_INDIAN_RED,
     X172_ORANGE3,
     X242_GREY42,
     X244_GREY50,
     X245_GREY54,
 };
+use crate::console::frontends::shared::format_time_estimate;
 use crate::console::frontends::terminal_ui::queue_display::generate_smart_collapsible_queue;
 use crate::console::frontends::terminal_ui::state::{LogState, UIPage, UIState};
 use crate::console::UserControlMessage;
@@ -56,7 +59,12 @@ const LOGS_TAB_TITLE_STYLE: Style = X143_DARK_KHAKI;
 
 const LOGS_TAB_LOG_TIME_STYLE: Style = X244_GREY50;
 
+const VALIDATION_TAB_BORDER_STYLE: Style = X124_RED3;
+const VALIDATION_TAB_TITLE_STYLE: Style = X167_INDIAN_RED;
+const VALIDATION_TAB_ATTRIBUTE_NAME_STYLE: Style = X143_DARK_KHAKI;
+
 const HEADER_TRANSCODING_TAB_TEXT_STYLE: Style = TRANSCODING_TAB_TITLE_STYLE;
+const HEADER_VALIDATION_TAB_TEXT_STYLE: Style = VALIDATION_TAB_TITLE_STYLE;
 const HEADER_LOGS_TAB_TEXT_STYLE: Style = LOGS_TAB_TITLE_STYLE;
 
 const PROGRESS_BAR_BLOCK_BORDER_STYLE: Style = X136_DARK_GOLDENROD;
@@ -98,7 +106,21 @@ fn render_header(
 
     let mut line_contents: Vec<Span> = Vec::new();
 
-    {
+    if ui_state.validation_errors.is_some() {
+        let mut text_style = HEADER_VALIDATION_TAB_TEXT_STYLE;
+        if ui_state.current_page == UIPage::Validation {
+            text_style = text_style.add_modifier(Modifier::BOLD);
+        }
+
+        line_contents.push(Span::styled(
+            if ui_state.current_page == UIPage::Validation {
+                "VALIDATION <V>"
+            } else {
+                "validation <v>"
+            },
+            text_style,
+        ));
+    } else {
         let queues_disabled =
             ui_state.album_queue.is_none() || ui_state.file_queue.is_none();
 
@@ -118,7 +140,7 @@ fn render_header(
             },
             text_style,
         ));
-    };
+    }
 
     line_contents.push(Span::styled(" | ", MUTED_TEXT_STYLE));
 
@@ -358,6 +380,117 @@ fn render_transcoding_tab(
 }
 
 
+fn render_validation_tab(
+    terminal_frame: &mut Frame,
+    body_rect: Rect,
+    ui_state: &UIState,
+) {
+    let Some(validation_errors) = &ui_state.validation_errors else {
+        // This shouldn't ever happen, but if it does for some reason, we
+        // should clear the rectangle that is reserved for it as we shouldn't display anything here.
+        terminal_frame.render_widget(Clear, body_rect);
+        return;
+    };
+
+    let validation_block = Block::default()
+        .title(Span::styled(
+            format!(
+                " Validation errors ({}) ",
+                validation_errors.errors.len()
+            ),
+            VALIDATION_TAB_TITLE_STYLE,
+        ))
+        .title_alignment(Alignment::Left)
+        .padding(Padding::horizontal(1))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .border_style(VALIDATION_TAB_BORDER_STYLE);
+
+    // Count the errors of each type (in the order they were first reported).
+    let mut error_counts: Vec<(&str, usize)> = Vec::new();
+    for error in &validation_errors.errors {
+        match error_counts
+            .iter_mut()
+            .find(|(header, _)| *header == error.header)
+        {
+            Some((_, count)) => *count += 1,
+            None => error_counts.push((&error.header, 1)),
+        }
+    }
+
+    let mut validation_lines: Vec<Line> = error_counts
+        .into_iter()
+        .map(|(header, count)| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{count} x "),
+                    VALIDATION_TAB_TITLE_STYLE.add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(header.to_string()),
+            ])
+        })
+        .collect();
+
+    validation_lines.push(Line::from(Span::styled(
+        format!(
+            "Showing errors from #{} onwards (scroll with <up>/<down>).",
+            validation_errors.scroll_offset + 1
+        ),
+        MUTED_TEXT_STYLE,
+    )));
+
+    // The paragraph simply cuts off any lines that don't fit.
+    for (error_index, error) in validation_errors
+        .errors
+        .iter()
+        .enumerate()
+        .skip(validation_errors.scroll_offset)
+    {
+        if validation_lines.len() >= body_rect.height as usize {
+            break;
+        }
+
+        validation_lines.push(Line::default());
+        validation_lines.push(Line::from(vec![
+            Span::styled(
+                format!("#{} ", error_index + 1),
+                VALIDATION_TAB_TITLE_STYLE.add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                error.header.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+        ]));
+
+        for (attribute_name, attribute_value) in &error.attributes {
+            // Values can span multiple lines (e.g. file trees), in which case
+            // we indent the rest of the lines to align with the first one.
+            let mut value_lines = attribute_value.lines();
+
+            validation_lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{attribute_name}: "),
+                    VALIDATION_TAB_ATTRIBUTE_NAME_STYLE,
+                ),
+                Span::raw(value_lines.next().unwrap_or_default().to_string()),
+            ]));
+
+            for value_line in value_lines {
+                validation_lines.push(Line::from(vec![
+                    Span::raw(" ".repeat(attribute_name.len() + 2)),
+                    Span::raw(value_line.to_string()),
+                ]));
+            }
+        }
+    }
+
+    let validation_paragraph =
+        Paragraph::new(validation_lines).block(validation_block);
+
+    terminal_frame.render_widget(validation_paragraph, body_rect);
+}
+
+
 fn render_progress_footer(
     terminal_frame: &mut Frame,
     footer_rect: Rect,
@@ -372,11 +505,21 @@ fn render_progress_footer(
 
     let progress = ui_state.progress.expect("BUG: progress shouldn't be None.");
 
+    let time_estimate = match progress.estimated_time_remaining() {
+        Some(time_remaining)
+            if progress.total_finished_or_errored() < progress.total_files =>
+        {
+            format!(", ETA {}", format_time_estimate(time_remaining))
+        }
+        _ => String::new(),
+    };
+
     let footer_block = Block::default()
         .title(Span::styled(
             format!(
-                " Overall file progress ({:.1}%) ",
-                progress.completion_ratio() * 100f64
+                " Overall file progress ({:.1}%{}) ",
+                progress.completion_ratio() * 100f64,
+                time_estimate
             ),
             PROGRESS_BAR_BLOCK_TITLE_STYLE,
         ))
@@ -460,6 +603,68 @@ fn render_progress_footer(
 }
 
 
+fn render_scan_progress_footer(
+    terminal_frame: &mut Frame,
+    footer_rect: Rect,
+    ui_state: &UIState,
+) {
+    let Some(scan_progress) = ui_state.scan_progress else {
+        // Same as in `render_progress_footer`, this shouldn't ever trigger.
+        terminal_frame.render_widget(Clear, footer_rect);
+        return;
+    };
+
+    let footer_block = Block::default()
+        .title(Span::styled(
+            format!(
+                " Scanning albums for changes ({:.1}%) ",
+                scan_progress.completion_ratio() * 100f64,
+            ),
+            PROGRESS_BAR_BLOCK_TITLE_STYLE,
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Plain)
+        .border_style(PROGRESS_BAR_BLOCK_BORDER_STYLE);
+    let footer_inner_rect = footer_block.inner(footer_rect);
+
+    terminal_frame.render_widget(footer_block, footer_rect);
+
+
+    let footer_constraints =
+        vec![Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)];
+
+    let footer_inner_layout = Layout::default()
+        .constraints(footer_constraints)
+        .split(footer_inner_rect);
+
+
+    // Progress bar line
+    let scan_progress_bar_gauge = LineGauge::default()
+        .gauge_style(PROGRESS_GAUGE_STYLE)
+        .line_set(ratatui::symbols::line::THICK)
+        .ratio(scan_progress.completion_ratio())
+        .label(Span::raw(""));
+
+    terminal_frame
+        .render_widget(scan_progress_bar_gauge, footer_inner_layout[0]);
+
+    // SCANNED status line
+    let scan_status_line = Paragraph::new(Line::from(vec![
+        Span::styled("SCANNED: ", MUTED_TEXT_STYLE),
+        Span::styled(
+            format!(
+                "{} of {} albums",
+                scan_progress.albums_scanned, scan_progress.total_albums
+            ),
+            PROGRESS_DESCRIPTION_PROCESSING_FILES_VALUES_STYLE,
+        ),
+    ]))
+    .alignment(Alignment::Center);
+
+    terminal_frame.render_widget(scan_status_line, footer_inner_layout[1]);
+}
+
+
 fn render_ui(
     log_state: &LogState,
     ui_state: &UIState,
@@ -513,6 +718,21 @@ fn render_ui(
     // |     OK files (audio/data): 19/1 | FAILED files (audio/data): 1/1     |
     // |----------------------------------------------------------------------|
     //
+    //
+    // Validation page (replaces the transcoding page when validating):
+    // /------- euphony 2.0.0 --------------- help ---------------------------\
+    // | VALIDATION <V> | logs <l>          | quit <q>                        |
+    // |----------------------------------------------------------------------|
+    // |- Validation errors (3) ----------------------------------------------|
+    // | 2 x Unexpected data file in album directory.                         |
+    // | 1 x Inter-library Album Collision                                    |
+    // | Showing errors from #1 onwards (scroll with <up>/<down>).            |
+    // |                                                                      |
+    // | #1 Unexpected data file in album directory.                          |
+    // | Library: Standard                                                    |
+    // | ...                                                                  |
+    // |----------------------------------------------------------------------|
+    //
 
     let frame_size = {
         let mut size = terminal_frame.size();
@@ -528,8 +748,8 @@ fn render_ui(
         Constraint::Length(3),
         // Body of the app (either transcoding queue or log view)
         Constraint::Min(5),
-        // Footer containing the progress bar and additional info.
-        if ui_state.progress.is_some() {
+        // Footer containing the progress bar (or the scanning progress bar) and additional info.
+        if ui_state.progress.is_some() || ui_state.scan_progress.is_some() {
             Constraint::Length(4)
         } else {
             Constraint::Length(0)
@@ -543,34 +763,41 @@ fn render_ui(
 
     render_header(terminal_frame, main_layout[0], ui_state);
 
-    // If any of the queues are disabled, always render the log view instead.
-    if ui_state.file_queue.is_none() || ui_state.album_queue.is_none() {
-        render_logs_tab(terminal_frame, main_layout[1], log_state)?;
-    } else {
-        match ui_state.current_page {
-            UIPage::Transcoding => {
-                render_transcoding_tab(terminal_frame, main_layout[1], ui_state);
-            }
-            UIPage::Logs => {
-                render_logs_tab(terminal_frame, main_layout[1], log_state)?;
-            }
-        };
-    }
+    // If the selected page has nothing to show (e.g. the queues are disabled),
+    // always render the log view instead.
+    match ui_state.current_page {
+        UIPage::Transcoding
+            if ui_state.file_queue.is_some()
+                && ui_state.album_queue.is_some() =>
+        {
+            render_transcoding_tab(terminal_frame, main_layout[1], ui_state);
+        }
+        UIPage::Validation if ui_state.validation_errors.is_some() => {
+            render_validation_tab(terminal_frame, main_layout[1], ui_state);
+        }
+        _ => {
+            render_logs_tab(terminal_frame, main_layout[1], log_state)?;
+        }
+    };
 
-    // Prevents the function from being called when the progress bar is disabled
+    // Prevents the functions from being called when the progress bars are disabled
     // (the Rect will have 0 height anyway).
     if ui_state.progress.is_some() {
         render_progress_footer(terminal_frame, main_layout[2], ui_state);
+    } else if ui_state.scan_progress.is_some() {
+        render_scan_progress_footer(terminal_frame, main_layout[2], ui_state);
     }
 
     Ok(())
 }
 
-const TERMINAL_REFRESH_INTERVAL_IN_SECONDS: f64 = 1f64 / 30f64;
-
+/// Runs the render loop of the fancy terminal UI.
+///
+/// The UI is redrawn at most once per `ui.refresh_interval_ms`, regardless of how often
+/// the queues or progress are updated - those changes are simply picked up by the next frame.
 pub fn run_render_loop(
     terminal: Arc<Mutex<Terminal<CrosstermBackend<Stdout>>>>,
-    transcoding_ui_config: TranscodingUiConfiguration,
+    ui_config: UiConfiguration,
     log_state: Arc<Mutex<LogState>>,
     ui_state: Arc<RwLock<UIState>>,
     user_control_sender: &broadcast::Sender<UserControlMessage>,
@@ -578,6 +805,8 @@ pub fn run_render_loop(
 ) -> Result<()> {
     // Continuously render the terminal UI.
     // Stop when the cancellation token is set.
+    let refresh_interval_in_seconds =
+        Duration::from_millis(ui_config.refresh_interval_ms).as_secs_f64();
 
     loop {
         let render_time_start = Instant::now();
@@ -612,12 +841,12 @@ pub fn run_render_loop(
 
             let since_last_render_start =
                 render_time_start.elapsed().as_secs_f64();
-            if since_last_render_start >= TERMINAL_REFRESH_INTERVAL_IN_SECONDS {
+            if since_last_render_start >= refresh_interval_in_seconds {
                 break;
             }
 
             let time_to_poll_for =
-                TERMINAL_REFRESH_INTERVAL_IN_SECONDS - since_last_render_start;
+                refresh_interval_in_seconds - since_last_render_start;
 
             // If less than 0.1 ms away from next render, don't bother with keybind polling.
             if time_to_poll_for < 0.0001 {
@@ -634,15 +863,45 @@ pub fn run_render_loop(
                         || miette!("Failed to read keyboard event."),
                     )?
                 {
-                    if let KeyCode::Char(char) = key.code {
+                    if matches!(
+                        key.code,
+                        KeyCode::Up
+                            | KeyCode::Down
+                            | KeyCode::Char('k')
+                            | KeyCode::Char('j')
+                    ) {
+                        let mut locked_ui_state = ui_state.write();
+                        if locked_ui_state.current_page == UIPage::Validation {
+                            if let Some(validation_errors) =
+                                &mut locked_ui_state.validation_errors
+                            {
+                                if matches!(
+                                    key.code,
+                                    KeyCode::Up | KeyCode::Char('k')
+                                ) {
+                                    validation_errors.scroll_up();
+                                } else {
+                                    validation_errors.scroll_down();
+                                }
+                            }
+                        }
+                    } else if let KeyCode::Char(char) = key.code {
                         if char == 'q' {
-                            if transcoding_ui_config.show_logs_tab_on_exit {
-                                let mut locked_ui_state = ui_state.write();
+                            let mut locked_ui_state = ui_state.write();
+
+                            // Validation errors remain visible after exiting.
+                            if ui_config.transcoding.show_logs_tab_on_exit
+                                && locked_ui_state.validation_errors.is_none()
+                            {
                                 locked_ui_state.current_page = UIPage::Logs;
                             }
+                            drop(locked_ui_state);
 
                             let _ = user_control_sender
                                 .send(UserControlMessage::Exit);
+                        } else if char == 'y' {
+                            let _ = user_control_sender
+                                .send(UserControlMessage::Confirm);
                         } else if char == 't' {
                             let mut locked_ui_state = ui_state.write();
                             if locked_ui_state.album_queue.is_some()
@@ -651,6 +910,12 @@ pub fn run_render_loop(
                                 locked_ui_state.current_page =
                                     UIPage::Transcoding;
                             }
+                        } else if char == 'v' {
+                            let mut locked_ui_state = ui_state.write();
+                            if locked_ui_state.validation_errors.is_some() {
+                                locked_ui_state.current_page =
+                                    UIPage::Validation;
+                            }
                         } else if char == 'l' {
                             let mut locked_ui_state = ui_state.write();
                             locked_ui_state.current_page = UIPage::Logs;
@@ -6,6 +6,7 @@ use std::sync::Arc;
 use std::thread::ScopedJoinHandle;
 
 use chrono::{DateTime, Local};
+use euphony_configuration::logging::LogFileFormat;
 use miette::Result;
 use parking_lot::Mutex;
 use ratatui::backend::CrosstermBackend;
@@ -18,12 +19,12 @@ use crate::console::frontends::shared::queue::{
     FileQueueItemFinishedResult,
     Queue,
 };
-use crate::console::frontends::shared::Progress;
+use crate::console::frontends::shared::{Progress, ScanProgress};
 use crate::console::frontends::terminal_ui::queue_items::{
     FancyAlbumQueueItem,
     FancyFileQueueItem,
 };
-use crate::console::UserControlMessage;
+use crate::console::{UserControlMessage, ValidationErrorInfo};
 
 
 const LOG_JOURNAL_DEFAULT_MAXIMUM_HISTORY: usize = 40;
@@ -47,6 +48,7 @@ pub enum LogOutputMode<'thread_scope> {
     None,
     ToFile {
         buf_writer: Arc<Mutex<BufWriter<strip_ansi_escapes::Writer<File>>>>,
+        format: LogFileFormat,
         writer_flushing_thread_handle:
             ScopedJoinHandle<'thread_scope, Result<()>>,
         writer_flushing_thread_cancellation_token: CancellationToken,
@@ -107,9 +109,37 @@ impl<'thread_scope> LogState<'thread_scope> {
 #[derive(Eq, PartialEq, Copy, Clone)]
 pub enum UIPage {
     Transcoding,
+    Validation,
     Logs,
 }
 
+/// Validation errors shown in the validation page of the terminal UI.
+pub struct ValidationErrorsState {
+    pub errors: Vec<ValidationErrorInfo>,
+
+    /// Index of the first error displayed in the (scrollable) error panel.
+    pub scroll_offset: usize,
+}
+
+impl ValidationErrorsState {
+    pub fn new() -> Self {
+        Self {
+            errors: Vec::new(),
+            scroll_offset: 0,
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.scroll_offset + 1 < self.errors.len() {
+            self.scroll_offset += 1;
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(1);
+    }
+}
+
 pub struct UIState<'config> {
     pub album_queue: Option<
         Queue<FancyAlbumQueueItem<'config>, AlbumQueueItemFinishedResult>,
@@ -120,6 +150,12 @@ pub struct UIState<'config> {
 
     pub progress: Option<Progress>,
 
+    /// Progress of the album scanning phase, while albums are being scanned for changes.
+    pub scan_progress: Option<ScanProgress>,
+
+    /// Validation errors, once the first one has been reported.
+    pub validation_errors: Option<ValidationErrorsState>,
+
     pub current_page: UIPage,
 }
 
@@ -129,6 +165,8 @@ impl<'config> UIState<'config> {
             album_queue: None,
             file_queue: None,
             progress: None,
+            scan_progress: None,
+            validation_errors: None,
             current_page: UIPage::Logs,
         }
     }
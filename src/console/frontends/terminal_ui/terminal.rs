@@ -9,6 +9,7 @@ use std::time::Duration;
 
 use chrono::Local;
 use crossterm::ExecutableCommand;
+use euphony_configuration::logging::LogFileFormat;
 use euphony_configuration::Configuration;
 use miette::{miette, Context, IntoDiagnostic, Result};
 use parking_lot::{Mutex, RwLock};
@@ -17,7 +18,11 @@ use ratatui::Terminal;
 use tokio::sync::broadcast;
 
 use crate::cancellation::CancellationToken;
-use crate::console::frontends::shared::logging::initialize_log_file_for_log_output;
+use crate::console::frontends::shared::logging::{
+    format_jsonl_log_line,
+    initialize_log_file_for_log_output,
+    LogLevel,
+};
 use crate::console::frontends::shared::queue::{
     AlbumQueueItem,
     AlbumQueueItemFinishedResult,
@@ -27,7 +32,7 @@ use crate::console::frontends::shared::queue::{
     QueueItem,
     QueueItemID,
 };
-use crate::console::frontends::shared::Progress;
+use crate::console::frontends::shared::{Progress, ScanProgress};
 use crate::console::frontends::terminal_ui::queue_items::{
     FancyAlbumQueueItem,
     FancyFileQueueItem,
@@ -39,6 +44,7 @@ use crate::console::frontends::terminal_ui::state::{
     TerminalState,
     UIPage,
     UIState,
+    ValidationErrorsState,
 };
 use crate::console::{
     LogBackend,
@@ -47,6 +53,8 @@ use crate::console::{
     TranscodeBackend,
     UserControlMessage,
     UserControllableBackend,
+    ValidationBackend,
+    ValidationErrorInfo,
 };
 
 const LOG_FILE_OUTPUT_FLUSHING_INTERVAL: Duration = Duration::from_secs(8);
@@ -118,6 +126,44 @@ impl<'thread_scope, 'config> FancyTerminalBackend<'thread_scope, 'config> {
             config,
         })
     }
+
+    /// Write the message into the log file (if enabled) and add it to the log journal.
+    fn log_message(&self, level: LogLevel, message: String) {
+        let mut state = self.log_state.lock();
+
+        // If enabled, write message into the log file (its BufWriter, to be precise).
+        match &state.log_output {
+            LogOutputMode::ToFile {
+                buf_writer, format, ..
+            } => {
+                let line = match format {
+                    LogFileFormat::Text => {
+                        let time_now = Local::now();
+                        let formatted_time_now =
+                            time_now.format("%Y-%m-%d %H:%M:%S%.3f ");
+
+                        format!("{formatted_time_now}{message}")
+                    }
+                    LogFileFormat::Jsonl => {
+                        format_jsonl_log_line(level, &message)
+                    }
+                };
+
+                let mut locked_buf_writer = buf_writer.lock();
+
+                locked_buf_writer.write_all(line.as_bytes()).expect(
+                    "Failed to write println contents to log file output.",
+                );
+                locked_buf_writer
+                    .write_all("\n".as_bytes())
+                    .expect("Failed to write newline to log file output.");
+            }
+            LogOutputMode::None => {}
+        }
+
+        // Add message to log journal.
+        state.log_journal.insert_entry(message);
+    }
 }
 
 impl<'scope, 'scope_env: 'scope, 'config: 'scope>
@@ -173,12 +219,12 @@ impl<'scope, 'scope_env: 'scope, 'config: 'scope>
         let user_control_sender_clone = user_control_sender.clone();
         let render_cancellation_token_clone = render_cancellation_token.clone();
 
-        let transcoding_ui_config = self.config.ui.transcoding.clone();
+        let ui_config = self.config.ui.clone();
 
         let render_thread_join_handle = scope.spawn(move || {
             rendering::run_render_loop(
                 terminal_arc_mutex_clone,
-                transcoding_ui_config,
+                ui_config,
                 log_state_arc_clone,
                 ui_state_arc_clone,
                 &user_control_sender_clone,
@@ -256,15 +302,20 @@ impl<'thread_scope, 'config> LogBackend
         let mut state = self.log_state.lock();
 
         // If enabled, write newline into the log file (its BufWriter, to be precise).
+        // Empty lines are only spacing, so they are left out of `jsonl` log files.
         match &state.log_output {
-            LogOutputMode::ToFile { buf_writer, .. } => {
+            LogOutputMode::ToFile {
+                buf_writer,
+                format: LogFileFormat::Text,
+                ..
+            } => {
                 let mut locked_buf_writer = buf_writer.lock();
 
                 locked_buf_writer
                     .write_all("\n".as_bytes())
                     .expect("Failed to write newline to log file output.");
             }
-            LogOutputMode::None => {}
+            LogOutputMode::ToFile { .. } | LogOutputMode::None => {}
         }
 
         // Add newline to log journal.
@@ -272,33 +323,13 @@ impl<'thread_scope, 'config> LogBackend
     }
 
     fn log_println<D: Display>(&self, content: D) {
-        let message = content.to_string();
-        let mut state = self.log_state.lock();
-
-        // If enabled, write message into the log file (its BufWriter, to be precise).
-        match &state.log_output {
-            LogOutputMode::ToFile { buf_writer, .. } => {
-                let time_now = Local::now();
-                let formatted_time_now =
-                    time_now.format("%Y-%m-%d %H:%M:%S%.3f ");
-
-                let mut locked_buf_writer = buf_writer.lock();
-
-                locked_buf_writer
-                    .write_all(formatted_time_now.to_string().as_bytes())
-                    .expect("Failed to write formatted time to log file output");
-                locked_buf_writer.write_all(message.as_bytes()).expect(
-                    "Failed to write println contents to log file output.",
-                );
-                locked_buf_writer
-                    .write_all("\n".as_bytes())
-                    .expect("Failed to write newline to log file output.");
-            }
-            LogOutputMode::None => {}
-        }
+        self.log_message(LogLevel::Info, content.to_string());
+    }
 
-        // Add message to log journal.
-        state.log_journal.insert_entry(message);
+    fn log_eprintln<D: Display>(&self, content: D) {
+        // The terminal UI is never used in quiet mode, so errors simply go into the log journal.
+        let message = content.to_string();
+        self.log_message(LogLevel::of_error_output(&message), message);
     }
 }
 
@@ -309,13 +340,16 @@ impl<'scope, 'scope_env: 'scope, 'config: 'scope>
     fn enable_saving_logs_to_file<P: AsRef<Path>>(
         &self,
         log_output_file_path: P,
+        log_file_format: LogFileFormat,
         scope: &'scope Scope<'scope, 'scope_env>,
     ) -> Result<()> {
-        let buf_writer =
-            initialize_log_file_for_log_output(log_output_file_path.as_ref())
-                .wrap_err_with(|| {
-                    miette!("Failed to initialize log file for log output.")
-                })?;
+        let buf_writer = initialize_log_file_for_log_output(
+            log_output_file_path.as_ref(),
+            log_file_format,
+        )
+        .wrap_err_with(|| {
+            miette!("Failed to initialize log file for log output.")
+        })?;
 
 
         let buf_writer_arc_mutex = Arc::new(Mutex::new(buf_writer));
@@ -339,6 +373,7 @@ impl<'scope, 'scope_env: 'scope, 'config: 'scope>
 
             locked_state.log_output = LogOutputMode::ToFile {
                 buf_writer: buf_writer_arc_mutex,
+                format: log_file_format,
                 writer_flushing_thread_handle: flushing_thread_handle,
                 writer_flushing_thread_cancellation_token:
                     flushing_thread_cancellation_token,
@@ -383,6 +418,25 @@ impl<'scope, 'scope_env: 'scope, 'config: 'scope>
     }
 }
 
+impl<'thread_scope, 'config> ValidationBackend
+    for FancyTerminalBackend<'thread_scope, 'config>
+{
+    fn validation_add_error(&self, error: ValidationErrorInfo) {
+        let mut locked_state = self.ui_state.write();
+
+        // Switch to the validation page once the first error comes in.
+        if locked_state.validation_errors.is_none() {
+            locked_state.current_page = UIPage::Validation;
+        }
+
+        locked_state
+            .validation_errors
+            .get_or_insert_with(ValidationErrorsState::new)
+            .errors
+            .push(error);
+    }
+}
+
 impl<'thread_scope, 'config> TranscodeBackend<'config>
     for FancyTerminalBackend<'thread_scope, 'config>
 {
@@ -423,6 +477,15 @@ impl<'thread_scope, 'config> TranscodeBackend<'config>
 
         let mut locked_state = self.ui_state.write();
 
+        debug_assert!(
+            !locked_state
+                .file_queue
+                .as_ref()
+                .is_some_and(|file_queue| file_queue.contains_item(item_id)),
+            "BUG: album queue item ID {} is already used in the file queue.",
+            *item_id
+        );
+
         locked_state
             .album_queue
             .as_mut()
@@ -516,6 +579,15 @@ impl<'thread_scope, 'config> TranscodeBackend<'config>
 
         let mut locked_state = self.ui_state.write();
 
+        debug_assert!(
+            !locked_state
+                .album_queue
+                .as_ref()
+                .is_some_and(|album_queue| album_queue.contains_item(item_id)),
+            "BUG: file queue item ID {} is already used in the album queue.",
+            *item_id
+        );
+
         locked_state
             .file_queue
             .as_mut()
@@ -535,6 +607,25 @@ impl<'thread_scope, 'config> TranscodeBackend<'config>
             .start_item(item_id)
     }
 
+    fn queue_file_item_set_progress(
+        &self,
+        item_id: QueueItemID,
+        percent: u8,
+    ) -> Result<()> {
+        let mut locked_state = self.ui_state.write();
+
+        locked_state
+            .file_queue
+            .as_mut()
+            .ok_or_else(|| {
+                miette!("File queue is disabled, can't update item progress.")
+            })?
+            .existing_item_mut(item_id)?
+            .progress_percent = Some(percent);
+
+        Ok(())
+    }
+
     fn queue_file_item_finish(
         &self,
         item_id: QueueItemID,
@@ -593,7 +684,7 @@ impl<'thread_scope, 'config> TranscodeBackend<'config>
             .ok_or_else(|| {
                 miette!("Progress bar is disabled, can't set total.")
             })?
-            .total_files = num_total;
+            .set_total_files(num_total);
 
         Ok(())
     }
@@ -699,6 +790,38 @@ impl<'thread_scope, 'config> TranscodeBackend<'config>
 
         Ok(())
     }
+
+    fn progress_set_scan_total(&self, num_total_albums: usize) {
+        let mut locked_state = self.ui_state.write();
+        locked_state.scan_progress = Some(ScanProgress {
+            total_albums: num_total_albums,
+            albums_scanned: 0,
+        });
+    }
+
+    fn progress_set_scan_albums_scanned(
+        &self,
+        num_albums_scanned: usize,
+    ) -> Result<()> {
+        let mut locked_state = self.ui_state.write();
+
+        locked_state
+            .scan_progress
+            .as_mut()
+            .ok_or_else(|| {
+                miette!(
+                    "Scanning progress is disabled, can't set albums scanned."
+                )
+            })?
+            .albums_scanned = num_albums_scanned;
+
+        Ok(())
+    }
+
+    fn progress_set_scan_finished(&self) {
+        let mut locked_state = self.ui_state.write();
+        locked_state.scan_progress = None;
+    }
 }
 
 impl<'thread_scope, 'config> UserControllableBackend
@@ -718,4 +841,19 @@ impl<'thread_scope, 'config> UserControllableBackend
             )),
         }
     }
+
+    fn get_user_control_sender(
+        &self,
+    ) -> Result<broadcast::Sender<UserControlMessage>> {
+        let locked_terminal = self.terminal_state.lock();
+
+        match locked_terminal.as_ref() {
+            Some(terminal_state) => {
+                Ok(terminal_state.user_control_sender.clone())
+            }
+            None => Err(miette!(
+                "Backend hasn't been set up, can't get user control sender."
+            )),
+        }
+    }
 }
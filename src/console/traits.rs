@@ -2,6 +2,7 @@ use std::fmt::Display;
 use std::path::Path;
 use std::thread::Scope;
 
+use euphony_configuration::logging::LogFileFormat;
 use miette::Result;
 use tokio::sync::broadcast;
 
@@ -32,13 +33,19 @@ pub trait LogBackend {
 
     /// Print a string into the log, followed by a new line.
     fn log_println<D: Display>(&self, content: D);
+
+    /// Print an error or a warning into the log, followed by a new line.
+    /// Unlike `log_println`, this is still shown in quiet mode (the bare backend prints it to stderr).
+    fn log_eprintln<D: Display>(&self, content: D);
 }
 
 /// Allows saving `LogBackend`'s log output to file (usually in addition to the terminal or whatever).
 pub trait LogToFileBackend<'scope, 'scope_env: 'scope> {
+    /// Start saving log output into `log_file_path`, in the given format (see `logging.file_format`).
     fn enable_saving_logs_to_file<P: AsRef<Path>>(
         &self,
         log_file_path: P,
+        log_file_format: LogFileFormat,
         scope: &'scope Scope<'scope, 'scope_env>,
     ) -> Result<()>;
     fn disable_saving_logs_to_file(&self) -> Result<()>;
@@ -107,6 +114,13 @@ pub trait TranscodeBackend<'config> {
     /// This will give it the `FileItemState::InProgress` state.
     fn queue_file_item_start(&self, item_id: QueueItemID) -> Result<()>;
 
+    /// Update the progress (0 to 100 percent) of the given in-progress file in the file queue.
+    fn queue_file_item_set_progress(
+        &self,
+        item_id: QueueItemID,
+        percent: u8,
+    ) -> Result<()>;
+
     /// Mark the given file in the file queue as "finished".
     /// This will give it the `FileItemState:Finished` state and the given `result`.
     fn queue_file_item_finish(
@@ -162,6 +176,19 @@ pub trait TranscodeBackend<'config> {
         &self,
         num_data_files_errored: usize,
     ) -> Result<()>;
+
+    /// Start showing the progress of the album scanning phase, with the given total number of albums.
+    /// This is independent of the transcoding progress bar (see `progress_enable`).
+    fn progress_set_scan_total(&self, num_total_albums: usize);
+
+    /// Set the number of albums that have been scanned for changes so far.
+    fn progress_set_scan_albums_scanned(
+        &self,
+        num_albums_scanned: usize,
+    ) -> Result<()>;
+
+    /// Stop showing the progress of the album scanning phase.
+    fn progress_set_scan_finished(&self);
 }
 
 /// Shared format for validation errors.
@@ -197,6 +224,8 @@ pub trait ValidationBackend {
 #[derive(Copy, Clone)]
 pub enum UserControlMessage {
     Exit,
+    /// The user confirmed a pending question (e.g. whether to start the first transcode).
+    Confirm,
 }
 
 /// Allows user input (whatever that means for the implementor - generally a key press)
@@ -206,4 +235,10 @@ pub trait UserControllableBackend {
     fn get_user_control_receiver(
         &self,
     ) -> Result<broadcast::Receiver<UserControlMessage>>;
+
+    /// Get a sender that can inject `UserControlMessage`s as if they came from the user
+    /// (used to forward termination signals, see `signals::forward_termination_signals`).
+    fn get_user_control_sender(
+        &self,
+    ) -> Result<broadcast::Sender<UserControlMessage>>;
 }
@@ -6,3 +6,13 @@ pub static VERBOSE: state::InitCell<bool> = state::InitCell::new();
 pub fn is_verbose_enabled() -> bool {
     VERBOSE.get().eq(&true)
 }
+
+/// A global boolean indicating whether we are running in quiet mode
+/// (only warnings and errors are printed, see `LogBackend::log_eprintln`).
+pub static QUIET: state::InitCell<bool> = state::InitCell::new();
+
+/// Shorthand to get the global flag value for quiet mode.
+#[inline]
+pub fn is_quiet_enabled() -> bool {
+    QUIET.get().eq(&true)
+}
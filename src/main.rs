@@ -1,13 +1,22 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::thread;
 use std::thread::Scope;
+use std::time::{Duration, SystemTime};
 
-use clap::{Args, Parser, Subcommand};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use crossterm::style::Stylize;
 use euphony_configuration::Configuration;
-use miette::{miette, Context, Result};
+use miette::{miette, Context, IntoDiagnostic, Result};
 
+use crate::commands::transcode::events::{
+    TranscodeEventStream,
+    TranscodeEventStreamTarget,
+};
+use crate::commands::transcode::staging::StagingDirectory;
+use crate::commands::{SerializedConfigurationFormat, TranscodeOptions};
 use crate::console::frontends::terminal_ui::terminal::FancyTerminalBackend;
 use crate::console::frontends::{
     BareTerminalBackend,
@@ -15,13 +24,21 @@ use crate::console::frontends::{
     TranscodeTerminal,
     ValidationTerminal,
 };
-use crate::console::{LogBackend, LogToFileBackend, TerminalBackend};
-use crate::globals::VERBOSE;
+use crate::console::{
+    LogBackend,
+    LogToFileBackend,
+    TerminalBackend,
+    UserControllableBackend,
+};
+use crate::globals::{is_quiet_enabled, is_verbose_enabled, QUIET, VERBOSE};
 
 mod cancellation;
 mod commands;
 mod console;
 mod globals;
+mod signals;
+#[cfg(test)]
+mod test_utilities;
 
 pub const EUPHONY_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -43,17 +60,87 @@ enum CLICommand {
     )]
     ValidateAll(ValidateAllArgs),
 
+    #[command(
+        name = "reindex",
+        about = "Regenerate all state files from the current contents of the source and transcoded libraries, \
+                 without transcoding anything. Useful after manually bringing the two in sync."
+    )]
+    Reindex,
+
+    #[command(
+        name = "relink",
+        about = "After moving or renaming albums in a source library, move their transcoded albums \
+                 and update the state files to match, so the next transcode doesn't redo them."
+    )]
+    Relink(RelinkArgs),
+
+    #[command(
+        name = "inspect-state",
+        about = "Print the saved source and transcoded state of a single album \
+                 and the changes euphony currently sees in it (read-only)."
+    )]
+    InspectState(InspectStateArgs),
+
+    #[command(
+        name = "diff",
+        about = "Print the changes euphony currently sees in a single album \
+                 (what `transcode` would add, change or remove) as JSON (read-only)."
+    )]
+    Diff(DiffArgs),
+
+    #[command(
+        name = "what-if-remove",
+        about = "List the transcoded files and directories that would be removed \
+                 if the given artist were removed from the source libraries (read-only)."
+    )]
+    WhatIfRemove(WhatIfRemoveArgs),
+
+    #[command(
+        name = "clean",
+        about = "List files in the transcoded library that don't belong to any transcode \
+                 (and remove them with --delete), without transcoding anything."
+    )]
+    Clean(CleanArgs),
+
+    #[command(
+        name = "stats",
+        about = "Print the number of artists, albums and tracked files in each library, \
+                 along with their total size and the space saved by transcoding them (read-only)."
+    )]
+    Stats(StatsArgs),
+
+    #[command(
+        name = "verify",
+        about = "Check that every file tracked in the transcoded album states still exists \
+                 and is unchanged and that no album is missing transcoded tracks (read-only)."
+    )]
+    Verify(VerifyArgs),
+
+    #[command(
+        name = "doctor",
+        about = "Check that the configured ffmpeg binary works and that all library paths \
+                 are accessible and writable, before starting a long transcode."
+    )]
+    Doctor,
+
     #[command(
         name = "show-config",
         about = "Loads, validates and prints the current configuration."
     )]
-    ShowConfig,
+    ShowConfig(ShowConfigArgs),
 
     #[command(
         name = "list-libraries",
         about = "List all the registered libraries registered in the configuration."
     )]
-    ListLibraries,
+    ListLibraries(ListLibrariesArgs),
+
+    #[command(
+        name = "init-config",
+        about = "Write a fully-commented configuration template to get started with \
+                 (doesn't require an existing configuration file)."
+    )]
+    InitConfig(InitConfigArgs),
 }
 
 #[derive(Args, Eq, PartialEq)]
@@ -72,15 +159,349 @@ struct TranscodeAllArgs {
         help = "Path to the log file. If this is unset, no logs are saved."
     )]
     log_to_file: Option<PathBuf>,
+
+    #[arg(
+        long = "retranscode-on-tag-change",
+        help = "Record the title, artist and album tags of source audio files and re-transcode \
+                any file whose tags changed since the last transcode. \
+                Overrides `aggregated_library.retranscode_on_tag_change` for this run."
+    )]
+    retranscode_on_tag_change: bool,
+
+    #[arg(
+        long = "export",
+        help = "One-shot export mode: transcode every album as if it were new (ignoring any saved state) \
+                and don't write any state files into the source or transcoded libraries."
+    )]
+    export: bool,
+
+    #[arg(
+        long = "remove-state-files",
+        requires = "export",
+        help = "In export mode, also remove any existing euphony state files \
+                from the processed albums and libraries."
+    )]
+    remove_state_files: bool,
+
+    #[arg(
+        long = "dry-run",
+        help = "Only print what would be transcoded, copied and deleted (and why), \
+                without changing anything on disk (state files included)."
+    )]
+    dry_run: bool,
+
+    #[arg(
+        long = "check",
+        conflicts_with_all = ["dry_run", "export", "remove_state_files", "list_errored"],
+        help = "Only check whether the transcoded collection is up to date: scan for changes, print a short summary \
+                and exit with a non-zero code if the next transcode would process any album. \
+                Nothing is changed on disk (state files included). Implies --bare-terminal."
+    )]
+    check: bool,
+
+    #[arg(
+        short = 'y',
+        long = "yes",
+        help = "Don't ask for confirmation before the first transcode \
+                (when no saved state exists in any library yet) or, with --confirm-deletions, \
+                before deleting excess files in the transcoded library."
+    )]
+    yes: bool,
+
+    #[arg(
+        long = "only-library",
+        help = "Only scan and transcode the library with this name (can be passed several times \
+                to select multiple libraries). Case-insensitive partial names are accepted as long as \
+                they match a single library (an exact name always wins)."
+    )]
+    only_library: Vec<String>,
+
+    #[arg(
+        long = "only-new",
+        conflicts_with = "export",
+        help = "Only transcode and copy new files (and files missing from the transcoded library). \
+                Changed and removed source files are ignored, so nothing is removed or overwritten. \
+                Useful for append-only libraries."
+    )]
+    only_new: bool,
+
+    #[arg(
+        long = "jobs",
+        value_name = "N",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        help = "Transcode each album with N threads for this run, overriding both \
+                `aggregated_library.transcode_threads` and any per-library `transcoding.transcode_threads`."
+    )]
+    jobs: Option<usize>,
+
+    #[arg(
+        long = "no-cache",
+        help = "Scan every album in full, ignoring the per-library scan cache \
+                (use this if files were modified without changing their modification times)."
+    )]
+    no_cache: bool,
+
+    #[arg(
+        long = "since",
+        value_name = "TIME",
+        value_parser = parse_since_cutoff,
+        conflicts_with = "export",
+        help = "Skip previously transcoded albums that have nothing modified since TIME, without scanning them \
+                for changes. TIME is either relative (e.g. \"30m\", \"24h\", \"7d\"; units are s, m, h, d and w) \
+                or an absolute local time (e.g. \"2024-05-01\" or \"2024-05-01 18:30\", RFC 3339 is also accepted). \
+                This relies on modification times only, so changes that keep old modification times are missed."
+    )]
+    since: Option<SystemTime>,
+
+    #[arg(
+        long = "allow-collisions",
+        help = "Transcode even if the same artist and album exist in more than one library \
+                (these would overwrite each other in the transcoded library). \
+                Without this, such collisions abort the transcode before anything is processed."
+    )]
+    allow_collisions: bool,
+
+    #[arg(
+        long = "fail-fast",
+        help = "Abort the entire transcode (with a non-zero exit code) as soon as any file fails to process. \
+                Without this, errored files are counted and the transcode continues."
+    )]
+    fail_fast: bool,
+
+    #[arg(
+        long = "strict",
+        help = "Abort the entire transcode as soon as any album can't be scanned for changes \
+                (e.g. because one of its files can't be read). Without this, such albums are skipped \
+                and listed once scanning is done."
+    )]
+    strict: bool,
+
+    #[arg(
+        long = "limit",
+        value_name = "N",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        help = "Only process the first N changed albums (across all libraries, sorted by library, artist and album), \
+                e.g. for trying out configuration changes. Libraries that aren't processed in full \
+                don't get their library state saved, so the next run still sees their remaining albums as changed."
+    )]
+    limit: Option<usize>,
+
+    #[arg(
+        long = "confirm-deletions",
+        help = "Before deleting files in the transcoded library that don't belong to any transcode \
+                (e.g. files added there by hand), list them and ask for confirmation. \
+                If the deletion isn't confirmed (or can't be asked for, e.g. with a non-interactive \
+                --bare-terminal run without --yes), the files are kept and a warning is printed."
+    )]
+    confirm_deletions: bool,
+
+    #[arg(
+        long = "events-jsonl",
+        value_name = "PATH",
+        help = "Also write a machine-parseable stream of album and file starts and finishes \
+                (one JSON object per line, with timestamps) to this file, or to standard output if set to \"-\". \
+                The human-readable log is unaffected. Standard output requires --bare-terminal \
+                (combine it with --quiet to keep the human-readable log out of the stream)."
+    )]
+    events_jsonl: Option<String>,
+
+    #[arg(
+        long = "list-errored",
+        help = "Instead of transcoding, print the files that errored the last time each album was transcoded \
+                (without scanning for changes)."
+    )]
+    list_errored: bool,
+
+    #[arg(
+        long = "json",
+        requires = "list_errored",
+        help = "Print the output of --list-errored as JSON."
+    )]
+    json: bool,
 }
 
 #[derive(Args, Eq, PartialEq)]
 struct ValidateAllArgs {
+    #[arg(
+        long = "bare-terminal",
+        help = "Whether to disable the fancy terminal UI and simply print the validation errors \
+                into the console (always the case with --format json)."
+    )]
+    bare_terminal: bool,
+
     #[arg(
         long = "log-to-file",
         help = "Path to the log file. If this is unset, no logs are saved."
     )]
     log_to_file: Option<PathBuf>,
+
+    #[arg(
+        long = "format",
+        value_enum,
+        default_value_t = ValidationOutputFormat::Pretty,
+        help = "Output format of the validation results. \
+                \"json\" prints a machine-readable array of all validation errors."
+    )]
+    format: ValidationOutputFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq)]
+enum ValidationOutputFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Args, Eq, PartialEq)]
+struct InspectStateArgs {
+    #[arg(
+        help = "The album to inspect, given as <library name>/<artist>/<album> \
+                (e.g. \"Lossless/Aindulmedir/The Lunar Lexicon\"). \
+                The library can also be given by its configuration key."
+    )]
+    album: String,
+}
+
+#[derive(Args, Eq, PartialEq)]
+struct RelinkArgs {
+    #[arg(
+        long = "library",
+        help = "Name of the library the albums were moved in. The configuration key is also accepted."
+    )]
+    library: String,
+
+    #[arg(
+        help = "Path to the mapping of old to new album paths (<artist>/<album>, relative to the library root). \
+                A .json file must contain an object of old to new paths, any other file is read \
+                as CSV with two columns (old path, new path)."
+    )]
+    mapping_file: PathBuf,
+}
+
+#[derive(Args, Eq, PartialEq)]
+struct DiffArgs {
+    #[arg(
+        long = "library",
+        help = "Name of the library the album is in. The configuration key is also accepted."
+    )]
+    library: String,
+
+    #[arg(
+        long = "artist",
+        help = "Name of the artist (i.e. the artist directory name)."
+    )]
+    artist: String,
+
+    #[arg(
+        long = "album",
+        help = "Title of the album (i.e. the album directory name)."
+    )]
+    album: String,
+}
+
+#[derive(Args, Eq, PartialEq)]
+struct CleanArgs {
+    #[arg(
+        long = "delete",
+        help = "Actually remove the orphaned files (and any album and artist directories left empty). \
+                Without this flag, the files are only listed."
+    )]
+    delete: bool,
+}
+
+#[derive(Args, Eq, PartialEq)]
+struct StatsArgs {
+    #[arg(
+        long = "format",
+        value_enum,
+        default_value_t = StatsOutputFormat::Pretty,
+        help = "Output format of the statistics. \
+                \"json\" prints a machine-readable object with per-library and total statistics."
+    )]
+    format: StatsOutputFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq)]
+enum StatsOutputFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Args, Eq, PartialEq)]
+struct VerifyArgs {
+    #[arg(
+        long = "format",
+        value_enum,
+        default_value_t = VerifyOutputFormat::Pretty,
+        help = "Output format of the verification results. \
+                \"json\" prints a machine-readable array of all verification errors."
+    )]
+    format: VerifyOutputFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq)]
+enum VerifyOutputFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Args, Eq, PartialEq)]
+struct ShowConfigArgs {
+    #[arg(
+        long = "format",
+        value_enum,
+        default_value_t = ShowConfigOutputFormat::Pretty,
+        help = "Output format of the configuration. \
+                \"json\" and \"toml\" print the fully-resolved configuration (with all placeholders \
+                and environment variables expanded and existing paths canonicalized) in that format."
+    )]
+    format: ShowConfigOutputFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq)]
+enum ShowConfigOutputFormat {
+    Pretty,
+    Json,
+    Toml,
+}
+
+#[derive(Args, Eq, PartialEq)]
+struct ListLibrariesArgs {
+    #[arg(
+        long = "format",
+        value_enum,
+        default_value_t = ListLibrariesOutputFormat::Pretty,
+        help = "Output format of the library list. \
+                \"json\" prints a machine-readable array with the name, path, allowed audio file extensions \
+                and ignored base directories of each library."
+    )]
+    format: ListLibrariesOutputFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq)]
+enum ListLibrariesOutputFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Args, Eq, PartialEq)]
+struct WhatIfRemoveArgs {
+    #[arg(help = "Name of the artist (i.e. the artist directory name).")]
+    artist: String,
+
+    #[arg(long = "json", help = "Print the report as JSON.")]
+    json: bool,
+}
+
+#[derive(Args, Eq, PartialEq)]
+struct InitConfigArgs {
+    #[arg(
+        help = "Where to write the configuration template. Defaults to ./data/configuration.toml \
+                (relative to the binary), which is where euphony loads it from without --config."
+    )]
+    path: Option<PathBuf>,
+
+    #[arg(long = "force", help = "Overwrite the file if it already exists.")]
+    force: bool,
 }
 
 #[derive(Parser)]
@@ -100,12 +521,21 @@ struct CLIArgs {
         short = 'c',
         long = "config",
         global = true,
-        help = "Optionally a path to your configuration file. Without this option, \
-                euphony tries to load ./data/configuration.toml (relative to the binary), \
-                but understandably this might not always be the most convenient location."
+        help = "Optionally a path to your configuration file. Without this option, euphony uses \
+                the first existing one of: ./configuration.toml (in the current directory), \
+                $XDG_CONFIG_HOME/euphony/configuration.toml, ~/.config/euphony/configuration.toml \
+                and ./data/configuration.toml (relative to the binary)."
     )]
     config: Option<String>,
 
+    #[arg(
+        long = "data-dir",
+        global = true,
+        help = "Optionally a path to the directory euphony keeps its own runtime files in \
+                (e.g. error records). Overrides `paths.data_directory_path` from the configuration file."
+    )]
+    data_dir: Option<PathBuf>,
+
     #[arg(
         short = 'v',
         long = "verbose",
@@ -114,18 +544,251 @@ struct CLIArgs {
     )]
     verbose: bool,
 
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        global = true,
+        conflicts_with = "verbose",
+        help = "Only print warnings and errors (to stderr), e.g. for scheduled runs. \
+                Implies --bare-terminal. Logging to file is unaffected."
+    )]
+    quiet: bool,
+
+    /// If omitted, `ui.default_command` from the configuration file is run instead.
+    #[command(subcommand)]
+    command: Option<CLICommand>,
+}
+
+/// Parses the subcommand configured in `ui.default_command`.
+#[derive(Parser)]
+#[command(name = "euphony", no_binary_name = true)]
+struct DefaultCLICommand {
     #[command(subcommand)]
     command: CLICommand,
 }
 
+/// Parse the value of `transcode --since`: either a duration before now
+/// (a number followed by one of `s`, `m`, `h`, `d` or `w`, e.g. `24h`) or an absolute time
+/// (RFC 3339, or `YYYY-MM-DD[ HH:MM[:SS]]` in local time).
+fn parse_since_cutoff(value: &str) -> Result<SystemTime, String> {
+    let value = value.trim();
+
+    if let Some(unit) = value.chars().last().filter(char::is_ascii_alphabetic) {
+        if let Ok(amount) = value[..value.len() - 1].parse::<u64>() {
+            let unit_seconds: u64 = match unit {
+                's' => 1,
+                'm' => 60,
+                'h' => 60 * 60,
+                'd' => 60 * 60 * 24,
+                'w' => 60 * 60 * 24 * 7,
+                _ => {
+                    return Err(format!(
+                        "unknown duration unit \"{unit}\" (expected s, m, h, d or w)"
+                    ))
+                }
+            };
+
+            return SystemTime::now()
+                .checked_sub(Duration::from_secs(
+                    amount.saturating_mul(unit_seconds),
+                ))
+                .ok_or_else(|| format!("duration \"{value}\" is too long"));
+        }
+    }
+
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date_time.into());
+    }
+
+    let naive_date_time = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| {
+            format!(
+                "\"{value}\" is neither a duration (e.g. \"24h\") nor a time (e.g. \"2024-05-01 18:30\")"
+            )
+        })?;
+
+    Local
+        .from_local_datetime(&naive_date_time)
+        .earliest()
+        .map(SystemTime::from)
+        .ok_or_else(|| {
+            format!("\"{value}\" does not exist in the local time zone")
+        })
+}
+
 /// Load and return the configuration, given the command line arguments
-/// (`-c`/`--config` can override the load path).
+/// (`-c`/`--config` can override the load path, otherwise it is found with `Configuration::discover`).
 fn get_configuration(args: &CLIArgs) -> Result<Configuration> {
     if args.config.is_some() {
         Configuration::load_from_path(args.config.clone().unwrap())
     } else {
-        Configuration::load_default_path()
+        let configuration_file_path = Configuration::discover()?;
+
+        // The terminal backends aren't set up yet, and stdout may be machine-readable output.
+        if is_verbose_enabled() {
+            eprintln!(
+                "Using configuration file: {}",
+                configuration_file_path.display()
+            );
+        }
+
+        Configuration::load_from_path(configuration_file_path)
+    }
+}
+
+/// Returns the subcommand to run: either the one given on the command line or,
+/// if none was given, the one configured in `ui.default_command`.
+///
+/// If neither is available, this prints the usual missing subcommand error (with help) and exits.
+fn resolve_cli_command(
+    command: Option<CLICommand>,
+    configuration: &Configuration,
+) -> Result<CLICommand> {
+    if let Some(command) = command {
+        return Ok(command);
+    }
+
+    let Some(default_command) = &configuration.ui.default_command else {
+        CLIArgs::command()
+            .error(
+                ErrorKind::MissingSubcommand,
+                "no subcommand was given and `ui.default_command` is not set in the configuration.",
+            )
+            .exit();
+    };
+
+    let default_command_args =
+        DefaultCLICommand::try_parse_from(default_command.split_whitespace())
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                miette!(
+                    "Invalid ui.default_command in configuration: \"{}\".",
+                    default_command
+                )
+            })?;
+
+    Ok(default_command_args.command)
+}
+
+/// Executes the `init-config` command. Unlike other commands, this one doesn't need a configuration
+/// (it is run before loading one), so it sets up its own terminal backend.
+fn run_init_config_command(init_args: &InitConfigArgs) -> Result<()> {
+    let output_file_path = match &init_args.path {
+        Some(path) => path.clone(),
+        None => Configuration::default_configuration_file_location()?,
+    };
+
+    thread::scope(|scope| {
+        let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
+
+        terminal.setup(scope).wrap_err_with(|| {
+            miette!("Failed to set up terminal UI backend.")
+        })?;
+
+
+        let result = commands::cmd_init_config(
+            &output_file_path,
+            init_args.force,
+            &mut terminal,
+        )
+        .wrap_err_with(|| miette!("Failed to write configuration template."));
+
+
+        terminal.destroy().wrap_err_with(|| {
+            miette!("Failed to destroy terminal UI backend.")
+        })?;
+
+        result
+    })
+}
+
+/// Executes the `doctor` command. The configuration is loaded as part of its checks (so that
+/// a configuration that doesn't load is reported like any other problem), so it sets up its own terminal backend.
+fn run_doctor_command(configuration_file_path: Option<&Path>) -> Result<()> {
+    thread::scope(|scope| {
+        let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
+
+        terminal.setup(scope).wrap_err_with(|| {
+            miette!("Failed to set up terminal UI backend.")
+        })?;
+
+
+        let result =
+            commands::cmd_doctor(configuration_file_path, &mut terminal)
+                .wrap_err_with(|| miette!("Failed to run checks."));
+        if let Err(error) = &result {
+            terminal.log_eprintln(format!("{error}").dark_red());
+        }
+
+
+        terminal.destroy().wrap_err_with(|| {
+            miette!("Failed to destroy terminal UI backend.")
+        })?;
+
+        // Exit with a non-zero code if any check failed (e.g. for scripts).
+        let num_failed_checks = result?;
+        if num_failed_checks > 0 {
+            return Err(miette!("{} checks failed.", num_failed_checks));
+        }
+
+        Ok(())
+    })
+}
+
+/// Apply any global and command-specific CLI overrides to the loaded configuration.
+fn apply_cli_overrides_to_configuration(
+    args: &CLIArgs,
+    command: &CLICommand,
+    configuration: &mut Configuration,
+) {
+    if let Some(data_directory_path) = &args.data_dir {
+        configuration.paths.data_directory_path = data_directory_path.clone();
     }
+
+    if let CLICommand::TranscodeAll(transcode_args) = command {
+        if transcode_args.retranscode_on_tag_change {
+            configuration.aggregated_library.retranscode_on_tag_change = true;
+        }
+    }
+}
+
+/// If `aggregated_library.staging_mode` is enabled and we're about to transcode, point the
+/// configured transcoded library path to its staging directory and return the `StagingDirectory`
+/// (which is swapped into place once the transcode succeeds).
+fn redirect_transcode_into_staging_directory(
+    command: &CLICommand,
+    configuration: &mut Configuration,
+) -> Result<Option<StagingDirectory>> {
+    let CLICommand::TranscodeAll(transcode_args) = command else {
+        return Ok(None);
+    };
+
+    // A dry run doesn't write anything, so it plans directly against the current transcoded library.
+    if !configuration.aggregated_library.staging_mode
+        || transcode_args.list_errored
+        || transcode_args.dry_run
+        || transcode_args.check
+    {
+        return Ok(None);
+    }
+
+    let staging_directory = StagingDirectory::for_transcoded_library(
+        &configuration.aggregated_library.path,
+    )?;
+
+    configuration.aggregated_library.path = staging_directory
+        .staging_directory_path()
+        .to_string_lossy()
+        .to_string();
+
+    Ok(Some(staging_directory))
 }
 
 /// Initializes and returns a terminal backend for transcoding.
@@ -139,6 +802,25 @@ fn get_transcode_terminal<'scope>(
     config: &Configuration,
     use_bare_terminal: bool,
 ) -> TranscodeTerminal<'_, 'scope> {
+    if use_bare_terminal {
+        BareTerminalBackend::new()
+            .with_progress_summary_interval(Duration::from_secs(
+                config.ui.bare.progress_summary_interval_seconds,
+            ))
+            .into()
+    } else {
+        FancyTerminalBackend::new(config)
+            .expect("Could not create fancy terminal UI backend.")
+            .into()
+    }
+}
+
+/// Initializes and returns a terminal backend for validation.
+/// If `use_bare_terminal` is true, this will return `BareConsoleBackend`, otherwise `TUITerminalBackend`.
+fn get_validation_terminal<'scope>(
+    config: &Configuration,
+    use_bare_terminal: bool,
+) -> ValidationTerminal<'_, 'scope> {
     if use_bare_terminal {
         BareTerminalBackend::new().into()
     } else {
@@ -150,23 +832,80 @@ fn get_transcode_terminal<'scope>(
 
 /// Initializes the required terminal backend and executes the given CLI command.
 fn run_requested_cli_command<'config: 'scope, 'scope, 'scope_env: 'scope>(
-    args: CLIArgs,
+    command: CLICommand,
     config: &'config Configuration,
+    staging_directory: Option<StagingDirectory>,
     scope: &'scope Scope<'scope, 'scope_env>,
 ) -> Result<()> {
-    if let CLICommand::TranscodeAll(transcode_args) = args.command {
+    if let CLICommand::TranscodeAll(transcode_args) = &command {
+        if transcode_args.list_errored {
+            let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
+
+            terminal.setup(scope).wrap_err_with(|| {
+                miette!("Failed to set up terminal UI backend.")
+            })?;
+
+
+            let result = commands::cmd_list_errored(
+                config,
+                transcode_args.json,
+                &mut terminal,
+            )
+            .wrap_err_with(|| miette!("Failed to list errored files."));
+            if let Err(error) = result {
+                terminal.log_eprintln(format!("{error}").dark_red());
+            }
+
+
+            terminal.destroy().wrap_err_with(|| {
+                miette!("Failed to destroy terminal UI backend.")
+            })?;
+
+            return Ok(());
+        }
+    }
+
+    if let CLICommand::TranscodeAll(transcode_args) = command {
+        let event_stream_target = transcode_args
+            .events_jsonl
+            .as_deref()
+            .map(TranscodeEventStreamTarget::from_argument);
+
+        if event_stream_target == Some(TranscodeEventStreamTarget::Stdout)
+            && !(transcode_args.bare_terminal || is_quiet_enabled())
+        {
+            return Err(miette!(
+                "--events-jsonl can only write to standard output with --bare-terminal \
+                (the terminal UI draws on standard output)."
+            ));
+        }
+
+        let event_stream = event_stream_target
+            .as_ref()
+            .map(TranscodeEventStream::open)
+            .transpose()?;
+
         // `transcode`/`transcode-all` has two available terminal frontends:
         // - the fancy one uses `ratatui` for a full-fledged terminal UI with progress bars and multiple "windows",
         // - the bare one (enabled with --bare-terminal) is a simple console echo implementation (no progress bars, etc.).
-        let terminal =
-            get_transcode_terminal(config, transcode_args.bare_terminal);
+        // The summary of `--check` has to remain visible after exiting, so it always uses the bare terminal.
+        let terminal = get_transcode_terminal(
+            config,
+            transcode_args.bare_terminal
+                || transcode_args.check
+                || is_quiet_enabled(),
+        );
 
         if let Some(log_file_path) = transcode_args
             .log_to_file
             .or_else(|| config.logging.default_log_output_path.clone())
         {
             terminal
-                .enable_saving_logs_to_file(log_file_path, scope)
+                .enable_saving_logs_to_file(
+                    log_file_path,
+                    config.logging.file_format,
+                    scope,
+                )
                 .wrap_err_with(|| {
                     miette!("Failed to enable logging to disk.")
                 })?;
@@ -176,13 +915,76 @@ fn run_requested_cli_command<'config: 'scope, 'scope, 'scope_env: 'scope>(
             miette!("Failed to set up terminal UI backend.")
         })?;
 
+        // Signals (e.g. Ctrl+C in the bare terminal or a SIGTERM from a script) cancel
+        // the transcode just like the "q" keybind does.
+        signals::forward_termination_signals(
+            terminal.get_user_control_sender()?,
+        )?;
 
-        let result = commands::cmd_transcode_all(config, &terminal)
-            .wrap_err_with(|| {
-                miette!("Failed to execute transcode command to completion.")
-            });
-        if let Err(error) = result {
-            terminal.log_println(format!("{error}").dark_red());
+
+        let transcode_options = TranscodeOptions {
+            export_mode: transcode_args.export,
+            remove_existing_state_files: transcode_args.remove_state_files,
+            staging_directory,
+            skip_first_run_confirmation: transcode_args.yes,
+            // `--check` must not write anything either (e.g. the scan cache).
+            dry_run: transcode_args.dry_run || transcode_args.check,
+            only_library: transcode_args.only_library.clone(),
+            only_new: transcode_args.only_new,
+            transcode_threads: transcode_args.jobs,
+            no_scan_cache: transcode_args.no_cache,
+            modified_since: transcode_args.since,
+            allow_collisions: transcode_args.allow_collisions,
+            fail_fast: transcode_args.fail_fast,
+            strict: transcode_args.strict,
+            album_limit: transcode_args.limit,
+            confirm_deletions: transcode_args.confirm_deletions,
+            event_stream,
+        };
+
+        if transcode_args.check {
+            let result = commands::cmd_transcode_check(
+                config,
+                &transcode_options,
+                &terminal,
+            )
+            .wrap_err_with(|| miette!("Failed to check for changes."));
+            if let Err(error) = &result {
+                terminal.log_eprintln(format!("{error}").dark_red());
+            }
+
+
+            terminal.destroy().wrap_err_with(|| {
+                miette!("Failed to destroy terminal UI backend.")
+            })?;
+
+            // Exit with a non-zero code if anything isn't up to date
+            // or could not be checked (e.g. for CI).
+            let summary = result
+                .map_err(|_| miette!("Check did not complete successfully."))?;
+            if summary.num_pending_albums > 0 {
+                return Err(miette!(
+                    "{} albums are not up to date.",
+                    summary.num_pending_albums
+                ));
+            }
+            if summary.num_unscannable_albums > 0 {
+                return Err(miette!(
+                    "{} albums could not be scanned.",
+                    summary.num_unscannable_albums
+                ));
+            }
+
+            return Ok(());
+        }
+
+        let result =
+            commands::cmd_transcode_all(config, &transcode_options, &terminal)
+                .wrap_err_with(|| {
+                    miette!("Failed to execute transcode command to completion.")
+                });
+        if let Err(error) = &result {
+            terminal.log_eprintln(format!("{error}").dark_red());
         }
 
 
@@ -190,16 +992,26 @@ fn run_requested_cli_command<'config: 'scope, 'scope, 'scope_env: 'scope>(
             miette!("Failed to destroy terminal UI backend.")
         })?;
 
-        Ok(())
-    } else if let CLICommand::ValidateAll(args) = args.command {
-        let mut terminal: ValidationTerminal = BareTerminalBackend::new().into();
+        // The error was already logged above, but automated runs need a non-zero exit code.
+        result.map_err(|_| miette!("Transcode did not complete successfully."))
+    } else if let CLICommand::ValidateAll(args) = command {
+        // JSON output must remain machine-readable, so it always uses the bare terminal.
+        let output_json = args.format == ValidationOutputFormat::Json;
+        let mut terminal = get_validation_terminal(
+            config,
+            args.bare_terminal || output_json || is_quiet_enabled(),
+        );
 
         if let Some(log_file_path) = args
             .log_to_file
             .or_else(|| config.logging.default_log_output_path.clone())
         {
             terminal
-                .enable_saving_logs_to_file(log_file_path, scope)
+                .enable_saving_logs_to_file(
+                    log_file_path,
+                    config.logging.file_format,
+                    scope,
+                )
                 .wrap_err_with(|| {
                     miette!("Failed to enable logging to disk.")
                 })?;
@@ -211,19 +1023,230 @@ fn run_requested_cli_command<'config: 'scope, 'scope, 'scope_env: 'scope>(
 
 
 
-        let result = commands::cmd_validate(config, &mut terminal)
+        let result = commands::cmd_validate(config, output_json, &mut terminal)
             .wrap_err_with(|| {
                 miette!("Failed to execute transcode command to completion.")
             });
 
-        match result {
-            Ok(_) => {}
+        // Warnings alone don't fail the validation.
+        let num_validation_errors = match result {
+            Ok(validation_counts) => validation_counts.errors,
             Err(error) => {
-                terminal.log_println(format!(
+                terminal.log_eprintln(format!(
                     "{}: {}",
                     "Something went wrong while validating:".red(),
                     error,
                 ));
+
+                0
+            }
+        };
+
+
+        terminal.destroy().wrap_err_with(|| {
+            miette!("Failed to destroy terminal UI backend.")
+        })?;
+
+        // Exit with a non-zero code if validation failed (e.g. for CI).
+        if num_validation_errors > 0 {
+            return Err(miette!(
+                "Validation found {} errors.",
+                num_validation_errors
+            ));
+        }
+
+        Ok(())
+    } else if command == CLICommand::Reindex {
+        let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
+
+        terminal.setup(scope).wrap_err_with(|| {
+            miette!("Failed to set up terminal UI backend.")
+        })?;
+
+
+        let result =
+            commands::cmd_reindex(config, &mut terminal).wrap_err_with(|| {
+                miette!("Failed to execute reindex command to completion.")
+            });
+        if let Err(error) = result {
+            terminal.log_eprintln(format!("{error}").dark_red());
+        }
+
+
+        terminal.destroy().wrap_err_with(|| {
+            miette!("Failed to destroy terminal UI backend.")
+        })?;
+
+        Ok(())
+    } else if let CLICommand::Relink(relink_args) = command {
+        let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
+
+        terminal.setup(scope).wrap_err_with(|| {
+            miette!("Failed to set up terminal UI backend.")
+        })?;
+
+
+        let result = commands::cmd_relink(
+            config,
+            &relink_args.library,
+            &relink_args.mapping_file,
+            &mut terminal,
+        )
+        .wrap_err_with(|| {
+            miette!("Failed to execute relink command to completion.")
+        });
+        if let Err(error) = result {
+            terminal.log_eprintln(format!("{error}").dark_red());
+        }
+
+
+        terminal.destroy().wrap_err_with(|| {
+            miette!("Failed to destroy terminal UI backend.")
+        })?;
+
+        Ok(())
+    } else if let CLICommand::InspectState(inspect_args) = command {
+        let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
+
+        terminal.setup(scope).wrap_err_with(|| {
+            miette!("Failed to set up terminal UI backend.")
+        })?;
+
+
+        let result = commands::cmd_inspect_state(
+            config,
+            &inspect_args.album,
+            &mut terminal,
+        )
+        .wrap_err_with(|| miette!("Failed to inspect album state."));
+        if let Err(error) = result {
+            terminal.log_eprintln(format!("{error}").dark_red());
+        }
+
+
+        terminal.destroy().wrap_err_with(|| {
+            miette!("Failed to destroy terminal UI backend.")
+        })?;
+
+        Ok(())
+    } else if let CLICommand::Diff(diff_args) = command {
+        let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
+
+        terminal.setup(scope).wrap_err_with(|| {
+            miette!("Failed to set up terminal UI backend.")
+        })?;
+
+
+        let result = commands::cmd_diff(
+            config,
+            &diff_args.library,
+            &diff_args.artist,
+            &diff_args.album,
+            &mut terminal,
+        )
+        .wrap_err_with(|| miette!("Failed to compute album changes."));
+        if let Err(error) = result {
+            terminal.log_eprintln(format!("{error}").dark_red());
+        }
+
+
+        terminal.destroy().wrap_err_with(|| {
+            miette!("Failed to destroy terminal UI backend.")
+        })?;
+
+        Ok(())
+    } else if let CLICommand::WhatIfRemove(what_if_args) = command {
+        let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
+
+        terminal.setup(scope).wrap_err_with(|| {
+            miette!("Failed to set up terminal UI backend.")
+        })?;
+
+
+        let result = commands::cmd_what_if_remove(
+            config,
+            &what_if_args.artist,
+            what_if_args.json,
+            &mut terminal,
+        )
+        .wrap_err_with(|| miette!("Failed to generate removal report."));
+        if let Err(error) = result {
+            terminal.log_eprintln(format!("{error}").dark_red());
+        }
+
+
+        terminal.destroy().wrap_err_with(|| {
+            miette!("Failed to destroy terminal UI backend.")
+        })?;
+
+        Ok(())
+    } else if let CLICommand::Clean(clean_args) = command {
+        let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
+
+        terminal.setup(scope).wrap_err_with(|| {
+            miette!("Failed to set up terminal UI backend.")
+        })?;
+
+
+        let result =
+            commands::cmd_clean(config, clean_args.delete, &mut terminal)
+                .wrap_err_with(|| {
+                    miette!("Failed to execute clean command to completion.")
+                });
+        if let Err(error) = result {
+            terminal.log_eprintln(format!("{error}").dark_red());
+        }
+
+
+        terminal.destroy().wrap_err_with(|| {
+            miette!("Failed to destroy terminal UI backend.")
+        })?;
+
+        Ok(())
+    } else if let CLICommand::Stats(stats_args) = command {
+        let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
+
+        terminal.setup(scope).wrap_err_with(|| {
+            miette!("Failed to set up terminal UI backend.")
+        })?;
+
+
+        let result = commands::cmd_stats(
+            config,
+            stats_args.format == StatsOutputFormat::Json,
+            &mut terminal,
+        )
+        .wrap_err_with(|| miette!("Failed to collect collection statistics."));
+        if let Err(error) = result {
+            terminal.log_eprintln(format!("{error}").dark_red());
+        }
+
+
+        terminal.destroy().wrap_err_with(|| {
+            miette!("Failed to destroy terminal UI backend.")
+        })?;
+
+        Ok(())
+    } else if let CLICommand::Verify(verify_args) = command {
+        let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
+
+        terminal.setup(scope).wrap_err_with(|| {
+            miette!("Failed to set up terminal UI backend.")
+        })?;
+
+
+        let result = commands::cmd_verify(
+            config,
+            verify_args.format == VerifyOutputFormat::Json,
+            &mut terminal,
+        )
+        .wrap_err_with(|| miette!("Failed to verify the transcoded libraries."));
+
+        let num_verification_errors = match result {
+            Ok(num_verification_errors) => num_verification_errors,
+            Err(error) => {
+                terminal.log_eprintln(format!("{error}").dark_red());
+                0
             }
         };
 
@@ -232,8 +1255,20 @@ fn run_requested_cli_command<'config: 'scope, 'scope, 'scope_env: 'scope>(
             miette!("Failed to destroy terminal UI backend.")
         })?;
 
+        // Exit with a non-zero code if verification found any problems (e.g. for scripts).
+        if num_verification_errors > 0 {
+            return Err(miette!(
+                "Verification found {} problems.",
+                num_verification_errors
+            ));
+        }
+
         Ok(())
-    } else if args.command == CLICommand::ShowConfig {
+    } else if command == CLICommand::Doctor {
+        // Only reachable through `ui.default_command`, as `main` handles `doctor`
+        // before loading the configuration.
+        run_doctor_command(Some(&config.configuration_file_path))
+    } else if let CLICommand::ShowConfig(show_args) = command {
         let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
 
         terminal.setup(scope).wrap_err_with(|| {
@@ -241,7 +1276,22 @@ fn run_requested_cli_command<'config: 'scope, 'scope, 'scope_env: 'scope>(
         })?;
 
 
-        commands::cmd_show_config(config, &mut terminal);
+        let serialized_format = match show_args.format {
+            ShowConfigOutputFormat::Pretty => None,
+            ShowConfigOutputFormat::Json => {
+                Some(SerializedConfigurationFormat::Json)
+            }
+            ShowConfigOutputFormat::Toml => {
+                Some(SerializedConfigurationFormat::Toml)
+            }
+        };
+
+        let result =
+            commands::cmd_show_config(config, serialized_format, &mut terminal)
+                .wrap_err_with(|| miette!("Failed to show configuration."));
+        if let Err(error) = result {
+            terminal.log_eprintln(format!("{error}").dark_red());
+        }
 
 
         terminal.destroy().wrap_err_with(|| {
@@ -249,7 +1299,7 @@ fn run_requested_cli_command<'config: 'scope, 'scope, 'scope_env: 'scope>(
         })?;
 
         Ok(())
-    } else if args.command == CLICommand::ListLibraries {
+    } else if let CLICommand::ListLibraries(list_args) = command {
         let mut terminal: SimpleTerminal = BareTerminalBackend::new().into();
 
         terminal.setup(scope).wrap_err_with(|| {
@@ -257,7 +1307,15 @@ fn run_requested_cli_command<'config: 'scope, 'scope, 'scope_env: 'scope>(
         })?;
 
 
-        commands::cmd_list_libraries(config, &mut terminal);
+        let result = commands::cmd_list_libraries(
+            config,
+            list_args.format == ListLibrariesOutputFormat::Json,
+            &mut terminal,
+        )
+        .wrap_err_with(|| miette!("Failed to list libraries."));
+        if let Err(error) = result {
+            terminal.log_eprintln(format!("{error}").dark_red());
+        }
 
 
         terminal.destroy().wrap_err_with(|| {
@@ -265,6 +1323,10 @@ fn run_requested_cli_command<'config: 'scope, 'scope, 'scope_env: 'scope>(
         })?;
 
         Ok(())
+    } else if let CLICommand::InitConfig(init_args) = command {
+        // Only reachable through `ui.default_command`, as `main` handles `init-config`
+        // before loading the configuration.
+        run_init_config_command(&init_args)
     } else {
         panic!("Unrecognized command!");
     }
@@ -274,15 +1336,48 @@ fn run_requested_cli_command<'config: 'scope, 'scope, 'scope_env: 'scope>(
 ///
 /// Parses CLI arguments, loads the configuration file and starts executing the requested command.
 fn main() -> Result<()> {
-    let args = CLIArgs::parse();
+    let mut args = CLIArgs::parse();
     VERBOSE.set(args.verbose);
+    QUIET.set(args.quiet);
+
+    // `init-config` creates the configuration file, so it must run before we try to load one.
+    if let Some(CLICommand::InitConfig(init_args)) = &args.command {
+        return run_init_config_command(init_args);
+    }
+
+    // `doctor` reports a configuration that doesn't load as a failed check, so it loads the configuration itself.
+    if args.command == Some(CLICommand::Doctor) {
+        let result = run_doctor_command(args.config.as_deref().map(Path::new));
 
-    let configuration = get_configuration(&args)
+        if let Err(error) = result {
+            eprintln!("{:?}", error);
+            exit(1);
+        }
+
+        return Ok(());
+    }
+
+    let mut configuration = get_configuration(&args)
         .wrap_err_with(|| miette!("Could not load configuration."))?;
+    let command = resolve_cli_command(args.command.take(), &configuration)?;
+
+    // `doctor` reports unavailable paths itself, as part of its checklist.
+    if command != CLICommand::Doctor {
+        configuration
+            .validate_paths()
+            .wrap_err_with(|| miette!("Could not load configuration."))?;
+    }
+    apply_cli_overrides_to_configuration(&args, &command, &mut configuration);
+    let staging_directory =
+        redirect_transcode_into_staging_directory(&command, &mut configuration)?;
 
     thread::scope(|scope| {
-        let command_result =
-            run_requested_cli_command(args, &configuration, scope);
+        let command_result = run_requested_cli_command(
+            command,
+            &configuration,
+            staging_directory,
+            scope,
+        );
 
         match command_result {
             Ok(_) => exit(0),
@@ -0,0 +1,102 @@
+use std::io::stdin;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use miette::{miette, Context, IntoDiagnostic, Result};
+use tokio::sync::broadcast;
+
+use crate::console::UserControlMessage;
+
+/// How often `read_line_unless_terminated` checks whether a termination signal was received.
+const READ_LINE_TERMINATION_CHECK_INTERVAL: Duration =
+    Duration::from_millis(100);
+
+/// Exit code used when a second termination signal forces an immediate exit (128 + SIGINT).
+const FORCED_EXIT_CODE: i32 = 130;
+
+/// Set by the first termination signal.
+static TERMINATION_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+
+/// Install a handler for SIGINT and SIGTERM (Ctrl+C and Ctrl+Break on Windows) that sends
+/// `UserControlMessage::Exit` through `user_control_sender`, so a signal cancels the transcode
+/// the same way as the "q" keybind does (e.g. unfinished files are removed)
+/// instead of killing the process in the middle of a file.
+///
+/// Before processing starts (while scanning or waiting for a confirmation),
+/// nothing is listening for `UserControlMessage::Exit` yet - that phase instead
+/// checks `termination_requested` (see `check_for_termination` and `read_line_unless_terminated`).
+///
+/// A second signal exits immediately, without waiting for the cancellation to finish.
+///
+/// This can only be called once per process.
+pub fn forward_termination_signals(
+    user_control_sender: broadcast::Sender<UserControlMessage>,
+) -> Result<()> {
+    ctrlc::set_handler(move || {
+        if TERMINATION_REQUESTED.swap(true, Ordering::SeqCst) {
+            // Best effort: don't leave the terminal in raw mode if the terminal UI was active.
+            let _ = crossterm::terminal::disable_raw_mode();
+            process::exit(FORCED_EXIT_CODE);
+        }
+
+        // There may be no receivers at the moment (e.g. before processing starts),
+        // in which case the termination flag is enough.
+        let _ = user_control_sender.send(UserControlMessage::Exit);
+    })
+    .into_diagnostic()
+    .wrap_err_with(|| miette!("Could not install termination signal handler."))
+}
+
+/// Returns `true` if a termination signal has been received.
+pub fn termination_requested() -> bool {
+    TERMINATION_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Returns an error if a termination signal has been received.
+/// Meant for long-running work that happens before processing starts (e.g. scanning).
+pub fn check_for_termination() -> Result<()> {
+    if termination_requested() {
+        return Err(miette!("Cancelled by a termination signal."));
+    }
+
+    Ok(())
+}
+
+/// Read a line from the standard input, but stop waiting as soon as a termination signal
+/// is received, in which case an error is returned (see `check_for_termination`).
+///
+/// A blocking read is not interrupted by the signal handler, so the line is read
+/// on a separate thread (which is left behind if the read is abandoned).
+pub fn read_line_unless_terminated() -> Result<String> {
+    let (line_sender, line_receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut line = String::new();
+        let result = stdin().read_line(&mut line).map(|_| line);
+
+        // The receiver is gone if the read was abandoned.
+        let _ = line_sender.send(result);
+    });
+
+    loop {
+        check_for_termination()?;
+
+        match line_receiver.recv_timeout(READ_LINE_TERMINATION_CHECK_INTERVAL) {
+            Ok(result) => {
+                return result.into_diagnostic().wrap_err_with(|| {
+                    miette!("Failed to read from standard input.")
+                });
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(miette!(
+                    "BUG: Standard input reader thread exited without a result."
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Helpers shared by the unit tests of this crate.
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Distinguishes directories of tests running in the same process.
+static NEXT_TEST_DIRECTORY_ID: AtomicUsize = AtomicUsize::new(0);
+
+
+/// An empty, uniquely-named test directory that is removed (along with its contents) when dropped,
+/// which also happens when the test fails (panics).
+pub struct TestDirectory {
+    path: PathBuf,
+}
+
+impl TestDirectory {
+    /// Create a new test directory inside the system temporary directory.
+    /// `name` only makes the directory easier to recognize.
+    pub fn new(name: &str) -> Self {
+        Self::new_in(&std::env::temp_dir(), name)
+    }
+
+    /// Create a new test directory inside `base_directory`
+    /// (e.g. to place it on a specific filesystem).
+    pub fn new_in(base_directory: &Path, name: &str) -> Self {
+        let path = base_directory.join(format!(
+            "euphony-test-{}-{}-{}",
+            process::id(),
+            NEXT_TEST_DIRECTORY_ID.fetch_add(1, Ordering::Relaxed),
+            name
+        ));
+
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        fs::create_dir_all(&path).unwrap();
+
+        Self { path }
+    }
+}
+
+impl Deref for TestDirectory {
+    type Target = Path;
+
+    fn deref(&self) -> &Self::Target {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TestDirectory {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        // Best effort, a leftover directory must not hide the actual test failure.
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}